@@ -2,38 +2,95 @@
 struct CameraUniform {
     center: vec2<f32>,
     size: vec2<f32>,
+    // Cosine and sine of the camera's rotation.
+    rotation: vec2<f32>,
+    // Size of the surface in pixels.
+    viewport: vec2<f32>,
 };
 
 @group(0) @binding(0) // 1.
 var<uniform> camera: CameraUniform;
 
+// Turns a world space vector the way the camera shows it.
+fn rotate(v: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(camera.rotation.x * v.x - camera.rotation.y * v.y, camera.rotation.y * v.x + camera.rotation.x * v.y);
+}
+
 struct VertexInput {
     @location(0) current: vec2<f32>,
-    @location(1) next: vec2<f32>,
-    @location(2) offset_distance: f32,
-    @location(3) color: vec3<f32>,
+    // The points before and after this one on the line, or this one at its ends.
+    @location(1) previous: vec2<f32>,
+    @location(2) next: vec2<f32>,
+    @location(3) offset_distance: f32,
+    @location(4) color: vec4<f32>,
+    // Nonzero when offset_distance is in pixels rather than world units.
+    @location(5) pixel_sized: u32,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
-    @location(0) color: vec3<f32>,
+    @location(0) color: vec4<f32>,
 };
 
+// Sharp corners are cut off rather than pushed out further than this many half widths.
+const MITER_LIMIT: f32 = 4.0;
+
+// Zero rather than NaN between points in the same place.
+fn direction(start: vec2<f32>, end: vec2<f32>) -> vec2<f32> {
+    let d = end - start;
+    if dot(d, d) < 1e-12 {
+        return vec2<f32>(0.0, 0.0);
+    }
+    return normalize(d);
+}
+
+fn perpendicular(v: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(-v.y, v.x);
+}
+
+// Offset of a vertex at `current` from the line through it, halfway between the normals of
+// the segments on either side. Zero when the line has no length there.
+fn miter(previous: vec2<f32>, current: vec2<f32>, next: vec2<f32>, distance: f32) -> vec2<f32> {
+    let incoming = direction(previous, current);
+    let outgoing = direction(current, next);
+    // The segment the offset is measured across, the incoming one at the end of the line.
+    var segment = outgoing;
+    if dot(segment, segment) == 0.0 {
+        segment = incoming;
+    }
+    // A line that doubles back has no corner to bisect.
+    var tangent = incoming + outgoing;
+    if dot(tangent, tangent) < 1e-6 {
+        tangent = segment;
+    }
+    if dot(tangent, tangent) == 0.0 {
+        return vec2<f32>(0.0, 0.0);
+    }
+    let normal = perpendicular(normalize(tangent));
+    let scale = 1.0 / max(dot(normal, perpendicular(segment)), 1.0 / MITER_LIMIT);
+    return normal * distance * scale;
+}
+
 @vertex
 fn vs_main(
     model: VertexInput,
 ) -> VertexOutput {
     var out: VertexOutput;
 
+    let current_projected = rotate(model.current - camera.center) / camera.size * 2.0;
 
-    var current_projected: vec2<f32> = vec2<f32>((model.current.x - camera.center.x) / camera.size.x * 2.0, (model.current.y - camera.center.y) / camera.size.y * 2.0);
-
-    // var next_projected: vec2<f32> = vec2<f32>((model.next.x - camera.center.x) / camera.size.x * 2.0, (model.next.y - camera.center.y) / camera.size.y * 2.0);
-
-    let dir = normalize(model.next - model.current);
-    let normal = vec2<f32>(-dir.y, dir.x) * model.offset_distance;
-    let normal_projected = vec2<f32>(normal.x / camera.size.x * 2.0, normal.y / camera.size.y * 2.0);
-
+    var normal_projected: vec2<f32>;
+    if model.pixel_sized != 0u {
+        // Offset across the line as it appears on screen, a set number of pixels.
+        let to_pixels = camera.viewport / 2.0;
+        let previous_projected = rotate(model.previous - camera.center) / camera.size * 2.0;
+        let next_projected = rotate(model.next - camera.center) / camera.size * 2.0;
+        let offset = miter(previous_projected * to_pixels, current_projected * to_pixels, next_projected * to_pixels, model.offset_distance);
+        normal_projected = offset / to_pixels;
+    } else {
+        let offset = miter(model.previous, model.current, model.next, model.offset_distance);
+        normal_projected = rotate(offset) / camera.size * 2.0;
+    }
 
     out.color = model.color;
     out.clip_position = vec4<f32>(current_projected + normal_projected, 0.0, 1.0);
@@ -44,5 +101,5 @@ fn vs_main(
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(in.color, 0.5);
+    return in.color;
 }
\ No newline at end of file
@@ -0,0 +1,43 @@
+struct OverlayUniform {
+    screen_size: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> overlay: OverlayUniform;
+@group(0) @binding(1)
+var atlas_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var atlas_sampler: sampler;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    // Overlay positions are in pixels from the top-left corner of the window.
+    let clip = vec2<f32>(model.position.x / overlay.screen_size.x * 2.0 - 1.0, 1.0 - model.position.y / overlay.screen_size.y * 2.0);
+    out.clip_position = vec4<f32>(clip, 0.0, 1.0);
+    out.tex_coords = model.tex_coords;
+    out.color = model.color;
+    return out;
+}
+
+// Fragment shader
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coverage = textureSample(atlas_texture, atlas_sampler, in.tex_coords).r;
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
@@ -0,0 +1,112 @@
+// Renders vertex and edge IDs instead of colors. 0 means nothing was hit.
+struct CameraUniform {
+    center: vec2<f32>,
+    size: vec2<f32>,
+    // Cosine and sine of the camera's rotation.
+    rotation: vec2<f32>,
+    // Size of the surface in pixels.
+    viewport: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// Only a few pixels around the cursor are rendered, into a texture just that big.
+struct PickWindow {
+    scale: vec2<f32>,
+    offset: vec2<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> pick_window: PickWindow;
+
+// Moves a clip space position on the surface into the pick window.
+fn to_pick_window(clip_position: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(clip_position.xy * pick_window.scale + pick_window.offset * clip_position.w, clip_position.zw);
+}
+
+// Turns a world space vector the way the camera shows it.
+fn rotate(v: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(camera.rotation.x * v.x - camera.rotation.y * v.y, camera.rotation.y * v.x + camera.rotation.x * v.y);
+}
+
+fn project(position: vec2<f32>) -> vec2<f32> {
+    return rotate(position - camera.center) / camera.size * 2.0;
+}
+
+struct PointInput {
+    @location(0) corner: vec2<f32>,
+    @location(1) position: vec2<f32>,
+    @location(2) size: f32,
+    @location(3) id: u32,
+    // Nonzero when size is in pixels rather than world units.
+    @location(4) pixel_sized: u32,
+};
+
+struct PointOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) corner: vec2<f32>,
+    @location(1) @interpolate(flat) id: u32,
+};
+
+@vertex
+fn vs_point(in: PointInput) -> PointOutput {
+    var out: PointOutput;
+    var offset: vec2<f32>;
+    if in.pixel_sized != 0u {
+        offset = in.corner * in.size / camera.viewport * 2.0;
+    } else {
+        offset = rotate(in.corner * in.size) / camera.size * 2.0;
+    }
+    out.clip_position = vec4<f32>(project(in.position) + offset, 0.0, 1.0);
+    out.corner = in.corner;
+    out.clip_position = to_pick_window(out.clip_position);
+    out.id = in.id;
+    return out;
+}
+
+@fragment
+fn fs_point(in: PointOutput) -> @location(0) u32 {
+    // The quad spans -1..1, and the drawn disk (outline included) has radius 1.
+    if dot(in.corner, in.corner) > 1.0 {
+        discard;
+    }
+    return in.id;
+}
+
+struct LineInput {
+    @location(0) current: vec2<f32>,
+    @location(1) next: vec2<f32>,
+    @location(2) offset_distance: f32,
+    @location(3) id: u32,
+    // Nonzero when offset_distance is in pixels rather than world units.
+    @location(4) pixel_sized: u32,
+};
+
+struct LineOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) @interpolate(flat) id: u32,
+};
+
+@vertex
+fn vs_line(in: LineInput) -> LineOutput {
+    var out: LineOutput;
+    var normal_projected: vec2<f32>;
+    if in.pixel_sized != 0u {
+        let dir = normalize((project(in.next) - project(in.current)) * camera.viewport);
+        normal_projected = vec2<f32>(-dir.y, dir.x) * in.offset_distance / camera.viewport * 2.0;
+    } else {
+        let dir = normalize(in.next - in.current);
+        let normal = vec2<f32>(-dir.y, dir.x) * in.offset_distance;
+        normal_projected = rotate(normal) / camera.size * 2.0;
+    }
+    out.clip_position = vec4<f32>(project(in.current) + normal_projected, 0.0, 1.0);
+    out.clip_position = to_pick_window(out.clip_position);
+    out.id = in.id;
+    return out;
+}
+
+@fragment
+fn fs_line(in: LineOutput) -> @location(0) u32 {
+    return in.id;
+}
@@ -1,15 +1,38 @@
 struct CameraUniform {
     center: vec2<f32>,
     size: vec2<f32>,
+    // Cosine and sine of the camera's rotation.
+    rotation: vec2<f32>,
+    // Size of the surface in pixels.
+    viewport: vec2<f32>,
 };
 
 @group(0) @binding(0) // 1.
 var<uniform> camera: CameraUniform;
 
+@group(1) @binding(0)
+var icon_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var icon_sampler: sampler;
+
+// Turns a world space vector the way the camera shows it.
+fn rotate(v: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(camera.rotation.x * v.x - camera.rotation.y * v.y, camera.rotation.y * v.x + camera.rotation.x * v.y);
+}
+
 struct InstanceInput {
     @location(2) position: vec2<f32>,
     @location(3) color: vec3<f32>,
     @location(4) size: f32,
+    // Nonzero when size is in pixels rather than world units.
+    @location(5) pixel_sized: u32,
+    @location(6) outline_color: vec3<f32>,
+    // Fraction of the radius the outline takes up.
+    @location(7) outline_thickness: f32,
+    // One of the shapes in shape_distance.
+    @location(8) shape: u32,
+    // Top left and bottom right of the icon in the atlas, all zero without one.
+    @location(9) icon_rect: vec4<f32>,
 }
 
 struct VertexInput {
@@ -21,6 +44,10 @@ struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
     @location(1) color: vec3<f32>,
+    @location(2) outline_color: vec3<f32>,
+    @location(3) outline_thickness: f32,
+    @location(4) @interpolate(flat) shape: u32,
+    @location(5) @interpolate(flat) icon_rect: vec4<f32>,
 }
 
 @vertex
@@ -30,28 +57,110 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.tex_coords = model.tex_coords;
-    let position_projected = vec2<f32>((instance.position.x - camera.center.x) / camera.size.x * 2.0, (instance.position.y - camera.center.y) / camera.size.y * 2.0);
-    let model_position_projected = vec2<f32>(model.position.x * instance.size / camera.size.x * 2.0, model.position.y * instance.size / camera.size.y * 2.0);
+    let position_projected = rotate(instance.position - camera.center) / camera.size * 2.0;
+    var model_position_projected: vec2<f32>;
+    if instance.pixel_sized != 0u {
+        model_position_projected = model.position * instance.size / camera.viewport * 2.0;
+    } else {
+        model_position_projected = rotate(model.position * instance.size) / camera.size * 2.0;
+    }
     out.clip_position = vec4<f32>(position_projected + model_position_projected, 0.0, 1.0);
     out.color = instance.color;
+    out.outline_color = instance.outline_color;
+    out.outline_thickness = instance.outline_thickness;
+    out.shape = instance.shape;
+    out.icon_rect = instance.icon_rect;
     return out;
 }
 
 // Fragment shader
 
-@fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let r2 = (in.tex_coords.x - 0.5) * (in.tex_coords.x - 0.5) + (in.tex_coords.y - 0.5) * (in.tex_coords.y - 0.5);
-    let outline_thickness = 0.1;
+// Signed distances to shapes that fit the unit disk, negative inside. Mostly from
+// https://iquilezles.org/articles/distfunctions2d/.
 
-    if r2 <= (0.5 - outline_thickness) * (0.5 - outline_thickness) {
-        return vec4<f32>(in.color, 1.0);
+fn box_distance(p: vec2<f32>, b: vec2<f32>) -> f32 {
+    let d = abs(p) - b;
+    return length(max(d, vec2<f32>(0.0))) + min(max(d.x, d.y), 0.0);
+}
+
+// Points up, with sides of 2r.
+fn triangle_distance(p_in: vec2<f32>, r: f32) -> f32 {
+    let k = sqrt(3.0);
+    var p = vec2<f32>(abs(p_in.x) - r, p_in.y + r / k);
+    if p.x + k * p.y > 0.0 {
+        p = vec2<f32>(p.x - k * p.y, -k * p.x - p.y) / 2.0;
     }
-    else if r2 <= 0.25 {
-        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    p.x -= clamp(p.x, -2.0 * r, 0.0);
+    return -length(p) * sign(p.y);
+}
+
+// Flat topped, r from the center to the middle of a side.
+fn hexagon_distance(p_in: vec2<f32>, r: f32) -> f32 {
+    let k = vec3<f32>(-0.866025404, 0.5, 0.577350269);
+    var p = abs(p_in);
+    p -= 2.0 * min(dot(k.xy, p), 0.0) * k.xy;
+    p -= vec2<f32>(clamp(p.x, -k.z * r, k.z * r), r);
+    return length(p) * sign(p.y);
+}
+
+// Five points at r from the center, the inner corners rf of the way out.
+fn star_distance(p_in: vec2<f32>, r: f32, rf: f32) -> f32 {
+    let k1 = vec2<f32>(0.809016994375, -0.587785252292);
+    let k2 = vec2<f32>(-k1.x, k1.y);
+    var p = vec2<f32>(abs(p_in.x), p_in.y);
+    p -= 2.0 * max(dot(k1, p), 0.0) * k1;
+    p -= 2.0 * max(dot(k2, p), 0.0) * k2;
+    p.x = abs(p.x);
+    p.y -= r;
+    let ba = rf * vec2<f32>(-k1.y, k1.x) - vec2<f32>(0.0, 1.0);
+    let h = clamp(dot(p, ba) / dot(ba, ba), 0.0, r);
+    return length(p - ba * h) * sign(p.y * ba.x - p.x * ba.y);
+}
+
+// In the same order as draw::Shape.
+fn shape_distance(shape: u32, p: vec2<f32>) -> f32 {
+    switch shape {
+        case 1u: {
+            return box_distance(p, vec2<f32>(0.8));
+        }
+        case 2u: {
+            return triangle_distance(p, 0.85);
+        }
+        case 3u: {
+            return (abs(p.x) + abs(p.y) - 1.0) * 0.707106781;
+        }
+        case 4u: {
+            return hexagon_distance(p, 0.85);
+        }
+        case 5u: {
+            return star_distance(p, 1.0, 0.45);
+        }
+        default: {
+            return length(p) - 1.0;
+        }
+    }
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Sampled before anything is discarded, where derivatives are still defined. Texture
+    // rows run down while texture coordinates run up.
+    let icon_coords = mix(in.icon_rect.xy, in.icon_rect.zw, vec2<f32>(in.tex_coords.x, 1.0 - in.tex_coords.y));
+    var icon = textureSample(icon_texture, icon_sampler, icon_coords);
+    if in.icon_rect.z <= in.icon_rect.x {
+        icon.a = 0.0;
     }
-    else {
+
+    let d = shape_distance(in.shape, in.tex_coords * 2.0 - 1.0);
+    // About a pixel, so the shape and its outline fade out over the same width at any size.
+    // Multisampling can't smooth these edges, since they aren't the edges of triangles.
+    let feather = fwidth(d);
+
+    let alpha = 1.0 - smoothstep(-feather, 0.0, d);
+    if alpha <= 0.0 {
         discard;
     }
+    let fill = 1.0 - smoothstep(-in.outline_thickness - feather, -in.outline_thickness, d);
+    let inside = mix(in.color, icon.rgb, icon.a);
+    return vec4<f32>(mix(in.outline_color, inside, fill), alpha);
 }
- 
\ No newline at end of file
@@ -0,0 +1,44 @@
+// Vertex shader
+struct CameraUniform {
+    center: vec2<f32>,
+    size: vec2<f32>,
+    // Cosine and sine of the camera's rotation.
+    rotation: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// Turns a world space vector the way the camera shows it.
+fn rotate(v: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(camera.rotation.x * v.x - camera.rotation.y * v.y, camera.rotation.y * v.x + camera.rotation.x * v.y);
+}
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let projected = rotate(model.position - camera.center) / camera.size * 2.0;
+
+    out.color = model.color;
+    out.clip_position = vec4<f32>(projected, 0.0, 1.0);
+    return out;
+}
+
+// Fragment shader
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
@@ -0,0 +1,171 @@
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+use crate::bindings::{Modifiers, Stroke};
+
+/// Everything a key or mouse button can be bound to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    /// Drag the vertex under the cursor, or pan when there is none.
+    Grab,
+    /// Drag out a new vertex from the one under the cursor.
+    AddVertex,
+    RemoveLastVertex,
+    ClearGraph,
+    TogglePause,
+    CycleIntegrator,
+    CycleMetric,
+    CycleColormap,
+    ToggleSizeByMetric,
+    CycleCommunities,
+    CycleCommunityBackground,
+    ToggleCommunityForce,
+    SetPathSource,
+    SetPathTarget,
+    ToggleWeightedPaths,
+    ToggleAllPaths,
+    ToggleHoverDistances,
+    CycleAnimation,
+    ToggleAnimation,
+    StepAnimationBack,
+    StepAnimationForward,
+    SlowDownAnimation,
+    SpeedUpAnimation,
+    RestartAnimation,
+    PinVertexInfo,
+    IncreaseWeight,
+    DecreaseWeight,
+    SplitEdge,
+    DeleteEdge,
+    OpenGeneratorMenu,
+    ToggleHud,
+    ToggleHelp,
+    /// Switch to the next built-in or loaded theme.
+    CycleTheme,
+    /// Bow every edge out, not only parallel ones.
+    ToggleCurvedEdges,
+    /// Draw edges bundled together along their paths.
+    ToggleEdgeBundling,
+    StrengthenBundling,
+    WeakenBundling,
+    ClearSelection,
+    /// Save the current frame as a PNG.
+    #[cfg(not(target_arch = "wasm32"))]
+    Screenshot,
+    /// Start or stop recording the layout.
+    #[cfg(not(target_arch = "wasm32"))]
+    ToggleRecording,
+}
+
+/// Name used in bindings files and description shown in the help sheet, in help sheet order.
+pub const ACTIONS: &[(Action, &str, &str)] = &[
+    (Action::Grab, "grab", "drag vertex or pan"),
+    (Action::AddVertex, "add_vertex", "drag out a new vertex"),
+    (Action::RemoveLastVertex, "remove_last_vertex", "remove last vertex"),
+    (Action::ClearGraph, "clear_graph", "clear graph"),
+    (Action::TogglePause, "toggle_pause", "pause layout"),
+    (Action::CycleIntegrator, "cycle_integrator", "next integrator"),
+    (Action::CycleMetric, "cycle_metric", "metric"),
+    (Action::CycleColormap, "cycle_colormap", "colormap"),
+    (Action::ToggleSizeByMetric, "toggle_size_by_metric", "size by metric"),
+    (Action::CycleCommunities, "cycle_communities", "communities"),
+    (Action::CycleCommunityBackground, "cycle_community_background", "community background"),
+    (Action::ToggleCommunityForce, "toggle_community_force", "community force"),
+    (Action::SetPathSource, "set_path_source", "path source (hovered)"),
+    (Action::SetPathTarget, "set_path_target", "path target (hovered)"),
+    (Action::ToggleWeightedPaths, "toggle_weighted_paths", "weighted shortest paths"),
+    (Action::ToggleAllPaths, "toggle_all_paths", "all shortest paths"),
+    (Action::ToggleHoverDistances, "toggle_hover_distances", "distances from hovered vertex"),
+    (Action::CycleAnimation, "cycle_animation", "algorithm animation"),
+    (Action::ToggleAnimation, "toggle_animation", "play animation"),
+    (Action::StepAnimationBack, "step_animation_back", "step back"),
+    (Action::StepAnimationForward, "step_animation_forward", "step forward"),
+    (Action::SlowDownAnimation, "slow_down_animation", "slower"),
+    (Action::SpeedUpAnimation, "speed_up_animation", "faster"),
+    (Action::RestartAnimation, "restart_animation", "restart animation"),
+    (Action::PinVertexInfo, "pin_vertex_info", "pin vertex info"),
+    (Action::IncreaseWeight, "increase_weight", "increase edge weight"),
+    (Action::DecreaseWeight, "decrease_weight", "decrease edge weight"),
+    (Action::SplitEdge, "split_edge", "split edge"),
+    (Action::DeleteEdge, "delete_edge", "delete edge"),
+    (Action::OpenGeneratorMenu, "open_generator_menu", "graph generators"),
+    (Action::ToggleHud, "toggle_hud", "statistics"),
+    (Action::ToggleHelp, "toggle_help", "this sheet"),
+    (Action::CycleTheme, "cycle_theme", "theme"),
+    (Action::ToggleCurvedEdges, "toggle_curved_edges", "curved edges"),
+    (Action::ToggleEdgeBundling, "toggle_edge_bundling", "edge bundling"),
+    (Action::StrengthenBundling, "strengthen_bundling", "stronger bundling"),
+    (Action::WeakenBundling, "weaken_bundling", "weaker bundling"),
+    (Action::ClearSelection, "clear_selection", "clear selection"),
+    #[cfg(not(target_arch = "wasm32"))]
+    (Action::Screenshot, "screenshot", "save screenshot"),
+    #[cfg(not(target_arch = "wasm32"))]
+    (Action::ToggleRecording, "toggle_recording", "record"),
+];
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        ACTIONS.iter().find(|(action, _, _)| *action == self).unwrap().1
+    }
+
+    pub fn description(self) -> &'static str {
+        ACTIONS.iter().find(|(action, _, _)| *action == self).unwrap().2
+    }
+
+    pub fn parse(name: &str) -> Option<Action> {
+        ACTIONS.iter().find(|(_, action_name, _)| *action_name == name).map(|(action, _, _)| *action)
+    }
+}
+
+/// What every action is bound to before a bindings file overrides it.
+pub fn default_bindings() -> Vec<(Vec<Stroke>, Action)> {
+    let none = Modifiers::default();
+    let key = |key| vec![Stroke::key(key, none)];
+    let mouse = |button| vec![Stroke::mouse(button, none)];
+
+    vec![
+        (mouse(MouseButton::Left), Action::Grab),
+        (mouse(MouseButton::Right), Action::AddVertex),
+        (mouse(MouseButton::Middle), Action::RemoveLastVertex),
+        (key(KeyCode::KeyZ), Action::RemoveLastVertex),
+        (key(KeyCode::KeyC), Action::ClearGraph),
+        (key(KeyCode::Space), Action::TogglePause),
+        (key(KeyCode::KeyI), Action::CycleIntegrator),
+        (key(KeyCode::KeyM), Action::CycleMetric),
+        (key(KeyCode::KeyK), Action::CycleColormap),
+        (key(KeyCode::KeyN), Action::ToggleSizeByMetric),
+        (key(KeyCode::KeyG), Action::CycleCommunities),
+        (key(KeyCode::KeyH), Action::CycleCommunityBackground),
+        (key(KeyCode::KeyJ), Action::ToggleCommunityForce),
+        (key(KeyCode::KeyS), Action::SetPathSource),
+        (key(KeyCode::KeyT), Action::SetPathTarget),
+        (key(KeyCode::KeyW), Action::ToggleWeightedPaths),
+        (key(KeyCode::KeyA), Action::ToggleAllPaths),
+        (key(KeyCode::KeyD), Action::ToggleHoverDistances),
+        (key(KeyCode::KeyV), Action::CycleAnimation),
+        (key(KeyCode::KeyP), Action::ToggleAnimation),
+        (key(KeyCode::Comma), Action::StepAnimationBack),
+        (key(KeyCode::Period), Action::StepAnimationForward),
+        (key(KeyCode::BracketLeft), Action::SlowDownAnimation),
+        (key(KeyCode::BracketRight), Action::SpeedUpAnimation),
+        (key(KeyCode::KeyR), Action::RestartAnimation),
+        (key(KeyCode::KeyF), Action::PinVertexInfo),
+        (key(KeyCode::Equal), Action::IncreaseWeight),
+        (key(KeyCode::Minus), Action::DecreaseWeight),
+        (key(KeyCode::KeyB), Action::SplitEdge),
+        (key(KeyCode::Delete), Action::DeleteEdge),
+        (key(KeyCode::Backspace), Action::DeleteEdge),
+        (key(KeyCode::Tab), Action::OpenGeneratorMenu),
+        (key(KeyCode::Backquote), Action::ToggleHud),
+        (key(KeyCode::Slash), Action::ToggleHelp),
+        (key(KeyCode::KeyL), Action::CycleTheme),
+        (key(KeyCode::KeyE), Action::ToggleCurvedEdges),
+        (key(KeyCode::KeyU), Action::ToggleEdgeBundling),
+        (vec![Stroke::key(KeyCode::Equal, Modifiers { shift: true, ..none })], Action::StrengthenBundling),
+        (vec![Stroke::key(KeyCode::Minus, Modifiers { shift: true, ..none })], Action::WeakenBundling),
+        (key(KeyCode::Escape), Action::ClearSelection),
+        #[cfg(not(target_arch = "wasm32"))]
+        (key(KeyCode::F12), Action::Screenshot),
+        #[cfg(not(target_arch = "wasm32"))]
+        (vec![Stroke::key(KeyCode::F12, Modifiers { shift: true, ..none })], Action::ToggleRecording),
+    ]
+}
@@ -0,0 +1,163 @@
+use std::sync::Arc;
+
+use gscientific::{graph::{Graph, petersen_graph}, linalg::BasicMatrix};
+use instant::Duration;
+use rand::{SeedableRng, rngs::StdRng};
+
+use crate::{community::{self, CommunityAlgorithm}, metrics::VertexMetric, simulation::Simulation};
+
+/// A graph on `vertex_ct` vertices with exactly `edges`.
+pub fn graph_from_edges(vertex_ct: usize, edges: &[(usize, usize)]) -> impl Graph + use<> {
+    let mut graph = petersen_graph(5, 2);
+    graph.resize(0);
+    graph.resize(vertex_ct);
+    for (u, v) in edges {
+        graph.add_edge(*u, *v);
+    }
+    graph
+}
+
+/// What is known about a graph beyond its edges. Any part left out of a request is
+/// computed, and the rest is passed through.
+#[derive(Clone, Default)]
+pub struct Analysis {
+    pub graph_distances: Option<Arc<BasicMatrix<i32>>>,
+    /// Community of every vertex.
+    pub communities: Option<Arc<Vec<usize>>>,
+    pub modularity: f64,
+    /// The metric for every vertex.
+    pub metric_values: Option<Arc<Vec<f32>>>,
+}
+
+pub struct AnalysisRequest {
+    /// Told apart from earlier requests by this, to know which one the analysis is for.
+    pub id: u64,
+    pub vertex_ct: usize,
+    pub edges: Vec<(usize, usize)>,
+    pub community_algorithm: Option<CommunityAlgorithm>,
+    pub metric: Option<VertexMetric>,
+    /// Seeds community detection.
+    pub seed: u64,
+    /// Results that still hold for this graph.
+    pub known: Analysis,
+}
+
+impl AnalysisRequest {
+    /// Computes whatever `known` is missing. Communities are only detected with an
+    /// algorithm, and metric values only computed with a metric.
+    pub fn complete(self) -> Analysis {
+        let graph = graph_from_edges(self.vertex_ct, &self.edges);
+        let known = self.known;
+        let graph_distances = known
+            .graph_distances
+            .unwrap_or_else(|| Arc::new(graph.unweighted_all_pairs_distance()));
+
+        let (communities, modularity) = match (known.communities, self.community_algorithm) {
+            (Some(communities), _) => (Some(communities), known.modularity),
+            (None, Some(algorithm)) => {
+                let communities = algorithm.detect(&graph, &mut StdRng::seed_from_u64(self.seed));
+                let modularity = community::modularity(&graph, &communities);
+                (Some(Arc::new(communities)), modularity)
+            }
+            (None, None) => (None, 0.0),
+        };
+        let metric_values = known.metric_values.or_else(|| {
+            self.metric
+                .map(|metric| Arc::new(metric.compute(&graph, &graph_distances)))
+        });
+
+        Analysis {
+            graph_distances: Some(graph_distances),
+            communities,
+            modularity,
+            metric_values,
+        }
+    }
+}
+
+/// The analysis of the last request computed.
+#[derive(Default)]
+pub struct AnalysisSnapshot {
+    pub id: u64,
+    pub analysis: Option<Arc<Analysis>>,
+}
+
+/// Analyzes graphs as a `Simulation`, so a `SimulationRunner` computes distances,
+/// communities and metrics off the render thread. Each request is computed in a single
+/// tick, and only the newest one waiting is.
+#[derive(Default)]
+pub struct GraphAnalyzer {
+    pub request: Option<AnalysisRequest>,
+    computed: AnalysisSnapshot,
+}
+
+impl Simulation for GraphAnalyzer {
+    type Snapshot = AnalysisSnapshot;
+
+    fn tick(&mut self, _delta_t: Duration) {
+        if let Some(request) = self.request.take() {
+            self.computed = AnalysisSnapshot {
+                id: request.id,
+                analysis: Some(Arc::new(request.complete())),
+            };
+        }
+    }
+
+    fn write_snapshot(&self, snapshot: &mut AnalysisSnapshot) {
+        snapshot.id = self.computed.id;
+        snapshot.analysis = self.computed.analysis.clone();
+    }
+
+    fn is_idle(&self) -> bool {
+        self.request.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gscientific::linalg::Matrix;
+
+    use super::*;
+    use crate::test_graphs;
+
+    fn request(edges: &[(usize, usize)], vertex_ct: usize, known: Analysis) -> AnalysisRequest {
+        AnalysisRequest {
+            id: 1,
+            vertex_ct,
+            edges: edges.to_vec(),
+            community_algorithm: Some(CommunityAlgorithm::LabelPropagation),
+            metric: Some(VertexMetric::Degree),
+            seed: 3,
+            known,
+        }
+    }
+
+    #[test]
+    fn computes_what_is_missing() {
+        let edges = [(0, 1), (1, 2), (2, 3)];
+        let analysis = request(&edges, 5, Analysis::default()).complete();
+
+        let distances = analysis.graph_distances.unwrap();
+        assert_eq!(*distances.get(0, 3), 3);
+        assert_eq!(*distances.get(0, 4), -1);
+        assert_eq!(analysis.communities.unwrap().len(), 5);
+        let degrees = analysis.metric_values.unwrap();
+        assert_eq!(*degrees, [1.0, 2.0, 2.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn keeps_what_is_known() {
+        let known = Analysis {
+            graph_distances: Some(Arc::new(test_graphs::path(2).unweighted_all_pairs_distance())),
+            communities: Some(Arc::new(vec![4, 4])),
+            modularity: 0.25,
+            metric_values: Some(Arc::new(vec![7.0, 8.0])),
+        };
+        let analysis = request(&[(0, 1)], 2, known.clone()).complete();
+
+        assert!(Arc::ptr_eq(analysis.graph_distances.as_ref().unwrap(), known.graph_distances.as_ref().unwrap()));
+        assert_eq!(*analysis.communities.unwrap(), [4, 4]);
+        assert_eq!(analysis.modularity, 0.25);
+        assert_eq!(*analysis.metric_values.unwrap(), [7.0, 8.0]);
+    }
+}
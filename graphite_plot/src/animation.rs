@@ -0,0 +1,623 @@
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+use gscientific::graph::Graph;
+use instant::Duration;
+
+use crate::{edge_weights::EdgeWeights, paths::QueueEntry};
+
+const DEFAULT_STEPS_PER_SECOND: f32 = 4.0;
+const MIN_STEPS_PER_SECOND: f32 = 0.25;
+const MAX_STEPS_PER_SECOND: f32 = 256.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Algorithm {
+    BreadthFirst,
+    DepthFirst,
+    Dijkstra,
+    Prim,
+    Kruskal,
+    TopologicalSort,
+}
+
+impl Algorithm {
+    /// The algorithm after `algorithm` when cycling through them, where `None` means no
+    /// animation.
+    pub fn cycle(algorithm: Option<Algorithm>) -> Option<Algorithm> {
+        match algorithm {
+            None => Some(Algorithm::BreadthFirst),
+            Some(Algorithm::BreadthFirst) => Some(Algorithm::DepthFirst),
+            Some(Algorithm::DepthFirst) => Some(Algorithm::Dijkstra),
+            Some(Algorithm::Dijkstra) => Some(Algorithm::Prim),
+            Some(Algorithm::Prim) => Some(Algorithm::Kruskal),
+            Some(Algorithm::Kruskal) => Some(Algorithm::TopologicalSort),
+            Some(Algorithm::TopologicalSort) => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::BreadthFirst => "BFS",
+            Algorithm::DepthFirst => "DFS",
+            Algorithm::Dijkstra => "Dijkstra",
+            Algorithm::Prim => "Prim",
+            Algorithm::Kruskal => "Kruskal",
+            Algorithm::TopologicalSort => "Topological sort",
+        }
+    }
+
+    /// Runs the algorithm from `source` and records every step it takes. Kruskal and the
+    /// topological sort ignore the source. The topological sort orients every edge from its
+    /// lower to its higher vertex index.
+    pub fn record<T: Graph>(self, graph: &T, weights: &EdgeWeights, source: usize) -> Vec<AnimationStep> {
+        match self {
+            Algorithm::BreadthFirst => breadth_first(graph, source),
+            Algorithm::DepthFirst => depth_first(graph, source),
+            Algorithm::Dijkstra => dijkstra(graph, weights, source),
+            Algorithm::Prim => prim(graph, weights, source),
+            Algorithm::Kruskal => kruskal(graph, weights),
+            Algorithm::TopologicalSort => topological_sort(graph),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AnimationStep {
+    /// A vertex was found and is waiting to be visited.
+    Discover(usize),
+    Visit(usize),
+    /// A vertex and everything reachable through it is done.
+    Finish(usize),
+    Examine(usize, usize),
+    /// An edge improved the tentative distance of its second vertex.
+    Relax(usize, usize),
+    /// An edge joined the tree being built.
+    Accept(usize, usize),
+    Reject(usize, usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum VertexState {
+    #[default]
+    Unvisited,
+    Discovered,
+    Visited,
+    Finished,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EdgeState {
+    Examined,
+    Relaxed,
+    Accepted,
+    Rejected,
+}
+
+/// Playback of a recorded algorithm run. The vertex and edge states are those after the
+/// first `position` steps.
+pub struct Animation {
+    pub algorithm: Algorithm,
+    steps: Vec<AnimationStep>,
+    position: usize,
+    pub playing: bool,
+    steps_per_second: f32,
+    time_accumulator: f32,
+
+    vertex_states: Vec<VertexState>,
+    /// Edge states, stored with the smaller endpoint first.
+    edge_states: HashMap<(usize, usize), EdgeState>,
+    /// The edge or vertex changed by the latest step.
+    current: Option<AnimationStep>,
+}
+
+impl Animation {
+    pub fn new<T: Graph>(algorithm: Algorithm, graph: &T, weights: &EdgeWeights, source: usize) -> Self {
+        Self {
+            algorithm,
+            steps: algorithm.record(graph, weights, source),
+            position: 0,
+            playing: false,
+            steps_per_second: DEFAULT_STEPS_PER_SECOND,
+            time_accumulator: 0.0,
+            vertex_states: vec![VertexState::Unvisited; graph.vertex_ct()],
+            edge_states: HashMap::new(),
+            current: None,
+        }
+    }
+
+    pub fn tick(&mut self, delta_t: Duration) {
+        if !self.playing {
+            return;
+        }
+        self.time_accumulator += delta_t.as_secs_f32() * self.steps_per_second;
+        while self.time_accumulator >= 1.0 {
+            self.time_accumulator -= 1.0;
+            if !self.step_forward() {
+                self.playing = false;
+                self.time_accumulator = 0.0;
+                break;
+            }
+        }
+    }
+
+    /// Applies the next step. Returns false once every step has been applied.
+    pub fn step_forward(&mut self) -> bool {
+        let Some(step) = self.steps.get(self.position).copied() else {
+            return false;
+        };
+        self.apply(step);
+        self.position += 1;
+        true
+    }
+
+    /// Undoes the latest step by replaying everything before it.
+    pub fn step_back(&mut self) {
+        let target = self.position.saturating_sub(1);
+        self.restart();
+        while self.position < target {
+            self.step_forward();
+        }
+    }
+
+    pub fn restart(&mut self) {
+        self.vertex_states.fill(VertexState::Unvisited);
+        self.edge_states.clear();
+        self.current = None;
+        self.position = 0;
+        self.time_accumulator = 0.0;
+    }
+
+    pub fn speed_up(&mut self) {
+        self.steps_per_second = (self.steps_per_second * 2.0).min(MAX_STEPS_PER_SECOND);
+    }
+
+    pub fn slow_down(&mut self) {
+        self.steps_per_second = (self.steps_per_second / 2.0).max(MIN_STEPS_PER_SECOND);
+    }
+
+    fn apply(&mut self, step: AnimationStep) {
+        let mut set_vertex = |u: usize, state: VertexState| {
+            if let Some(vertex_state) = self.vertex_states.get_mut(u) {
+                *vertex_state = state;
+            }
+        };
+        match step {
+            AnimationStep::Discover(u) => set_vertex(u, VertexState::Discovered),
+            AnimationStep::Visit(u) => set_vertex(u, VertexState::Visited),
+            AnimationStep::Finish(u) => set_vertex(u, VertexState::Finished),
+            AnimationStep::Examine(u, v) => self.set_edge(u, v, EdgeState::Examined),
+            AnimationStep::Relax(u, v) => self.set_edge(u, v, EdgeState::Relaxed),
+            AnimationStep::Accept(u, v) => self.set_edge(u, v, EdgeState::Accepted),
+            AnimationStep::Reject(u, v) => self.set_edge(u, v, EdgeState::Rejected),
+        }
+        self.current = Some(step);
+    }
+
+    fn set_edge(&mut self, u: usize, v: usize, state: EdgeState) {
+        // Relaxing a tree edge again doesn't take it out of the tree.
+        let key = (u.min(v), u.max(v));
+        if self.edge_states.get(&key) != Some(&EdgeState::Accepted) {
+            self.edge_states.insert(key, state);
+        }
+    }
+
+    pub fn vertex_state(&self, u: usize) -> VertexState {
+        self.vertex_states.get(u).copied().unwrap_or_default()
+    }
+
+    pub fn edge_state(&self, u: usize, v: usize) -> Option<EdgeState> {
+        self.edge_states.get(&(u.min(v), u.max(v))).copied()
+    }
+
+    /// Whether `u` was touched by the latest step.
+    pub fn is_current_vertex(&self, u: usize) -> bool {
+        matches!(
+            self.current,
+            Some(AnimationStep::Discover(w) | AnimationStep::Visit(w) | AnimationStep::Finish(w)) if w == u
+        )
+    }
+
+    /// Whether the edge between `u` and `v` was touched by the latest step.
+    pub fn is_current_edge(&self, u: usize, v: usize) -> bool {
+        match self.current {
+            Some(
+                AnimationStep::Examine(a, b)
+                | AnimationStep::Relax(a, b)
+                | AnimationStep::Accept(a, b)
+                | AnimationStep::Reject(a, b),
+            ) => (a.min(b), a.max(b)) == (u.min(v), u.max(v)),
+            _ => false,
+        }
+    }
+
+    /// One line describing playback, for the overlay.
+    pub fn describe(&self) -> String {
+        let step = match self.current {
+            Some(step) => format!("{:?}", step),
+            None => "start".to_string(),
+        };
+        format!(
+            "{}: step {}/{} ({}), {} steps/s{}",
+            self.algorithm.name(),
+            self.position,
+            self.steps.len(),
+            step,
+            self.steps_per_second,
+            if self.playing { "" } else { ", paused" }
+        )
+    }
+}
+
+fn breadth_first<T: Graph>(graph: &T, source: usize) -> Vec<AnimationStep> {
+    let mut steps = vec![AnimationStep::Discover(source)];
+    let mut discovered = vec![false; graph.vertex_ct()];
+    let mut queue = VecDeque::from([source]);
+    discovered[source] = true;
+
+    while let Some(u) = queue.pop_front() {
+        steps.push(AnimationStep::Visit(u));
+        for (v, _) in graph.neighbors(u) {
+            steps.push(AnimationStep::Examine(u, v));
+            if !discovered[v] {
+                discovered[v] = true;
+                steps.push(AnimationStep::Accept(u, v));
+                steps.push(AnimationStep::Discover(v));
+                queue.push_back(v);
+            }
+        }
+        steps.push(AnimationStep::Finish(u));
+    }
+
+    steps
+}
+
+fn depth_first<T: Graph>(graph: &T, source: usize) -> Vec<AnimationStep> {
+    let mut steps = vec![AnimationStep::Visit(source)];
+    let mut visited = vec![false; graph.vertex_ct()];
+    // Each frame holds a vertex and the neighbors it still has to try.
+    let mut stack = vec![(source, graph.neighbors(source).map(|(v, _)| v).collect::<Vec<_>>())];
+    visited[source] = true;
+
+    while let Some((u, remaining)) = stack.last_mut() {
+        let u = *u;
+        match remaining.pop() {
+            Some(v) => {
+                steps.push(AnimationStep::Examine(u, v));
+                if !visited[v] {
+                    visited[v] = true;
+                    steps.push(AnimationStep::Accept(u, v));
+                    steps.push(AnimationStep::Visit(v));
+                    let mut neighbors = graph.neighbors(v).map(|(w, _)| w).collect::<Vec<_>>();
+                    // Popping from the back should try neighbors in their natural order.
+                    neighbors.reverse();
+                    stack.push((v, neighbors));
+                }
+            }
+            None => {
+                steps.push(AnimationStep::Finish(u));
+                stack.pop();
+            }
+        }
+    }
+
+    steps
+}
+
+fn dijkstra<T: Graph>(graph: &T, weights: &EdgeWeights, source: usize) -> Vec<AnimationStep> {
+    let n = graph.vertex_ct();
+    let mut steps = vec![AnimationStep::Discover(source)];
+    let mut distance = vec![f64::INFINITY; n];
+    let mut parent = vec![None; n];
+    let mut done = vec![false; n];
+    let mut queue = BinaryHeap::from([QueueEntry {
+        distance: 0.0,
+        vertex: source,
+    }]);
+    distance[source] = 0.0;
+
+    while let Some(QueueEntry { distance: d, vertex: u }) = queue.pop() {
+        if done[u] || d > distance[u] {
+            continue;
+        }
+        done[u] = true;
+        if let Some(p) = parent[u] {
+            steps.push(AnimationStep::Accept(p, u));
+        }
+        steps.push(AnimationStep::Visit(u));
+
+        for (v, weight) in weights.neighbors(graph, u) {
+            if done[v] {
+                continue;
+            }
+            steps.push(AnimationStep::Examine(u, v));
+            let candidate = d + weight.max(0.0);
+            if candidate < distance[v] {
+                distance[v] = candidate;
+                parent[v] = Some(u);
+                steps.push(AnimationStep::Relax(u, v));
+                steps.push(AnimationStep::Discover(v));
+                queue.push(QueueEntry {
+                    distance: candidate,
+                    vertex: v,
+                });
+            }
+        }
+        steps.push(AnimationStep::Finish(u));
+    }
+
+    steps
+}
+
+fn prim<T: Graph>(graph: &T, weights: &EdgeWeights, source: usize) -> Vec<AnimationStep> {
+    let n = graph.vertex_ct();
+    let mut steps = vec![AnimationStep::Discover(source)];
+    let mut best = vec![f64::INFINITY; n];
+    let mut parent = vec![None; n];
+    let mut in_tree = vec![false; n];
+    let mut queue = BinaryHeap::from([QueueEntry {
+        distance: 0.0,
+        vertex: source,
+    }]);
+    best[source] = 0.0;
+
+    while let Some(QueueEntry { distance: d, vertex: u }) = queue.pop() {
+        if in_tree[u] || d > best[u] {
+            continue;
+        }
+        in_tree[u] = true;
+        if let Some(p) = parent[u] {
+            steps.push(AnimationStep::Accept(p, u));
+        }
+        steps.push(AnimationStep::Visit(u));
+
+        for (v, weight) in weights.neighbors(graph, u) {
+            if in_tree[v] {
+                continue;
+            }
+            steps.push(AnimationStep::Examine(u, v));
+            if weight < best[v] {
+                best[v] = weight;
+                parent[v] = Some(u);
+                steps.push(AnimationStep::Relax(u, v));
+                steps.push(AnimationStep::Discover(v));
+                queue.push(QueueEntry {
+                    distance: weight,
+                    vertex: v,
+                });
+            }
+        }
+        steps.push(AnimationStep::Finish(u));
+    }
+
+    steps
+}
+
+fn kruskal<T: Graph>(graph: &T, weights: &EdgeWeights) -> Vec<AnimationStep> {
+    let mut steps = Vec::new();
+    let mut edges = weights.edges(graph).filter(|(u, v, _)| u != v).collect::<Vec<_>>();
+    edges.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+    // Union-find with path halving.
+    let mut parent = (0..graph.vertex_ct()).collect::<Vec<_>>();
+    let find = |parent: &mut Vec<usize>, mut u: usize| {
+        while parent[u] != u {
+            parent[u] = parent[parent[u]];
+            u = parent[u];
+        }
+        u
+    };
+
+    for (u, v, _) in edges {
+        steps.push(AnimationStep::Examine(u, v));
+        let (root_u, root_v) = (find(&mut parent, u), find(&mut parent, v));
+        if root_u == root_v {
+            steps.push(AnimationStep::Reject(u, v));
+        } else {
+            parent[root_u] = root_v;
+            steps.push(AnimationStep::Accept(u, v));
+            steps.push(AnimationStep::Visit(u));
+            steps.push(AnimationStep::Visit(v));
+        }
+    }
+
+    steps
+}
+
+/// Kahn's algorithm, with every edge pointing from its lower to its higher vertex.
+fn topological_sort<T: Graph>(graph: &T) -> Vec<AnimationStep> {
+    let n = graph.vertex_ct();
+    let mut steps = Vec::new();
+    let mut in_degree = (0..n)
+        .map(|v| graph.neighbors(v).filter(|(u, _)| *u < v).count())
+        .collect::<Vec<_>>();
+    let mut queue = (0..n).filter(|v| in_degree[*v] == 0).collect::<VecDeque<_>>();
+    for &v in &queue {
+        steps.push(AnimationStep::Discover(v));
+    }
+
+    while let Some(u) = queue.pop_front() {
+        steps.push(AnimationStep::Visit(u));
+        for (v, _) in graph.neighbors(u).filter(|(v, _)| *v > u) {
+            steps.push(AnimationStep::Accept(u, v));
+            in_degree[v] -= 1;
+            if in_degree[v] == 0 {
+                steps.push(AnimationStep::Discover(v));
+                queue.push_back(v);
+            }
+        }
+        steps.push(AnimationStep::Finish(u));
+    }
+
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnimationStep::*, *};
+    use crate::test_graphs;
+
+    /// 0 - 1 - 2 with weights 1 and a heavier chord 0 - 2.
+    fn triangle() -> (impl Graph, EdgeWeights) {
+        let graph = test_graphs::graph(3, &[(0, 1), (1, 2), (0, 2)]);
+        let mut weights = EdgeWeights::default();
+        for (u, v, weight) in [(0, 1, 1.0), (1, 2, 1.0), (0, 2, 5.0)] {
+            weights.set(u, v, weight);
+        }
+        (graph, weights)
+    }
+
+    fn accepted(steps: &[AnimationStep]) -> Vec<(usize, usize)> {
+        steps
+            .iter()
+            .filter_map(|step| match step {
+                Accept(u, v) => Some((*u.min(v), *u.max(v))),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn breadth_first_order() {
+        let steps = Algorithm::BreadthFirst.record(&test_graphs::path(3), &EdgeWeights::default(), 0);
+        assert_eq!(
+            steps,
+            [
+                Discover(0),
+                Visit(0),
+                Examine(0, 1),
+                Accept(0, 1),
+                Discover(1),
+                Finish(0),
+                Visit(1),
+                Examine(1, 0),
+                Examine(1, 2),
+                Accept(1, 2),
+                Discover(2),
+                Finish(1),
+                Visit(2),
+                Examine(2, 1),
+                Finish(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn depth_first_order() {
+        let steps = Algorithm::DepthFirst.record(&test_graphs::path(3), &EdgeWeights::default(), 0);
+        assert_eq!(
+            steps,
+            [
+                Visit(0),
+                Examine(0, 1),
+                Accept(0, 1),
+                Visit(1),
+                Examine(1, 0),
+                Examine(1, 2),
+                Accept(1, 2),
+                Visit(2),
+                Examine(2, 1),
+                Finish(2),
+                Finish(1),
+                Finish(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn dijkstra_order() {
+        let (graph, weights) = triangle();
+        let steps = Algorithm::Dijkstra.record(&graph, &weights, 0);
+        assert_eq!(
+            steps,
+            [
+                Discover(0),
+                Visit(0),
+                Examine(0, 1),
+                Relax(0, 1),
+                Discover(1),
+                Examine(0, 2),
+                Relax(0, 2),
+                Discover(2),
+                Finish(0),
+                Accept(0, 1),
+                Visit(1),
+                Examine(1, 2),
+                Relax(1, 2),
+                Discover(2),
+                Finish(1),
+                Accept(1, 2),
+                Visit(2),
+                Finish(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn spanning_trees() {
+        let (graph, weights) = triangle();
+        let prim = Algorithm::Prim.record(&graph, &weights, 2);
+        assert_eq!(accepted(&prim), [(1, 2), (0, 1)]);
+
+        let kruskal = Algorithm::Kruskal.record(&graph, &weights, 0);
+        let mut tree = accepted(&kruskal);
+        tree.sort();
+        assert_eq!(tree, [(0, 1), (1, 2)]);
+        // The heaviest edge comes last and would close a cycle.
+        assert_eq!(kruskal[kruskal.len() - 2..], [Examine(0, 2), Reject(0, 2)]);
+    }
+
+    #[test]
+    fn topological_order() {
+        let graph = test_graphs::graph(4, &[(0, 2), (1, 2), (2, 3)]);
+        let steps = Algorithm::TopologicalSort.record(&graph, &EdgeWeights::default(), 0);
+        assert_eq!(
+            steps,
+            [
+                Discover(0),
+                Discover(1),
+                Visit(0),
+                Accept(0, 2),
+                Finish(0),
+                Visit(1),
+                Accept(1, 2),
+                Discover(2),
+                Finish(1),
+                Visit(2),
+                Accept(2, 3),
+                Discover(3),
+                Finish(2),
+                Visit(3),
+                Finish(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn playback() {
+        let mut animation = Animation::new(Algorithm::BreadthFirst, &test_graphs::path(3), &EdgeWeights::default(), 0);
+        animation.tick(Duration::from_secs(1));
+        assert_eq!(animation.vertex_state(0), VertexState::Unvisited);
+
+        // One second at the default 4 steps per second.
+        animation.playing = true;
+        animation.tick(Duration::from_secs(1));
+        assert_eq!(animation.vertex_state(0), VertexState::Visited);
+        assert_eq!(animation.vertex_state(1), VertexState::Unvisited);
+        assert_eq!(animation.edge_state(1, 0), Some(EdgeState::Accepted));
+        assert!(animation.is_current_edge(1, 0));
+
+        animation.step_back();
+        assert_eq!(animation.edge_state(0, 1), Some(EdgeState::Examined));
+        assert!(animation.is_current_edge(0, 1));
+
+        // Playback stops at the end, and examining a tree edge again keeps it in the tree.
+        animation.tick(Duration::from_secs(10));
+        assert!(!animation.playing);
+        assert!((0..3).all(|u| animation.vertex_state(u) == VertexState::Finished));
+        assert_eq!(animation.edge_state(0, 1), Some(EdgeState::Accepted));
+        assert!(animation.describe().starts_with("BFS: step 15/15"));
+
+        animation.restart();
+        assert_eq!(animation.vertex_state(0), VertexState::Unvisited);
+        assert_eq!(animation.edge_state(0, 1), None);
+    }
+}
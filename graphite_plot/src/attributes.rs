@@ -0,0 +1,75 @@
+use std::{fs, io, path::Path};
+
+/// Per-vertex values read from a file, shown in the vertex info panel. Row `i` belongs to
+/// vertex `i`.
+#[derive(Clone, Debug, Default)]
+pub struct VertexAttributes {
+    names: Vec<String>,
+    rows: Vec<Vec<String>>,
+    /// Column holding vertex labels, if there is one named "label".
+    label_column: Option<usize>,
+}
+
+impl VertexAttributes {
+    pub fn label(&self, v: usize) -> Option<&str> {
+        let column = self.label_column?;
+        self.rows.get(v)?.get(column).map(String::as_str).filter(|label| !label.is_empty())
+    }
+
+    /// The value of `v` in the column called `name`, ignoring case, if it has one.
+    pub fn value(&self, v: usize, name: &str) -> Option<&str> {
+        let column = self.names.iter().position(|column| column.eq_ignore_ascii_case(name))?;
+        self.rows.get(v)?.get(column).map(String::as_str).filter(|value| !value.is_empty())
+    }
+
+    /// Every attribute of `v` except its label, as name and value.
+    pub fn get(&self, v: usize) -> impl Iterator<Item = (&str, &str)> {
+        let row = self.rows.get(v).map(Vec::as_slice).unwrap_or_default();
+        self.names
+            .iter()
+            .zip(row)
+            .enumerate()
+            .filter(|(column, _)| Some(*column) != self.label_column)
+            .map(|(_, (name, value))| (name.as_str(), value.as_str()))
+    }
+
+    /// Drops the row of `v`, keeping the rest matched with the vertices after it as
+    /// `Graph::remove_vertex` renumbers them.
+    pub fn remove_vertex(&mut self, v: usize) {
+        if v < self.rows.len() {
+            self.rows.remove(v);
+        }
+    }
+
+    pub fn truncate(&mut self, n: usize) {
+        self.rows.truncate(n);
+    }
+
+    pub fn clear(&mut self) {
+        self.rows.clear();
+    }
+}
+
+/// Reads comma separated attributes with a header line naming the columns. Blank lines and
+/// lines starting with `#` are skipped.
+pub fn read_attributes(path: impl AsRef<Path>) -> io::Result<VertexAttributes> {
+    let contents = fs::read_to_string(path)?;
+    let mut lines = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    let split = |line: &str| line.split(',').map(|field| field.trim().to_string()).collect::<Vec<_>>();
+
+    let names = lines
+        .next()
+        .map(split)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing header line"))?;
+    let rows = lines.map(split).collect();
+    let label_column = names.iter().position(|name| name.eq_ignore_ascii_case("label"));
+
+    Ok(VertexAttributes {
+        names,
+        rows,
+        label_column,
+    })
+}
@@ -0,0 +1,460 @@
+use std::{fmt, fs, io, path::Path};
+
+use winit::{
+    event::MouseButton,
+    keyboard::{KeyCode, ModifiersState},
+};
+
+use crate::actions::{ACTIONS, Action, default_bindings};
+
+/// Width of the key column in the help sheet.
+const HELP_KEY_COLUMN: usize = 11;
+
+/// Modifier keys held during a stroke.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    /// The Windows, Command or Super key.
+    pub logo: bool,
+}
+
+impl From<ModifiersState> for Modifiers {
+    fn from(state: ModifiersState) -> Self {
+        Self {
+            shift: state.shift_key(),
+            ctrl: state.control_key(),
+            alt: state.alt_key(),
+            logo: state.super_key(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Input {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+/// A key or mouse button pressed while exactly `modifiers` are held.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Stroke {
+    pub modifiers: Modifiers,
+    pub input: Input,
+}
+
+impl Stroke {
+    pub fn key(key: KeyCode, modifiers: impl Into<Modifiers>) -> Self {
+        Self {
+            modifiers: modifiers.into(),
+            input: Input::Key(key),
+        }
+    }
+
+    pub fn mouse(button: MouseButton, modifiers: impl Into<Modifiers>) -> Self {
+        Self {
+            modifiers: modifiers.into(),
+            input: Input::Mouse(button),
+        }
+    }
+
+    /// Parses strokes like `Space`, `Ctrl+Shift+Z` or `Alt+Left`.
+    fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.split('+').map(str::trim).collect::<Vec<_>>();
+        let input = parse_input(parts.pop()?)?;
+        let mut modifiers = Modifiers::default();
+        for part in parts {
+            match part.to_ascii_lowercase().as_str() {
+                "shift" => modifiers.shift = true,
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "alt" | "option" => modifiers.alt = true,
+                "logo" | "super" | "cmd" | "win" => modifiers.logo = true,
+                _ => return None,
+            }
+        }
+
+        Some(Self { modifiers, input })
+    }
+}
+
+impl fmt::Display for Stroke {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [
+            (self.modifiers.ctrl, "Ctrl"),
+            (self.modifiers.alt, "Alt"),
+            (self.modifiers.shift, "Shift"),
+            (self.modifiers.logo, "Logo"),
+        ] {
+            if held {
+                write!(f, "{}+", name)?;
+            }
+        }
+        match self.input {
+            Input::Key(key) => write!(f, "{}", key_name(key)),
+            Input::Mouse(button) => write!(f, "{}", mouse_name(button)),
+        }
+    }
+}
+
+/// Which action each key, mouse button or chord triggers. A chord is a sequence of strokes,
+/// written with spaces between them, e.g. `Q G`.
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    bindings: Vec<(Vec<Stroke>, Action)>,
+    /// Strokes of a chord typed so far.
+    pending: Vec<Stroke>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            bindings: default_bindings(),
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl Bindings {
+    /// Feeds one pressed key or button and returns the action it completes, if any.
+    /// A stroke that continues no chord starts over as the first stroke of a new one.
+    /// Bindings match as soon as they are complete, so a chord starting with a stroke that
+    /// is bound on its own can never be typed.
+    pub fn press(&mut self, stroke: Stroke) -> Option<Action> {
+        if let Input::Key(key) = stroke.input
+            && is_modifier(key)
+        {
+            return None;
+        }
+
+        self.pending.push(stroke);
+        loop {
+            if let Some((_, action)) = self.bindings.iter().find(|(strokes, _)| *strokes == self.pending) {
+                self.pending.clear();
+                return Some(*action);
+            }
+            if self.bindings.iter().any(|(strokes, _)| strokes.starts_with(&self.pending)) {
+                return None;
+            }
+            if self.pending.len() == 1 {
+                self.pending.clear();
+                return None;
+            }
+            self.pending = vec![stroke];
+        }
+    }
+
+    /// The key sheet, one action per line with everything bound to it.
+    pub fn help(&self) -> String {
+        let mut lines = Vec::new();
+        for (action, _, description) in ACTIONS {
+            let strokes = self
+                .bindings
+                .iter()
+                .filter(|(_, bound)| bound == action)
+                .map(|(strokes, _)| strokes.iter().map(Stroke::to_string).collect::<Vec<_>>().join(" "))
+                .collect::<Vec<_>>();
+            if !strokes.is_empty() {
+                lines.push(format!("{:<width$}{}", strokes.join("/"), description, width = HELP_KEY_COLUMN));
+            }
+        }
+        lines.push(format!("{:<width$}{}", "Wheel", "zoom", width = HELP_KEY_COLUMN));
+
+        lines.join("\n")
+    }
+
+    /// Action bound to `input` as a single stroke, whatever the modifiers. For actions that
+    /// last while the input is held, whose release has to match even if the modifiers
+    /// changed in between.
+    // Only the 3D camera has actions that last while held.
+    #[allow(dead_code)]
+    pub fn held(&self, input: Input) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(strokes, _)| strokes.len() == 1 && strokes[0].input == input)
+            .map(|(_, action)| *action)
+    }
+
+    /// Replaces everything bound to `action`. Nothing is bound to it when `bindings` is
+    /// empty.
+    pub fn rebind(&mut self, action: Action, bindings: Vec<Vec<Stroke>>) {
+        self.bindings.retain(|(_, bound)| *bound != action);
+        self.bindings.extend(bindings.into_iter().map(|strokes| (strokes, action)));
+        self.pending.clear();
+    }
+}
+
+/// Reads overrides of the default bindings, one action per line:
+///
+/// ```text
+/// cycle_integrator = I, Ctrl+I
+/// open_generator_menu = Q G
+/// grab = Shift+MouseLeft
+/// cycle_theme =
+/// ```
+///
+/// Each line replaces everything bound to its action; an empty right side unbinds it.
+/// Blank lines and lines starting with `#` are skipped.
+pub fn read_bindings(path: impl AsRef<Path>) -> io::Result<Bindings> {
+    let contents = fs::read_to_string(path)?;
+    let mut bindings = Bindings::default();
+    let invalid = |line: usize, message: String| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line + 1, message))
+    };
+
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (name, strokes) = line
+            .split_once('=')
+            .ok_or_else(|| invalid(i, "expected <action> = <keys>".to_string()))?;
+        let action = Action::parse(name.trim())
+            .ok_or_else(|| invalid(i, format!("unknown action \"{}\"", name.trim())))?;
+        let chords = strokes
+            .split(',')
+            .map(str::trim)
+            .filter(|chord| !chord.is_empty())
+            .map(|chord| {
+                chord
+                    .split_whitespace()
+                    .map(|stroke| Stroke::parse(stroke).ok_or_else(|| invalid(i, format!("unknown key \"{}\"", stroke))))
+                    .collect::<io::Result<Vec<_>>>()
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        bindings.rebind(action, chords);
+    }
+
+    Ok(bindings)
+}
+
+fn is_modifier(key: KeyCode) -> bool {
+    matches!(
+        key,
+        KeyCode::ShiftLeft
+            | KeyCode::ShiftRight
+            | KeyCode::ControlLeft
+            | KeyCode::ControlRight
+            | KeyCode::AltLeft
+            | KeyCode::AltRight
+            | KeyCode::SuperLeft
+            | KeyCode::SuperRight
+    )
+}
+
+/// Keys that aren't a letter, digit or function key, by the name bindings files use.
+const NAMED_KEYS: &[(&str, KeyCode)] = &[
+    ("Space", KeyCode::Space),
+    ("Tab", KeyCode::Tab),
+    ("Enter", KeyCode::Enter),
+    ("Escape", KeyCode::Escape),
+    ("Backspace", KeyCode::Backspace),
+    ("Delete", KeyCode::Delete),
+    ("Insert", KeyCode::Insert),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Up", KeyCode::ArrowUp),
+    ("Down", KeyCode::ArrowDown),
+    ("Left", KeyCode::ArrowLeft),
+    ("Right", KeyCode::ArrowRight),
+    ("`", KeyCode::Backquote),
+    ("-", KeyCode::Minus),
+    ("=", KeyCode::Equal),
+    ("[", KeyCode::BracketLeft),
+    ("]", KeyCode::BracketRight),
+    ("\\", KeyCode::Backslash),
+    (";", KeyCode::Semicolon),
+    ("'", KeyCode::Quote),
+    (",", KeyCode::Comma),
+    (".", KeyCode::Period),
+    ("/", KeyCode::Slash),
+];
+
+const LETTER_KEYS: [KeyCode; 26] = [
+    KeyCode::KeyA, KeyCode::KeyB, KeyCode::KeyC, KeyCode::KeyD, KeyCode::KeyE, KeyCode::KeyF,
+    KeyCode::KeyG, KeyCode::KeyH, KeyCode::KeyI, KeyCode::KeyJ, KeyCode::KeyK, KeyCode::KeyL,
+    KeyCode::KeyM, KeyCode::KeyN, KeyCode::KeyO, KeyCode::KeyP, KeyCode::KeyQ, KeyCode::KeyR,
+    KeyCode::KeyS, KeyCode::KeyT, KeyCode::KeyU, KeyCode::KeyV, KeyCode::KeyW, KeyCode::KeyX,
+    KeyCode::KeyY, KeyCode::KeyZ,
+];
+
+const DIGIT_KEYS: [KeyCode; 10] = [
+    KeyCode::Digit0, KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+    KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+];
+
+const FUNCTION_KEYS: [KeyCode; 12] = [
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+    KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+];
+
+const MOUSE_BUTTONS: &[(&str, MouseButton)] = &[
+    ("MouseLeft", MouseButton::Left),
+    ("MouseRight", MouseButton::Right),
+    ("MouseMiddle", MouseButton::Middle),
+    ("MouseBack", MouseButton::Back),
+    ("MouseForward", MouseButton::Forward),
+];
+
+/// Letters and digits are written as themselves, function keys as `F1` to `F12` and mouse
+/// buttons as `MouseLeft`, `MouseRight` and so on. Names ignore case.
+fn parse_input(name: &str) -> Option<Input> {
+    let upper = name.to_ascii_uppercase();
+    let mut chars = upper.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_uppercase() {
+            return Some(Input::Key(LETTER_KEYS[(c as u8 - b'A') as usize]));
+        }
+        if c.is_ascii_digit() {
+            return Some(Input::Key(DIGIT_KEYS[(c as u8 - b'0') as usize]));
+        }
+    }
+    if let Some(n) = upper.strip_prefix('F').and_then(|n| n.parse::<usize>().ok()) {
+        return FUNCTION_KEYS.get(n.wrapping_sub(1)).map(|key| Input::Key(*key));
+    }
+
+    NAMED_KEYS
+        .iter()
+        .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+        .map(|(_, key)| Input::Key(*key))
+        .or_else(|| {
+            MOUSE_BUTTONS
+                .iter()
+                .find(|(button_name, _)| button_name.eq_ignore_ascii_case(name))
+                .map(|(_, button)| Input::Mouse(*button))
+        })
+}
+
+fn key_name(key: KeyCode) -> String {
+    if let Some(i) = LETTER_KEYS.iter().position(|letter| *letter == key) {
+        return ((b'A' + i as u8) as char).to_string();
+    }
+    if let Some(i) = DIGIT_KEYS.iter().position(|digit| *digit == key) {
+        return i.to_string();
+    }
+    if let Some(i) = FUNCTION_KEYS.iter().position(|function| *function == key) {
+        return format!("F{}", i + 1);
+    }
+
+    NAMED_KEYS
+        .iter()
+        .find(|(_, named)| *named == key)
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_else(|| format!("{:?}", key))
+}
+
+fn mouse_name(button: MouseButton) -> String {
+    MOUSE_BUTTONS
+        .iter()
+        .find(|(_, named)| *named == button)
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_else(|| format!("{:?}", button))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press_key(bindings: &mut Bindings, key: KeyCode, modifiers: Modifiers) -> Option<Action> {
+        bindings.press(Stroke::key(key, modifiers))
+    }
+
+    /// Reads `contents` as a bindings file. `name` keeps tests running at once apart.
+    fn read(name: &str, contents: &str) -> io::Result<Bindings> {
+        let path = std::env::temp_dir().join(format!("graphite_plot_bindings_{}_{}.txt", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        let bindings = read_bindings(&path);
+        fs::remove_file(&path).unwrap();
+        bindings
+    }
+
+    #[test]
+    fn chords_complete_and_start_over() {
+        let none = Modifiers::default();
+        let mut bindings = Bindings::default();
+        let chord = vec![Stroke::key(KeyCode::KeyO, none), Stroke::key(KeyCode::KeyX, none)];
+        bindings.rebind(Action::OpenGeneratorMenu, vec![chord]);
+
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyO, none), None);
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyX, none), Some(Action::OpenGeneratorMenu));
+
+        // A second O starts the chord over instead of failing it.
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyO, none), None);
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyO, none), None);
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyX, none), Some(Action::OpenGeneratorMenu));
+
+        // A stroke bound on its own breaks the chord and still triggers.
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyO, none), None);
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyI, none), Some(Action::CycleIntegrator));
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyX, none), None);
+
+        // Modifier keys pressed on their own leave a chord alone.
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyO, none), None);
+        assert_eq!(press_key(&mut bindings, KeyCode::ShiftLeft, none), None);
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyX, none), Some(Action::OpenGeneratorMenu));
+    }
+
+    #[test]
+    fn modifiers_match_exactly() {
+        let none = Modifiers::default();
+        let shift = Modifiers { shift: true, ..none };
+        let mut bindings = Bindings::default();
+        bindings.rebind(Action::CycleTheme, vec![vec![Stroke::key(KeyCode::KeyL, shift)]]);
+
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyI, none), Some(Action::CycleIntegrator));
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyI, shift), None);
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyL, shift), Some(Action::CycleTheme));
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyL, none), None);
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyL, Modifiers { ctrl: true, ..shift }), None);
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyL, Modifiers { logo: true, ..none }), None);
+        assert_eq!(bindings.held(Input::Key(KeyCode::KeyL)), Some(Action::CycleTheme));
+    }
+
+    #[test]
+    fn parses_strokes() {
+        let ctrl_shift = Modifiers { shift: true, ctrl: true, ..Modifiers::default() };
+        let stroke = Stroke::parse("Ctrl+Shift+Z").unwrap();
+        assert_eq!(stroke, Stroke::key(KeyCode::KeyZ, ctrl_shift));
+        assert_eq!(stroke.to_string(), "Ctrl+Shift+Z");
+        assert_eq!(Stroke::parse("shift + control + z"), Some(stroke));
+
+        assert_eq!(Stroke::parse("Alt+Left"), Some(Stroke::key(KeyCode::ArrowLeft, Modifiers { alt: true, ..Modifiers::default() })));
+        assert_eq!(Stroke::parse("f12"), Some(Stroke::key(KeyCode::F12, Modifiers::default())));
+        assert_eq!(Stroke::parse("MouseRight"), Some(Stroke::mouse(MouseButton::Right, Modifiers::default())));
+        assert_eq!(Stroke::parse("Hyper+Z"), None);
+        assert_eq!(Stroke::parse("Ctrl+"), None);
+        assert_eq!(Stroke::parse("F13"), None);
+        assert_eq!(Stroke::parse("F0"), None);
+    }
+
+    #[test]
+    fn reads_overrides() {
+        let contents = "# comment\n\ncycle_integrator = Q, Ctrl+I\ncycle_theme =\ngrab = O X\n";
+        let mut bindings = read("overrides", contents).unwrap();
+        let none = Modifiers::default();
+
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyI, none), None);
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyQ, none), Some(Action::CycleIntegrator));
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyI, Modifiers { ctrl: true, ..none }), Some(Action::CycleIntegrator));
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyL, none), None);
+        assert_eq!(bindings.press(Stroke::mouse(MouseButton::Left, none)), None);
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyO, none), None);
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyX, none), Some(Action::Grab));
+        // Everything not mentioned keeps its default.
+        assert_eq!(press_key(&mut bindings, KeyCode::KeyE, none), Some(Action::ToggleCurvedEdges));
+    }
+
+    #[test]
+    fn rejects_unknown_actions_and_keys() {
+        let error = |name, contents| read(name, contents).unwrap_err().to_string();
+
+        assert_eq!(error("action", "\nfly = Space\n"), "line 2: unknown action \"fly\"");
+        assert_eq!(error("key", "cycle_theme = L, Ctrl+Nope\n"), "line 1: unknown key \"Ctrl+Nope\"");
+        assert_eq!(error("modifier", "cycle_theme = Hyper+L\n"), "line 1: unknown key \"Hyper+L\"");
+        assert_eq!(error("separator", "cycle_theme L\n"), "line 1: expected <action> = <keys>");
+    }
+}
@@ -0,0 +1,342 @@
+use std::{collections::HashMap, sync::Arc};
+
+use cgmath::{InnerSpace, Vector2, Zero};
+use instant::Duration;
+
+use crate::simulation::Simulation;
+
+/// Rounds of subdividing every edge and letting the edges attract each other.
+const CYCLE_CT: usize = 5;
+/// Iterations in the first cycle. Each later cycle runs two thirds as many.
+const FIRST_ITERATION_CT: f32 = 40.0;
+/// How far a point moves towards the edges it's attracted to per iteration of the first
+/// cycle, as a fraction of its edge's length. Halves every cycle.
+const FIRST_STEP: f32 = 0.02;
+/// How far each point moves back towards its neighbors along the edge per iteration.
+const SPRING_CONSTANT: f32 = 0.1;
+/// Edges less compatible than this don't attract each other.
+const COMPATIBILITY_THRESHOLD: f32 = 0.6;
+/// Most edges one edge is attracted to, keeping the most compatible.
+const MAX_COMPATIBLE_CT: usize = 32;
+/// Farthest apart the middles of two compatible edges can be, in lengths of the longer edge.
+/// Every factor of the compatibility is at most 1, so the position factor alone has to reach
+/// the threshold, which it can't from any farther.
+const MAX_MIDDLE_DISTANCE: f32 = 1.0 / COMPATIBILITY_THRESHOLD - 1.0;
+
+/// Edges bent towards similar edges near them, so edges running the same way merge into
+/// bundles. Uses force-directed edge bundling (Holten and van Wijk, 2009).
+///
+/// The bends are kept relative to each edge's endpoints, so the bundles follow the layout
+/// as it moves until they are computed again.
+#[derive(Clone, Debug, Default)]
+pub struct EdgeBundles {
+    /// Interior points of each edge going from its lower endpoint to its higher. Each point
+    /// is stored as how far along and across the edge it is, in edge lengths.
+    paths: HashMap<(usize, usize), Vec<Vector2<f32>>>,
+}
+
+/// An edge another edge is attracted to.
+struct Compatible {
+    edge: usize,
+    compatibility: f32,
+    /// Whether the edges run opposite ways, so their points pair up from opposite ends.
+    reversed: bool,
+}
+
+impl EdgeBundles {
+    /// Bundles `edges` as laid out at `positions`. A `strength` of 0 leaves them straight,
+    /// and 1 pulls them as close together as their springs allow. Self-loops and edges
+    /// with no length are left alone.
+    pub fn compute(edges: impl Iterator<Item = (usize, usize)>, positions: &[Vector2<f32>], strength: f32) -> Self {
+        let mut pairs = edges
+            .filter(|(u, v)| u != v && *u < positions.len() && *v < positions.len())
+            .map(|(u, v)| (u.min(v), u.max(v)))
+            .filter(|(u, v)| positions[*u] != positions[*v])
+            .collect::<Vec<_>>();
+        // Parallel edges share a path.
+        pairs.sort_unstable();
+        pairs.dedup();
+        let ends = pairs.iter().map(|(u, v)| (positions[*u], positions[*v])).collect::<Vec<_>>();
+
+        let mut compatible = (0..ends.len()).map(|_| Vec::new()).collect::<Vec<_>>();
+        for (i, j, compatibility) in compatible_pairs(&ends) {
+            let reversed = (ends[i].1 - ends[i].0).dot(ends[j].1 - ends[j].0) < 0.0;
+            compatible[i].push(Compatible { edge: j, compatibility, reversed });
+            compatible[j].push(Compatible { edge: i, compatibility, reversed });
+        }
+        for compatible in &mut compatible {
+            compatible.sort_by(|a, b| b.compatibility.total_cmp(&a.compatibility));
+            compatible.truncate(MAX_COMPATIBLE_CT);
+        }
+
+        let mut points = ends.iter().map(|(a, b)| vec![*a, (a + b) / 2.0, *b]).collect::<Vec<_>>();
+        let mut step = FIRST_STEP;
+        let mut iteration_ct = FIRST_ITERATION_CT;
+        for cycle in 0..CYCLE_CT {
+            if cycle > 0 {
+                points = points.iter().map(|path| subdivide(path)).collect();
+                step /= 2.0;
+                iteration_ct *= 2.0 / 3.0;
+            }
+            for _ in 0..iteration_ct.round() as usize {
+                points = points
+                    .iter()
+                    .zip(&ends)
+                    .zip(&compatible)
+                    .map(|((path, (a, b)), compatible)| {
+                        let reach = strength * step * (b - a).magnitude();
+                        let last = path.len() - 1;
+                        let mut moved = path.clone();
+                        for k in 1..last {
+                            let spring = (path[k - 1] + path[k + 1] - path[k] * 2.0) * SPRING_CONSTANT;
+                            // The average direction to the matching points of the compatible
+                            // edges, weighted by how compatible they are.
+                            let mut pull = Vector2::zero();
+                            let mut total = 0.0;
+                            for other in compatible {
+                                let target = points[other.edge][if other.reversed { last - k } else { k }];
+                                let offset = target - path[k];
+                                let distance = offset.magnitude();
+                                if distance > f32::EPSILON {
+                                    pull += offset / distance * other.compatibility;
+                                }
+                                total += other.compatibility;
+                            }
+                            if total > 0.0 {
+                                pull /= total;
+                            }
+                            moved[k] = path[k] + spring + pull * reach;
+                        }
+                        moved
+                    })
+                    .collect();
+            }
+        }
+
+        let paths = pairs
+            .into_iter()
+            .zip(points)
+            .zip(ends)
+            .map(|((pair, path), (a, b))| {
+                let along = b - a;
+                let across = Vector2::new(-along.y, along.x);
+                let length_squared = along.magnitude2();
+                let interior = path[1..path.len() - 1]
+                    .iter()
+                    .map(|point| {
+                        let offset = point - a;
+                        Vector2::new(offset.dot(along), offset.dot(across)) / length_squared
+                    })
+                    .collect();
+                (pair, interior)
+            })
+            .collect();
+
+        Self { paths }
+    }
+
+    /// The bundled path of the edge from `u` at `a` to `v` at `b`, endpoints included, if
+    /// it was bundled.
+    pub fn path(&self, u: usize, v: usize, a: Vector2<f32>, b: Vector2<f32>) -> Option<Vec<Vector2<f32>>> {
+        let interior = self.paths.get(&(u.min(v), u.max(v)))?;
+        let (low, high) = if u < v { (a, b) } else { (b, a) };
+        let along = high - low;
+        let across = Vector2::new(-along.y, along.x);
+
+        let mut path = Vec::with_capacity(interior.len() + 2);
+        path.push(low);
+        path.extend(interior.iter().map(|point| low + along * point.x + across * point.y));
+        path.push(high);
+        if u > v {
+            path.reverse();
+        }
+        Some(path)
+    }
+}
+
+/// Every pair of edges compatible enough to attract each other, once each, with how
+/// compatible they are. Edge middles are bucketed in a grid, and each edge only looks at the
+/// edges no longer than itself with middles near enough to be compatible, which finds every
+/// pair since compatibility is symmetric.
+fn compatible_pairs(ends: &[(Vector2<f32>, Vector2<f32>)]) -> Vec<(usize, usize, f32)> {
+    let lengths = ends.iter().map(|(a, b)| (b - a).magnitude()).collect::<Vec<_>>();
+    let middles = ends.iter().map(|(a, b)| (a + b) / 2.0).collect::<Vec<_>>();
+    let cell_size = (lengths.iter().sum::<f32>() / lengths.len().max(1) as f32).max(f32::EPSILON);
+    let cell = |point: Vector2<f32>| ((point.x / cell_size).floor() as i32, (point.y / cell_size).floor() as i32);
+
+    let mut grid = HashMap::<(i32, i32), Vec<usize>>::new();
+    for (i, middle) in middles.iter().enumerate() {
+        grid.entry(cell(*middle)).or_default().push(i);
+    }
+    // Long edges reach past the occupied cells, which there's no need to look through.
+    let min = grid.keys().fold((i32::MAX, i32::MAX), |min, key| (min.0.min(key.0), min.1.min(key.1)));
+    let max = grid.keys().fold((i32::MIN, i32::MIN), |max, key| (max.0.max(key.0), max.1.max(key.1)));
+
+    let mut pairs = Vec::new();
+    for i in 0..ends.len() {
+        let reach = MAX_MIDDLE_DISTANCE * lengths[i];
+        let low = cell(middles[i] - Vector2::new(reach, reach));
+        let high = cell(middles[i] + Vector2::new(reach, reach));
+        for x in low.0.max(min.0)..=high.0.min(max.0) {
+            for y in low.1.max(min.1)..=high.1.min(max.1) {
+                for &j in grid.get(&(x, y)).into_iter().flatten() {
+                    let shorter = lengths[j].total_cmp(&lengths[i]).then(j.cmp(&i)).is_lt();
+                    if !shorter || (middles[j] - middles[i]).magnitude() > reach {
+                        continue;
+                    }
+                    let compatibility = compatibility(ends[i], ends[j]);
+                    if compatibility >= COMPATIBILITY_THRESHOLD {
+                        pairs.push((i, j, compatibility));
+                    }
+                }
+            }
+        }
+    }
+    pairs
+}
+
+/// How likely two edges are to belong in the same bundle, from 0 to 1. Edges are more
+/// compatible the more parallel, similar in length, close together and in view of each
+/// other they are.
+fn compatibility(p: (Vector2<f32>, Vector2<f32>), q: (Vector2<f32>, Vector2<f32>)) -> f32 {
+    let (p_along, q_along) = (p.1 - p.0, q.1 - q.0);
+    let (p_length, q_length) = (p_along.magnitude(), q_along.magnitude());
+    let average_length = (p_length + q_length) / 2.0;
+
+    let angle = (p_along.dot(q_along) / (p_length * q_length)).abs();
+    let scale = 2.0 / (average_length / p_length.min(q_length) + p_length.max(q_length) / average_length);
+    let middle_distance = ((p.0 + p.1) / 2.0 - (q.0 + q.1) / 2.0).magnitude();
+    let position = average_length / (average_length + middle_distance);
+
+    angle * scale * position * visibility(p, q).min(visibility(q, p))
+}
+
+/// How much `q` overlaps `p` when projected onto the line through `p`, from 0 (not at all)
+/// to 1 (centered on the middle of `p`).
+fn visibility(p: (Vector2<f32>, Vector2<f32>), q: (Vector2<f32>, Vector2<f32>)) -> f32 {
+    let along = p.1 - p.0;
+    let project = |point: Vector2<f32>| p.0 + along * ((point - p.0).dot(along) / along.magnitude2());
+    let (start, end) = (project(q.0), project(q.1));
+    let span = (end - start).magnitude();
+    if span <= f32::EPSILON {
+        return 0.0;
+    }
+    let middle_offset = ((p.0 + p.1) / 2.0 - (start + end) / 2.0).magnitude();
+    (1.0 - 2.0 * middle_offset / span).max(0.0)
+}
+
+/// `path` with a point added halfway along each segment.
+fn subdivide(path: &[Vector2<f32>]) -> Vec<Vector2<f32>> {
+    let mut subdivided = Vec::with_capacity(2 * path.len() - 1);
+    for segment in path.windows(2) {
+        subdivided.push(segment[0]);
+        subdivided.push((segment[0] + segment[1]) / 2.0);
+    }
+    subdivided.extend(path.last());
+    subdivided
+}
+
+/// Edges to bundle, as laid out when bundling was asked for.
+pub struct BundleRequest {
+    /// Told apart from earlier requests by this, to know which one the bundles are for.
+    pub id: u64,
+    pub edges: Vec<(usize, usize)>,
+    pub positions: Vec<Vector2<f32>>,
+    pub strength: f32,
+}
+
+/// The bundles of the last request computed.
+#[derive(Default)]
+pub struct BundleSnapshot {
+    pub id: u64,
+    pub bundles: Option<Arc<EdgeBundles>>,
+}
+
+/// Bundles edges as a `Simulation`, so a `SimulationRunner` computes them off the render
+/// thread. Each request is computed in a single tick, and only the newest one waiting is.
+#[derive(Default)]
+pub struct EdgeBundler {
+    pub request: Option<BundleRequest>,
+    computed: BundleSnapshot,
+}
+
+impl Simulation for EdgeBundler {
+    type Snapshot = BundleSnapshot;
+
+    fn tick(&mut self, _delta_t: Duration) {
+        if let Some(request) = self.request.take() {
+            let bundles = EdgeBundles::compute(request.edges.into_iter(), &request.positions, request.strength);
+            self.computed = BundleSnapshot {
+                id: request.id,
+                bundles: Some(Arc::new(bundles)),
+            };
+        }
+    }
+
+    fn write_snapshot(&self, snapshot: &mut BundleSnapshot) {
+        snapshot.id = self.computed.id;
+        snapshot.bundles = self.computed.bundles.clone();
+    }
+
+    fn is_idle(&self) -> bool {
+        self.request.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    /// How far across the edge from `a` to `b` each interior point of its path is, in edge
+    /// lengths.
+    fn offsets(bundles: &EdgeBundles, u: usize, v: usize, a: Vector2<f32>, b: Vector2<f32>) -> Vec<f32> {
+        let along = b - a;
+        let across = Vector2::new(-along.y, along.x);
+        let path = bundles.path(u, v, a, b).unwrap();
+        path[1..path.len() - 1].iter().map(|point| (point - a).dot(across) / along.magnitude2()).collect()
+    }
+
+    #[test]
+    fn parallel_edges_bend_together() {
+        let positions = [
+            Vector2::new(0.0, 0.0),
+            Vector2::new(10.0, 0.0),
+            Vector2::new(0.0, 1.0),
+            Vector2::new(10.0, 1.0),
+            // Far off, across the others.
+            Vector2::new(100.0, 100.0),
+            Vector2::new(100.0, 110.0),
+        ];
+        let bundles = EdgeBundles::compute([(0, 1), (2, 3), (4, 5)].into_iter(), &positions, 1.0);
+
+        // Across the edges points up, towards the upper edge for the lower one.
+        let lower = offsets(&bundles, 0, 1, positions[0], positions[1]);
+        let upper = offsets(&bundles, 2, 3, positions[2], positions[3]);
+        assert!(lower.iter().all(|offset| *offset >= 0.0) && lower.iter().any(|offset| *offset > 0.01), "{:?}", lower);
+        assert!(upper.iter().all(|offset| *offset <= 0.0) && upper.iter().any(|offset| *offset < -0.01), "{:?}", upper);
+
+        let lone = offsets(&bundles, 4, 5, positions[4], positions[5]);
+        assert!(lone.iter().all(|offset| offset.abs() < 1e-6), "{:?}", lone);
+    }
+
+    #[test]
+    fn bucketing_finds_every_compatible_pair() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut point = || Vector2::new(rng.random_range(0.0..20.0), rng.random_range(0.0..20.0));
+        let ends = (0..300).map(|_| (point(), point())).collect::<Vec<_>>();
+
+        let mut bucketed = compatible_pairs(&ends)
+            .into_iter()
+            .map(|(i, j, _)| (i.min(j), i.max(j)))
+            .collect::<Vec<_>>();
+        bucketed.sort_unstable();
+        let every = (0..ends.len())
+            .flat_map(|i| (i + 1..ends.len()).map(move |j| (i, j)))
+            .filter(|(i, j)| compatibility(ends[*i], ends[*j]) >= COMPATIBILITY_THRESHOLD)
+            .collect::<Vec<_>>();
+        assert!(!every.is_empty());
+        assert_eq!(bucketed, every);
+    }
+}
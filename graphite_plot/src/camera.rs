@@ -4,7 +4,8 @@ use cgmath::{EuclideanSpace, Vector2};
 pub struct Camera {
     pub center: cgmath::Point2<f32>,
     pub size: PhysicalSize<f32>,
-
+    /// Counterclockwise turn of the plot on screen, in radians.
+    pub rotation: f32,
 }
 
 impl Camera {
@@ -13,12 +14,40 @@ impl Camera {
     }
 
     pub fn zoom(&mut self, wheel_delta: f32) {
-        self.size.width *= (-0.1 * wheel_delta).exp();
-        self.size.height *= (-0.1 * wheel_delta).exp();
+        self.zoom_by((0.1 * wheel_delta).exp());
+    }
+
+    /// Magnifies the plot `factor` times around the center of the view.
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.size.width /= factor;
+        self.size.height /= factor;
+    }
+
+    /// Turns the plot counterclockwise around the center of the view.
+    pub fn rotate(&mut self, angle: f32) {
+        self.rotation += angle;
+    }
+
+    /// Zooms and turns the plot, then pans it so the point that was under `from` ends up
+    /// under `to`, the way a two-finger gesture moves it.
+    pub fn transform_about(
+        &mut self,
+        from: PhysicalPosition<f32>,
+        to: PhysicalPosition<f32>,
+        zoom: f32,
+        rotation: f32,
+        window_size: PhysicalSize<u32>,
+    ) {
+        let anchor = self.pixel_to_camera(from, window_size);
+        self.zoom_by(zoom);
+        self.rotate(rotation);
+        self.center += anchor - self.pixel_to_camera(to, window_size);
     }
 
     pub fn pixel_to_camera(&self, pixel: PhysicalPosition<f32>, window_size: PhysicalSize<u32>) -> Vector2<f32> {
-        let ret = self.center.to_vec() + Vector2::new((pixel.x / window_size.width as f32 - 0.5) * self.size.width, (0.5 - pixel.y / window_size.height as f32) * self.size.height);
+        let view = Vector2::new((pixel.x / window_size.width as f32 - 0.5) * self.size.width, (0.5 - pixel.y / window_size.height as f32) * self.size.height);
+        let (sin, cos) = self.rotation.sin_cos();
+        let ret = self.center.to_vec() + Vector2::new(cos * view.x + sin * view.y, -sin * view.x + cos * view.y);
         ret
     }
 }
@@ -28,6 +57,10 @@ impl Camera {
 pub struct CameraUniform {
     center: [f32; 2],
     size: [f32; 2],
+    /// Cosine and sine of the rotation.
+    rotation: [f32; 2],
+    /// Size of the surface in pixels, for lines and points sized in pixels.
+    viewport: [f32; 2],
 }
 
 impl CameraUniform {
@@ -38,12 +71,16 @@ impl CameraUniform {
         Self {
             center,
             size,
+            rotation: [1.0, 0.0],
+            viewport: [1.0, 1.0],
         }
     }
 
-    pub fn update(&mut self, camera: &Camera) {
+    pub fn update(&mut self, camera: &Camera, viewport: PhysicalSize<u32>) {
 
         self.center = camera.center.into();
         self.size = camera.size.into();
+        self.rotation = [camera.rotation.cos(), camera.rotation.sin()];
+        self.viewport = [viewport.width.max(1) as f32, viewport.height.max(1) as f32];
     }
 }
\ No newline at end of file
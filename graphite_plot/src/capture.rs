@@ -0,0 +1,117 @@
+use std::path::PathBuf;
+
+use instant::Duration;
+
+/// Buffer rows for texture copies must be padded to this many bytes.
+const ROW_ALIGNMENT: u32 = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum RecordFormat {
+    /// A single animated GIF.
+    #[default]
+    Gif,
+    /// A directory of numbered PNGs, for a video editor or ffmpeg.
+    PngSequence,
+}
+
+impl RecordFormat {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "gif" => Some(Self::Gif),
+            "png" => Some(Self::PngSequence),
+            _ => None,
+        }
+    }
+}
+
+/// Where screenshots and recordings go and how recordings are made.
+#[derive(Clone, Debug)]
+pub struct CaptureOptions {
+    pub directory: PathBuf,
+    pub record_format: RecordFormat,
+    /// Frames per second of recordings. Every recorded frame advances the layout by the same
+    /// simulated time, however long it took to draw, so recordings play back smoothly.
+    pub record_fps: u32,
+}
+
+impl Default for CaptureOptions {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("."),
+            record_format: RecordFormat::Gif,
+            record_fps: 30,
+        }
+    }
+}
+
+impl CaptureOptions {
+    pub fn frame_interval(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.record_fps.max(1) as f64)
+    }
+}
+
+/// A texture to draw a frame for capture into, the size and format of the surface. Frames
+/// are captured from their own pass rather than the surface, which leaves out the interface
+/// drawn over the plot and works where the surface can't be copied from.
+pub fn capture_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Capture Texture"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+/// A frame copied into a buffer, ready to be handed to `Capturer::read_back` once the copy
+/// is submitted.
+// Only the capturer reads frames back, and wasm builds leave it out.
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub struct CopiedFrame {
+    pub buffer: wgpu::Buffer,
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+}
+
+/// Copies a texture made by `capture_texture` into a new buffer, as part of the commands in
+/// `encoder`.
+pub fn copy_frame(device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) -> CopiedFrame {
+    let padded_row = padded_row_size(texture.width());
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Capture Buffer"),
+        size: padded_row as u64 * texture.height() as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row),
+                rows_per_image: Some(texture.height()),
+            },
+        },
+        texture.size(),
+    );
+
+    CopiedFrame {
+        buffer,
+        width: texture.width(),
+        height: texture.height(),
+        format: texture.format(),
+    }
+}
+
+pub fn padded_row_size(width: u32) -> u32 {
+    (width * 4).div_ceil(ROW_ALIGNMENT) * ROW_ALIGNMENT
+}
@@ -0,0 +1,439 @@
+//! Saving screenshots and recordings to disk. Only native targets have a file system to
+//! save them to.
+
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::capture::{CaptureOptions, CopiedFrame, RecordFormat, padded_row_size};
+
+/// GIF frame delays are counted in hundredths of a second.
+const GIF_TICKS_PER_SECOND: u32 = 100;
+/// Trades GIF palette quality for encoding time, from 1 (best) to 30.
+const GIF_QUANTIZER_SPEED: i32 = 10;
+/// Frames being read back or waiting for the writer, at most, before recording waits.
+const MAX_FRAMES_IN_FLIGHT: usize = 3;
+
+const READBACK_PENDING: u8 = 0;
+const READBACK_MAPPED: u8 = 1;
+const READBACK_FAILED: u8 = 2;
+
+/// A frame read back from the GPU.
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA, one row after another from the top.
+    pub pixels: Vec<u8>,
+}
+
+impl Frame {
+    /// Strips the row padding of a texture copy and converts it to RGBA.
+    fn from_copy(copy: &CopiedBytes) -> Self {
+        let row = copy.width as usize * 4;
+        let mut pixels = Vec::with_capacity(row * copy.height as usize);
+        for padded in copy.bytes.chunks(padded_row_size(copy.width) as usize) {
+            pixels.extend_from_slice(&padded[..row]);
+        }
+        if copy.swap_red_blue {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        Self { width: copy.width, height: copy.height, pixels }
+    }
+}
+
+pub fn save_png(path: &Path, frame: &Frame) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), frame.width, frame.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(&frame.pixels).map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)
+}
+
+/// Saves `frame` under the next free `screenshot-NNNN.png` name in `directory`.
+pub fn save_screenshot(directory: &Path, frame: &Frame) -> io::Result<PathBuf> {
+    let path = free_path(directory, "screenshot", ".png");
+    save_png(&path, frame)?;
+    Ok(path)
+}
+
+/// The first `<stem>-NNNN<suffix>` in `directory` that doesn't exist yet.
+fn free_path(directory: &Path, stem: &str, suffix: &str) -> PathBuf {
+    (1..)
+        .map(|i| directory.join(format!("{}-{:04}{}", stem, i, suffix)))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+/// A recording in progress. Frames must all have the size of the first; others are
+/// skipped.
+pub struct Recording {
+    path: PathBuf,
+    format: RecordFormat,
+    /// Hundredths of a second each GIF frame is shown.
+    gif_delay: u16,
+    gif: Option<gif::Encoder<BufWriter<File>>>,
+    size: Option<(u32, u32)>,
+    frame_ct: usize,
+}
+
+impl Recording {
+    /// Claims a free `recording-NNNN` name in the capture directory. PNG sequences get a
+    /// directory of that name.
+    pub fn start(options: &CaptureOptions) -> io::Result<Self> {
+        let path = match options.record_format {
+            RecordFormat::Gif => free_path(&options.directory, "recording", ".gif"),
+            RecordFormat::PngSequence => {
+                let path = free_path(&options.directory, "recording", "");
+                fs::create_dir_all(&path)?;
+                path
+            }
+        };
+
+        Ok(Self {
+            path,
+            format: options.record_format,
+            gif_delay: (GIF_TICKS_PER_SECOND as f64 / options.record_fps.max(1) as f64).round().max(1.0) as u16,
+            gif: None,
+            size: None,
+            frame_ct: 0,
+        })
+    }
+
+    pub fn push(&mut self, frame: &Frame) -> io::Result<()> {
+        let size = *self.size.get_or_insert((frame.width, frame.height));
+        if size != (frame.width, frame.height) {
+            log::warn!("Skipping a {}x{} frame in a {}x{} recording", frame.width, frame.height, size.0, size.1);
+            return Ok(());
+        }
+
+        match self.format {
+            RecordFormat::Gif => {
+                let (Ok(width), Ok(height)) = (u16::try_from(frame.width), u16::try_from(frame.height)) else {
+                    return Err(io::Error::other("frame too large for a GIF"));
+                };
+                if self.gif.is_none() {
+                    let mut encoder = gif::Encoder::new(BufWriter::new(File::create(&self.path)?), width, height, &[])
+                        .map_err(io::Error::other)?;
+                    encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+                    self.gif = Some(encoder);
+                }
+                let mut pixels = frame.pixels.clone();
+                let mut gif_frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, GIF_QUANTIZER_SPEED);
+                gif_frame.delay = self.gif_delay;
+                self.gif.as_mut().unwrap().write_frame(&gif_frame).map_err(io::Error::other)?;
+            }
+            RecordFormat::PngSequence => {
+                save_png(&self.path.join(format!("frame-{:05}.png", self.frame_ct + 1)), frame)?;
+            }
+        }
+        self.frame_ct += 1;
+        Ok(())
+    }
+
+    /// Writes out whatever is still buffered and returns where the recording was saved and
+    /// how many frames it has.
+    pub fn finish(self) -> (PathBuf, usize) {
+        // The GIF trailer is written when the encoder is dropped.
+        drop(self.gif);
+        (self.path, self.frame_ct)
+    }
+}
+
+/// Bytes mapped from a `CopiedFrame`, row padding included.
+struct CopiedBytes {
+    width: u32,
+    height: u32,
+    swap_red_blue: bool,
+    bytes: Vec<u8>,
+}
+
+/// A copy being mapped, and what the frame is for once it is.
+struct Readback {
+    frame: CopiedFrame,
+    swap_red_blue: bool,
+    state: Arc<AtomicU8>,
+    screenshot: bool,
+    record: bool,
+}
+
+/// Work for the writer, carried out in the order it was sent.
+enum Job {
+    Frame { copy: CopiedBytes, screenshot: bool, record: bool },
+    StartRecording(Recording),
+    FinishRecording,
+}
+
+/// What the writer keeps between jobs.
+struct WriterState {
+    directory: PathBuf,
+    recording: Option<Recording>,
+    /// Set when a recorded frame couldn't be written and the recording was given up.
+    failed: Arc<AtomicBool>,
+}
+
+impl WriterState {
+    fn handle(&mut self, job: Job) {
+        match job {
+            Job::Frame { copy, screenshot, record } => {
+                let frame = Frame::from_copy(&copy);
+                if screenshot {
+                    match save_screenshot(&self.directory, &frame) {
+                        Ok(path) => log::info!("Saved {}", path.display()),
+                        Err(error) => log::error!("Couldn't save screenshot: {}", error),
+                    }
+                }
+                let Some(recording) = self.recording.as_mut().filter(|_| record) else {
+                    return;
+                };
+                if let Err(error) = recording.push(&frame) {
+                    log::error!("Couldn't record frame: {}", error);
+                    self.finish_recording();
+                    self.failed.store(true, Ordering::Release);
+                }
+            }
+            Job::StartRecording(recording) => {
+                self.finish_recording();
+                self.recording = Some(recording);
+            }
+            Job::FinishRecording => self.finish_recording(),
+        }
+    }
+
+    fn finish_recording(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            let (path, frame_ct) = recording.finish();
+            log::info!("Saved {} frames to {}", frame_ct, path.display());
+        }
+    }
+}
+
+/// Encodes and saves frames on its own thread, so GIF quantisation and PNG compression stay
+/// off the render thread.
+struct Writer {
+    jobs: Option<Sender<Job>>,
+    thread: Option<JoinHandle<()>>,
+    /// Frames sent to the writer that it hasn't saved yet.
+    queued: Arc<AtomicUsize>,
+    failed: Arc<AtomicBool>,
+}
+
+impl Writer {
+    fn new(directory: PathBuf) -> Self {
+        let queued = Arc::new(AtomicUsize::new(0));
+        let failed = Arc::new(AtomicBool::new(false));
+        let state = WriterState {
+            directory,
+            recording: None,
+            failed: failed.clone(),
+        };
+
+        let (jobs, receiver) = mpsc::channel();
+        let worker_queued = queued.clone();
+        let thread = thread::Builder::new()
+            .name("capture".to_string())
+            .spawn(move || run_writer(state, receiver, worker_queued))
+            .expect("Couldn't spawn capture thread");
+
+        Self {
+            jobs: Some(jobs),
+            thread: Some(thread),
+            queued,
+            failed,
+        }
+    }
+
+    fn send(&mut self, job: Job) {
+        if matches!(job, Job::Frame { .. }) {
+            self.queued.fetch_add(1, Ordering::AcqRel);
+        }
+        if let Some(jobs) = self.jobs.as_ref()
+            && jobs.send(job).is_err()
+        {
+            log::error!("Capture thread has stopped");
+        }
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        // Closing the channel lets the writer finish what it has and stop, which writes out
+        // a recording still in progress.
+        self.jobs.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn run_writer(mut state: WriterState, receiver: Receiver<Job>, queued: Arc<AtomicUsize>) {
+    for job in receiver {
+        let is_frame = matches!(job, Job::Frame { .. });
+        state.handle(job);
+        if is_frame {
+            queued.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+    state.finish_recording();
+}
+
+/// Something waiting its turn to go to the writer.
+enum Pending {
+    Readback(Readback),
+    Job(Job),
+}
+
+/// Takes screenshots and recordings without waiting on the GPU or the encoder. Copies of
+/// drawn frames are mapped asynchronously and picked up by `poll` on later frames, then
+/// handed to the writer in the order they were drawn.
+pub struct Capturer {
+    options: CaptureOptions,
+    /// Frames being read back, and recordings started or finished after them.
+    pending: VecDeque<Pending>,
+    writer: Writer,
+    recording: bool,
+}
+
+impl Capturer {
+    pub fn new(options: CaptureOptions) -> Self {
+        Self {
+            writer: Writer::new(options.directory.clone()),
+            options,
+            pending: VecDeque::new(),
+            recording: false,
+        }
+    }
+
+    pub fn options(&self) -> &CaptureOptions {
+        &self.options
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// Claims a name for a new recording, which recorded frames go to until
+    /// `stop_recording`.
+    pub fn start_recording(&mut self) -> io::Result<()> {
+        let recording = Recording::start(&self.options)?;
+        self.writer.failed.store(false, Ordering::Release);
+        self.pending.push_back(Pending::Job(Job::StartRecording(recording)));
+        self.recording = true;
+        Ok(())
+    }
+
+    /// Ends the recording after the frames already drawn for it.
+    pub fn stop_recording(&mut self) {
+        self.pending.push_back(Pending::Job(Job::FinishRecording));
+        self.recording = false;
+    }
+
+    /// Whether another frame can be recorded without frames piling up behind the readback
+    /// or the encoder. Recording waits for them otherwise, and the layout with it.
+    pub fn can_record(&self) -> bool {
+        let readback_ct = self.pending.iter().filter(|pending| matches!(pending, Pending::Readback(_))).count();
+        readback_ct + self.writer.queued.load(Ordering::Acquire) < MAX_FRAMES_IN_FLIGHT
+    }
+
+    /// Starts mapping a frame copied by `copy_frame`, once the copy has been submitted.
+    pub fn read_back(&mut self, frame: CopiedFrame, screenshot: bool, record: bool) {
+        // Only 8-bit RGBA and BGRA surfaces are supported, which is what every backend
+        // offers in practice.
+        let swap_red_blue = match frame.format {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            format => {
+                log::warn!("Can't capture frames from a {:?} surface", format);
+                return;
+            }
+        };
+
+        let state = Arc::new(AtomicU8::new(READBACK_PENDING));
+        let mapped_state = state.clone();
+        frame.buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            let state = if result.is_ok() { READBACK_MAPPED } else { READBACK_FAILED };
+            mapped_state.store(state, Ordering::Release);
+        });
+        self.pending.push_back(Pending::Readback(Readback {
+            frame,
+            swap_red_blue,
+            state,
+            screenshot,
+            record: record && self.recording,
+        }));
+    }
+
+    /// Hands frames that have been read back to the writer, without blocking. Returns false
+    /// if the recording had to be given up because a frame couldn't be written.
+    pub fn poll(&mut self, device: &wgpu::Device) -> bool {
+        if self.pending.iter().any(|pending| matches!(pending, Pending::Readback(_))) {
+            device.poll(wgpu::Maintain::Poll);
+        }
+
+        while let Some(pending) = self.pending.front() {
+            if let Pending::Readback(readback) = pending
+                && readback.state.load(Ordering::Acquire) == READBACK_PENDING
+            {
+                break;
+            }
+            match self.pending.pop_front().unwrap() {
+                Pending::Readback(readback) => self.finish_readback(readback),
+                Pending::Job(job) => self.writer.send(job),
+            }
+        }
+
+        !(self.recording && self.writer.failed.load(Ordering::Acquire))
+    }
+
+    fn finish_readback(&mut self, readback: Readback) {
+        if readback.state.load(Ordering::Acquire) == READBACK_FAILED {
+            log::warn!("Couldn't read back the frame");
+            return;
+        }
+
+        let slice = readback.frame.buffer.slice(..);
+        let bytes = slice.get_mapped_range().to_vec();
+        readback.frame.buffer.unmap();
+        let copy = CopiedBytes {
+            width: readback.frame.width,
+            height: readback.frame.height,
+            swap_red_blue: readback.swap_red_blue,
+            bytes,
+        };
+        self.writer.send(Job::Frame {
+            copy,
+            screenshot: readback.screenshot,
+            record: readback.record,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_lose_row_padding_and_become_rgba() {
+        let padded_row = padded_row_size(2) as usize;
+        let mut bytes = vec![0; padded_row * 2];
+        bytes[..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        bytes[padded_row..padded_row + 8].copy_from_slice(&[9, 10, 11, 12, 13, 14, 15, 16]);
+        let copy = CopiedBytes { width: 2, height: 2, swap_red_blue: true, bytes };
+
+        let frame = Frame::from_copy(&copy);
+        assert_eq!((frame.width, frame.height), (2, 2));
+        assert_eq!(frame.pixels, [3, 2, 1, 4, 7, 6, 5, 8, 11, 10, 9, 12, 15, 14, 13, 16]);
+    }
+}
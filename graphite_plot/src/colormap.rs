@@ -0,0 +1,68 @@
+use crate::draw::Color;
+
+const VIRIDIS: [u32; 11] = [
+    0x440154, 0x482475, 0x414487, 0x355F8D, 0x2A788E, 0x21918C, 0x22A884, 0x44BF70, 0x7AD151,
+    0xBDDF26, 0xFDE725,
+];
+const MAGMA: [u32; 11] = [
+    0x000004, 0x140E36, 0x3B0F70, 0x641A80, 0x8C2981, 0xB73779, 0xDE4968, 0xF7705C, 0xFE9F6D,
+    0xFECF92, 0xFCFDBF,
+];
+/// ColorBrewer RdBu, running from blue (low) to red (high).
+const DIVERGING: [u32; 9] = [
+    0x2166AC, 0x4393C3, 0x92C5DE, 0xD1E5F0, 0xF7F7F7, 0xFDDBC7, 0xF4A582, 0xD6604D, 0xB2182B,
+];
+/// Tableau 10, for values that are labels rather than magnitudes.
+const CATEGORICAL: [u32; 10] = [
+    0x4E79A7, 0xF28E2B, 0xE15759, 0x76B7B2, 0x59A14F, 0xEDC948, 0xB07AA1, 0xFF9DA7, 0x9C755F,
+    0xBAB0AC,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Colormap {
+    Viridis,
+    Magma,
+    Diverging,
+}
+
+impl Colormap {
+    pub fn next(self) -> Self {
+        match self {
+            Colormap::Viridis => Colormap::Magma,
+            Colormap::Magma => Colormap::Diverging,
+            Colormap::Diverging => Colormap::Viridis,
+        }
+    }
+
+    fn stops(self) -> &'static [u32] {
+        match self {
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Magma => &MAGMA,
+            Colormap::Diverging => &DIVERGING,
+        }
+    }
+
+    /// Color at `t`, which is clamped to `[0, 1]`.
+    pub fn sample(self, t: f32) -> Color {
+        let stops = self.stops();
+        let t = if t.is_finite() { t.clamp(0.0, 1.0) } else { 0.0 };
+        let scaled = t * (stops.len() - 1) as f32;
+        let i = (scaled.floor() as usize).min(stops.len() - 2);
+
+        Color::hex(stops[i]).lerp(Color::hex(stops[i + 1]), scaled - i as f32)
+    }
+
+    /// Evenly spaced samples of the colormap, for drawing a colorbar.
+    pub fn gradient(self, sample_ct: usize) -> Vec<Color> {
+        (0..sample_ct)
+            .map(|i| self.sample(i as f32 / (sample_ct - 1).max(1) as f32))
+            .collect()
+    }
+}
+
+/// Color for category `index`. Categories past the end of the palette wrap around.
+pub fn categorical(index: usize) -> Color {
+    Color::hex(CATEGORICAL[index % CATEGORICAL.len()])
+}
+
+pub const CATEGORICAL_COLOR_CT: usize = CATEGORICAL.len();
@@ -0,0 +1,521 @@
+use std::collections::{HashMap, VecDeque};
+
+use gscientific::graph::Graph;
+use rand::{Rng, rngs::StdRng, seq::SliceRandom};
+
+const RESOLUTION: f64 = 1.0;
+const MAX_LEVELS: usize = 32;
+const MAX_LABEL_PROPAGATION_SWEEPS: usize = 100;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CommunityAlgorithm {
+    Louvain,
+    Leiden,
+    LabelPropagation,
+}
+
+impl CommunityAlgorithm {
+    /// The algorithm after `algorithm` when cycling through them, where `None` means no
+    /// community detection.
+    pub fn cycle(algorithm: Option<CommunityAlgorithm>) -> Option<CommunityAlgorithm> {
+        match algorithm {
+            None => Some(CommunityAlgorithm::Louvain),
+            Some(CommunityAlgorithm::Louvain) => Some(CommunityAlgorithm::Leiden),
+            Some(CommunityAlgorithm::Leiden) => Some(CommunityAlgorithm::LabelPropagation),
+            Some(CommunityAlgorithm::LabelPropagation) => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CommunityAlgorithm::Louvain => "Louvain",
+            CommunityAlgorithm::Leiden => "Leiden",
+            CommunityAlgorithm::LabelPropagation => "Label propagation",
+        }
+    }
+
+    /// Community label of every vertex, numbered from 0 in order of first appearance.
+    pub fn detect<T: Graph>(self, graph: &T, rng: &mut StdRng) -> Vec<usize> {
+        let network = Network::from_graph(graph);
+
+        let labels = match self {
+            CommunityAlgorithm::Louvain => louvain(network, rng),
+            CommunityAlgorithm::Leiden => leiden(network, MAX_LEVELS, rng),
+            CommunityAlgorithm::LabelPropagation => label_propagation(&network, rng),
+        };
+
+        renumber(&labels)
+    }
+}
+
+/// Newman modularity of `communities` on `graph`.
+pub fn modularity<T: Graph>(graph: &T, communities: &[usize]) -> f64 {
+    let network = Network::from_graph(graph);
+    let community_ct = communities.iter().max().map_or(0, |max| max + 1);
+    let total_weight = network.total_weight();
+    if total_weight == 0.0 {
+        return 0.0;
+    }
+
+    let mut internal = vec![0.0; community_ct];
+    let mut totals = vec![0.0; community_ct];
+    for u in 0..network.node_ct() {
+        totals[communities[u]] += network.strength[u];
+        internal[communities[u]] += network.self_weight[u];
+        for &(v, w) in &network.adjacency[u] {
+            if communities[u] == communities[v] {
+                internal[communities[u]] += w;
+            }
+        }
+    }
+
+    internal
+        .iter()
+        .zip(&totals)
+        .map(|(inside, total)| inside / total_weight - RESOLUTION * (total / total_weight).powi(2))
+        .sum()
+}
+
+/// A weighted undirected graph that can be aggregated. Every edge appears in the adjacency of
+/// both endpoints; edges collapsed into a node are kept as `self_weight`, counted from both
+/// ends as well.
+struct Network {
+    adjacency: Vec<Vec<(usize, f64)>>,
+    self_weight: Vec<f64>,
+    strength: Vec<f64>,
+}
+
+impl Network {
+    /// Edge weights that aren't positive and finite are treated as 1.
+    fn from_graph<T: Graph>(graph: &T) -> Self {
+        let adjacency = (0..graph.vertex_ct())
+            .map(|u| {
+                graph
+                    .neighbors(u)
+                    .filter(|(v, _)| *v != u)
+                    .map(|(v, w)| (v, if w.is_finite() && w > 0.0 { w } else { 1.0 }))
+                    .collect()
+            })
+            .collect();
+
+        Self::new(adjacency, vec![0.0; graph.vertex_ct()])
+    }
+
+    fn new(adjacency: Vec<Vec<(usize, f64)>>, self_weight: Vec<f64>) -> Self {
+        let strength = adjacency
+            .iter()
+            .zip(&self_weight)
+            .map(|(neighbors, self_weight): (&Vec<(usize, f64)>, &f64)| {
+                neighbors.iter().map(|(_, w)| w).sum::<f64>() + self_weight
+            })
+            .collect();
+
+        Self {
+            adjacency,
+            self_weight,
+            strength,
+        }
+    }
+
+    fn node_ct(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    fn total_weight(&self) -> f64 {
+        self.strength.iter().sum()
+    }
+
+    /// Collapses every community of `partition` (labelled `0..community_ct`) into one node.
+    fn aggregate(&self, partition: &[usize], community_ct: usize) -> Network {
+        let mut weights = vec![HashMap::new(); community_ct];
+        let mut self_weight = vec![0.0; community_ct];
+
+        for u in 0..self.node_ct() {
+            let cu = partition[u];
+            self_weight[cu] += self.self_weight[u];
+            for &(v, w) in &self.adjacency[u] {
+                let cv = partition[v];
+                if cu == cv {
+                    self_weight[cu] += w;
+                } else {
+                    *weights[cu].entry(cv).or_insert(0.0) += w;
+                }
+            }
+        }
+
+        let adjacency = weights
+            .into_iter()
+            .map(|neighbors| {
+                let mut neighbors: Vec<(usize, f64)> = neighbors.into_iter().collect();
+                neighbors.sort_by_key(|(v, _)| *v);
+                neighbors
+            })
+            .collect();
+
+        Network::new(adjacency, self_weight)
+    }
+}
+
+/// Community bookkeeping shared by the local moving phases.
+struct Partition {
+    community: Vec<usize>,
+    totals: Vec<f64>,
+    /// Scratch space for the weight from the current node to each community.
+    neighbor_weights: Vec<f64>,
+    neighbor_communities: Vec<usize>,
+}
+
+impl Partition {
+    fn new(network: &Network, community: Vec<usize>) -> Self {
+        let n = network.node_ct();
+        let mut totals = vec![0.0; n];
+        for u in 0..n {
+            totals[community[u]] += network.strength[u];
+        }
+
+        Self {
+            community,
+            totals,
+            neighbor_weights: vec![0.0; n],
+            neighbor_communities: Vec::new(),
+        }
+    }
+
+    /// Moves `u` to the community with the best modularity gain and returns whether it moved.
+    fn move_node(&mut self, network: &Network, u: usize, total_weight: f64) -> bool {
+        let current = self.community[u];
+        let strength = network.strength[u];
+
+        for &(v, w) in &network.adjacency[u] {
+            let c = self.community[v];
+            if self.neighbor_weights[c] == 0.0 {
+                self.neighbor_communities.push(c);
+            }
+            self.neighbor_weights[c] += w;
+        }
+
+        self.totals[current] -= strength;
+        let gain = |weight: f64, total: f64| weight - RESOLUTION * total * strength / total_weight;
+        let mut best = current;
+        let mut best_gain = gain(self.neighbor_weights[current], self.totals[current]);
+        for &c in &self.neighbor_communities {
+            let candidate = gain(self.neighbor_weights[c], self.totals[c]);
+            if candidate > best_gain {
+                best = c;
+                best_gain = candidate;
+            }
+        }
+        self.totals[best] += strength;
+        self.community[u] = best;
+
+        for c in self.neighbor_communities.drain(..) {
+            self.neighbor_weights[c] = 0.0;
+        }
+
+        best != current
+    }
+}
+
+fn singletons(n: usize) -> Vec<usize> {
+    (0..n).collect()
+}
+
+/// Relabels `labels` to `0..k` in order of first appearance.
+fn renumber(labels: &[usize]) -> Vec<usize> {
+    let mut mapping = HashMap::new();
+    labels
+        .iter()
+        .map(|label| {
+            let next = mapping.len();
+            *mapping.entry(*label).or_insert(next)
+        })
+        .collect()
+}
+
+fn louvain(mut network: Network, rng: &mut StdRng) -> Vec<usize> {
+    let total_weight = network.total_weight();
+    let mut membership = singletons(network.node_ct());
+    if total_weight == 0.0 {
+        return membership;
+    }
+
+    for _ in 0..MAX_LEVELS {
+        let mut partition = Partition::new(&network, singletons(network.node_ct()));
+        let mut order = singletons(network.node_ct());
+        let mut improved = false;
+
+        loop {
+            order.shuffle(rng);
+            let mut moved = false;
+            for &u in &order {
+                moved |= partition.move_node(&network, u, total_weight);
+            }
+            improved |= moved;
+            if !moved {
+                break;
+            }
+        }
+
+        if !improved {
+            break;
+        }
+
+        let community = renumber(&partition.community);
+        let community_ct = community.iter().max().map_or(0, |max| max + 1);
+        for label in &mut membership {
+            *label = community[*label];
+        }
+        network = network.aggregate(&community, community_ct);
+    }
+
+    membership
+}
+
+/// Leiden: local moving from a queue, then a refinement that only merges nodes into
+/// well-connected subcommunities of their community, so no community ends up disconnected.
+/// The refinement merges greedily rather than with randomised acceptance. Stops after
+/// `max_levels` aggregations.
+fn leiden(mut network: Network, max_levels: usize, rng: &mut StdRng) -> Vec<usize> {
+    let total_weight = network.total_weight();
+    let mut membership = singletons(network.node_ct());
+    if total_weight == 0.0 {
+        return membership;
+    }
+    // Community of every aggregate node, carried over from the previous level.
+    let mut initial = singletons(network.node_ct());
+
+    for _ in 0..max_levels {
+        let n = network.node_ct();
+        let community = move_nodes_fast(&network, initial, total_weight, rng);
+        let refined = refine(&network, &community, total_weight, rng);
+        let refined_ct = refined.iter().max().map_or(0, |max| max + 1);
+
+        if refined_ct == n {
+            // Nothing merged, so every community is already a single node.
+            for label in &mut membership {
+                *label = community[*label];
+            }
+            return renumber(&membership);
+        }
+
+        for label in &mut membership {
+            *label = refined[*label];
+        }
+        initial = vec![0; refined_ct];
+        for u in 0..n {
+            initial[refined[u]] = community[u];
+        }
+        network = network.aggregate(&refined, refined_ct);
+    }
+
+    // Out of levels, so the aggregate nodes are refined subcommunities. Report the
+    // communities they were moved into instead.
+    for label in &mut membership {
+        *label = initial[*label];
+    }
+    renumber(&membership)
+}
+
+/// Leiden's local moving: nodes start in their `initial` community and move while that
+/// improves modularity, revisiting only the neighbors of nodes that moved. Returns the
+/// renumbered community of every node.
+fn move_nodes_fast(network: &Network, initial: Vec<usize>, total_weight: f64, rng: &mut StdRng) -> Vec<usize> {
+    let n = network.node_ct();
+    let mut partition = Partition::new(network, initial);
+
+    let mut queue: VecDeque<usize> = {
+        let mut order = singletons(n);
+        order.shuffle(rng);
+        order.into()
+    };
+    let mut queued = vec![true; n];
+    while let Some(u) = queue.pop_front() {
+        queued[u] = false;
+        if partition.move_node(network, u, total_weight) {
+            for &(v, _) in &network.adjacency[u] {
+                if !queued[v] && partition.community[v] != partition.community[u] {
+                    queued[v] = true;
+                    queue.push_back(v);
+                }
+            }
+        }
+    }
+
+    renumber(&partition.community)
+}
+
+fn refine(network: &Network, community: &[usize], total_weight: f64, rng: &mut StdRng) -> Vec<usize> {
+    let n = network.node_ct();
+    let community_ct = community.iter().max().map_or(0, |max| max + 1);
+    let mut community_totals = vec![0.0; community_ct];
+    for u in 0..n {
+        community_totals[community[u]] += network.strength[u];
+    }
+
+    let mut refined = singletons(n);
+    let mut totals = network.strength.clone();
+    // Weight from each subcommunity to the rest of its community.
+    let mut external: Vec<f64> = (0..n)
+        .map(|u| {
+            network.adjacency[u]
+                .iter()
+                .filter(|(v, _)| community[*v] == community[u])
+                .map(|(_, w)| w)
+                .sum()
+        })
+        .collect();
+    let mut singleton = vec![true; n];
+    let well_connected = |external: f64, total: f64, community_total: f64| {
+        external >= RESOLUTION * total * (community_total - total) / total_weight
+    };
+
+    let mut order = singletons(n);
+    order.shuffle(rng);
+    let mut weights: HashMap<usize, f64> = HashMap::new();
+
+    for u in order {
+        let c = community[u];
+        if !singleton[u] || !well_connected(external[u], totals[u], community_totals[c]) {
+            continue;
+        }
+
+        weights.clear();
+        for &(v, w) in &network.adjacency[u] {
+            if community[v] == c {
+                *weights.entry(refined[v]).or_insert(0.0) += w;
+            }
+        }
+
+        let strength = network.strength[u];
+        let mut best = None;
+        let mut best_gain = 0.0;
+        let mut candidates: Vec<(&usize, &f64)> = weights.iter().collect();
+        // Hash map order isn't seeded, so sort to keep the result reproducible.
+        candidates.sort_by_key(|(s, _)| **s);
+        for (&s, &w) in candidates {
+            if s == refined[u] || !well_connected(external[s], totals[s], community_totals[c]) {
+                continue;
+            }
+            let gain = w - RESOLUTION * totals[s] * strength / total_weight;
+            if gain > best_gain || (gain == best_gain && best.is_some() && rng.random::<bool>()) {
+                best = Some((s, w));
+                best_gain = gain;
+            }
+        }
+
+        if let Some((s, w)) = best {
+            external[s] += external[u] - 2.0 * w;
+            totals[s] += strength;
+            totals[u] = 0.0;
+            refined[u] = s;
+            singleton[u] = false;
+            singleton[s] = false;
+        }
+    }
+
+    renumber(&refined)
+}
+
+fn label_propagation(network: &Network, rng: &mut StdRng) -> Vec<usize> {
+    let n = network.node_ct();
+    let mut labels = singletons(n);
+    let mut order = singletons(n);
+    let mut weights = vec![0.0; n];
+    let mut seen = Vec::new();
+    let mut best_labels = Vec::new();
+
+    for _ in 0..MAX_LABEL_PROPAGATION_SWEEPS {
+        order.shuffle(rng);
+        let mut changed = false;
+
+        for &u in &order {
+            for &(v, w) in &network.adjacency[u] {
+                if weights[labels[v]] == 0.0 {
+                    seen.push(labels[v]);
+                }
+                weights[labels[v]] += w;
+            }
+            if seen.is_empty() {
+                continue;
+            }
+
+            let best_weight = seen.iter().map(|l| weights[*l]).fold(0.0, f64::max);
+            best_labels.clear();
+            best_labels.extend(seen.iter().copied().filter(|l| weights[*l] == best_weight));
+            // Keeping the current label on ties is what lets the sweeps converge.
+            if !best_labels.contains(&labels[u]) {
+                labels[u] = best_labels[rng.random_range(0..best_labels.len())];
+                changed = true;
+            }
+
+            for l in seen.drain(..) {
+                weights[l] = 0.0;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::test_graphs;
+
+    /// The two cliques of a barbell, with the first clique labelled 0.
+    fn cliques(clique_size: usize) -> Vec<usize> {
+        (0..2 * clique_size).map(|v| v / clique_size).collect()
+    }
+
+    #[test]
+    fn barbell_modularity() {
+        let barbell = test_graphs::barbell(4);
+        // 13 edges: each clique holds 6 of them and has total degree 13.
+        let expected = 2.0 * (6.0 / 13.0 - 0.25);
+        assert!((modularity(&barbell, &cliques(4)) - expected).abs() < 1e-12);
+        assert!(modularity(&barbell, &[0; 8]).abs() < 1e-12);
+        assert!(modularity(&barbell, &[0, 1, 0, 1, 0, 1, 0, 1]) < 0.0);
+    }
+
+    #[test]
+    fn finds_barbell_cliques() {
+        let barbell = test_graphs::barbell(5);
+        for algorithm in [CommunityAlgorithm::Louvain, CommunityAlgorithm::Leiden, CommunityAlgorithm::LabelPropagation] {
+            for seed in 0..8 {
+                let communities = algorithm.detect(&barbell, &mut StdRng::seed_from_u64(seed));
+                assert_eq!(communities, cliques(5), "{} with seed {}", algorithm.name(), seed);
+            }
+        }
+    }
+
+    #[test]
+    fn isolated_vertices_are_singletons() {
+        let graph = test_graphs::graph(3, &[]);
+        for algorithm in [CommunityAlgorithm::Louvain, CommunityAlgorithm::Leiden, CommunityAlgorithm::LabelPropagation] {
+            assert_eq!(algorithm.detect(&graph, &mut StdRng::seed_from_u64(0)), vec![0, 1, 2]);
+        }
+    }
+
+    #[test]
+    fn leiden_out_of_levels_reports_communities() {
+        // Refinement often splits the patches of a grid that local moving groups together.
+        let graph = test_graphs::grid(6, 6);
+        for seed in 0..8 {
+            let network = Network::from_graph(&graph);
+            let communities = move_nodes_fast(
+                &network,
+                singletons(network.node_ct()),
+                network.total_weight(),
+                &mut StdRng::seed_from_u64(seed),
+            );
+            let leiden = leiden(Network::from_graph(&graph), 1, &mut StdRng::seed_from_u64(seed));
+            assert_eq!(leiden, communities, "seed {}", seed);
+        }
+    }
+}
@@ -1,15 +1,17 @@
 
 use bytemuck::{Pod, Zeroable};
-use cgmath::Vector2;
+use cgmath::{InnerSpace, Vector2};
 use wgpu::util::DeviceExt;
 
 use crate::{
+    icons::IconAtlas,
+    overlay::{GetOverlay, OverlayElement},
     plot_graphic::{PlotGraphicElement, PlotGraphicState},
     plot_window::PlotWindowState,
     texture,
 };
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Color {
     pub r: f32,
     pub g: f32,
@@ -27,6 +29,174 @@ impl Color {
     pub fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
         Self { r, g, b, a }
     }
+
+    /// Color from an sRGB hex code such as `0x440154`, converted to the linear values the
+    /// surface expects.
+    pub fn hex(code: u32) -> Self {
+        let channel = |shift: u32| srgb_to_linear(((code >> shift) & 0xff) as f32 / 255.0);
+
+        Self::rgb(channel(16), channel(8), channel(0))
+    }
+
+    pub fn lerp(self, other: Color, t: f32) -> Self {
+        Self {
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
+            a: self.a + (other.a - self.a) * t,
+        }
+    }
+}
+
+/// What line widths and point sizes are measured in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SizeUnits {
+    /// Units of the plot, so lines and points grow and shrink as it zooms.
+    #[default]
+    World,
+    /// Surface pixels, so lines and points look the same at any zoom.
+    Pixels,
+}
+
+impl SizeUnits {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "world" => Some(Self::World),
+            "pixels" | "px" => Some(Self::Pixels),
+            _ => None,
+        }
+    }
+
+    /// The flag the shaders take, nonzero for pixels.
+    pub fn shader_flag(self) -> u32 {
+        match self {
+            Self::World => 0,
+            Self::Pixels => 1,
+        }
+    }
+}
+
+/// How the plot is drawn, as opposed to what it shows.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderOptions {
+    /// Samples per pixel. Falls back to 1 where the device can't multisample that much.
+    pub msaa_samples: u32,
+    pub line_units: SizeUnits,
+    /// Vertex sizes stay in world units by default, since they're also how far apart the
+    /// layout keeps vertices.
+    pub point_units: SizeUnits,
+    /// Bow every edge out, not only parallel ones, which always fan out.
+    pub curved_edges: bool,
+    /// Bundle edges with this strength, from 0 to 1.
+    pub edge_bundling: Option<f32>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            msaa_samples: 4,
+            line_units: SizeUnits::Pixels,
+            point_units: SizeUnits::World,
+            curved_edges: false,
+            edge_bundling: None,
+        }
+    }
+}
+
+/// Outline of a point. Every shape fits the same disk, so changing a point's shape doesn't
+/// change how much room it takes up.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Shape {
+    #[default]
+    Circle,
+    Square,
+    Triangle,
+    Diamond,
+    Hexagon,
+    Star,
+}
+
+impl Shape {
+    pub const ALL: [Shape; 6] = [
+        Self::Circle,
+        Self::Square,
+        Self::Triangle,
+        Self::Diamond,
+        Self::Hexagon,
+        Self::Star,
+    ];
+
+    pub fn parse(text: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|shape| shape.name() == text.to_ascii_lowercase())
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Circle => "circle",
+            Self::Square => "square",
+            Self::Triangle => "triangle",
+            Self::Diamond => "diamond",
+            Self::Hexagon => "hexagon",
+            Self::Star => "star",
+        }
+    }
+
+    /// The index the point shader switches on.
+    fn shader_index(self) -> u32 {
+        self as u32
+    }
+}
+
+/// How a point is drawn.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PointStyle {
+    pub color: Color,
+    /// Radius, in the units of the element drawing the point.
+    pub size: f32,
+    pub shape: Shape,
+    pub outline_color: Color,
+    /// How much of the point's radius its outline takes up, from 0 to 1.
+    pub outline_width: f32,
+    /// Cell of the element's icon atlas drawn over the fill.
+    pub icon: Option<u32>,
+}
+
+impl PointStyle {
+    /// A circle with a thin black outline and no icon.
+    pub fn new(color: Color, size: f32) -> Self {
+        Self {
+            color,
+            size,
+            shape: Shape::Circle,
+            outline_color: Color::rgb(0.0, 0.0, 0.0),
+            outline_width: 0.2,
+            icon: None,
+        }
+    }
+
+    pub fn with_shape(mut self, shape: Shape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    pub fn with_outline(mut self, color: Color, width: f32) -> Self {
+        self.outline_color = color;
+        self.outline_width = width;
+        self
+    }
+
+    pub fn with_icon(mut self, icon: Option<u32>) -> Self {
+        self.icon = icon;
+        self
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
 }
 
 impl Into<[f32; 3]> for Color {
@@ -35,57 +205,124 @@ impl Into<[f32; 3]> for Color {
     }
 }
 
+impl Into<[f32; 4]> for Color {
+    fn into(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+/// Segments a Bézier curve is drawn with.
+const CURVE_SEGMENT_CT: usize = 16;
+
+/// Path of a line, from its first point to its last.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Curve {
+    Straight(Vector2<f32>, Vector2<f32>),
+    /// Quadratic Bézier curve, pulled towards the middle point.
+    Quadratic(Vector2<f32>, Vector2<f32>, Vector2<f32>),
+    /// Cubic Bézier curve, leaving towards the second point and arriving from the third.
+    Cubic(Vector2<f32>, Vector2<f32>, Vector2<f32>, Vector2<f32>),
+    /// Straight segments through every point in turn.
+    Polyline(Vec<Vector2<f32>>),
+}
+
+impl Curve {
+    /// The point `t` of the way along the curve. Polylines spend the same share of `t` on
+    /// each segment.
+    pub fn point(&self, t: f32) -> Vector2<f32> {
+        let s = 1.0 - t;
+        match self {
+            Curve::Straight(a, b) => a * s + b * t,
+            Curve::Quadratic(a, b, c) => a * (s * s) + b * (2.0 * s * t) + c * (t * t),
+            Curve::Cubic(a, b, c, d) => {
+                a * (s * s * s) + b * (3.0 * s * s * t) + c * (3.0 * s * t * t) + d * (t * t * t)
+            }
+            Curve::Polyline(points) => {
+                let Some(last) = points.len().checked_sub(1) else {
+                    return Vector2::new(0.0, 0.0);
+                };
+                let position = t.clamp(0.0, 1.0) * last as f32;
+                let i = (position as usize).min(last.saturating_sub(1));
+                let next = (i + 1).min(last);
+                points[i] + (points[next] - points[i]) * (position - i as f32)
+            }
+        }
+    }
+
+    /// Points along the curve from start to end, close enough together to draw it with
+    /// straight segments.
+    pub fn points(self) -> impl Iterator<Item = Vector2<f32>> {
+        let point_ct = match &self {
+            Curve::Straight(..) => 2,
+            Curve::Polyline(points) => points.len(),
+            _ => CURVE_SEGMENT_CT + 1,
+        };
+        (0..point_ct).map(move |i| match &self {
+            Curve::Polyline(points) => points[i],
+            curve => curve.point(i as f32 / (point_ct - 1) as f32),
+        })
+    }
+
+    /// The curve with its ends moved `start` and `end` towards the points after and before
+    /// them, which trims about that much off each end. An end closer than that to the next
+    /// point stays where it is.
+    pub fn trimmed(self, start: f32, end: f32) -> Curve {
+        let toward = |from: Vector2<f32>, to: Vector2<f32>, distance: f32| {
+            let length = (to - from).magnitude();
+            if length > distance { from + (to - from) * (distance / length) } else { from }
+        };
+        match self {
+            Curve::Straight(a, b) => Curve::Straight(toward(a, b, start), toward(b, a, end)),
+            Curve::Quadratic(a, b, c) => Curve::Quadratic(toward(a, b, start), b, toward(c, b, end)),
+            Curve::Cubic(a, b, c, d) => Curve::Cubic(toward(a, b, start), b, c, toward(d, c, end)),
+            Curve::Polyline(mut points) => {
+                let n = points.len();
+                if n >= 2 {
+                    points[0] = toward(points[0], points[1], start);
+                    points[n - 1] = toward(points[n - 1], points[n - 2], end);
+                }
+                Curve::Polyline(points)
+            }
+        }
+    }
+
+    /// The straight segments `points` joins.
+    pub fn segments(self) -> impl Iterator<Item = (Vector2<f32>, Vector2<f32>)> {
+        let mut points = self.points();
+        let first = points.next();
+        points.scan(first, |previous, point| {
+            let segment = (previous.replace(point)?, point);
+            Some(segment)
+        })
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 struct LineVertex {
     position: [f32; 2],
+    previous: [f32; 2],
     next: [f32; 2],
     offset_distance: f32,
-    color: [f32; 3],
+    color: [f32; 4],
+    pixel_sized: u32,
 }
 
 impl LineVertex {
-    pub fn new(
-        v: Vector2<f32>,
-        next: Vector2<f32>,
-        offset_distance: f32,
-        color: [f32; 3],
-    ) -> LineVertex {
-        Self {
-            position: v.into(),
-            next: next.into(),
-            offset_distance: offset_distance,
-            color: color,
-        }
-    }
+    const ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float32x2,
+        2 => Float32x2,
+        3 => Float32,
+        4 => Float32x4,
+        5 => Uint32,
+    ];
 
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<LineVertex>() as wgpu::BufferAddress,
             step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x2,
-                },
-                wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x2,
-                },
-                wgpu::VertexAttribute {
-                    offset: 2 * std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
-                    shader_location: 2,
-                    format: wgpu::VertexFormat::Float32,
-                },
-                wgpu::VertexAttribute {
-                    offset: (2 * std::mem::size_of::<[f32; 2]>() + std::mem::size_of::<f32>())
-                        as wgpu::BufferAddress,
-                    shader_location: 3,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-            ],
+            attributes: &Self::ATTRIBUTES,
         }
     }
 }
@@ -145,34 +382,34 @@ struct PointInstance {
     position: [f32; 2],
     color: [f32; 3],
     size: f32,
+    pixel_sized: u32,
+    outline_color: [f32; 3],
+    outline_thickness: f32,
+    shape: u32,
+    /// Corners of the icon in the atlas, or all zero for no icon.
+    icon_rect: [f32; 4],
 }
 
 impl PointInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 8] = wgpu::vertex_attr_array![
+        2 => Float32x2,
+        3 => Float32x3,
+        4 => Float32,
+        5 => Uint32,
+        6 => Float32x3,
+        7 => Float32,
+        8 => Uint32,
+        9 => Float32x4,
+    ];
+
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
-        use std::mem;
         wgpu::VertexBufferLayout {
-            array_stride: mem::size_of::<PointInstance>() as wgpu::BufferAddress,
+            array_stride: std::mem::size_of::<PointInstance>() as wgpu::BufferAddress,
             // We need to switch from using a step mode of Vertex to Instance
             // This means that our shaders will only change to use the next
             // instance when the shader starts processing a new instance
             step_mode: wgpu::VertexStepMode::Instance,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 2,
-                    format: wgpu::VertexFormat::Float32x2,
-                },
-                wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
-                    shader_location: 3,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-                wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 2]>() as wgpu::BufferAddress + mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                    shader_location: 4,
-                    format: wgpu::VertexFormat::Float32,
-                },
-            ],
+            attributes: &Self::ATTRIBUTES,
         }
     }
 }
@@ -182,27 +419,46 @@ pub struct PointElement {
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     instance_buffer: wgpu::Buffer,
+    units: SizeUnits,
+    icon_bind_group_layout: wgpu::BindGroupLayout,
+    icon_bind_group: wgpu::BindGroup,
+    icons: IconAtlas,
 
     instances: Vec<PointInstance>,
 }
 
+/// Points as their center and style.
 pub trait GetPoints {
-    fn get_points(&self) -> impl Iterator<Item = (Vector2<f32>, Color, f32)>;
+    fn get_points(&self) -> impl Iterator<Item = (Vector2<f32>, PointStyle)>;
 }
 
 impl<T: GetPoints> PlotGraphicElement<T> for PointElement {
     fn update(&mut self, window_state: &PlotWindowState, graphic: &T) {
         self.instances.clear();
 
-        for (position, color, size) in graphic.get_points() {
+        for (position, style) in graphic.get_points() {
             self.instances.push(PointInstance {
                 position: position.into(),
-                color: color.into(),
-                size: size,
+                color: style.color.into(),
+                size: style.size,
+                pixel_sized: self.units.shader_flag(),
+                outline_color: style.outline_color.into(),
+                outline_thickness: style.outline_width.clamp(0.0, 1.0),
+                shape: style.shape.shader_index(),
+                icon_rect: style.icon.and_then(|icon| self.icons.cell(icon)).unwrap_or_default(),
             });
         }
 
         // println!("{:?}", self.instances);
+        let instance_bytes = std::mem::size_of_val(self.instances.as_slice()) as u64;
+        if instance_bytes > self.instance_buffer.size() {
+            self.instance_buffer = window_state.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Point Instance Buffer"),
+                size: instance_bytes.next_power_of_two(),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        }
         window_state.queue.write_buffer(
             &self.instance_buffer,
             0,
@@ -213,6 +469,7 @@ impl<T: GetPoints> PlotGraphicElement<T> for PointElement {
     fn render(&self, graphic_state: &PlotGraphicState<T>, render_pass: &mut wgpu::RenderPass) {
         render_pass.set_pipeline(&self.render_pipeline);
         render_pass.set_bind_group(0, &graphic_state.camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.icon_bind_group, &[]);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
         render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
@@ -236,12 +493,44 @@ impl PointElement {
                 source: wgpu::ShaderSource::Wgsl(include_str!("../assets/point_shader.wgsl").into()),
             });
 
+        let icon_bind_group_layout =
+            window_state.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+                label: Some("icon_bind_group_layout"),
+            });
+        let icons = IconAtlas::empty();
+        let icon_bind_group = Self::icon_bind_group(window_state, &icon_bind_group_layout, &icons);
+
+        let render_pipeline_layout =
+            window_state.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Point Pipeline Layout"),
+                bind_group_layouts: &[&graphic_state.camera_bind_group_layout, &icon_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
         let render_pipeline =
             window_state
                 .device
                 .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
                     label: Some("Render Pipeline"),
-                    layout: Some(&graphic_state.render_pipeline_layout),
+                    layout: Some(&render_pipeline_layout),
                     vertex: wgpu::VertexState {
                         module: &shader,
                         entry_point: Some("vs_main"), // 1.
@@ -281,7 +570,7 @@ impl PointElement {
                     // }), // 1.
                     depth_stencil: None,
                     multisample: wgpu::MultisampleState {
-                        count: 1,                         // 2.
+                        count: window_state.sample_count, // 2.
                         mask: !0,                         // 3.
                         alpha_to_coverage_enabled: false, // 4.
                     },
@@ -321,47 +610,126 @@ impl PointElement {
             instance_buffer,
             vertex_buffer,
             index_buffer,
+            units: SizeUnits::World,
+            icon_bind_group_layout,
+            icon_bind_group,
+            icons,
             instances,
         }
     }
+
+    /// Measures point sizes in `units` instead of world units.
+    pub fn with_units(mut self, units: SizeUnits) -> Self {
+        self.units = units;
+        self
+    }
+
+    /// Takes the icons points ask for from `icons`.
+    pub fn with_icons(mut self, window_state: &PlotWindowState, icons: &IconAtlas) -> Self {
+        self.icon_bind_group = Self::icon_bind_group(window_state, &self.icon_bind_group_layout, icons);
+        self.icons = icons.clone();
+        self
+    }
+
+    fn icon_bind_group(
+        window_state: &PlotWindowState,
+        layout: &wgpu::BindGroupLayout,
+        icons: &IconAtlas,
+    ) -> wgpu::BindGroup {
+        let texture = texture::Texture::from_rgba(
+            &window_state.device,
+            &window_state.queue,
+            icons.width,
+            icons.height,
+            &icons.pixels,
+            "Icon Atlas",
+        );
+
+        window_state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                },
+            ],
+            label: Some("icon_bind_group"),
+        })
+    }
 }
 
+/// Lines as their path, color and width, in the units of the element drawing them.
 pub trait GetLines {
-    fn get_lines(&self) -> impl Iterator<Item = (Vector2<f32>, Vector2<f32>, Color)>;
+    fn get_lines(&self) -> impl Iterator<Item = (Curve, Color, f32)>;
 }
 
 pub struct LineElement {
     render_pipeline: wgpu::RenderPipeline,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
+    units: SizeUnits,
 
     vertices: Vec<LineVertex>,
-    indices: Vec<u16>,
+    indices: Vec<u32>,
+    /// Points of the curve being tessellated.
+    points: Vec<Vector2<f32>>,
 }
 
 impl<T: GetLines> PlotGraphicElement<T> for LineElement {
     fn update(&mut self, window_state: &PlotWindowState, lines: &T) {
-        let mut index = 0;
         self.vertices.clear();
         self.indices.clear();
 
-        for (x0, x1, color) in lines.get_lines() {
-            self.vertices
-                .push(LineVertex::new(x0, x1, 0.02, color.into()));
-            self.vertices
-                .push(LineVertex::new(x0, x1, -0.02, color.into()));
-            self.vertices
-                .push(LineVertex::new(x1, x0, -0.02, color.into()));
-            self.vertices
-                .push(LineVertex::new(x1, x0, 0.02, color.into()));
-
-            self.indices.push(index + 0);
-            self.indices.push(index + 1);
-            self.indices.push(index + 3);
-            self.indices.push(index + 0);
-            self.indices.push(index + 3);
-            self.indices.push(index + 2);
-            index += 4;
+        for (curve, color, width) in lines.get_lines() {
+            self.points.clear();
+            self.points.extend(curve.points());
+
+            // Each point gets a vertex on either side, pushed out along the corner it's on
+            // so consecutive segments meet without gaps.
+            let first = self.vertices.len() as u32;
+            let last = self.points.len() - 1;
+            for (i, point) in self.points.iter().enumerate() {
+                let previous = self.points[i.saturating_sub(1)];
+                let next = self.points[(i + 1).min(last)];
+                for offset_distance in [width / 2.0, -width / 2.0] {
+                    self.vertices.push(LineVertex {
+                        position: (*point).into(),
+                        previous: previous.into(),
+                        next: next.into(),
+                        offset_distance,
+                        color: color.into(),
+                        pixel_sized: self.units.shader_flag(),
+                    });
+                }
+            }
+            for i in 0..last as u32 {
+                let index = first + 2 * i;
+                self.indices
+                    .extend_from_slice(&[index, index + 1, index + 3, index, index + 3, index + 2]);
+            }
+        }
+
+        let vertex_bytes = std::mem::size_of_val(self.vertices.as_slice()) as u64;
+        if vertex_bytes > self.vertex_buffer.size() {
+            self.vertex_buffer = window_state.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Edge Vertex Buffer"),
+                size: vertex_bytes.next_power_of_two(),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        }
+        let index_bytes = std::mem::size_of_val(self.indices.as_slice()) as u64;
+        if index_bytes > self.index_buffer.size() {
+            self.index_buffer = window_state.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Edge Index Buffer"),
+                size: index_bytes.next_power_of_two(),
+                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
         }
 
         window_state.queue.write_buffer(
@@ -375,10 +743,13 @@ impl<T: GetLines> PlotGraphicElement<T> for LineElement {
     }
 
     fn render(&self, graphic_state: &PlotGraphicState<T>, render_pass: &mut wgpu::RenderPass) {
+        if self.indices.is_empty() {
+            return;
+        }
         render_pass.set_pipeline(&self.render_pipeline); // 2.
         render_pass.set_bind_group(0, &graphic_state.camera_bind_group, &[]);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
         render_pass.draw_indexed(0..(self.indices.len() as u32), 0, 0..1);
     }
 }
@@ -440,7 +811,7 @@ impl LineElement {
                     // }), // 1.
                     depth_stencil: None,
                     multisample: wgpu::MultisampleState {
-                        count: 1,                         // 2.
+                        count: window_state.sample_count, // 2.
                         mask: !0,                         // 3.
                         alpha_to_coverage_enabled: false, // 4.
                     },
@@ -469,8 +840,212 @@ impl LineElement {
             render_pipeline,
             vertex_buffer,
             index_buffer,
+            units: SizeUnits::World,
             indices,
             vertices,
+            points: Vec::new(),
         }
     }
+
+    /// Measures line widths in `units` instead of world units.
+    pub fn with_units(mut self, units: SizeUnits) -> Self {
+        self.units = units;
+        self
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct PolygonVertex {
+    position: [f32; 2],
+    color: [f32; 4],
+}
+
+impl PolygonVertex {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<PolygonVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// Filled convex polygons in world space, given as their boundary in order.
+pub trait GetPolygons {
+    fn get_polygons(&self) -> impl Iterator<Item = (Vec<Vector2<f32>>, Color)>;
+}
+
+pub struct PolygonElement {
+    render_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+
+    vertices: Vec<PolygonVertex>,
+    indices: Vec<u32>,
+}
+
+impl<T: GetPolygons> PlotGraphicElement<T> for PolygonElement {
+    fn update(&mut self, window_state: &PlotWindowState, polygons: &T) {
+        self.vertices.clear();
+        self.indices.clear();
+
+        for (boundary, color) in polygons.get_polygons() {
+            if boundary.len() < 3 {
+                continue;
+            }
+            let first = self.vertices.len() as u32;
+            for position in &boundary {
+                self.vertices.push(PolygonVertex {
+                    position: (*position).into(),
+                    color: color.into(),
+                });
+            }
+            for i in 1..(boundary.len() as u32 - 1) {
+                self.indices.extend_from_slice(&[first, first + i, first + i + 1]);
+            }
+        }
+
+        let vertex_bytes = std::mem::size_of_val(self.vertices.as_slice()) as u64;
+        if vertex_bytes > self.vertex_buffer.size() {
+            self.vertex_buffer = window_state.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Polygon Vertex Buffer"),
+                size: vertex_bytes.next_power_of_two(),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        }
+        let index_bytes = std::mem::size_of_val(self.indices.as_slice()) as u64;
+        if index_bytes > self.index_buffer.size() {
+            self.index_buffer = window_state.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Polygon Index Buffer"),
+                size: index_bytes.next_power_of_two(),
+                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        }
+
+        window_state.queue.write_buffer(
+            &self.vertex_buffer,
+            0,
+            bytemuck::cast_slice(&self.vertices),
+        );
+        window_state
+            .queue
+            .write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(&self.indices));
+    }
+
+    fn render(&self, graphic_state: &PlotGraphicState<T>, render_pass: &mut wgpu::RenderPass) {
+        if self.indices.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &graphic_state.camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..(self.indices.len() as u32), 0, 0..1);
+    }
+}
+
+impl PolygonElement {
+    pub fn new<T: GetPolygons>(
+        graphic_state: &PlotGraphicState<T>,
+        window_state: &PlotWindowState,
+    ) -> Self {
+        let shader = window_state
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Polygon Shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("../assets/polygon_shader.wgsl").into()),
+            });
+
+        let render_pipeline =
+            window_state
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("Polygon Pipeline"),
+                    layout: Some(&graphic_state.render_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: Some("vs_main"),
+                        buffers: &[PolygonVertex::desc()],
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: Some("fs_main"),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: window_state.config.format,
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Ccw,
+                        // Boundaries may wind either way.
+                        cull_mode: None,
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        unclipped_depth: false,
+                        conservative: false,
+                    },
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState {
+                        count: window_state.sample_count,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    multiview: None,
+                    cache: None,
+                });
+
+        let vertex_buffer = window_state.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Polygon Vertex Buffer"),
+            size: 1 << 16,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let index_buffer = window_state.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Polygon Index Buffer"),
+            size: 1 << 16,
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            render_pipeline,
+            vertex_buffer,
+            index_buffer,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+}
+
+impl<T: GetOverlay> PlotGraphicElement<T> for OverlayElement {
+    fn update(&mut self, window_state: &PlotWindowState, data: &T) {
+        self.prepare(window_state, data);
+    }
+
+    fn render(&self, _graphic_state: &PlotGraphicState<T>, render_pass: &mut wgpu::RenderPass) {
+        self.draw(render_pass);
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
 }
@@ -0,0 +1,101 @@
+/// A graph given as its edges, each with an optional weight. Vertices are numbered from 0.
+#[derive(Clone, Debug, Default)]
+pub struct EdgeList {
+    pub vertex_ct: usize,
+    pub edges: Vec<(usize, usize, Option<f64>)>,
+}
+
+impl EdgeList {
+    /// Without `vertex_ct`, the vertex count is one more than the largest vertex named.
+    pub fn new(vertex_ct: Option<usize>, edges: Vec<(usize, usize, Option<f64>)>) -> Result<Self, String> {
+        let named = edges.iter().map(|(u, v, _)| u.max(v) + 1).max().unwrap_or(0);
+        let vertex_ct = vertex_ct.unwrap_or(named);
+        if named > vertex_ct {
+            return Err(format!("vertex {} out of range for {} vertices", named - 1, vertex_ct));
+        }
+        // Dijkstra and Prim need weights of 0 or above.
+        let invalid_weight = edges
+            .iter()
+            .filter_map(|(_, _, weight)| *weight)
+            .find(|weight| weight.is_nan() || *weight < 0.0);
+        if let Some(weight) = invalid_weight {
+            return Err(format!("invalid weight {}, weights can't be negative", weight));
+        }
+
+        Ok(Self { vertex_ct, edges })
+    }
+}
+
+/// Parses one edge per line, as `u v` or `u v weight`, the format the plot exports edges in.
+/// Blank lines and lines starting with `#` are skipped.
+pub fn parse_edge_list(text: &str) -> Result<EdgeList, String> {
+    let mut edges = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid = || format!("line {}: expected \"u v\" or \"u v weight\", got \"{}\"", i + 1, line);
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        let vertex = |field: &str| field.parse::<usize>().map_err(|_| invalid());
+        let (u, v, weight) = match fields.as_slice() {
+            [u, v] => (vertex(u)?, vertex(v)?, None),
+            [u, v, weight] => (vertex(u)?, vertex(v)?, Some(weight.parse::<f64>().map_err(|_| invalid())?)),
+            _ => return Err(invalid()),
+        };
+        edges.push((u, v, weight));
+    }
+
+    EdgeList::new(None, edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let list = parse_edge_list("# a triangle\n\n0 1\n  1 2 0.5  \n\t\n# done\n2 0 3\n").unwrap();
+        assert_eq!(list.vertex_ct, 3);
+        assert_eq!(list.edges, [(0, 1, None), (1, 2, Some(0.5)), (2, 0, Some(3.0))]);
+
+        let empty = parse_edge_list("# nothing\n\n").unwrap();
+        assert_eq!(empty.vertex_ct, 0);
+        assert!(empty.edges.is_empty());
+    }
+
+    #[test]
+    fn counts_vertices_up_to_the_largest_named() {
+        assert_eq!(EdgeList::new(None, vec![(4, 1, None)]).unwrap().vertex_ct, 5);
+        assert_eq!(EdgeList::new(Some(8), vec![(4, 1, None)]).unwrap().vertex_ct, 8);
+        assert_eq!(EdgeList::new(Some(5), vec![(4, 1, None)]).unwrap().vertex_ct, 5);
+        assert_eq!(
+            EdgeList::new(Some(4), vec![(0, 1, None), (1, 4, None)]).unwrap_err(),
+            "vertex 4 out of range for 4 vertices"
+        );
+    }
+
+    #[test]
+    fn rejects_negative_and_nan_weights() {
+        assert!(EdgeList::new(None, vec![(0, 1, Some(0.0))]).is_ok());
+        assert_eq!(
+            EdgeList::new(None, vec![(0, 1, Some(1.0)), (1, 2, Some(-2.0))]).unwrap_err(),
+            "invalid weight -2, weights can't be negative"
+        );
+        assert!(EdgeList::new(None, vec![(0, 1, Some(f64::NAN))]).unwrap_err().starts_with("invalid weight NaN"));
+        assert!(parse_edge_list("0 1 NaN\n").is_err());
+        assert!(parse_edge_list("0 1 -1\n").is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        let error = |text| parse_edge_list(text).unwrap_err();
+        assert_eq!(error("0 1\n2\n"), "line 2: expected \"u v\" or \"u v weight\", got \"2\"");
+        assert_eq!(error("0 1 2 3\n"), "line 1: expected \"u v\" or \"u v weight\", got \"0 1 2 3\"");
+        assert!(error("0 -1\n").starts_with("line 1:"));
+        assert!(error("a b\n").starts_with("line 1:"));
+        assert!(error("0 1 heavy\n").starts_with("line 1:"));
+        assert!(error("\n\n0 1.5\n").starts_with("line 3:"));
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use gscientific::graph::Graph;
+
+/// Edge weights edited in the plot. Every other edge keeps the weight the graph gives it.
+#[derive(Default)]
+pub struct EdgeWeights {
+    /// Keyed with the smaller endpoint first.
+    weights: HashMap<(usize, usize), f64>,
+}
+
+fn key(u: usize, v: usize) -> (usize, usize) {
+    (u.min(v), u.max(v))
+}
+
+impl EdgeWeights {
+    pub fn get(&self, u: usize, v: usize, graph_weight: f64) -> f64 {
+        self.weights.get(&key(u, v)).copied().unwrap_or(graph_weight)
+    }
+
+    pub fn set(&mut self, u: usize, v: usize, weight: f64) {
+        self.weights.insert(key(u, v), weight);
+    }
+
+    pub fn remove_edge(&mut self, u: usize, v: usize) {
+        self.weights.remove(&key(u, v));
+    }
+
+    /// Forgets the edges of `v` and renumbers the vertices after it, the way
+    /// `Graph::remove_vertex` does.
+    pub fn remove_vertex(&mut self, v: usize) {
+        let shift = |u: usize| if u > v { u - 1 } else { u };
+        self.weights = self
+            .weights
+            .drain()
+            .filter(|((a, b), _)| *a != v && *b != v)
+            .map(|((a, b), weight)| ((shift(a), shift(b)), weight))
+            .collect();
+    }
+
+    /// Forgets the edges of every vertex from `n` on, for `Graph::resize`.
+    pub fn truncate(&mut self, n: usize) {
+        self.weights.retain(|(_, b), _| *b < n);
+    }
+
+    pub fn clear(&mut self) {
+        self.weights.clear();
+    }
+
+    /// `graph.neighbors(u)` with edited weights in place of the graph's.
+    pub fn neighbors<'a, T: Graph>(&'a self, graph: &'a T, u: usize) -> impl Iterator<Item = (usize, f64)> + 'a {
+        graph.neighbors(u).map(move |(v, weight)| (v, self.get(u, v, weight)))
+    }
+
+    /// `graph.edges()` with edited weights in place of the graph's.
+    pub fn edges<'a, T: Graph>(&'a self, graph: &'a T) -> impl Iterator<Item = (usize, usize, f64)> + 'a {
+        graph.edges().map(move |(u, v, weight)| (u, v, self.get(u, v, weight)))
+    }
+
+    /// Weight of the edge between `u` and `v`, if there is one.
+    pub fn weight<T: Graph>(&self, graph: &T, u: usize, v: usize) -> Option<f64> {
+        self.neighbors(graph, u).find(|(w, _)| *w == v).map(|(_, weight)| weight)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_graphs;
+
+    #[test]
+    fn removing_a_vertex_renumbers_the_weights_after_it() {
+        let mut graph = test_graphs::path(5);
+        let mut weights = EdgeWeights::default();
+        weights.set(1, 0, 2.0);
+        weights.set(1, 2, 3.0);
+        weights.set(3, 2, 4.0);
+        weights.set(3, 4, 5.0);
+
+        graph.remove_vertex(1);
+        weights.remove_vertex(1);
+
+        // 2 - 3 - 4 are now 1 - 2 - 3, and 0 is left on its own.
+        assert_eq!(weights.weight(&graph, 0, 1), None);
+        assert_eq!(weights.weight(&graph, 2, 1), Some(4.0));
+        assert_eq!(weights.weight(&graph, 2, 3), Some(5.0));
+        assert_eq!(weights.get(0, 1, -1.0), -1.0);
+    }
+
+    #[test]
+    fn truncating_forgets_edges_past_the_end() {
+        let mut weights = EdgeWeights::default();
+        weights.set(0, 1, 2.0);
+        weights.set(3, 1, 3.0);
+        weights.truncate(3);
+
+        assert_eq!(weights.get(1, 0, -1.0), 2.0);
+        assert_eq!(weights.get(1, 3, -1.0), -1.0);
+    }
+}
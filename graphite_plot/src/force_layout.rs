@@ -0,0 +1,645 @@
+use std::sync::Arc;
+
+use instant::{Duration, Instant};
+
+use gscientific::linalg::{BasicMatrix, Matrix};
+
+use cgmath::{InnerSpace, Vector2, Zero};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::simulation::Simulation;
+
+const EDGE_SPRING_STIFFNESS: f32 = 1.0;
+const EDGE_EQ_LENGTH_MULT: f32 = 2.0;
+const RESISTANCE: f32 = 1.0;
+const CENTERING: f32 = 0.1;
+const COMMUNITY_ATTRACTION: f32 = 0.5;
+const SPEED_LIMIT: f32 = 100.0;
+pub const SIMULATION_TIMESTEP: f32 = 1.0 / 120.0;
+const MAX_STEPS_PER_TICK: u32 = 8;
+const SETTLED_KINETIC_ENERGY: f32 = 1e-4;
+const SETTLED_STEP_CT: u32 = 60;
+/// Pair forces are gathered in up to this many blocks of particles, each into its own force
+/// buffer. The count only depends on the particle count, not the thread count, so the
+/// buffers are summed in the same order however the blocks are scheduled.
+const MAX_FORCE_BLOCK_CT: usize = 64;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Integrator {
+    Euler,
+    #[default]
+    VelocityVerlet,
+    RungeKutta4,
+}
+
+impl Integrator {
+    pub fn next(self) -> Self {
+        match self {
+            Integrator::Euler => Integrator::VelocityVerlet,
+            Integrator::VelocityVerlet => Integrator::RungeKutta4,
+            Integrator::RungeKutta4 => Integrator::Euler,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Integrator::Euler => "Euler",
+            Integrator::VelocityVerlet => "Velocity Verlet",
+            Integrator::RungeKutta4 => "Runge-Kutta 4",
+        }
+    }
+}
+
+/// Force constants the layout can be tuned with while it runs.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LayoutParameters {
+    pub spring_stiffness: f32,
+    /// Rest length of the spring between two vertices per unit of graph distance.
+    pub eq_length_mult: f32,
+    pub resistance: f32,
+    pub centering: f32,
+    pub community_attraction: f32,
+}
+
+impl Default for LayoutParameters {
+    fn default() -> Self {
+        Self {
+            spring_stiffness: EDGE_SPRING_STIFFNESS,
+            eq_length_mult: EDGE_EQ_LENGTH_MULT,
+            resistance: RESISTANCE,
+            centering: CENTERING,
+            community_attraction: COMMUNITY_ATTRACTION,
+        }
+    }
+}
+
+/// The physical side of a force plot. It only knows particles and graph distances so it can
+/// run on its own thread while the plot keeps the graph itself.
+pub struct ForceLayout {
+    pub particles: Vec<PhysicsParticle>,
+    pub graph_distances: Arc<BasicMatrix<i32>>,
+    /// Set while the graph has changed and its distances are still being computed. The
+    /// layout holds still until they arrive.
+    pub distances_outdated: bool,
+    /// A particle held by the user, which the integrator leaves alone.
+    pub pinned: Option<usize>,
+    /// A particle that is still being placed. It feels no forces from the others and exerts
+    /// none on them.
+    pub isolated: Option<usize>,
+    pub pause: bool,
+    pub integrator: Integrator,
+    pub parameters: LayoutParameters,
+    /// Community of every particle. When set, members are pulled towards their community's
+    /// centroid.
+    pub communities: Option<Arc<Vec<usize>>>,
+    /// While set, time only passes in `advance_frame`, this much per call. Recordings use it
+    /// so every frame shows the same simulated time, however long frames take to draw.
+    pub fixed_frame: Option<Duration>,
+    /// Number of `advance_frame` calls so far.
+    frame_ct: u64,
+    time_accumulator: f32,
+    settled: bool,
+    settled_step_ct: u32,
+    /// Wall time the latest tick spent stepping.
+    tick_time: Duration,
+}
+
+#[derive(Default)]
+pub struct LayoutSnapshot {
+    pub positions: Vec<Vector2<f32>>,
+    pub velocities: Vec<Vector2<f32>>,
+    pub settled: bool,
+    pub paused: bool,
+    pub integrator: Integrator,
+    pub kinetic_energy: f32,
+    pub tick_time: Duration,
+    pub frame_ct: u64,
+}
+
+impl ForceLayout {
+    pub fn new(particles: Vec<PhysicsParticle>, graph_distances: Arc<BasicMatrix<i32>>) -> Self {
+        Self {
+            particles,
+            graph_distances,
+            distances_outdated: false,
+            pinned: None,
+            isolated: None,
+            pause: false,
+            integrator: Integrator::VelocityVerlet,
+            parameters: LayoutParameters::default(),
+            communities: None,
+            fixed_frame: None,
+            frame_ct: 0,
+            time_accumulator: 0.0,
+            settled: false,
+            settled_step_ct: 0,
+            tick_time: Duration::ZERO,
+        }
+    }
+
+    /// Advances the simulation by however many fixed timesteps fit into `delta_t`, so the
+    /// layout doesn't depend on the frame rate. Does nothing while frames are fixed.
+    pub fn tick(&mut self, delta_t: Duration) {
+        if self.fixed_frame.is_some() {
+            return;
+        }
+        self.advance(delta_t);
+    }
+
+    /// Advances the simulation by one `fixed_frame`.
+    pub fn advance_frame(&mut self) {
+        if let Some(frame) = self.fixed_frame {
+            self.advance(frame);
+        }
+        self.frame_ct += 1;
+    }
+
+    fn advance(&mut self, delta_t: Duration) {
+        if self.pause || self.settled || self.distances_outdated {
+            self.time_accumulator = 0.0;
+            self.tick_time = Duration::ZERO;
+            return;
+        }
+        let start = Instant::now();
+
+        self.time_accumulator = (self.time_accumulator + delta_t.as_secs_f32())
+            .min(MAX_STEPS_PER_TICK as f32 * SIMULATION_TIMESTEP);
+
+        while self.time_accumulator >= SIMULATION_TIMESTEP {
+            self.step(SIMULATION_TIMESTEP);
+            self.time_accumulator -= SIMULATION_TIMESTEP;
+
+            if self.settled {
+                self.time_accumulator = 0.0;
+                break;
+            }
+        }
+        self.tick_time = start.elapsed();
+    }
+
+    pub fn step(&mut self, delta_t: f32) {
+        match self.integrator {
+            Integrator::Euler => {
+                self.accumulate_forces();
+                for u in 0..self.particles.len() {
+                    if !self.is_pinned(u) {
+                        self.particles[u].step(delta_t);
+                    }
+                }
+            }
+            Integrator::VelocityVerlet => {
+                self.accumulate_forces();
+                for u in 0..self.particles.len() {
+                    if !self.is_pinned(u) {
+                        self.particles[u].verlet_position_step(delta_t);
+                    }
+                }
+                self.accumulate_forces();
+                for u in 0..self.particles.len() {
+                    if !self.is_pinned(u) {
+                        self.particles[u].verlet_velocity_step(delta_t);
+                    }
+                }
+            }
+            Integrator::RungeKutta4 => self.runge_kutta_step(delta_t),
+        }
+
+        self.update_settled();
+    }
+
+    fn runge_kutta_step(&mut self, delta_t: f32) {
+        let start = self
+            .particles
+            .iter()
+            .map(|particle| (particle.position, particle.velocity))
+            .collect::<Vec<_>>();
+        let mut k_position = vec![[Vector2::zero(); 4]; self.particles.len()];
+        let mut k_velocity = vec![[Vector2::zero(); 4]; self.particles.len()];
+        let stage_t = [0.0, 0.5 * delta_t, 0.5 * delta_t, delta_t];
+
+        for stage in 0..4 {
+            if stage > 0 {
+                for u in 0..self.particles.len() {
+                    if self.is_pinned(u) {
+                        continue;
+                    }
+                    self.particles[u].position = start[u].0 + k_position[u][stage - 1] * stage_t[stage];
+                    self.particles[u].velocity = start[u].1 + k_velocity[u][stage - 1] * stage_t[stage];
+                }
+            }
+
+            self.accumulate_forces();
+            for u in 0..self.particles.len() {
+                k_position[u][stage] = self.particles[u].velocity;
+                k_velocity[u][stage] = self.particles[u].get_acceleration();
+            }
+        }
+
+        for u in 0..self.particles.len() {
+            if self.is_pinned(u) {
+                continue;
+            }
+            let k_x = k_position[u];
+            let k_v = k_velocity[u];
+            self.particles[u].position =
+                start[u].0 + (k_x[0] + k_x[1] * 2.0 + k_x[2] * 2.0 + k_x[3]) * (delta_t / 6.0);
+            self.particles[u].velocity =
+                start[u].1 + (k_v[0] + k_v[1] * 2.0 + k_v[2] * 2.0 + k_v[3]) * (delta_t / 6.0);
+            self.particles[u].limit_speed();
+        }
+    }
+
+    /// The sequential and `parallel` builds split the pair forces into the same blocks and
+    /// sum them in the same order, so they produce identical layouts.
+    fn accumulate_forces(&mut self) {
+        let centroids = self.community_centroids();
+        let communities = self
+            .communities
+            .as_deref()
+            .map(|communities| (communities.as_slice(), centroids.as_slice()));
+        let forces = pair_forces(&self.particles, &self.graph_distances, &self.parameters, self.isolated);
+
+        for (u, force) in forces.into_iter().enumerate() {
+            let force = force + particle_force(&self.particles[u], &self.parameters, self.isolated == Some(u), communities, u);
+            self.particles[u].clear_force();
+            self.particles[u].add_force(force);
+        }
+    }
+
+    fn community_centroids(&self) -> Vec<Vector2<f32>> {
+        let Some(communities) = self.communities.as_deref() else {
+            return Vec::new();
+        };
+        let community_ct = communities.iter().max().map_or(0, |max| max + 1);
+        let mut sums = vec![(Vector2::zero(), 0); community_ct];
+
+        for (u, (particle, community)) in self.particles.iter().zip(communities).enumerate() {
+            if self.isolated != Some(u) {
+                sums[*community].0 += particle.position;
+                sums[*community].1 += 1;
+            }
+        }
+
+        sums.into_iter()
+            .map(|(sum, ct): (Vector2<f32>, usize)| sum / ct.max(1) as f32)
+            .collect()
+    }
+
+    fn is_pinned(&self, u: usize) -> bool {
+        self.pinned == Some(u)
+    }
+
+    pub fn kinetic_energy(&self) -> f32 {
+        self.particles
+            .iter()
+            .map(|particle| particle.kinetic_energy())
+            .sum()
+    }
+
+    fn update_settled(&mut self) {
+        let particle_ct = self.particles.len().max(1) as f32;
+        if self.kinetic_energy() / particle_ct < SETTLED_KINETIC_ENERGY {
+            self.settled_step_ct += 1;
+        } else {
+            self.settled_step_ct = 0;
+        }
+
+        if self.settled_step_ct >= SETTLED_STEP_CT {
+            self.settled = true;
+        }
+    }
+
+    /// Resumes a settled simulation. Called whenever the graph or a particle is edited.
+    pub fn wake(&mut self) {
+        self.settled = false;
+        self.settled_step_ct = 0;
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.settled
+    }
+
+    pub fn set_position(&mut self, u: usize, position: Vector2<f32>) {
+        if let Some(particle) = self.particles.get_mut(u) {
+            particle.position = position;
+            self.wake();
+        }
+    }
+}
+
+impl Simulation for ForceLayout {
+    type Snapshot = LayoutSnapshot;
+
+    fn tick(&mut self, delta_t: Duration) {
+        ForceLayout::tick(self, delta_t);
+    }
+
+    fn write_snapshot(&self, snapshot: &mut LayoutSnapshot) {
+        snapshot.positions.clear();
+        snapshot
+            .positions
+            .extend(self.particles.iter().map(|particle| particle.position));
+        snapshot.velocities.clear();
+        snapshot
+            .velocities
+            .extend(self.particles.iter().map(|particle| particle.velocity));
+        snapshot.settled = self.settled;
+        snapshot.paused = self.pause;
+        snapshot.integrator = self.integrator;
+        snapshot.kinetic_energy = self.kinetic_energy();
+        snapshot.tick_time = self.tick_time;
+        snapshot.frame_ct = self.frame_ct;
+    }
+
+    fn is_idle(&self) -> bool {
+        self.pause || self.settled || self.distances_outdated || self.fixed_frame.is_some()
+    }
+}
+
+/// Force on `particle`, the `u`th, that doesn't come from another particle. An `isolated`
+/// particle (a vertex that is still being placed) only feels damping and centering.
+/// `communities` holds the community of every particle and the centroid of every community.
+fn particle_force(
+    particle: &PhysicsParticle,
+    parameters: &LayoutParameters,
+    isolated: bool,
+    communities: Option<(&[usize], &[Vector2<f32>])>,
+    u: usize,
+) -> Vector2<f32> {
+    let mut force = -particle.get_velocity() * parameters.resistance
+        - particle.get_position() * parameters.centering;
+
+    if isolated {
+        return force;
+    }
+
+    if let Some((communities, centroids)) = communities
+        && let Some(community) = communities.get(u)
+    {
+        force += (centroids[*community] - particle.get_position()) * parameters.community_attraction;
+    }
+
+    force
+}
+
+/// Spring force between every pair of particles, each pair computed once and applied to
+/// both ends. The `isolated` particle exerts no force and feels none.
+fn pair_forces(
+    particles: &[PhysicsParticle],
+    graph_distances: &BasicMatrix<i32>,
+    parameters: &LayoutParameters,
+    isolated: Option<usize>,
+) -> Vec<Vector2<f32>> {
+    let block_ct = MAX_FORCE_BLOCK_CT.min(particles.len());
+    let block_forces = |block| pair_block_forces(particles, graph_distances, parameters, isolated, block, block_ct);
+
+    #[cfg(feature = "parallel")]
+    let blocks = (0..block_ct).into_par_iter().map(block_forces).collect::<Vec<_>>();
+    #[cfg(not(feature = "parallel"))]
+    let blocks = (0..block_ct).map(block_forces);
+
+    sum_forces(particles.len(), blocks)
+}
+
+/// Forces between every particle `u` in `block`, which holds every `block_ct`th particle,
+/// and the particles after it. Interleaving the blocks gives each a similar share of the
+/// pairs.
+fn pair_block_forces(
+    particles: &[PhysicsParticle],
+    graph_distances: &BasicMatrix<i32>,
+    parameters: &LayoutParameters,
+    isolated: Option<usize>,
+    block: usize,
+    block_ct: usize,
+) -> Vec<Vector2<f32>> {
+    let mut forces = vec![Vector2::zero(); particles.len()];
+    for u in (block..particles.len()).step_by(block_ct) {
+        if isolated == Some(u) {
+            continue;
+        }
+        for v in u + 1..particles.len() {
+            if isolated == Some(v) {
+                continue;
+            }
+            let graph_distance = *graph_distances.get(u, v);
+            let eq_length = if graph_distance != -1 { parameters.eq_length_mult * graph_distance as f32 } else { 20.0 * parameters.eq_length_mult };
+            let r = (particles[u].get_position() - particles[v].get_position()).magnitude();
+            let direction = (particles[u].get_position() - particles[v].get_position()) / r;
+
+            let force = -(r / eq_length).ln() * parameters.spring_stiffness * direction;
+            forces[u] += force;
+            forces[v] -= force;
+        }
+    }
+    forces
+}
+
+/// Sums per-block force buffers in block order.
+fn sum_forces(particle_ct: usize, blocks: impl IntoIterator<Item = Vec<Vector2<f32>>>) -> Vec<Vector2<f32>> {
+    blocks.into_iter().fold(vec![Vector2::zero(); particle_ct], |mut sum, block| {
+        for (sum, force) in sum.iter_mut().zip(block) {
+            *sum += force;
+        }
+        sum
+    })
+}
+
+pub struct PhysicsParticle {
+    position: Vector2<f32>,
+    velocity: Vector2<f32>,
+    force: Vector2<f32>,
+    previous_acceleration: Vector2<f32>,
+    mass: f32,
+}
+
+impl PhysicsParticle {
+    pub fn new(position: Vector2<f32>, mass: f32) -> PhysicsParticle {
+        Self {
+            position: position,
+            velocity: Vector2::zero(),
+            force: Vector2::zero(),
+            previous_acceleration: Vector2::zero(),
+            mass: mass,
+        }
+    }
+
+    pub fn add_force(&mut self, force: Vector2<f32>) {
+        self.force += force;
+    }
+
+    pub fn clear_force(&mut self) {
+        self.force = Vector2::zero();
+    }
+
+    pub fn clear_velocity(&mut self) {
+        self.velocity = Vector2::zero();
+    }
+
+    /// Semi-implicit Euler step.
+    pub fn step(&mut self, delta_t: f32) {
+        self.velocity += self.get_acceleration() * delta_t;
+        self.limit_speed();
+        self.position += self.velocity * delta_t;
+    }
+
+    /// First half of a velocity-Verlet step. Forces must be re-accumulated at the new
+    /// position before calling `verlet_velocity_step`.
+    pub fn verlet_position_step(&mut self, delta_t: f32) {
+        self.previous_acceleration = self.get_acceleration();
+        self.position += self.velocity * delta_t + 0.5 * self.previous_acceleration * delta_t * delta_t;
+    }
+
+    pub fn verlet_velocity_step(&mut self, delta_t: f32) {
+        self.velocity += 0.5 * (self.previous_acceleration + self.get_acceleration()) * delta_t;
+        self.limit_speed();
+    }
+
+    pub fn limit_speed(&mut self) {
+        if self.velocity.magnitude() > SPEED_LIMIT {
+            self.velocity = self.velocity.normalize() * SPEED_LIMIT;
+        }
+    }
+
+    pub fn get_position(&self) -> Vector2<f32> {
+        self.position
+    }
+
+    pub fn get_velocity(&self) -> Vector2<f32> {
+        self.velocity
+    }
+
+    pub fn get_acceleration(&self) -> Vector2<f32> {
+        self.force / self.mass
+    }
+
+    pub fn kinetic_energy(&self) -> f32 {
+        0.5 * self.mass * self.velocity.magnitude2()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_graphs;
+    use gscientific::graph::Graph;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    /// Two particles joined by an edge, off balance.
+    fn spring(integrator: Integrator) -> ForceLayout {
+        let particles = vec![
+            PhysicsParticle::new(Vector2::new(-1.0, 0.0), 1.0),
+            PhysicsParticle::new(Vector2::new(1.5, 0.3), 1.0),
+        ];
+        let graph_distances = test_graphs::path(2).unweighted_all_pairs_distance();
+        let mut layout = ForceLayout::new(particles, Arc::new(graph_distances));
+        layout.integrator = integrator;
+        layout
+    }
+
+    fn positions(layout: &ForceLayout) -> Vec<Vector2<f32>> {
+        layout.particles.iter().map(|particle| particle.get_position()).collect()
+    }
+
+    #[test]
+    fn spring_converges() {
+        for integrator in [Integrator::VelocityVerlet, Integrator::RungeKutta4] {
+            let mut layout = spring(integrator);
+            // Centering is much weaker than damping, so the pair drifts to the origin slowly.
+            for _ in 0..24000 {
+                layout.step(SIMULATION_TIMESTEP);
+            }
+
+            assert!(layout.is_settled(), "{} didn't settle", integrator.name());
+            layout.accumulate_forces();
+            for particle in &layout.particles {
+                assert!(particle.get_velocity().magnitude() < 1e-3, "{}", integrator.name());
+                assert!(particle.get_acceleration().magnitude() < 1e-3, "{}", integrator.name());
+            }
+        }
+    }
+
+    #[test]
+    fn ticks_whole_timesteps() {
+        let mut ticked = spring(Integrator::VelocityVerlet);
+        let mut stepped = spring(Integrator::VelocityVerlet);
+
+        // Two and a half steps' worth runs two, and the half left over carries into the next
+        // tick.
+        ticked.tick(Duration::from_secs_f32(2.5 * SIMULATION_TIMESTEP));
+        stepped.step(SIMULATION_TIMESTEP);
+        stepped.step(SIMULATION_TIMESTEP);
+        assert_eq!(positions(&ticked), positions(&stepped));
+
+        ticked.tick(Duration::from_secs_f32(0.6 * SIMULATION_TIMESTEP));
+        stepped.step(SIMULATION_TIMESTEP);
+        assert_eq!(positions(&ticked), positions(&stepped));
+    }
+
+    #[test]
+    fn long_ticks_are_capped() {
+        let mut ticked = spring(Integrator::VelocityVerlet);
+        let mut stepped = spring(Integrator::VelocityVerlet);
+
+        ticked.tick(Duration::from_secs(1));
+        for _ in 0..MAX_STEPS_PER_TICK {
+            stepped.step(SIMULATION_TIMESTEP);
+        }
+        assert_eq!(positions(&ticked), positions(&stepped));
+    }
+
+    #[test]
+    fn pair_forces_match_sequential_sum() {
+        // More particles than blocks, and vertices off the path so some pairs are unreachable.
+        let vertex_ct = 150;
+        let edges = (1..130).map(|v| (v - 1, v)).collect::<Vec<_>>();
+        let graph_distances = test_graphs::graph(vertex_ct, &edges).unweighted_all_pairs_distance();
+        let mut rng = StdRng::seed_from_u64(1);
+        let particles = (0..vertex_ct)
+            .map(|_| PhysicsParticle::new(Vector2::new(rng.random(), rng.random()) * 10.0, 1.0))
+            .collect::<Vec<_>>();
+        let parameters = LayoutParameters::default();
+        let isolated = Some(3);
+
+        // Whichever build this is, it sums exactly like the sequential one.
+        let forces = pair_forces(&particles, &graph_distances, &parameters, isolated);
+        let sequential = sum_forces(
+            vertex_ct,
+            (0..MAX_FORCE_BLOCK_CT).map(|block| {
+                pair_block_forces(&particles, &graph_distances, &parameters, isolated, block, MAX_FORCE_BLOCK_CT)
+            }),
+        );
+        assert_eq!(forces, sequential);
+
+        // And it agrees with every particle gathering its own forces.
+        for u in 0..vertex_ct {
+            let mut expected = Vector2::zero();
+            for v in (0..vertex_ct).filter(|v| *v != u && isolated != Some(*v) && isolated != Some(u)) {
+                let graph_distance = *graph_distances.get(u, v);
+                let eq_length = parameters.eq_length_mult * if graph_distance != -1 { graph_distance as f32 } else { 20.0 };
+                let offset = particles[u].get_position() - particles[v].get_position();
+                expected += -(offset.magnitude() / eq_length).ln() * parameters.spring_stiffness * offset.normalize();
+            }
+            assert!((forces[u] - expected).magnitude() < 1e-3 * expected.magnitude().max(1.0), "particle {}", u);
+        }
+        assert_eq!(forces[3], Vector2::zero());
+    }
+
+    #[test]
+    fn settles_after_quiet_steps() {
+        let mut layout = ForceLayout::new(
+            vec![PhysicsParticle::new(Vector2::zero(), 1.0)],
+            Arc::new(test_graphs::path(1).unweighted_all_pairs_distance()),
+        );
+        for _ in 1..SETTLED_STEP_CT {
+            layout.step(SIMULATION_TIMESTEP);
+        }
+        assert!(!layout.is_settled());
+        layout.step(SIMULATION_TIMESTEP);
+        assert!(layout.is_settled());
+        assert!(layout.is_idle());
+
+        layout.wake();
+        assert!(!layout.is_settled());
+    }
+}
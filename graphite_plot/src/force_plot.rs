@@ -1,271 +1,737 @@
-use instant::Duration;
-use winit::{dpi::PhysicalSize, event::{ElementState, KeyEvent, MouseButton}, keyboard::{KeyCode, PhysicalKey}};
+use std::{collections::HashMap, sync::Arc};
+
+use winit::{dpi::PhysicalPosition, event::{ElementState, KeyEvent}, keyboard::{KeyCode, PhysicalKey}};
 
 use crate::{
-    draw::{Color, GetLines, GetPoints, LineElement, PointElement},
+    analysis::{Analysis, AnalysisRequest, AnalysisSnapshot, GraphAnalyzer},
+    animation::{Algorithm, Animation, EdgeState, VertexState},
+    actions::Action,
+    attributes::VertexAttributes,
+    bindings::{Bindings, Stroke},
+    bundling::{BundleRequest, BundleSnapshot, EdgeBundler, EdgeBundles},
+    colormap::{self, CATEGORICAL_COLOR_CT, Colormap},
+    community::CommunityAlgorithm,
+    draw::{Color, Curve, GetLines, GetPoints, GetPolygons, LineElement, PointElement, PointStyle, PolygonElement, Shape, SizeUnits},
+    edge_weights::EdgeWeights,
+    geometry::{convex_hull, inflate},
+    hud::Hud,
+    icons::IconAtlas,
+    force_layout::{ForceLayout, LayoutSnapshot, PhysicsParticle, SIMULATION_TIMESTEP},
+    generators::{Generator, GeneratorMenu, MenuInput},
+    initial_placement::InitialPlacement,
+    metrics::VertexMetric,
     mouse::{MouseEvent, MouseTracker},
+    options::PlotOptions,
+    paths::PathSelection,
+    picking::{GetPickables, Pick, Picker, hit_test},
+    overlay::{Anchor, GetOverlay, OverlayElement, OverlayItem, text_size},
     plot_graphic::PlotGraphicState,
     plot_window::{PlotGraphic, PlotWindowState},
+    simulation::SimulationRunner,
+    theme::Theme,
 };
 
 use gscientific::{graph::Graph, linalg::{BasicMatrix, Matrix}};
 
 use cgmath::{EuclideanSpace, InnerSpace, Vector2, Zero, num_traits::Pow};
-use rand::random;
+use rand::{Rng, SeedableRng, random, rngs::StdRng};
+#[cfg(feature = "egui")]
+use crate::force_layout::{Integrator, LayoutParameters};
+#[cfg(target_arch = "wasm32")]
+use crate::{edge_list::EdgeList, web::EmbeddedPlot};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{capture::CopiedFrame, capturer::Capturer};
+#[cfg(all(feature = "egui", not(target_arch = "wasm32")))]
+use crate::{attributes::read_attributes, initial_placement::{read_positions, write_positions}, theme::read_theme};
 
-const EDGE_SPRING_STIFFNESS: f32 = 1.0;
-const EDGE_EQ_LENGTH_MULT: f32 = 2.0;
-const RESISTANCE: f32 = 1.0;
-const CENTERING: f32 = 0.1;
 const INIT_SPACING_MULTIPLIER: f32 = 1.0;
-const SPEED_LIMIT: f32 = 100.0;
+const DEFAULT_VERTEX_SIZE: f32 = 0.1;
+/// Vertex size range used when sizing by metric.
+const METRIC_SIZE_RANGE: (f32, f32) = (0.05, 0.2);
+const LEGEND_MARGIN: f32 = 12.0;
+const LEGEND_TEXT_SCALE: f32 = 2.0;
+const COLORBAR_SIZE: Vector2<f32> = Vector2::new(240.0, 16.0);
+const COLORBAR_SAMPLE_CT: usize = 32;
+const EDGE_WIDTH: f32 = 0.04;
+/// Width of an ordinary edge drawn in pixels. Wider edges keep their proportion to it.
+const EDGE_WIDTH_PIXELS: f32 = 1.5;
+/// Radius of a default-sized vertex drawn in pixels. Other sizes keep their proportion to it.
+const VERTEX_SIZE_PIXELS: f32 = 6.0;
+const PATH_EDGE_WIDTH: f32 = 0.12;
+/// How far apart parallel edges bow out, as a fraction of their length.
+const PARALLEL_EDGE_SPREAD: f32 = 0.2;
+/// How far every edge bows out with curved edges on, as a fraction of its length.
+const CURVED_EDGE_BEND: f32 = 0.15;
+/// How far a self-loop reaches from its vertex, in default vertex radii. Each further loop
+/// on the same vertex reaches half again as far.
+const LOOP_SIZE: f32 = 4.0;
+/// Angle in radians between where a self-loop leaves its vertex and the way it points.
+const LOOP_SPREAD: f32 = 0.5;
+/// How much bundled edges' alpha is scaled by, so bundles grow more opaque the more edges
+/// run along them.
+const BUNDLED_EDGE_ALPHA: f32 = 0.35;
+const DEFAULT_BUNDLING_STRENGTH: f32 = 0.5;
+/// How much the bundling strength keys change it.
+const BUNDLING_STRENGTH_STEP: f32 = 0.1;
+/// Edges are easier to hit than they are to see.
+const EDGE_PICK_WIDTH: f32 = 0.12;
+/// How much + and - change the selected edge's weight.
+const WEIGHT_STEP: f64 = 0.5;
+/// How far dimmed vertices and edges are blended towards the background.
+const DIM_AMOUNT: f32 = 0.8;
+const UNREACHABLE_COLOR: Color = Color { r: 0.6, g: 0.6, b: 0.6, a: 1.0 };
+const COMMUNITY_BACKGROUND_ALPHA: f32 = 0.15;
+const HULL_PADDING: f32 = 0.15;
+const BLOB_PADDING: f32 = 0.4;
+const BLOB_SEGMENTS_PER_QUARTER: usize = 6;
+/// Outline width of the hovered vertex, drawn in the theme's hover color.
+const HOVER_OUTLINE_WIDTH: f32 = 0.35;
+/// Distance in pixels from the cursor to the tooltip.
+const TOOLTIP_OFFSET: f32 = 16.0;
+/// Longest neighbor list spelled out in the vertex info.
+const NEIGHBOR_LIST_LIMIT: usize = 10;
+
+/// What is drawn behind each detected community.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum CommunityBackground {
+    Off,
+    Hull,
+    Blob,
+}
+
+impl CommunityBackground {
+    fn next(self) -> Self {
+        match self {
+            CommunityBackground::Off => CommunityBackground::Hull,
+            CommunityBackground::Hull => CommunityBackground::Blob,
+            CommunityBackground::Blob => CommunityBackground::Off,
+        }
+    }
+}
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 enum InteractAction {
     Drag,
     New,
     AddEdge,
 }
 
+/// What changed since the graph was last analyzed.
+#[derive(Clone, Copy, Default)]
+struct Outdated {
+    /// The graph itself, which outdates the whole analysis.
+    graph: bool,
+    communities: bool,
+    metric: bool,
+}
+
+impl Outdated {
+    const GRAPH: Outdated = Outdated { graph: true, communities: false, metric: false };
+    const COMMUNITIES: Outdated = Outdated { graph: false, communities: true, metric: false };
+    const METRIC: Outdated = Outdated { graph: false, communities: false, metric: true };
+
+    fn any(self) -> bool {
+        self.graph || self.communities || self.metric
+    }
+}
+
 struct ForcePlotModel<T: Graph> {
     graph: T,
-    graph_distances: BasicMatrix<i32>,
-    particles: Vec<PhysicsParticle>,
+    /// Unweighted distances between every pair of vertices. Computed again off the render
+    /// thread after edits, and stale until then.
+    graph_distances: Arc<BasicMatrix<i32>>,
+    /// What the graph analysis is waiting to catch up with.
+    outdated: Outdated,
+    /// Counts changes to the graph, metric and community algorithm, so an analysis started
+    /// before the latest one is dropped.
+    analysis_version: u64,
+    /// The latest positions published by the simulation.
+    layout: LayoutSnapshot,
     colors: Vec<Color>,
     sizes: Vec<f32>,
     interacted_particle: Option<(usize, InteractAction)>,
-    pause: bool,
+
+    /// The metric vertices are colored by, if any.
+    metric: Option<VertexMetric>,
+    /// `metric` of every vertex.
+    metric_values: Option<Arc<Vec<f32>>>,
+    colormap: Colormap,
+    size_by_metric: bool,
+    metric_range: (f32, f32),
+    component_ct: usize,
+
+    community_algorithm: Option<CommunityAlgorithm>,
+    /// Community of every vertex, found by `community_algorithm`.
+    communities: Option<Arc<Vec<usize>>>,
+    community_ct: usize,
+    modularity: f64,
+    community_background: CommunityBackground,
+    /// Whether the layout pulls community members together.
+    community_force: bool,
+
+    /// Weights edited by the user, used by the weighted paths and animations.
+    edge_weights: EdgeWeights,
+    path: PathSelection,
+    /// Color vertices by distance from the hovered vertex.
+    distance_on_hover: bool,
+    hovered: Option<usize>,
+    /// Edges are stored with the smaller endpoint first.
+    hovered_edge: Option<(usize, usize)>,
+    selected_edge: Option<(usize, usize)>,
+    /// Vertex last clicked.
+    selected_vertex: Option<usize>,
+    /// Cursor position in surface pixels, while it is in the window.
+    cursor: Option<Vector2<f32>>,
+    window_size: Vector2<f32>,
+    line_units: SizeUnits,
+    point_units: SizeUnits,
+    /// Bow every edge out, not only parallel ones.
+    curved_edges: bool,
+    edge_bundling: bool,
+    /// How hard bundled edges pull together, from 0 to 1.
+    bundling_strength: f32,
+    /// Paths of the bundled edges, computed again after edits and once the layout settles.
+    bundles: Option<Arc<EdgeBundles>>,
+    /// Whether `bundles` were computed from a settled layout.
+    bundled_settled: bool,
+    /// Whether the graph or the bundling strength changed since the last bundles were asked
+    /// for. The bundles drawn stay up until new ones are done.
+    bundles_outdated: bool,
+    /// World units per surface pixel at the current zoom.
+    pixel_size: f32,
+    /// Colors of everything not colored by the data.
+    theme: Theme,
+    /// Vertex whose info panel is pinned.
+    info_vertex: Option<usize>,
+    attributes: VertexAttributes,
+    hud: Hud,
+    /// Key sheet for the current bindings.
+    help_text: String,
+    edge_ct: usize,
+
+    animation: Option<Animation>,
+    generator_menu: Option<GeneratorMenu>,
 }
 
 impl<T: Graph> ForcePlotModel<T> {
     fn new(
         graph: T,
-        particles: Vec<PhysicsParticle>,
         colors: Vec<Color>,
         sizes: Vec<f32>,
         interacted_particle: Option<(usize, InteractAction)>,
     ) -> Self {
-        let graph_distances = graph.unweighted_all_pairs_distance();
-        let pause = false;
+        let graph_distances = Arc::new(graph.unweighted_all_pairs_distance());
+        let layout = LayoutSnapshot::default();
+        let edge_ct = graph.edges().count();
 
         Self {
             graph,
             graph_distances,
-            particles,
+            outdated: Outdated::default(),
+            analysis_version: 0,
+            layout,
             colors,
             sizes,
             interacted_particle,
-            pause,
+            metric: None,
+            metric_values: None,
+            colormap: Colormap::Viridis,
+            size_by_metric: false,
+            metric_range: (0.0, 0.0),
+            component_ct: 0,
+            community_algorithm: None,
+            communities: None,
+            community_ct: 0,
+            modularity: 0.0,
+            community_background: CommunityBackground::Off,
+            community_force: false,
+            edge_weights: EdgeWeights::default(),
+            path: PathSelection::default(),
+            distance_on_hover: false,
+            hovered: None,
+            hovered_edge: None,
+            selected_edge: None,
+            selected_vertex: None,
+            cursor: None,
+            window_size: Vector2::zero(),
+            line_units: SizeUnits::World,
+            point_units: SizeUnits::World,
+            curved_edges: false,
+            edge_bundling: false,
+            bundling_strength: DEFAULT_BUNDLING_STRENGTH,
+            bundles: None,
+            bundled_settled: false,
+            bundles_outdated: false,
+            pixel_size: 0.0,
+            theme: Theme::default(),
+            info_vertex: None,
+            attributes: VertexAttributes::default(),
+            hud: Hud::default(),
+            help_text: String::new(),
+            edge_ct,
+            animation: None,
+            generator_menu: None,
         }
     }
 
-    fn tick(&mut self, delta_t: Duration) {
-        if self.pause {
-            return;
+    /// An edge width in world units, in the units edges are drawn in.
+    fn line_width(&self, width: f32) -> f32 {
+        match self.line_units {
+            SizeUnits::World => width,
+            SizeUnits::Pixels => width / EDGE_WIDTH * EDGE_WIDTH_PIXELS,
         }
+    }
 
-        for u in 0..self.particles.len() {
-            self.particles[u].clear_force();
-            let tmp_force = -self.particles[u].get_velocity() * RESISTANCE
-                - self.particles[u].get_position() * CENTERING;
-            self.particles[u].add_force(tmp_force);
+    /// A vertex size in world units, in the units vertices are drawn in.
+    fn point_size(&self, size: f32) -> f32 {
+        match self.point_units {
+            SizeUnits::World => size,
+            SizeUnits::Pixels => size / DEFAULT_VERTEX_SIZE * VERTEX_SIZE_PIXELS,
+        }
+    }
 
-            if self.interacted_particle.is_some()
-                && u == self.interacted_particle.as_ref().unwrap().0  && self.interacted_particle.as_ref().unwrap().1 == InteractAction::New
-            {
-                continue;
+    /// How far from its center a vertex of `size` is drawn, in world units at the current
+    /// zoom.
+    fn world_radius(&self, size: f32) -> f32 {
+        match self.point_units {
+            SizeUnits::World => size,
+            SizeUnits::Pixels => self.point_size(size) * self.pixel_size,
+        }
+    }
+
+    /// Whether `pick` names a vertex or edge of the graph as it is now. Picks read back a
+    /// frame late may not after an edit.
+    fn contains(&self, pick: Pick) -> bool {
+        let vertex_ct = self.graph.vertex_ct().min(self.layout.positions.len());
+        match pick {
+            Pick::Vertex(v) => v < vertex_ct,
+            Pick::Edge(u, v) => u < vertex_ct && v < vertex_ct && self.graph.adjacent(u, v),
+        }
+    }
+
+    /// Changes the bundling strength by `step`, bundling the edges again.
+    fn adjust_bundling_strength(&mut self, step: f32) {
+        self.bundling_strength = (self.bundling_strength + step).clamp(0.0, 1.0);
+        self.bundles_outdated = true;
+    }
+
+    /// Color of an ordinary edge. Bundled edges are translucent, so their overlaps add up.
+    fn edge_color(&self) -> Color {
+        if self.bundles.is_some() {
+            Color { a: self.theme.edge.a * BUNDLED_EDGE_ALPHA, ..self.theme.edge }
+        } else {
+            self.theme.edge
+        }
+    }
+
+    /// Every edge with both endpoints laid out, and the curve between their centers it's
+    /// drawn along. Parallel edges fan out and self-loops on the same vertex nest. With
+    /// bundling on, edges follow their bundled paths instead.
+    fn edge_curves(&self) -> impl Iterator<Item = (usize, usize, Curve)> {
+        let positions = &self.layout.positions;
+        let mut parallel_cts = HashMap::new();
+        for (u, v, _) in self.graph.edges() {
+            *parallel_cts.entry((u.min(v), u.max(v))).or_insert(0) += 1;
+        }
+
+        let mut drawn_cts = HashMap::new();
+        self.graph
+            .edges()
+            .filter(|(u, v, _)| *u < positions.len() && *v < positions.len())
+            .map(move |(u, v, _)| {
+                let pair = (u.min(v), u.max(v));
+                let drawn_ct = drawn_cts.entry(pair).or_insert(0);
+                let bundled = self.bundles.as_ref().and_then(|bundles| {
+                    bundles.path(u, v, positions[u], positions[v])
+                });
+                let curve = if u == v {
+                    self.loop_curve(u, *drawn_ct)
+                } else if let Some(path) = bundled {
+                    Curve::Polyline(path)
+                } else {
+                    self.edge_curve(u, v, *drawn_ct, parallel_cts[&pair])
+                };
+                *drawn_ct += 1;
+                (u, v, curve)
+            })
+    }
+
+    /// The `index`th of `parallel_ct` edges between `u` and `v`.
+    fn edge_curve(&self, u: usize, v: usize, index: usize, parallel_ct: usize) -> Curve {
+        let (a, b) = (self.layout.positions[u], self.layout.positions[v]);
+        let mut bend = (index as f32 - (parallel_ct - 1) as f32 / 2.0) * PARALLEL_EDGE_SPREAD;
+        if self.curved_edges {
+            bend += CURVED_EDGE_BEND;
+        }
+        if bend == 0.0 || a == b {
+            return Curve::Straight(a, b);
+        }
+
+        // Bends are measured from the lower endpoint, so parallel edges fan out the same
+        // whichever way round the graph lists them.
+        let along = if u < v { b - a } else { a - b };
+        let across = Vector2::new(-along.y, along.x);
+        // A quadratic curve gets halfway to its middle point.
+        Curve::Quadratic(a, (a + b) / 2.0 + across * (2.0 * bend), b)
+    }
+
+    /// The `index`th self-loop on `u`, pointing away from its neighbors, where there's most
+    /// room for it.
+    fn loop_curve(&self, u: usize, index: usize) -> Curve {
+        let positions = &self.layout.positions;
+        let center = positions[u];
+        let toward_neighbors = self
+            .graph
+            .neighbors(u)
+            .filter(|(v, _)| *v != u && *v < positions.len())
+            .map(|(v, _)| positions[v] - center)
+            .fold(Vector2::zero(), |sum, offset| sum + offset);
+        let outward = if toward_neighbors.magnitude2() > 0.0 {
+            -toward_neighbors.normalize()
+        } else {
+            Vector2::unit_y()
+        };
+
+        let size = self.world_radius(DEFAULT_VERTEX_SIZE) * LOOP_SIZE * (1.0 + 0.5 * index as f32);
+        let (sin, cos) = LOOP_SPREAD.sin_cos();
+        let leaving = Vector2::new(outward.x * cos - outward.y * sin, outward.x * sin + outward.y * cos);
+        let returning = Vector2::new(outward.x * cos + outward.y * sin, outward.y * cos - outward.x * sin);
+        Curve::Cubic(center, center + leaving * size, center + returning * size, center)
+    }
+
+    /// Vertex the hover distances are measured from, if hover distances are on.
+    fn distance_source(&self) -> Option<usize> {
+        self.hovered
+            .filter(|hovered| self.distance_on_hover && *hovered < self.graph.vertex_ct())
+    }
+
+    /// Everything known about `u`, one fact per line.
+    fn describe_vertex(&self, u: usize) -> String {
+        let mut text = match self.attributes.label(u) {
+            Some(label) => format!("Vertex {} \"{}\"", u, label),
+            None => format!("Vertex {}", u),
+        };
+
+        let neighbors = self.graph.neighbors(u).map(|(v, _)| v).collect::<Vec<_>>();
+        text.push_str(&format!("\nDegree {}", neighbors.len()));
+        if !neighbors.is_empty() {
+            let listed = neighbors
+                .iter()
+                .take(NEIGHBOR_LIST_LIMIT)
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            text.push_str(&format!("\nNeighbors {}", listed));
+            if neighbors.len() > NEIGHBOR_LIST_LIMIT {
+                text.push_str(&format!(" and {} more", neighbors.len() - NEIGHBOR_LIST_LIMIT));
             }
+        }
 
-            for v in 0..u {
-                if self.interacted_particle.is_some()
-                    && v == self.interacted_particle.as_ref().unwrap().0 && self.interacted_particle.as_ref().unwrap().1 == InteractAction::New
-                {
-                    continue;
-                }
-                let graph_distance = *self.graph_distances.get(u, v);
-                let eq_length = if graph_distance != -1 { EDGE_EQ_LENGTH_MULT * graph_distance as f32 } else { 20.0 * EDGE_EQ_LENGTH_MULT };
-                let r = (self.particles[u].get_position() - self.particles[v].get_position())
-                    .magnitude();
-                let direction =
-                    (self.particles[u].get_position() - self.particles[v].get_position()) / r;
-
-                // self.particles[u].add_force(1. / (r * r) * VERTEX_REPULSION * direction);
-                // self.particles[v].add_force(-1. / (r * r) * VERTEX_REPULSION * direction);
-
-                self.particles[u].add_force(
-                    -(r / eq_length).ln()
-                        * EDGE_SPRING_STIFFNESS
-                        * direction,
-                );
-                self.particles[v].add_force(
-                    (r / eq_length).ln() * EDGE_SPRING_STIFFNESS * direction,
+        // Greatest distance to any reachable vertex, as in `VertexMetric::Eccentricity`.
+        if let Some(graph_distances) = self.graph_distances() {
+            let eccentricity = (0..self.graph.vertex_ct())
+                .map(|v| *graph_distances.get(u, v))
+                .max()
+                .unwrap_or(0)
+                .max(0);
+            text.push_str(&format!("\nEccentricity {}", eccentricity));
+        }
+
+        if let Some(position) = self.layout.positions.get(u) {
+            text.push_str(&format!("\nPosition ({:.2}, {:.2})", position.x, position.y));
+        }
+        if let Some(velocity) = self.layout.velocities.get(u) {
+            text.push_str(&format!("\nVelocity ({:.2}, {:.2})", velocity.x, velocity.y));
+        }
+        for (name, value) in self.attributes.get(u) {
+            text.push_str(&format!("\n{}: {}", name, value));
+        }
+
+        text
+    }
+
+    /// Distances between every pair of vertices, unless the graph changed since they were
+    /// computed.
+    fn graph_distances(&self) -> Option<&BasicMatrix<i32>> {
+        (!self.outdated.graph).then_some(self.graph_distances.as_ref())
+    }
+
+    /// Marks what changed, so the graph is analyzed again. Analyses already started are
+    /// dropped when they finish.
+    fn outdate(&mut self, outdated: Outdated) {
+        self.outdated.graph |= outdated.graph;
+        self.outdated.communities |= outdated.communities;
+        self.outdated.metric |= outdated.metric;
+        self.analysis_version += 1;
+    }
+
+    /// The parts of the analysis nothing has changed since.
+    fn known_analysis(&self) -> Analysis {
+        Analysis {
+            graph_distances: self.graph_distances().is_some().then(|| self.graph_distances.clone()),
+            communities: self
+                .communities
+                .clone()
+                .filter(|_| !self.outdated.graph && !self.outdated.communities),
+            modularity: self.modularity,
+            metric_values: self
+                .metric_values
+                .clone()
+                .filter(|_| !self.outdated.graph && !self.outdated.metric),
+        }
+    }
+
+    /// Takes in an analysis of the current graph, metric and community algorithm. Recolors
+    /// when the metric or communities color the vertices or were just turned off, and keeps
+    /// colors set by hand otherwise.
+    fn apply_analysis(&mut self, analysis: &Analysis) {
+        let outdated = std::mem::take(&mut self.outdated);
+        if let Some(graph_distances) = analysis.graph_distances.clone() {
+            self.graph_distances = graph_distances;
+        }
+        if outdated.graph || outdated.communities {
+            self.communities = analysis.communities.clone();
+            self.community_ct = self
+                .communities
+                .as_ref()
+                .and_then(|communities| communities.iter().max())
+                .map_or(0, |max| max + 1);
+            self.modularity = analysis.modularity;
+            if let Some(algorithm) = self.community_algorithm {
+                log::info!(
+                    "{}: {} communities, modularity {:.3}",
+                    algorithm.name(),
+                    self.community_ct,
+                    self.modularity
                 );
             }
         }
+        self.metric_values = analysis.metric_values.clone();
+        if self.metric.is_some() || self.community_algorithm.is_some() || outdated.metric || outdated.communities {
+            self.recolor();
+        }
+    }
 
-        for u in 0..self.particles.len() {
-            if self.interacted_particle.is_some()
-                && u == self.interacted_particle.as_ref().unwrap().0
-            {
-                continue;
-            }
-            self.particles[u].step(delta_t.as_secs_f32());
+    /// The communities the layout should pull together, if that force is on.
+    fn layout_communities(&self) -> Option<Arc<Vec<usize>>> {
+        self.communities.clone().filter(|_| self.community_force)
+    }
+
+    /// Rewrites `colors` and `sizes` from the current metric and communities, or back to the
+    /// defaults without either. Communities take over the colors but not the sizes. Values
+    /// left over from before a vertex was added or removed are skipped until the analysis
+    /// catches up.
+    fn recolor(&mut self) {
+        self.recolor_by_metric();
+        if let Some(communities) = self.communities.as_ref()
+            && communities.len() == self.graph.vertex_ct()
+        {
+            self.colors = communities.iter().map(|community| colormap::categorical(*community)).collect();
+        }
+    }
+
+    fn recolor_by_metric(&mut self) {
+        let n = self.graph.vertex_ct();
+        let Some(metric) = self.metric else {
+            self.colors = vec![self.theme.vertex_fill; n];
+            self.sizes = vec![DEFAULT_VERTEX_SIZE; n];
+            return;
+        };
+
+        let Some(values) = self.metric_values.clone().filter(|values| values.len() == n) else {
+            return;
+        };
+        let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        self.metric_range = if values.is_empty() { (0.0, 0.0) } else { (min, max) };
+        let span = self.metric_range.1 - self.metric_range.0;
+        let normalized = values
+            .iter()
+            .map(|value| if span > 0.0 { (value - self.metric_range.0) / span } else { 0.5 });
+
+        if metric.is_categorical() {
+            self.component_ct = if values.is_empty() { 0 } else { max as usize + 1 };
+            self.colors = values.iter().map(|value| colormap::categorical(*value as usize)).collect();
+        } else {
+            self.colors = normalized.clone().map(|t| self.colormap.sample(t)).collect();
         }
+
+        self.sizes = if self.size_by_metric && !metric.is_categorical() {
+            normalized
+                .map(|t| METRIC_SIZE_RANGE.0 + t * (METRIC_SIZE_RANGE.1 - METRIC_SIZE_RANGE.0))
+                .collect()
+        } else {
+            vec![DEFAULT_VERTEX_SIZE; n]
+        };
     }
 }
 
 pub struct ForcePlot<T: Graph> {
     model: ForcePlotModel<T>,
+    simulation: SimulationRunner<ForceLayout>,
+    /// Computes graph distances, communities and metrics off the render thread.
+    analyzer: SimulationRunner<GraphAnalyzer>,
+    /// What the analyzer last published.
+    analyzed: AnalysisSnapshot,
+    /// Model version the analysis being computed is for.
+    analysis_request: Option<u64>,
+    /// Bundles edges off the render thread.
+    bundler: SimulationRunner<EdgeBundler>,
+    /// What the bundler last published.
+    bundled: BundleSnapshot,
+    /// The bundling request being computed, and whether the layout had settled when it was
+    /// sent.
+    bundle_request: Option<(u64, bool)>,
+    bundle_request_ct: u64,
+
+    rng: StdRng,
 
     mouse_tracker: MouseTracker,
+    bindings: Bindings,
     drag_previous_position: Vector2<f32>,
+    /// Whether the current grab started over empty space and drags the view.
+    panning: bool,
     state: Option<PlotGraphicState<ForcePlotModel<T>>>,
+    picker: Option<Picker>,
+    /// Built-in themes and any loaded ones, in the order switching themes goes through them.
+    themes: Vec<Theme>,
+    /// Icons the "icon" attribute picks from.
+    icons: Option<IconAtlas>,
+    #[cfg(not(target_arch = "wasm32"))]
+    capturer: Capturer,
+    /// Save the next frame drawn.
+    #[cfg(not(target_arch = "wasm32"))]
+    screenshot_requested: bool,
+    /// Layout frame the recording is waiting for before it captures again.
+    #[cfg(not(target_arch = "wasm32"))]
+    next_recorded_frame: u64,
+    #[cfg(feature = "egui")]
+    panels: GuiPanels,
+}
+
+/// What the egui panels remember between frames.
+#[cfg(feature = "egui")]
+#[derive(Default)]
+struct GuiPanels {
+    /// Last values sent to the layout. The layout doesn't publish them back.
+    layout_parameters: LayoutParameters,
+    generator_spec: String,
+    /// Outcome of the last generator or file action.
+    message: Option<String>,
 }
 
 impl<T: Graph> PlotGraphic for ForcePlot<T> {
     fn init(&mut self, state: &PlotWindowState) {
         self.state = Some(PlotGraphicState::new(state));
-        let point_element = PointElement::new(self.state.as_ref().unwrap(), state);
-        let line_element = LineElement::new(self.state.as_ref().unwrap(), state);
+        self.state.as_mut().unwrap().background = self.model.theme.background;
+        let polygon_element = PolygonElement::new(self.state.as_ref().unwrap(), state);
+        let mut point_element = PointElement::new(self.state.as_ref().unwrap(), state).with_units(self.model.point_units);
+        if let Some(icons) = &self.icons {
+            point_element = point_element.with_icons(state, icons);
+        }
+        let line_element = LineElement::new(self.state.as_ref().unwrap(), state).with_units(self.model.line_units);
+        let overlay_element = OverlayElement::new(self.state.as_ref().unwrap(), state, None);
+        self.state.as_mut().unwrap().add_element(polygon_element);
         self.state.as_mut().unwrap().add_element(point_element);
         self.state.as_mut().unwrap().add_element(line_element);
+        self.state.as_mut().unwrap().add_element(overlay_element);
+        self.picker = Some(
+            Picker::new(self.state.as_ref().unwrap(), state).with_units(self.model.point_units, self.model.line_units),
+        );
+        self.simulation.start();
+        self.analyzer.start();
+        self.bundler.start();
     }
 
     fn tick(&mut self, delta_t: instant::Duration) {
-        self.model.tick(delta_t);
+        self.model.hud.record_frame(delta_t);
+        self.simulation.tick(delta_t);
+        self.analyzer.tick(delta_t);
+        self.bundler.tick(delta_t);
+        if let Some(animation) = self.model.animation.as_mut() {
+            animation.tick(delta_t);
+        }
     }
 
-    fn input(&mut self, event: &winit::event::WindowEvent, window_size: PhysicalSize<u32>) -> bool {
-        match event {
-            winit::event::WindowEvent::KeyboardInput { event: KeyEvent {
-                state: ElementState::Pressed,
-                physical_key: PhysicalKey::Code(KeyCode::Space),
-                ..
-            }, .. } => {
-                self.model.pause = !self.model.pause;
-            },
-            winit::event::WindowEvent::KeyboardInput { event: KeyEvent {
+    fn input(&mut self, event: &winit::event::WindowEvent, window_state: &PlotWindowState) -> bool {
+        let window_size = window_state.size;
+        // The overlay is laid out in surface pixels.
+        self.model.window_size = Vector2::new(window_state.config.width as f32, window_state.config.height as f32);
+        if self.model.generator_menu.is_some()
+            && let winit::event::WindowEvent::KeyboardInput { event: KeyEvent {
                 state: ElementState::Pressed,
-                physical_key: PhysicalKey::Code(KeyCode::KeyZ),
+                physical_key: PhysicalKey::Code(key),
                 ..
-            }, .. } => {
-                self.model.particles.pop();
-                self.model.graph.resize(self.model.graph.vertex_ct() - 1);
-                self.model.colors.pop();
-                self.model.sizes.pop();
+            }, .. } = event
+        {
+            self.generator_menu_input(*key);
+            return true;
+        }
 
-                self.model.graph_distances = self.model.graph.unweighted_all_pairs_distance();
-            },
+        let mouse_event = self.mouse_tracker.translate_event(event);
+        let modifiers = self.mouse_tracker.get_modifiers();
+        let stroke = match event {
             winit::event::WindowEvent::KeyboardInput { event: KeyEvent {
                 state: ElementState::Pressed,
-                physical_key: PhysicalKey::Code(KeyCode::KeyC),
+                physical_key: PhysicalKey::Code(key),
                 ..
-            }, .. } => {
-                self.model.particles.clear();
-                self.model.graph.resize(1);
-                self.model.colors.clear();
-                self.model.sizes.clear();
-                self.model.particles.push(PhysicsParticle::new(Vector2::zero(), 1.0));
-                self.model.sizes.push(0.1);
-                self.model.colors.push(Color::rgb(0.1, 0.9, 0.1));
-
-                self.model.graph_distances = self.model.graph.unweighted_all_pairs_distance();
-
+            }, .. } => Some(Stroke::key(*key, modifiers)),
+            _ => match mouse_event {
+                // Touches arrive as mouse buttons too.
+                Some(MouseEvent::ButtonPressed(button)) => Some(Stroke::mouse(button, modifiers)),
+                _ => None,
             },
-            _ => {}
+        };
+        if let Some(action) = stroke.and_then(|stroke| self.bindings.press(stroke)) {
+            self.perform(action, window_state);
         }
 
-        match self.mouse_tracker.translate_event(event) {
+        match mouse_event {
             Some(mouse_event) => {
                 match mouse_event {
-                    MouseEvent::ButtonPressed(MouseButton::Left) => {
-                        let mouse_position = self
-                            .state
-                            .as_ref()
-                            .unwrap()
-                            .camera
-                            .pixel_to_camera(self.mouse_tracker.get_position(), window_size);
-                        let mouse_position_delta =
-                            self.state.as_ref().unwrap().camera.center.to_vec() - mouse_position;
-                        self.drag_previous_position = mouse_position_delta;
-                        self.model.interacted_particle = self
-                            .get_particle_by_position(mouse_position)
-                            .map(|index| (index, InteractAction::Drag))
-                    }
-                    MouseEvent::ButtonPressed(MouseButton::Right) => {
-                        let mouse_position = self
-                            .state
-                            .as_ref()
-                            .unwrap()
-                            .camera
-                            .pixel_to_camera(self.mouse_tracker.get_position(), window_size);
-
-                        match self.get_particle_by_position(mouse_position) {
-                            Some(index) => {
-                                let v = self.model.graph.add_vertex();
-                                self.model.graph.add_edge(index, v);
-                                self.model.graph_distances =
-                                    self.model.graph.unweighted_all_pairs_distance();
-                                self.model
-                                    .particles
-                                    .push(PhysicsParticle::new(mouse_position, 1.0));
-                                self.model.colors.push(Color::rgb(0.1, 0.7, 0.1));
-                                self.model.sizes.push(0.1);
-                                self.model.interacted_particle = Some((v, InteractAction::New));
-                            }
-                            None => {}
-                        }
-                    }
-                    MouseEvent::ButtonPressed(MouseButton::Middle) => {
-                        self.model.particles.pop();
-                        self.model.graph.resize(self.model.graph.vertex_ct() - 1);
-                        self.model.colors.pop();
-                        self.model.sizes.pop();
-
-                        self.model.graph_distances = self.model.graph.unweighted_all_pairs_distance();
-                    }
                     MouseEvent::ButtonReleased(button) => {
-                        match &self.model.interacted_particle {
+                        match self.model.interacted_particle {
                             Some((index, action)) => match action {
                                 InteractAction::New => {
-                                    let mouse_position =
-                                        self.state.as_ref().unwrap().camera.pixel_to_camera(
-                                            self.mouse_tracker.get_position(),
-                                            window_size,
-                                        );
-                                    let to_merge = self.get_particle_by_position(mouse_position);
+                                    let to_merge = self.pick_vertex(window_state);
                                     match to_merge {
                                         Some(merge_index) => {
                                             let incident_index =
-                                                self.model.graph.neighbors(*index).next().unwrap().0;
+                                                self.model.graph.neighbors(index).next().unwrap().0;
                                             if incident_index != merge_index {
-                                                self.model.particles.pop();
                                                 self.model
                                                     .graph
                                                     .resize(self.model.graph.vertex_ct() - 1);
+                                                self.model
+                                                    .edge_weights
+                                                    .truncate(self.model.graph.vertex_ct());
                                                 self.model.colors.pop();
-                                                self.model.graph_distances =
-                                                    self.model.graph.unweighted_all_pairs_distance();
                                                 self.model.sizes.pop();
 
                                                 self.model
                                                     .graph
                                                     .add_edge(incident_index, merge_index);
-                                                self.model.graph_distances =
-                                                    self.model.graph.unweighted_all_pairs_distance();
+                                                self.graph_changed(|layout| {
+                                                    layout.particles.pop();
+                                                });
                                             }
                                             else {
-                                                self.model.graph.remove_vertex(*index);
+                                                self.model.graph.remove_vertex(index);
                                                 self.model.graph.remove_vertex(incident_index);
+                                                self.model.edge_weights.remove_vertex(index);
+                                                self.model.edge_weights.remove_vertex(incident_index);
+                                                self.model.attributes.remove_vertex(index);
+                                                self.model.attributes.remove_vertex(incident_index);
 
-                                                self.model.colors.remove(*index);
+                                                self.model.colors.remove(index);
                                                 self.model.colors.remove(incident_index);
-                                                self.model.particles.remove(*index);
-                                                self.model.particles.remove(incident_index);
-                                                self.model.sizes.remove(*index);
+                                                self.model.sizes.remove(index);
                                                 self.model.sizes.remove(incident_index);
 
-                                                self.model.graph_distances = self.model.graph.unweighted_all_pairs_distance();
+                                                self.graph_changed(move |layout| {
+                                                    layout.particles.remove(index);
+                                                    layout.particles.remove(incident_index);
+                                                });
                                             }
                                         }
                                         None => {}
@@ -276,8 +742,13 @@ impl<T: Graph> PlotGraphic for ForcePlot<T> {
                             None => {}
                         }
                         self.model.interacted_particle = None;
+                        self.panning = false;
+                        self.simulation.send(|layout| {
+                            layout.pinned = None;
+                            layout.isolated = None;
+                        });
                     }
-                    MouseEvent::CursorDragged(button) => {
+                    MouseEvent::CursorDragged(_) => {
                         let mouse_position = self
                             .state
                             .as_ref()
@@ -286,7 +757,9 @@ impl<T: Graph> PlotGraphic for ForcePlot<T> {
                             .pixel_to_camera(self.mouse_tracker.get_position(), window_size);
                         match &self.model.interacted_particle {
                             Some((index, action)) => {
-                                self.model.particles[*index].position = mouse_position;
+                                let index = *index;
+                                self.simulation
+                                    .send(move |layout| layout.set_position(index, mouse_position));
 
                                 // TODO
                                 // let mouse_clip = pixel_to_clip(self.mouse_tracker.get_position(), window_size);
@@ -295,20 +768,27 @@ impl<T: Graph> PlotGraphic for ForcePlot<T> {
                                 // let new_world = self.state.as_ref().unwrap().camera.clip_to_world(new_clip);
                                 // self.model.particles[index].position = new_world;
                             }
-                            None => match button {
-                                MouseButton::Left => {
-                                    let mouse_position_delta =
-                                        self.state.as_ref().unwrap().camera.center.to_vec()
-                                            - mouse_position;
-                                    let delta = mouse_position_delta - self.drag_previous_position;
-                                    self.drag_previous_position = mouse_position_delta;
-
-                                    self.state.as_mut().unwrap().camera.pan(delta);
-                                }
-                                _ => {}
-                            },
+                            None if self.panning => {
+                                let mouse_position_delta =
+                                    self.state.as_ref().unwrap().camera.center.to_vec()
+                                        - mouse_position;
+                                let delta = mouse_position_delta - self.drag_previous_position;
+                                self.drag_previous_position = mouse_position_delta;
+
+                                self.state.as_mut().unwrap().camera.pan(delta);
+                            }
+                            None => {}
                         }
                     }
+                    MouseEvent::CursorMoved => {
+                        let position = window_state.to_surface(self.mouse_tracker.get_position());
+                        self.model.cursor = Some(Vector2::new(position.x, position.y));
+                    }
+                    MouseEvent::CursorExited => {
+                        self.model.cursor = None;
+                        self.model.hovered = None;
+                        self.model.hovered_edge = None;
+                    }
                     MouseEvent::WheelScrolled => {
                         self.state
                             .as_mut()
@@ -316,6 +796,20 @@ impl<T: Graph> PlotGraphic for ForcePlot<T> {
                             .camera
                             .zoom(self.mouse_tracker.consume_scroll_delta());
                     }
+                    MouseEvent::Gestured => {
+                        let gesture = self.mouse_tracker.consume_gesture();
+                        let from = PhysicalPosition::new(
+                            gesture.center.x - gesture.pan.x,
+                            gesture.center.y - gesture.pan.y,
+                        );
+                        self.state.as_mut().unwrap().camera.transform_about(
+                            from,
+                            gesture.center,
+                            gesture.zoom,
+                            gesture.rotation,
+                            window_size,
+                        );
+                    }
                     _ => {}
                 }
             }
@@ -328,7 +822,16 @@ impl<T: Graph> PlotGraphic for ForcePlot<T> {
     fn render(&mut self, window_state: &mut PlotWindowState) -> Result<(), wgpu::SurfaceError> {
         match &self.state {
             Some(state) => {
-                state.render(window_state)?;
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    let record = self.capturer.is_recording()
+                        && self.capturer.can_record()
+                        && self.model.layout.frame_ct >= self.next_recorded_frame;
+                    let frame = state.render(window_state, self.screenshot_requested || record)?;
+                    self.frame_drawn(window_state, frame, record);
+                }
+                #[cfg(target_arch = "wasm32")]
+                state.render(window_state, false)?;
             }
             None => {
                 println!("Graphic state not initialized yet!");
@@ -339,114 +842,325 @@ impl<T: Graph> PlotGraphic for ForcePlot<T> {
     }
 
     fn update(&mut self, window_state: &mut PlotWindowState, rewrite_data: bool) {
+        self.simulation.latest(&mut self.model.layout);
+        self.update_analysis();
+        self.update_bundles();
         let state = self.state.as_mut();
 
         state.map(|state| {
+            self.model.pixel_size = state.camera.size.height / window_state.config.height.max(1) as f32;
             if rewrite_data {
                 state.update_data(window_state, &self.model);
+                if let Some(picker) = self.picker.as_mut() {
+                    picker.update(window_state, &self.model);
+                }
             }
             state.update(window_state);
         });
+        self.resolve_picks(window_state);
+    }
+
+    #[cfg(feature = "egui")]
+    fn gui(&mut self, context: &egui::Context) {
+        egui::Window::new("Layout").default_open(false).show(context, |ui| self.layout_panel(ui));
+        egui::Window::new("Appearance").default_open(false).show(context, |ui| self.appearance_panel(ui));
+        egui::Window::new("Graph").default_open(false).show(context, |ui| self.graph_panel(ui));
+        egui::Window::new("Inspector").default_open(false).show(context, |ui| self.inspector_panel(ui));
+        egui::Window::new("File").default_open(false).show(context, |ui| self.file_panel(ui));
     }
 }
 
 impl<T: Graph> ForcePlot<T> {
-    fn get_particle_by_position(&self, position: Vector2<f32>) -> Option<usize> {
-        let interacted_particle = match self.model.interacted_particle {
-            Some((index, _)) => index,
-            None => usize::MAX,
-        };
-        for (i, particle) in self.model.particles.iter().enumerate() {
-            if i == interacted_particle {
-                continue;
-            }
-            if (particle.position - position).magnitude() < self.model.sizes[i] {
-                return Some(i);
-            }
-        }
+    /// Carries out what a key or mouse binding asked for.
+    fn perform(&mut self, action: Action, window_state: &PlotWindowState) {
+        let window_size = window_state.size;
+        match action {
+            Action::OpenGeneratorMenu => {
+                self.model.generator_menu = Some(GeneratorMenu::default());
+            },
+            Action::TogglePause => {
+                self.simulation.send(|layout| {
+                    layout.pause = !layout.pause;
+                    layout.wake();
+                });
+            },
+            Action::CycleIntegrator => {
+                self.simulation.send(|layout| {
+                    layout.integrator = layout.integrator.next();
+                    log::info!("Integrator: {:?}", layout.integrator);
+                    layout.wake();
+                });
+            },
+            Action::CycleMetric => {
+                self.model.metric = VertexMetric::cycle(self.model.metric);
+                self.model.outdate(Outdated::METRIC);
+            },
+            Action::CycleColormap => {
+                self.model.colormap = self.model.colormap.next();
+                self.model.recolor();
+            },
+            Action::ToggleSizeByMetric => {
+                self.model.size_by_metric = !self.model.size_by_metric;
+                self.model.recolor();
+            },
+            Action::CycleCommunities => {
+                self.model.community_algorithm = CommunityAlgorithm::cycle(self.model.community_algorithm);
+                self.model.outdate(Outdated::COMMUNITIES);
+            },
+            Action::CycleCommunityBackground => {
+                self.model.community_background = self.model.community_background.next();
+            },
+            Action::ToggleCommunityForce => {
+                self.model.community_force = !self.model.community_force;
+                self.communities_changed();
+            },
+            Action::SetPathSource => {
+                self.model.path.source = self.model.hovered;
+                self.model.path.target = None;
+                self.model.path.update(&self.model.graph, &self.model.edge_weights);
+            },
+            Action::SetPathTarget => {
+                if self.model.path.is_active() {
+                    self.model.path.target = self.model.hovered;
+                    self.model.path.update(&self.model.graph, &self.model.edge_weights);
+                }
+            },
+            Action::ToggleWeightedPaths => {
+                self.model.path.weighted = !self.model.path.weighted;
+                self.model.path.update(&self.model.graph, &self.model.edge_weights);
+            },
+            Action::ToggleAllPaths => {
+                self.model.path.all_paths = !self.model.path.all_paths;
+                self.model.path.update(&self.model.graph, &self.model.edge_weights);
+            },
+            Action::ToggleHoverDistances => {
+                self.model.distance_on_hover = !self.model.distance_on_hover;
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::Screenshot => {
+                self.screenshot_requested = true;
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::ToggleRecording => {
+                self.toggle_recording();
+            },
+            Action::ClearSelection => {
+                self.model.path.clear();
+                self.model.selected_edge = None;
+                self.model.selected_vertex = None;
+                self.model.info_vertex = None;
+            },
+            Action::ToggleHud => {
+                self.model.hud.visible = !self.model.hud.visible;
+            },
+            Action::CycleTheme => {
+                self.cycle_theme();
+            },
+            Action::ToggleCurvedEdges => {
+                self.model.curved_edges = !self.model.curved_edges;
+            },
+            Action::ToggleEdgeBundling => {
+                self.model.edge_bundling = !self.model.edge_bundling;
+            },
+            Action::StrengthenBundling => {
+                self.model.adjust_bundling_strength(BUNDLING_STRENGTH_STEP);
+            },
+            Action::WeakenBundling => {
+                self.model.adjust_bundling_strength(-BUNDLING_STRENGTH_STEP);
+            },
+            Action::ToggleHelp => {
+                self.model.hud.show_help = !self.model.hud.show_help;
+            },
+            Action::PinVertexInfo => {
+                self.model.info_vertex = self.model.hovered;
+            },
+            Action::DeleteEdge => {
+                self.delete_selected_edge();
+            },
+            Action::IncreaseWeight => {
+                self.adjust_selected_weight(WEIGHT_STEP);
+            },
+            Action::DecreaseWeight => {
+                self.adjust_selected_weight(-WEIGHT_STEP);
+            },
+            Action::SplitEdge => {
+                self.split_selected_edge();
+            },
+            Action::CycleAnimation => {
+                let algorithm = Algorithm::cycle(self.model.animation.as_ref().map(|animation| animation.algorithm));
+                let source = self.model.path.source.or(self.model.hovered).unwrap_or(0);
+                self.model.animation = algorithm
+                    .filter(|_| source < self.model.graph.vertex_ct())
+                    .map(|algorithm| Animation::new(algorithm, &self.model.graph, &self.model.edge_weights, source));
+            },
+            Action::ToggleAnimation => {
+                if let Some(animation) = self.model.animation.as_mut() {
+                    animation.playing = !animation.playing;
+                }
+            },
+            Action::StepAnimationForward => {
+                if let Some(animation) = self.model.animation.as_mut() {
+                    animation.playing = false;
+                    animation.step_forward();
+                }
+            },
+            Action::StepAnimationBack => {
+                if let Some(animation) = self.model.animation.as_mut() {
+                    animation.playing = false;
+                    animation.step_back();
+                }
+            },
+            Action::SpeedUpAnimation => {
+                if let Some(animation) = self.model.animation.as_mut() {
+                    animation.speed_up();
+                }
+            },
+            Action::SlowDownAnimation => {
+                if let Some(animation) = self.model.animation.as_mut() {
+                    animation.slow_down();
+                }
+            },
+            Action::RestartAnimation => {
+                if let Some(animation) = self.model.animation.as_mut() {
+                    animation.restart();
+                }
+            },
+            Action::Grab => {
+                let mouse_position = self
+                    .state
+                    .as_ref()
+                    .unwrap()
+                    .camera
+                    .pixel_to_camera(self.mouse_tracker.get_position(), window_size);
+                let mouse_position_delta =
+                    self.state.as_ref().unwrap().camera.center.to_vec() - mouse_position;
+                self.drag_previous_position = mouse_position_delta;
+                let pick = self.pick(window_state);
+                self.model.selected_edge = pick.and_then(Pick::edge);
+                self.model.selected_vertex = pick.and_then(Pick::vertex);
+                self.model.interacted_particle = pick
+                    .and_then(Pick::vertex)
+                    .map(|index| (index, InteractAction::Drag));
+                let pinned = self.model.interacted_particle.as_ref().map(|(index, _)| *index);
+                self.simulation.send(move |layout| layout.pinned = pinned);
+                self.panning = self.model.interacted_particle.is_none();
+            },
+            Action::AddVertex => {
+                let mouse_position = self
+                    .state
+                    .as_ref()
+                    .unwrap()
+                    .camera
+                    .pixel_to_camera(self.mouse_tracker.get_position(), window_size);
 
-        None
-    }
-}
+                match self.pick_vertex(window_state) {
+                    Some(index) => {
+                        let v = self.model.graph.add_vertex();
+                        self.model.graph.add_edge(index, v);
+                        self.model.colors.push(self.model.theme.vertex_fill);
+                        self.model.sizes.push(0.1);
+                        self.model.interacted_particle = Some((v, InteractAction::New));
+                        self.graph_changed(move |layout| {
+                            layout.particles.push(PhysicsParticle::new(mouse_position, 1.0));
+                            layout.pinned = Some(v);
+                            layout.isolated = Some(v);
+                        });
+                    }
+                    None => {}
+                }
+            },
+            Action::RemoveLastVertex => {
+                self.model.graph.resize(self.model.graph.vertex_ct() - 1);
+                self.model.edge_weights.truncate(self.model.graph.vertex_ct());
+                self.model.attributes.truncate(self.model.graph.vertex_ct());
+                self.model.colors.pop();
+                self.model.sizes.pop();
 
-// fn logistic(x: f32) -> f32 {
-//     1.0 / (1.0 + (-x).exp())
-// }
+                self.graph_changed(|layout| {
+                    layout.particles.pop();
+                });
+            },
+            Action::ClearGraph => {
+                self.model.graph.resize(1);
+                self.model.edge_weights.clear();
+                self.model.attributes.clear();
+                self.model.colors.clear();
+                self.model.sizes.clear();
+                self.model.sizes.push(0.1);
+                self.model.colors.push(self.model.theme.vertex_fill);
 
-pub struct PhysicsParticle {
-    position: Vector2<f32>,
-    velocity: Vector2<f32>,
-    force: Vector2<f32>,
-    mass: f32,
-}
+                self.graph_changed(|layout| {
+                    layout.particles.clear();
+                    layout.particles.push(PhysicsParticle::new(Vector2::zero(), 1.0));
+                });
 
-impl PhysicsParticle {
-    pub fn new(position: Vector2<f32>, mass: f32) -> PhysicsParticle {
-        Self {
-            position: position,
-            velocity: Vector2::zero(),
-            force: Vector2::zero(),
-            mass: mass,
+            },
         }
     }
 
-    pub fn add_force(&mut self, force: Vector2<f32>) {
-        self.force += force;
-    }
+    /// The vertex or edge under the cursor. Uses the picking pass's answer if it was read
+    /// back for this cursor position, and tests the geometry on the CPU otherwise.
+    fn pick(&self, window_state: &PlotWindowState) -> Option<Pick> {
+        let pixel = self.mouse_tracker.get_position();
+        let state = self.state.as_ref()?;
+        if let Some(pick) = self.picker.as_ref().and_then(|picker| picker.latest(pixel)) {
+            return pick.filter(|pick| self.model.contains(*pick));
+        }
 
-    pub fn clear_force(&mut self) {
-        self.force = Vector2::zero();
+        let position = state.camera.pixel_to_camera(pixel, window_state.size);
+        hit_test(&self.model, position, self.model.point_units, self.model.line_units, self.model.pixel_size)
     }
 
-    pub fn clear_velocity(&mut self) {
-        self.velocity = Vector2::zero();
+    fn pick_vertex(&self, window_state: &PlotWindowState) -> Option<usize> {
+        self.pick(window_state).and_then(Pick::vertex)
     }
 
-    pub fn step(&mut self, delta_t: f32) {
-        let weight = 0.0;
-
-        let acc = self.force / self.mass;
-        let new_velocity = self.velocity + acc * delta_t;
-        self.position += (weight * self.velocity + (1. - weight) * new_velocity) * delta_t; // + 0.5 * acc * delta_t * delta_t;
-        self.velocity = new_velocity;
-        if self.velocity.magnitude() > SPEED_LIMIT {
-            self.velocity = self.velocity.normalize() * SPEED_LIMIT;
+    /// Hovers whatever the pick read back since the last frame found, and asks for a new one
+    /// whenever the last has been read back, so hover follows the layout as it moves.
+    fn resolve_picks(&mut self, window_state: &PlotWindowState) {
+        let (Some(state), Some(picker)) = (self.state.as_ref(), self.picker.as_mut()) else {
+            return;
+        };
+        if let Some(pick) = picker.poll(window_state) {
+            let pick = pick.filter(|pick| self.model.contains(*pick));
+            self.model.hovered = pick.and_then(Pick::vertex);
+            self.model.hovered_edge = pick.and_then(Pick::edge);
+        }
+        if self.model.cursor.is_some() {
+            picker.request(state, window_state, self.mouse_tracker.get_position());
         }
-    }
-
-    pub fn get_position(&self) -> Vector2<f32> {
-        self.position
-    }
-
-    pub fn get_velocity(&self) -> Vector2<f32> {
-        self.velocity
     }
 }
 
 impl<T: Graph> ForcePlot<T> {
-    pub fn new(graph: T) -> Self {
-        let particles = (0..graph.vertex_ct())
-            .map(|_i| {
-                PhysicsParticle::new(
-                    Vector2::new(random::<f32>().cos(), random::<f32>().sin())
-                        * (graph.vertex_ct() as f32).pow(1. / 2.)
-                        * INIT_SPACING_MULTIPLIER,
-                    1.0,
-                )
-            })
-            .collect::<Vec<PhysicsParticle>>();
-        let colors = (0..graph.vertex_ct())
-            .map(|i| {
-                    Color::rgb(0.1, 0.7, 0.1)
-            })
-            .collect();
-        let mut sizes = Vec::new();
-        sizes.resize(graph.vertex_ct(), 0.1);
+    pub fn with_options(graph: T, options: &PlotOptions) -> Self {
+        let seed = options.seed.unwrap_or_else(random);
+        log::info!("Layout seed: {}", seed);
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let particles = place_particles(&graph, &options.initial_placement, &mut rng);
+        let colors = vec![options.theme.vertex_fill; graph.vertex_ct()];
+        let sizes = vec![DEFAULT_VERTEX_SIZE; graph.vertex_ct()];
         let state = None;
         let interacted_particle = None;
         let drag_previous_position = Vector2::zero();
 
-        let mut model = ForcePlotModel::new(graph, particles, colors, sizes, interacted_particle);
+        let mut model = ForcePlotModel::new(graph, colors, sizes, interacted_particle);
+        model.attributes = options.attributes.clone();
+        model.help_text = options.bindings.help();
+        model.line_units = options.render.line_units;
+        model.point_units = options.render.point_units;
+        model.curved_edges = options.render.curved_edges;
+        if let Some(strength) = options.render.edge_bundling {
+            model.edge_bundling = true;
+            model.bundling_strength = strength;
+        }
+        model.theme = options.theme.clone();
+        let mut themes = Theme::builtins();
+        if !themes.contains(&options.theme) {
+            themes.push(options.theme.clone());
+        }
+        let simulation = SimulationRunner::new(ForceLayout::new(particles, model.graph_distances.clone()));
 
         // for _ in 0..10000 {
         //     model.tick(Duration::new(0, 10000000));
@@ -458,33 +1172,1299 @@ impl<T: Graph> ForcePlot<T> {
 
         Self {
             model,
+            simulation,
+            analyzer: SimulationRunner::new(GraphAnalyzer::default()),
+            analyzed: AnalysisSnapshot::default(),
+            analysis_request: None,
+            bundler: SimulationRunner::new(EdgeBundler::default()),
+            bundled: BundleSnapshot::default(),
+            bundle_request: None,
+            bundle_request_ct: 0,
+            rng,
             state,
             mouse_tracker: MouseTracker::new(),
+            bindings: options.bindings.clone(),
             drag_previous_position,
+            panning: false,
+            picker: None,
+            themes,
+            icons: options.icons.clone(),
+            #[cfg(not(target_arch = "wasm32"))]
+            capturer: Capturer::new(options.capture.clone()),
+            #[cfg(not(target_arch = "wasm32"))]
+            screenshot_requested: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            next_recorded_frame: 0,
+            #[cfg(feature = "egui")]
+            panels: GuiPanels::default(),
         }
     }
-}
 
-impl<T: Graph> GetPoints for ForcePlotModel<T> {
-    fn get_points(&self) -> impl Iterator<Item = (Vector2<f32>, Color, f32)> {
-        (0..self.graph.vertex_ct())
-            .map(|i| (self.particles[i].position, self.colors[i], self.sizes[i]))
+    /// Runs `step_ct` fixed simulation steps without rendering. Together with a fixed seed
+    /// this reproduces a layout exactly.
+    /// Only possible before the plot is shown, while the simulation hasn't moved to its own
+    /// thread.
+    pub fn advance(&mut self, step_ct: usize) {
+        if let Some(layout) = self.simulation.local_mut() {
+            for _ in 0..step_ct {
+                if layout.is_settled() {
+                    break;
+                }
+                layout.step(SIMULATION_TIMESTEP);
+            }
+        }
     }
-    // fn get_points(&self) -> impl Iterator<Item = Vector3<f32>> {
-    //     self.particles.iter().map(|particle| particle.position)
-    // }
-}
 
-impl<T: Graph> GetLines for ForcePlotModel<T> {
-    fn get_lines(&self) -> impl Iterator<Item = (Vector2<f32>, Vector2<f32>, Color)> {
-        self.graph.edges().map(|(u, v, _)| {
-            (
-                self.particles[u].position
-                    + (self.particles[v].position - self.particles[u].position).normalize() * 0.1,
-                self.particles[v].position
-                    - (self.particles[v].position - self.particles[u].position).normalize() * 0.1,
-                Color::rgba(0., 0., 0., 0.5),
-            )
-        })
+    /// Passes a key to the generator menu, and replaces the graph with the generator it picks.
+    fn generator_menu_input(&mut self, key: KeyCode) {
+        let Some(menu) = self.model.generator_menu.as_mut() else {
+            return;
+        };
+        match menu.input(key) {
+            MenuInput::Stay => {}
+            MenuInput::Close => self.model.generator_menu = None,
+            MenuInput::Generate(generator) => {
+                self.model.generator_menu = None;
+                self.replace_graph(&generator);
+            }
+        }
+    }
+
+    /// Switches colors. Vertices with the old theme's fill take the new one; vertices colored
+    /// by anything else keep their colors.
+    fn set_theme(&mut self, theme: Theme) {
+        let old_fill = self.model.theme.vertex_fill;
+        for color in self.model.colors.iter_mut().filter(|color| **color == old_fill) {
+            *color = theme.vertex_fill;
+        }
+        if let Some(state) = self.state.as_mut() {
+            state.background = theme.background;
+        }
+        self.model.theme = theme;
+    }
+
+    fn cycle_theme(&mut self) {
+        let current = self.themes.iter().position(|theme| *theme == self.model.theme);
+        let next = current.map_or(0, |i| (i + 1) % self.themes.len());
+        self.set_theme(self.themes[next].clone());
+    }
+
+    /// Swaps in a freshly generated graph and restarts the layout from a random disk.
+    fn replace_graph(&mut self, generator: &Generator) {
+        log::info!("Generating {}", generator.describe());
+        generator.build(&mut self.model.graph, &mut self.rng);
+        self.graph_replaced();
+    }
+
+    /// Forgets everything tied to the old vertices after the whole graph was swapped out, and
+    /// restarts the layout from a random disk.
+    fn graph_replaced(&mut self) {
+        let vertex_ct = self.model.graph.vertex_ct();
+        self.model.colors = vec![self.model.theme.vertex_fill; vertex_ct];
+        self.model.sizes = vec![DEFAULT_VERTEX_SIZE; vertex_ct];
+        self.model.interacted_particle = None;
+        self.model.hovered = None;
+        self.model.info_vertex = None;
+        self.model.selected_vertex = None;
+        self.model.edge_weights.clear();
+        self.model.attributes.clear();
+        self.model.path.clear();
+
+        let particles = place_particles(&self.model.graph, &InitialPlacement::Disk, &mut self.rng);
+        self.graph_changed(move |layout| {
+            layout.particles = particles;
+            layout.pinned = None;
+            layout.isolated = None;
+        });
+    }
+
+    /// Moves every vertex to a fresh `placement` and lets the layout run from there.
+    #[cfg(feature = "egui")]
+    fn restart_layout(&mut self, placement: &InitialPlacement) {
+        let particles = place_particles(&self.model.graph, placement, &mut self.rng);
+        self.model.interacted_particle = None;
+        self.simulation.send(move |layout| {
+            layout.particles = particles;
+            layout.pinned = None;
+            layout.isolated = None;
+            layout.wake();
+        });
+    }
+
+    /// Takes in the analysis the analyzer finished, and asks it for a new one when the graph,
+    /// metric or community algorithm changed. One request is computed at a time.
+    fn update_analysis(&mut self) {
+        if self.analyzer.latest(&mut self.analyzed)
+            && self.analysis_request == Some(self.analyzed.id)
+        {
+            self.analysis_request = None;
+            // An analysis of a graph or settings that have since changed is dropped.
+            if self.analyzed.id == self.model.analysis_version
+                && let Some(analysis) = self.analyzed.analysis.clone()
+            {
+                let layout_outdated = self.model.outdated.graph || self.model.outdated.communities;
+                self.model.apply_analysis(&analysis);
+                if layout_outdated {
+                    let graph_distances = self.model.graph_distances.clone();
+                    let communities = self.model.layout_communities();
+                    self.simulation.send(move |layout| {
+                        layout.graph_distances = graph_distances;
+                        layout.distances_outdated = false;
+                        layout.communities = communities;
+                        layout.wake();
+                    });
+                }
+            }
+        }
+
+        if !self.model.outdated.any() || self.analysis_request.is_some() {
+            return;
+        }
+        let model = &self.model;
+        let request = AnalysisRequest {
+            id: model.analysis_version,
+            vertex_ct: model.graph.vertex_ct(),
+            edges: model.graph.edges().map(|(u, v, _)| (u, v)).collect(),
+            community_algorithm: model.community_algorithm,
+            metric: model.metric,
+            seed: self.rng.random(),
+            known: model.known_analysis(),
+        };
+        self.analysis_request = Some(request.id);
+        self.analyzer.send(move |analyzer| analyzer.request = Some(request));
+    }
+
+    /// Swaps in the bundles the bundler finished, and asks it for new ones when bundling is
+    /// on and there are no bundles yet, the graph or strength changed, or the ones there are
+    /// were computed before the layout settled. One request is computed at a time.
+    fn update_bundles(&mut self) {
+        let model = &mut self.model;
+        if !model.edge_bundling {
+            model.bundles = None;
+            return;
+        }
+
+        if self.bundler.latest(&mut self.bundled)
+            && let Some((id, settled)) = self.bundle_request
+            && id == self.bundled.id
+        {
+            self.bundle_request = None;
+            // Bundles for a graph or strength that has since changed are dropped.
+            if !model.bundles_outdated {
+                model.bundles = self.bundled.bundles.clone();
+                model.bundled_settled = settled;
+            }
+        }
+
+        // Bundles computed from a settled layout are stale once it moves again.
+        if !model.layout.settled {
+            model.bundled_settled = false;
+        }
+        let wanted = model.bundles.is_none()
+            || model.bundles_outdated
+            || (model.layout.settled && !model.bundled_settled);
+        if !wanted || self.bundle_request.is_some() {
+            return;
+        }
+
+        self.bundle_request_ct += 1;
+        let request = BundleRequest {
+            id: self.bundle_request_ct,
+            edges: model.graph.edges().map(|(u, v, _)| (u, v)).collect(),
+            positions: model.layout.positions.clone(),
+            strength: model.bundling_strength,
+        };
+        self.bundle_request = Some((request.id, model.layout.settled));
+        model.bundles_outdated = false;
+        self.bundler.send(move |bundler| bundler.request = Some(request));
+    }
+
+    /// Hands the frame a screenshot or the recording asked for to the capturer, and passes
+    /// on frames read back since. A recorded frame lets the layout move on to the next one.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn frame_drawn(&mut self, window_state: &PlotWindowState, frame: Option<CopiedFrame>, record: bool) {
+        let screenshot = std::mem::take(&mut self.screenshot_requested);
+        if let Some(frame) = frame {
+            self.capturer.read_back(frame, screenshot, record);
+        }
+        if !self.capturer.poll(&window_state.device) {
+            self.toggle_recording();
+            return;
+        }
+
+        if record {
+            self.next_recorded_frame += 1;
+            self.simulation.send(|layout| layout.advance_frame());
+        }
+    }
+
+    /// Starts recording with the layout on a fixed frame clock, or stops the recording and
+    /// hands the layout back to the wall clock.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn toggle_recording(&mut self) {
+        if self.capturer.is_recording() {
+            self.capturer.stop_recording();
+            self.simulation.send(|layout| layout.fixed_frame = None);
+            return;
+        }
+
+        match self.capturer.start_recording() {
+            Ok(()) => {
+                let frame_interval = self.capturer.options().frame_interval();
+                self.simulation.latest(&mut self.model.layout);
+                self.next_recorded_frame = self.model.layout.frame_ct + 1;
+                self.simulation.send(move |layout| {
+                    layout.fixed_frame = Some(frame_interval);
+                    layout.advance_frame();
+                });
+            }
+            Err(error) => log::error!("Couldn't start recording: {}", error),
+        }
+    }
+
+    /// Sends the communities the layout should pull together after they or the community
+    /// force changed.
+    fn communities_changed(&mut self) {
+        let communities = self.model.layout_communities();
+        self.simulation.send(move |layout| {
+            layout.communities = communities;
+            layout.wake();
+        });
+    }
+
+    fn delete_selected_edge(&mut self) {
+        let Some((u, v)) = self.model.selected_edge else {
+            return;
+        };
+        self.model.graph.remove_edge(u, v);
+        self.model.edge_weights.remove_edge(u, v);
+        self.graph_changed(|_| {});
+    }
+
+    fn adjust_selected_weight(&mut self, delta: f64) {
+        let Some((u, v)) = self.model.selected_edge else {
+            return;
+        };
+        if let Some(weight) = self.model.edge_weights.weight(&self.model.graph, u, v) {
+            self.set_selected_weight(weight + delta);
+        }
+    }
+
+    /// Weights stay at 0 or above, which Dijkstra and Prim need.
+    fn set_selected_weight(&mut self, weight: f64) {
+        let Some((u, v)) = self.model.selected_edge else {
+            return;
+        };
+        self.model.edge_weights.set(u, v, weight.max(0.0));
+        self.model.path.update(&self.model.graph, &self.model.edge_weights);
+        // The recorded run used the old weight.
+        self.model.animation = None;
+    }
+
+    /// Inserts a vertex at the middle of the selected edge. The two halves split the weight,
+    /// so weighted path lengths through the edge stay the same.
+    fn split_selected_edge(&mut self) {
+        let Some((u, v)) = self.model.selected_edge else {
+            return;
+        };
+        let positions = &self.model.layout.positions;
+        if v >= positions.len() {
+            return;
+        }
+        let midpoint = (positions[u] + positions[v]) / 2.0;
+        let weight = self.model.edge_weights.weight(&self.model.graph, u, v).unwrap_or(1.0);
+
+        let w = self.model.graph.add_vertex();
+        self.model.graph.remove_edge(u, v);
+        self.model.graph.add_edge(u, w);
+        self.model.graph.add_edge(w, v);
+        self.model.edge_weights.remove_edge(u, v);
+        self.model.edge_weights.set(u, w, weight / 2.0);
+        self.model.edge_weights.set(w, v, weight / 2.0);
+        self.model.colors.push(self.model.theme.vertex_fill);
+        self.model.sizes.push(DEFAULT_VERTEX_SIZE);
+        self.graph_changed(move |layout| {
+            layout.particles.push(PhysicsParticle::new(midpoint, 1.0));
+        });
+    }
+
+    /// Has the graph analyzed again after an edit and applies `edit` to the layout. The layout
+    /// holds still until the new distances arrive, so it never steps with particles and
+    /// distances that disagree.
+    fn graph_changed(&mut self, edit: impl FnOnce(&mut ForceLayout) + Send + 'static) {
+        self.model.outdate(Outdated::GRAPH);
+        self.model.edge_ct = self.model.graph.edges().count();
+        self.model.path.update(&self.model.graph, &self.model.edge_weights);
+        // The bundles drawn are keyed by the old vertex numbers.
+        self.model.bundles = None;
+        self.model.bundles_outdated = true;
+        // A recorded run refers to vertices that may no longer exist, and removing a vertex
+        // renumbers the edges after it.
+        self.model.animation = None;
+        self.model.hovered_edge = None;
+        self.model.selected_edge = None;
+        let vertex_ct = self.model.graph.vertex_ct();
+        self.model.info_vertex = self.model.info_vertex.filter(|v| *v < vertex_ct);
+        self.model.selected_vertex = self.model.selected_vertex.filter(|v| *v < vertex_ct);
+        self.simulation.send(move |layout| {
+            edit(layout);
+            layout.distances_outdated = true;
+            layout.wake();
+        });
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl<T: Graph> EmbeddedPlot for ForcePlot<T> {
+    fn load_edge_list(&mut self, edge_list: &EdgeList) {
+        self.model.graph.resize(0);
+        self.model.graph.resize(edge_list.vertex_ct);
+        for (u, v, _) in &edge_list.edges {
+            if !self.model.graph.adjacent(*u, *v) {
+                self.model.graph.add_edge(*u, *v);
+            }
+        }
+        self.graph_replaced();
+        for (u, v, weight) in &edge_list.edges {
+            if let Some(weight) = weight {
+                self.model.edge_weights.set(*u, *v, *weight);
+            }
+        }
+    }
+
+    /// New vertices start unconnected, somewhere near the middle of the layout.
+    fn add_vertex(&mut self) -> usize {
+        let v = self.model.graph.add_vertex();
+        self.model.colors.push(self.model.theme.vertex_fill);
+        self.model.sizes.push(DEFAULT_VERTEX_SIZE);
+        let position = Vector2::new(self.rng.random_range(-1.0..1.0), self.rng.random_range(-1.0..1.0));
+        self.graph_changed(move |layout| {
+            layout.particles.push(PhysicsParticle::new(position, 1.0));
+        });
+        v
+    }
+
+    fn remove_vertex(&mut self, v: usize) -> Result<(), String> {
+        self.check_vertex(v)?;
+        self.model.graph.remove_vertex(v);
+        self.model.edge_weights.remove_vertex(v);
+        self.model.attributes.remove_vertex(v);
+        self.model.colors.remove(v);
+        self.model.sizes.remove(v);
+        // Every vertex after `v` moves down one index.
+        self.model.interacted_particle = None;
+        self.model.hovered = None;
+        self.model.selected_vertex = None;
+        self.graph_changed(move |layout| {
+            layout.particles.remove(v);
+            layout.pinned = None;
+            layout.isolated = None;
+        });
+        Ok(())
+    }
+
+    fn add_edge(&mut self, u: usize, v: usize, weight: Option<f64>) -> Result<(), String> {
+        EdgeList::new(Some(self.model.graph.vertex_ct()), vec![(u, v, weight)])?;
+        if !self.model.graph.adjacent(u, v) {
+            self.model.graph.add_edge(u, v);
+        }
+        if let Some(weight) = weight {
+            self.model.edge_weights.set(u, v, weight);
+        }
+        self.graph_changed(|_| {});
+        Ok(())
+    }
+
+    fn remove_edge(&mut self, u: usize, v: usize) -> Result<(), String> {
+        self.check_vertex(u)?;
+        self.check_vertex(v)?;
+        if !self.model.graph.adjacent(u, v) {
+            return Err(format!("no edge between {} and {}", u, v));
+        }
+        self.model.graph.remove_edge(u, v);
+        self.model.edge_weights.remove_edge(u, v);
+        self.graph_changed(|_| {});
+        Ok(())
+    }
+
+    fn set_vertex_color(&mut self, v: usize, color: Color) -> Result<(), String> {
+        self.check_vertex(v)?;
+        self.model.colors[v] = color;
+        Ok(())
+    }
+
+    fn set_theme(&mut self, theme: Theme) {
+        ForcePlot::set_theme(self, theme);
+    }
+
+    fn vertex_ct(&self) -> usize {
+        self.model.graph.vertex_ct()
+    }
+
+    fn positions(&mut self) -> &[Vector2<f32>] {
+        self.simulation.latest(&mut self.model.layout);
+        &self.model.layout.positions
+    }
+
+    fn hovered_pick(&self) -> Option<Pick> {
+        self.model
+            .hovered
+            .map(Pick::Vertex)
+            .or(self.model.hovered_edge.map(|(u, v)| Pick::Edge(u, v)))
+    }
+
+    fn selected_pick(&self) -> Option<Pick> {
+        self.model
+            .selected_vertex
+            .map(Pick::Vertex)
+            .or(self.model.selected_edge.map(|(u, v)| Pick::Edge(u, v)))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl<T: Graph> ForcePlot<T> {
+    fn check_vertex(&self, v: usize) -> Result<(), String> {
+        let vertex_ct = self.model.graph.vertex_ct();
+        if v < vertex_ct {
+            Ok(())
+        } else {
+            Err(format!("vertex {} out of range for {} vertices", v, vertex_ct))
+        }
+    }
+}
+
+#[cfg(feature = "egui")]
+impl<T: Graph> ForcePlot<T> {
+    fn layout_panel(&mut self, ui: &mut egui::Ui) {
+        let mut paused = self.model.layout.paused;
+        if ui.checkbox(&mut paused, "Paused").changed() {
+            self.simulation.send(move |layout| {
+                layout.pause = paused;
+                layout.wake();
+            });
+        }
+
+        let mut integrator = self.model.layout.integrator;
+        ui.horizontal(|ui| {
+            for option in [Integrator::Euler, Integrator::VelocityVerlet, Integrator::RungeKutta4] {
+                ui.radio_value(&mut integrator, option, option.name());
+            }
+        });
+        if integrator != self.model.layout.integrator {
+            self.simulation.send(move |layout| {
+                layout.integrator = integrator;
+                layout.wake();
+            });
+        }
+
+        ui.separator();
+        let parameters = &mut self.panels.layout_parameters;
+        let mut changed = false;
+        for (value, range, text) in [
+            (&mut parameters.spring_stiffness, 0.01..=10.0, "Spring stiffness"),
+            (&mut parameters.eq_length_mult, 0.1..=10.0, "Edge length"),
+            (&mut parameters.resistance, 0.01..=10.0, "Resistance"),
+            (&mut parameters.centering, 0.001..=1.0, "Centering"),
+            (&mut parameters.community_attraction, 0.01..=10.0, "Community attraction"),
+        ] {
+            changed |= ui
+                .add(egui::Slider::new(value, range).logarithmic(true).text(text))
+                .changed();
+        }
+        if ui.button("Defaults").clicked() {
+            *parameters = LayoutParameters::default();
+            changed = true;
+        }
+        if changed {
+            let parameters = *parameters;
+            self.simulation.send(move |layout| {
+                layout.parameters = parameters;
+                layout.wake();
+            });
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Restart from disk").clicked() {
+                self.restart_layout(&InitialPlacement::Disk);
+            }
+            if ui.button("Restart spectral").clicked() {
+                self.restart_layout(&InitialPlacement::Spectral);
+            }
+        });
+    }
+
+    fn appearance_panel(&mut self, ui: &mut egui::Ui) {
+        let metric = self.model.metric.map_or("Off", VertexMetric::name);
+        if ui.button(format!("Metric: {}", metric)).clicked() {
+            self.model.metric = VertexMetric::cycle(self.model.metric);
+            self.model.outdate(Outdated::METRIC);
+        }
+        if ui.button(format!("Colormap: {:?}", self.model.colormap)).clicked() {
+            self.model.colormap = self.model.colormap.next();
+            self.model.recolor();
+        }
+        if ui.checkbox(&mut self.model.size_by_metric, "Size by metric").changed() {
+            self.model.recolor();
+        }
+
+        ui.separator();
+        let algorithm = self.model.community_algorithm.map_or("Off", CommunityAlgorithm::name);
+        if ui.button(format!("Communities: {}", algorithm)).clicked() {
+            self.model.community_algorithm = CommunityAlgorithm::cycle(self.model.community_algorithm);
+            self.model.outdate(Outdated::COMMUNITIES);
+        }
+        if ui.button(format!("Background: {:?}", self.model.community_background)).clicked() {
+            self.model.community_background = self.model.community_background.next();
+        }
+        if ui.checkbox(&mut self.model.community_force, "Pull communities together").changed() {
+            self.communities_changed();
+        }
+
+        ui.separator();
+        if ui.button(format!("Theme: {}", self.model.theme.name)).clicked() {
+            self.cycle_theme();
+        }
+        ui.checkbox(&mut self.model.curved_edges, "Curved edges");
+        ui.checkbox(&mut self.model.edge_bundling, "Bundle edges");
+        ui.add_enabled_ui(self.model.edge_bundling, |ui| {
+            let strength = egui::Slider::new(&mut self.model.bundling_strength, 0.0..=1.0).text("Bundling strength");
+            if ui.add(strength).changed() {
+                self.model.bundles_outdated = true;
+            }
+        });
+        ui.checkbox(&mut self.model.distance_on_hover, "Distances from hovered vertex");
+        ui.checkbox(&mut self.model.hud.visible, "Statistics");
+    }
+
+    fn graph_panel(&mut self, ui: &mut egui::Ui) {
+        ui.label(format!("{} vertices, {} edges", self.model.graph.vertex_ct(), self.model.edge_ct));
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.panels.generator_spec)
+                .on_hover_text("family:param,..., e.g. grid:20,30 or er:200,0.05");
+            if ui.button("Generate").clicked() {
+                match Generator::parse(self.panels.generator_spec.trim()) {
+                    Ok(generator) => {
+                        self.panels.message = None;
+                        self.replace_graph(&generator);
+                    }
+                    Err(error) => self.panels.message = Some(error),
+                }
+            }
+        });
+
+        ui.separator();
+        for generator in Generator::gallery() {
+            if ui.button(generator.describe()).clicked() {
+                self.replace_graph(&generator);
+            }
+        }
+
+        if let Some(message) = self.panels.message.as_ref() {
+            ui.separator();
+            ui.label(message);
+        }
+    }
+
+    fn inspector_panel(&mut self, ui: &mut egui::Ui) {
+        match self.model.info_vertex.or(self.model.hovered) {
+            Some(u) if u < self.model.graph.vertex_ct() => {
+                ui.label(self.model.describe_vertex(u));
+                let pinned = self.model.info_vertex == Some(u);
+                if ui.button(if pinned { "Unpin" } else { "Pin" }).clicked() {
+                    self.model.info_vertex = if pinned { None } else { Some(u) };
+                }
+            }
+            _ => {
+                ui.label("Hover a vertex to inspect it");
+            }
+        }
+
+        ui.separator();
+        let Some((u, v)) = self.model.selected_edge else {
+            ui.label("Click an edge to select it");
+            return;
+        };
+        let Some(mut weight) = self.model.edge_weights.weight(&self.model.graph, u, v) else {
+            return;
+        };
+        ui.label(format!("Edge {} - {}", u, v));
+        ui.horizontal(|ui| {
+            ui.label("Weight");
+            if ui
+                .add(egui::DragValue::new(&mut weight).speed(0.1).range(0.0..=f64::INFINITY))
+                .changed()
+            {
+                self.set_selected_weight(weight);
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Split").clicked() {
+                self.split_selected_edge();
+            }
+            if ui.button("Delete").clicked() {
+                self.delete_selected_edge();
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn file_panel(&mut self, ui: &mut egui::Ui) {
+        let dialog = || rfd::FileDialog::new().add_filter("Text", &["txt", "csv"]);
+
+        if ui.button("Open positions...").clicked() {
+            if let Some(path) = dialog().pick_file() {
+                match read_positions(&path) {
+                    Ok(positions) => {
+                        self.panels.message = None;
+                        self.restart_layout(&InitialPlacement::Positions(positions));
+                    }
+                    Err(error) => self.panels.message = Some(format!("{}: {}", path.display(), error)),
+                }
+            }
+        }
+        if ui.button("Open attributes...").clicked() {
+            if let Some(path) = dialog().pick_file() {
+                match read_attributes(&path) {
+                    Ok(attributes) => {
+                        self.panels.message = None;
+                        self.model.attributes = attributes;
+                    }
+                    Err(error) => self.panels.message = Some(format!("{}: {}", path.display(), error)),
+                }
+            }
+        }
+
+        if ui.button("Open theme...").clicked() {
+            if let Some(path) = rfd::FileDialog::new().pick_file() {
+                match read_theme(&path) {
+                    Ok(theme) => {
+                        self.panels.message = None;
+                        // A theme loaded again under the same name replaces the old one.
+                        self.themes.retain(|loaded| loaded.name != theme.name);
+                        self.themes.push(theme.clone());
+                        self.set_theme(theme);
+                    }
+                    Err(error) => self.panels.message = Some(format!("{}: {}", path.display(), error)),
+                }
+            }
+        }
+
+        ui.separator();
+        if ui.button("Save positions...").clicked() {
+            if let Some(path) = dialog().set_file_name("positions.txt").save_file() {
+                let result = write_positions(&path, &self.model.layout.positions);
+                self.panels.message = Some(match result {
+                    Ok(()) => format!("Saved {}", path.display()),
+                    Err(error) => format!("{}: {}", path.display(), error),
+                });
+            }
+        }
+        if ui.button("Export edge list...").clicked() {
+            if let Some(path) = dialog().set_file_name("edges.txt").save_file() {
+                let contents = self
+                    .model
+                    .edge_weights
+                    .edges(&self.model.graph)
+                    .map(|(u, v, weight)| format!("{} {} {}\n", u, v, weight))
+                    .collect::<String>();
+                self.panels.message = Some(match std::fs::write(&path, contents) {
+                    Ok(()) => format!("Saved {}", path.display()),
+                    Err(error) => format!("{}: {}", path.display(), error),
+                });
+            }
+        }
+
+        if let Some(message) = self.panels.message.as_ref() {
+            ui.separator();
+            ui.label(message);
+        }
+    }
+
+    /// Browsers give no file system to open from or save to.
+    #[cfg(target_arch = "wasm32")]
+    fn file_panel(&mut self, ui: &mut egui::Ui) {
+        ui.label("File dialogs are only available in the native build");
+    }
+}
+
+/// Initial particles for every vertex of `graph`, spread over an area that grows with the
+/// vertex count.
+fn place_particles<T: Graph>(graph: &T, placement: &InitialPlacement, rng: &mut StdRng) -> Vec<PhysicsParticle> {
+    placement
+        .place(
+            graph,
+            (graph.vertex_ct() as f32).pow(1. / 2.) * INIT_SPACING_MULTIPLIER,
+            rng,
+        )
+        .into_iter()
+        .map(|position| PhysicsParticle::new(position, 1.0))
+        .collect()
+}
+
+// The layout snapshot can lag a frame behind graph edits, so only vertices the
+// simulation has already published are drawn.
+// An algorithm animation or hover distances take over the colors; a selected path dims every
+// vertex off it. Shapes and icons come from the "shape" and "icon" attributes.
+impl<T: Graph> GetPoints for ForcePlotModel<T> {
+    fn get_points(&self) -> impl Iterator<Item = (Vector2<f32>, PointStyle)> {
+        let distance_source = self.distance_source().zip(self.graph_distances());
+        let max_distance = distance_source.map_or(0, |(source, graph_distances)| {
+            (0..self.graph.vertex_ct())
+                .map(|v| *graph_distances.get(source, v))
+                .max()
+                .unwrap_or(0)
+        });
+
+        self.layout
+            .positions
+            .iter()
+            .zip(&self.colors)
+            .zip(&self.sizes)
+            .enumerate()
+            .map(move |(u, ((position, color), size))| {
+                let style = |color| {
+                    let style = PointStyle::new(color, self.point_size(*size))
+                        .with_shape(self.attributes.value(u, "shape").and_then(Shape::parse).unwrap_or_default())
+                        .with_icon(self.attributes.value(u, "icon").and_then(|icon| icon.parse().ok()));
+                    match self.hovered {
+                        Some(hovered) if hovered == u => style.with_outline(self.theme.hover, HOVER_OUTLINE_WIDTH),
+                        _ => style.with_outline(self.theme.vertex_outline, self.theme.outline_thickness),
+                    }
+                };
+                if Some(u) == self.selected_vertex {
+                    return (*position, style(self.theme.selection));
+                }
+                if let Some(animation) = self.animation.as_ref() {
+                    return (*position, style(animation_vertex_color(animation, u, *color, &self.theme)));
+                }
+                let color = match distance_source {
+                    Some((source, graph_distances)) => match *graph_distances.get(source, u) {
+                        -1 => UNREACHABLE_COLOR,
+                        distance => self.colormap.sample(distance as f32 / max_distance.max(1) as f32),
+                    },
+                    None if self.path.is_active() && !self.path.contains_vertex(u) => {
+                        dim(*color, &self.theme)
+                    }
+                    None => *color,
+                };
+                (*position, style(color))
+            })
+    }
+    // fn get_points(&self) -> impl Iterator<Item = Vector3<f32>> {
+    //     self.particles.iter().map(|particle| particle.position)
+    // }
+}
+
+// The vertex being dragged out of another is left out, so releasing it picks whatever is
+// underneath.
+impl<T: Graph> GetPickables for ForcePlotModel<T> {
+    fn get_pick_points(&self) -> impl Iterator<Item = (Vector2<f32>, f32)> {
+        let interacted_particle = self.interacted_particle.map(|(index, _)| index);
+        self.layout
+            .positions
+            .iter()
+            .zip(&self.sizes)
+            .enumerate()
+            .map(move |(u, (position, size))| {
+                (*position, if Some(u) == interacted_particle { 0.0 } else { self.point_size(*size) })
+            })
+    }
+
+    fn get_pick_lines(&self) -> impl Iterator<Item = (Vector2<f32>, Vector2<f32>, f32, (usize, usize))> {
+        let width = self.line_width(EDGE_PICK_WIDTH);
+        self.edge_curves().flat_map(move |(u, v, curve)| {
+            curve.segments().map(move |(x0, x1)| (x0, x1, width, (u, v)))
+        })
+    }
+}
+
+impl<T: Graph> GetLines for ForcePlotModel<T> {
+    fn get_lines(&self) -> impl Iterator<Item = (Curve, Color, f32)> {
+        let path_color = Color::hex(0xE15759);
+        // Edges stop at the rim of a default-sized vertex.
+        let trim = self.world_radius(DEFAULT_VERTEX_SIZE);
+        self.edge_curves()
+            .map(move |(u, v, curve)| {
+                let edge = Some((u.min(v), u.max(v)));
+                let (color, width) = if edge == self.selected_edge {
+                    (self.theme.selection, PATH_EDGE_WIDTH)
+                } else if edge == self.hovered_edge {
+                    (self.theme.hover, PATH_EDGE_WIDTH)
+                } else if let Some(animation) = self.animation.as_ref() {
+                    animation_edge_style(animation, u, v, &self.theme)
+                } else if !self.path.is_active() {
+                    (self.edge_color(), EDGE_WIDTH)
+                } else if self.path.contains_edge(u, v) {
+                    (path_color, PATH_EDGE_WIDTH)
+                } else {
+                    (dim(self.edge_color(), &self.theme), EDGE_WIDTH)
+                };
+                (curve.trimmed(trim, trim), color, self.line_width(width))
+            })
+    }
+}
+
+/// `text` on a translucent backdrop, so it stays readable over the graph.
+fn push_panel(items: &mut Vec<OverlayItem>, theme: &Theme, text: String, anchor: Anchor, position: Vector2<f32>) {
+    let size = text_size(&text, LEGEND_TEXT_SCALE);
+    let padding = Vector2::new(LEGEND_MARGIN, LEGEND_MARGIN) / 2.0;
+    items.push(OverlayItem::Rect {
+        anchor,
+        position: position - padding,
+        size: size + padding * 2.0,
+        color: theme.panel,
+    });
+    items.push(OverlayItem::Text {
+        text,
+        anchor,
+        position,
+        color: theme.text,
+        scale: LEGEND_TEXT_SCALE,
+    });
+}
+
+/// `color` blended most of the way into the background, keeping its alpha.
+fn dim(color: Color, theme: &Theme) -> Color {
+    color.lerp(Color { a: color.a, ..theme.background }, DIM_AMOUNT)
+}
+
+fn animation_vertex_color(animation: &Animation, u: usize, base: Color, theme: &Theme) -> Color {
+    if animation.is_current_vertex(u) {
+        return Color::hex(0xE15759);
+    }
+    match animation.vertex_state(u) {
+        VertexState::Unvisited => dim(base, theme),
+        VertexState::Discovered => Color::hex(0xEDC948),
+        VertexState::Visited => Color::hex(0xF28E2B),
+        VertexState::Finished => Color::hex(0x4E79A7),
+    }
+}
+
+fn animation_edge_style(animation: &Animation, u: usize, v: usize, theme: &Theme) -> (Color, f32) {
+    let (color, width) = match animation.edge_state(u, v) {
+        None => (dim(theme.edge, theme), EDGE_WIDTH),
+        Some(EdgeState::Examined) => (Color::hex(0x9C755F), EDGE_WIDTH),
+        Some(EdgeState::Relaxed) => (Color::hex(0xEDC948), EDGE_WIDTH * 2.0),
+        Some(EdgeState::Accepted) => (Color::hex(0x59A14F), PATH_EDGE_WIDTH),
+        Some(EdgeState::Rejected) => (Color::hex(0xE15759), EDGE_WIDTH),
+    };
+    if animation.is_current_edge(u, v) {
+        (color, width * 1.5)
+    } else {
+        (color, width)
+    }
+}
+
+/// Legend for the current coloring: the detected communities, a colorbar with the metric's
+/// range, or a swatch per component.
+impl<T: Graph> GetOverlay for ForcePlotModel<T> {
+    fn get_overlay(&self) -> impl Iterator<Item = OverlayItem> {
+        let mut items = Vec::new();
+        let text_color = self.theme.text;
+        let line_height = text_size("", LEGEND_TEXT_SCALE).y + LEGEND_MARGIN / 2.0;
+        let mut y = LEGEND_MARGIN;
+
+        let mut status = Vec::new();
+        if let Some(source) = self.distance_source() {
+            status.push(format!("Distance from {}", source));
+        }
+        status.extend(self.path.describe());
+        if let Some((u, v)) = self.selected_edge
+            && let Some(weight) = self.edge_weights.weight(&self.graph, u, v)
+        {
+            status.push(format!(
+                "Edge {} - {}: weight {} (+/- weight, B split, Delete remove)",
+                u, v, weight
+            ));
+        }
+        status.extend(self.animation.as_ref().map(|animation| animation.describe()));
+        if !status.is_empty() {
+            items.push(OverlayItem::Text {
+                text: status.join("\n"),
+                anchor: Anchor::BottomLeft,
+                position: Vector2::new(LEGEND_MARGIN, LEGEND_MARGIN),
+                color: text_color,
+                scale: LEGEND_TEXT_SCALE,
+            });
+        }
+
+        if let Some(menu) = self.generator_menu.as_ref() {
+            push_panel(&mut items, &self.theme, menu.describe(), Anchor::TopRight, Vector2::new(LEGEND_MARGIN, LEGEND_MARGIN));
+        } else {
+            // The menu takes the HUD's corner while it is open.
+            let mut hud_y = LEGEND_MARGIN;
+            if self.hud.visible {
+                let mut text = self.hud.describe(&self.layout, self.graph.vertex_ct(), self.edge_ct);
+                if !self.hud.show_help {
+                    text.push_str("\n? for key bindings");
+                }
+                hud_y += text_size(&text, LEGEND_TEXT_SCALE).y + LEGEND_MARGIN * 1.5;
+                push_panel(&mut items, &self.theme, text, Anchor::TopRight, Vector2::new(LEGEND_MARGIN, LEGEND_MARGIN));
+            }
+            if self.hud.show_help {
+                push_panel(&mut items, &self.theme, self.help_text.clone(), Anchor::TopRight, Vector2::new(LEGEND_MARGIN, hud_y));
+            }
+        }
+
+        if let Some(u) = self.info_vertex.filter(|u| *u < self.graph.vertex_ct()) {
+            let text = format!("{}\n(F to pin another, Escape to close)", self.describe_vertex(u));
+            push_panel(&mut items, &self.theme, text, Anchor::BottomRight, Vector2::new(LEGEND_MARGIN, LEGEND_MARGIN));
+        }
+
+        // The tooltip follows the cursor and flips to its other side near the window's edges.
+        let tooltip_vertex = self
+            .hovered
+            .filter(|u| *u < self.graph.vertex_ct() && Some(*u) != self.info_vertex)
+            .filter(|_| self.interacted_particle.is_none() && self.generator_menu.is_none());
+        if let (Some(u), Some(cursor)) = (tooltip_vertex, self.cursor) {
+            let text = self.describe_vertex(u);
+            let size = text_size(&text, LEGEND_TEXT_SCALE);
+            let mut position = cursor + Vector2::new(TOOLTIP_OFFSET, TOOLTIP_OFFSET);
+            if position.x + size.x > self.window_size.x {
+                position.x = (cursor.x - TOOLTIP_OFFSET - size.x).max(0.0);
+            }
+            if position.y + size.y > self.window_size.y {
+                position.y = (cursor.y - TOOLTIP_OFFSET - size.y).max(0.0);
+            }
+            push_panel(&mut items, &self.theme, text, Anchor::TopLeft, position);
+        }
+
+        if let Some(algorithm) = self.community_algorithm {
+            let mut text = format!(
+                "{}: {} communities\nModularity {:.3}",
+                algorithm.name(),
+                self.community_ct,
+                self.modularity
+            );
+            if self.community_force {
+                text.push_str("\nCommunity force on");
+            }
+            items.push(OverlayItem::Text {
+                text,
+                anchor: Anchor::TopLeft,
+                position: Vector2::new(LEGEND_MARGIN, y),
+                color: text_color,
+                scale: LEGEND_TEXT_SCALE,
+            });
+            return items.into_iter();
+        }
+
+        let Some(metric) = self.metric else {
+            return items.into_iter();
+        };
+
+        let mut title = metric.name().to_string();
+        if self.size_by_metric && !metric.is_categorical() {
+            title.push_str(" (size)");
+        }
+        items.push(OverlayItem::Text {
+            text: title,
+            anchor: Anchor::TopLeft,
+            position: Vector2::new(LEGEND_MARGIN, y),
+            color: text_color,
+            scale: LEGEND_TEXT_SCALE,
+        });
+        y += line_height;
+
+        if metric.is_categorical() {
+            let swatch = Vector2::new(1.0, 1.0) * text_size("", LEGEND_TEXT_SCALE).y;
+            for component in 0..self.component_ct.min(CATEGORICAL_COLOR_CT) {
+                items.push(OverlayItem::Rect {
+                    anchor: Anchor::TopLeft,
+                    position: Vector2::new(LEGEND_MARGIN, y),
+                    size: swatch,
+                    color: colormap::categorical(component),
+                });
+                items.push(OverlayItem::Text {
+                    text: format!("{}", component),
+                    anchor: Anchor::TopLeft,
+                    position: Vector2::new(LEGEND_MARGIN * 1.5 + swatch.x, y),
+                    color: text_color,
+                    scale: LEGEND_TEXT_SCALE,
+                });
+                y += line_height;
+            }
+            if self.component_ct > CATEGORICAL_COLOR_CT {
+                items.push(OverlayItem::Text {
+                    text: format!("{} components, colors repeat", self.component_ct),
+                    anchor: Anchor::TopLeft,
+                    position: Vector2::new(LEGEND_MARGIN, y),
+                    color: text_color,
+                    scale: LEGEND_TEXT_SCALE,
+                });
+            }
+        } else {
+            items.push(OverlayItem::Gradient {
+                anchor: Anchor::TopLeft,
+                position: Vector2::new(LEGEND_MARGIN, y),
+                size: COLORBAR_SIZE,
+                colors: self.colormap.gradient(COLORBAR_SAMPLE_CT),
+            });
+            y += COLORBAR_SIZE.y + LEGEND_MARGIN / 2.0;
+
+            let (min, max) = self.metric_range;
+            let max_label = format_metric(max);
+            items.push(OverlayItem::Text {
+                text: format_metric(min),
+                anchor: Anchor::TopLeft,
+                position: Vector2::new(LEGEND_MARGIN, y),
+                color: text_color,
+                scale: LEGEND_TEXT_SCALE,
+            });
+            items.push(OverlayItem::Text {
+                position: Vector2::new(
+                    LEGEND_MARGIN + COLORBAR_SIZE.x - text_size(&max_label, LEGEND_TEXT_SCALE).x,
+                    y,
+                ),
+                text: max_label,
+                anchor: Anchor::TopLeft,
+                color: text_color,
+                scale: LEGEND_TEXT_SCALE,
+            });
+        }
+
+        items.into_iter()
+    }
+}
+
+fn format_metric(value: f32) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e6 {
+        format!("{}", value)
+    } else {
+        format!("{:.3}", value)
+    }
+}
+
+/// A hull or blob behind every community with at least one published vertex.
+impl<T: Graph> GetPolygons for ForcePlotModel<T> {
+    fn get_polygons(&self) -> impl Iterator<Item = (Vec<Vector2<f32>>, Color)> {
+        let mut members = vec![Vec::new(); self.community_ct];
+        if let Some(communities) = self.communities.as_ref()
+            && self.community_background != CommunityBackground::Off
+        {
+            for (position, community) in self.layout.positions.iter().zip(communities.iter()) {
+                members[*community].push(*position);
+            }
+        }
+
+        let (padding, segments_per_quarter) = match self.community_background {
+            CommunityBackground::Blob => (BLOB_PADDING, BLOB_SEGMENTS_PER_QUARTER),
+            _ => (HULL_PADDING, 1),
+        };
+
+        members
+            .into_iter()
+            .enumerate()
+            .filter(|(_, points)| !points.is_empty())
+            .map(move |(community, points)| {
+                let mut color = colormap::categorical(community);
+                color.a = COMMUNITY_BACKGROUND_ALPHA;
+                (inflate(&convex_hull(&points), padding, segments_per_quarter), color)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{initial_placement::{read_positions, write_positions}, test_graphs};
+    use gscientific::graph::petersen_graph;
+
+    const STEP_CT: usize = 200;
+
+    /// Positions after `STEP_CT` steps from `placement`, starting from `seed`.
+    fn layout(placement: &InitialPlacement, seed: u64) -> Vec<Vector2<f32>> {
+        let options = PlotOptions {
+            seed: Some(seed),
+            initial_placement: placement.clone(),
+            ..PlotOptions::default()
+        };
+        let mut plot = ForcePlot::with_options(petersen_graph(10, 3), &options);
+        plot.advance(STEP_CT);
+        plot.simulation.latest(&mut plot.model.layout);
+        plot.model.layout.positions.clone()
+    }
+
+    fn assert_reproducible(placement: &InitialPlacement) {
+        let first = layout(placement, 7);
+        // Bit-equal, not just close.
+        let bits = |positions: &[Vector2<f32>]| {
+            positions.iter().map(|p| (p.x.to_bits(), p.y.to_bits())).collect::<Vec<_>>()
+        };
+        assert_eq!(bits(&first), bits(&layout(placement, 7)), "{:?}", placement);
+        assert_ne!(bits(&first), bits(&layout(placement, 8)), "{:?}", placement);
+    }
+
+    #[test]
+    fn disk_layouts_are_reproducible() {
+        assert_reproducible(&InitialPlacement::Disk);
+    }
+
+    #[test]
+    fn spectral_layouts_are_reproducible() {
+        assert_reproducible(&InitialPlacement::Spectral);
+    }
+
+    #[test]
+    fn file_layouts_are_reproducible() {
+        let path = std::env::temp_dir().join(format!("graphite_plot_positions_{}.txt", std::process::id()));
+        // Fewer positions than vertices, so the rest are placed at random.
+        let positions = (0..12).map(|i| Vector2::new(i as f32, (i * i) as f32 / 10.0)).collect::<Vec<_>>();
+        write_positions(&path, &positions).unwrap();
+        let placement = InitialPlacement::Positions(read_positions(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        assert_reproducible(&placement);
+    }
+
+    #[test]
+    fn edits_hold_the_layout_until_their_analysis_arrives() {
+        let mut plot = ForcePlot::with_options(petersen_graph(10, 3), &PlotOptions::default());
+        let v = plot.model.graph.add_vertex();
+        plot.model.graph.add_edge(0, v);
+        plot.model.colors.push(plot.model.theme.vertex_fill);
+        plot.model.sizes.push(DEFAULT_VERTEX_SIZE);
+        plot.graph_changed(|layout| layout.particles.push(PhysicsParticle::new(Vector2::zero(), 1.0)));
+        assert!(plot.model.graph_distances().is_none());
+        assert!(plot.simulation.local_mut().unwrap().distances_outdated);
+
+        plot.update_analysis();
+        // Turning on a metric while the edit is analyzed drops that analysis for a new one.
+        plot.model.metric = Some(VertexMetric::Degree);
+        plot.model.outdate(Outdated::METRIC);
+        plot.analyzer.tick(instant::Duration::ZERO);
+        plot.update_analysis();
+        assert!(plot.model.graph_distances().is_none());
+        assert!(plot.model.metric_values.is_none());
+
+        plot.analyzer.tick(instant::Duration::ZERO);
+        plot.update_analysis();
+        assert_eq!(*plot.model.graph_distances().unwrap().get(0, v), 1);
+        assert_eq!(plot.model.metric_values.as_ref().unwrap()[0], 4.0);
+        assert_eq!(plot.model.colors.len(), 21);
+        let layout = plot.simulation.local_mut().unwrap();
+        assert!(!layout.distances_outdated);
+        assert!(Arc::ptr_eq(&layout.graph_distances, &plot.model.graph_distances));
+    }
+
+    #[test]
+    fn deleting_the_selected_edge_forgets_its_weight() {
+        let mut plot = ForcePlot::with_options(test_graphs::path(3), &PlotOptions::default());
+        plot.model.selected_edge = Some((0, 1));
+        plot.set_selected_weight(5.0);
+        plot.delete_selected_edge();
+
+        assert!(!plot.model.graph.adjacent(0, 1));
+        assert_eq!(plot.model.edge_weights.get(0, 1, -1.0), -1.0);
+        assert_eq!(plot.model.edge_ct, 1);
+        assert_eq!(plot.model.selected_edge, None);
+        assert!(plot.model.graph_distances().is_none());
+    }
+
+    #[test]
+    fn weights_adjust_and_stay_at_zero_or_above() {
+        let mut plot = ForcePlot::with_options(test_graphs::path(3), &PlotOptions::default());
+        let weight = |plot: &ForcePlot<_>| plot.model.edge_weights.weight(&plot.model.graph, 1, 2).unwrap();
+        let initial = weight(&plot);
+        plot.model.selected_edge = Some((1, 2));
+
+        plot.adjust_selected_weight(0.5);
+        assert_eq!(weight(&plot), initial + 0.5);
+        plot.adjust_selected_weight(-100.0);
+        assert_eq!(weight(&plot), 0.0);
+        // Only the selected edge changes.
+        assert_eq!(plot.model.edge_weights.weight(&plot.model.graph, 0, 1), Some(initial));
+    }
+
+    #[test]
+    fn splitting_an_edge_halves_its_weight_at_its_middle() {
+        let mut plot = ForcePlot::with_options(test_graphs::path(2), &PlotOptions::default());
+        plot.simulation.latest(&mut plot.model.layout);
+        let positions = plot.model.layout.positions.clone();
+        plot.model.selected_edge = Some((0, 1));
+        plot.set_selected_weight(3.0);
+        plot.split_selected_edge();
+
+        let graph = &plot.model.graph;
+        assert_eq!(graph.vertex_ct(), 3);
+        assert!(graph.adjacent(0, 2) && graph.adjacent(2, 1) && !graph.adjacent(0, 1));
+        assert_eq!(plot.model.edge_weights.weight(graph, 0, 2), Some(1.5));
+        assert_eq!(plot.model.edge_weights.weight(graph, 2, 1), Some(1.5));
+        assert_eq!((plot.model.colors.len(), plot.model.sizes.len()), (3, 3));
+        let layout = plot.simulation.local_mut().unwrap();
+        assert_eq!(layout.particles.len(), 3);
+        assert_eq!(layout.particles[2].get_position(), (positions[0] + positions[1]) / 2.0);
+    }
+
+    /// A plot of `graph` with its vertices at `positions` and sized in world units.
+    fn placed<T: Graph>(graph: T, positions: Vec<Vector2<f32>>) -> ForcePlot<T> {
+        let mut plot = ForcePlot::with_options(graph, &PlotOptions::default());
+        plot.model.layout.positions = positions;
+        plot.model.point_units = SizeUnits::World;
+        plot
+    }
+
+    /// The middle of a loop's control points, relative to its vertex.
+    fn loop_direction(curve: Curve) -> Vector2<f32> {
+        let Curve::Cubic(start, leaving, returning, end) = curve else {
+            panic!("loops are cubic");
+        };
+        assert_eq!(start, end);
+        for point in [leaving, returning] {
+            assert!(point.x.is_finite() && point.y.is_finite(), "{:?}", point);
+            assert_ne!(point, start);
+        }
+        (leaving + returning) / 2.0 - start
+    }
+
+    #[test]
+    fn loops_point_away_from_the_neighbors() {
+        let positions = vec![Vector2::new(0.0, 0.0), Vector2::new(3.0, 0.0), Vector2::new(5.0, 5.0)];
+        let plot = placed(test_graphs::graph(3, &[(0, 1)]), positions);
+
+        let away = loop_direction(plot.model.loop_curve(0, 0));
+        assert!(away.x < 0.0 && away.y.abs() < 1e-6, "{:?}", away);
+        let nested = loop_direction(plot.model.loop_curve(0, 1));
+        assert!(nested.magnitude() > away.magnitude());
+
+        // Isolated vertices have no neighbors to point away from.
+        let isolated = loop_direction(plot.model.loop_curve(2, 0));
+        assert!(isolated.y > 0.0 && isolated.x.abs() < 1e-6, "{:?}", isolated);
+    }
+
+    #[test]
+    fn loops_stay_finite_on_top_of_their_neighbors() {
+        let positions = vec![Vector2::new(1.0, 1.0), Vector2::new(1.0, 1.0)];
+        let plot = placed(test_graphs::graph(2, &[(0, 1)]), positions);
+        loop_direction(plot.model.loop_curve(0, 0));
+    }
+
+    #[test]
+    fn parallel_edges_fan_out_evenly() {
+        let (a, b) = (Vector2::new(0.0, 0.0), Vector2::new(4.0, 2.0));
+        let plot = placed(test_graphs::graph(2, &[(0, 1)]), vec![a, b]);
+        // How far across the edge from `a` to `b` each curve's control point is.
+        let bend = |curve: Curve| match curve {
+            Curve::Straight(..) => 0.0,
+            Curve::Quadratic(_, control, _) => {
+                let along = b - a;
+                (control - (a + b) / 2.0).dot(Vector2::new(-along.y, along.x)) / along.magnitude2()
+            }
+            curve => panic!("unexpected {:?}", curve),
+        };
+
+        for parallel_ct in 1..=5 {
+            let bends = (0..parallel_ct).map(|i| bend(plot.model.edge_curve(0, 1, i, parallel_ct))).collect::<Vec<_>>();
+            assert!(bends.windows(2).all(|pair| pair[1] - pair[0] > 0.1), "{:?}", bends);
+            assert!(bends.iter().sum::<f32>().abs() < 1e-5, "{:?}", bends);
+            for (bend, mirrored) in bends.iter().zip(bends.iter().rev()) {
+                assert!((bend + mirrored).abs() < 1e-5, "{:?}", bends);
+            }
+
+            // Listing the edge the other way round draws the same curves.
+            let reversed = (0..parallel_ct).map(|i| bend(plot.model.edge_curve(1, 0, i, parallel_ct))).collect::<Vec<_>>();
+            assert_eq!(bends, reversed);
+        }
     }
 }
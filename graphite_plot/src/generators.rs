@@ -0,0 +1,604 @@
+use std::collections::{BinaryHeap, HashSet};
+use std::cmp::Reverse;
+
+use gscientific::graph::Graph;
+use rand::{Rng, rngs::StdRng};
+use winit::keyboard::KeyCode;
+
+const RANDOM_REGULAR_ATTEMPTS: usize = 100;
+/// Random stub pairs tried before looking through every pair for one that makes an edge.
+const STUB_PICK_ATTEMPTS: usize = 32;
+/// Largest graphs the generators build. The layout and the graph distances grow with the
+/// square of the vertex count.
+const MAX_VERTEX_CT: usize = 20_000;
+/// Most edges, or expected edges for the random families.
+const MAX_EDGE_CT: usize = 500_000;
+
+/// Parametrised graph families that can replace the plotted graph.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Generator {
+    /// Generalised Petersen graph GP(n, k).
+    Petersen { n: usize, k: usize },
+    Grid { width: usize, height: usize },
+    Torus { width: usize, height: usize },
+    Hypercube { dimension: usize },
+    Complete { n: usize },
+    CompleteBipartite { m: usize, n: usize },
+    /// A labelled tree chosen uniformly at random.
+    RandomTree { n: usize },
+    ErdosRenyi { n: usize, p: f64 },
+    /// Preferential attachment, each new vertex bringing `m` edges.
+    BarabasiAlbert { n: usize, m: usize },
+    /// A ring where every vertex joins its `k` nearest neighbors, each edge rewired with
+    /// probability `beta`.
+    WattsStrogatz { n: usize, k: usize, beta: f64 },
+    /// Points in the unit square joined when closer than `radius`.
+    RandomGeometric { n: usize, radius: f64 },
+    RandomRegular { n: usize, degree: usize },
+}
+
+pub const GENERATOR_USAGE: &str = "graphs: petersen:<n>,<k> grid:<w>,<h> torus:<w>,<h> hypercube:<d> complete:<n> \
+bipartite:<m>,<n> tree:<n> er:<n>,<p> ba:<n>,<m> ws:<n>,<k>,<beta> geometric:<n>,<radius> regular:<n>,<d>";
+
+impl Default for Generator {
+    fn default() -> Self {
+        Generator::Petersen { n: 500, k: 11 }
+    }
+}
+
+impl Generator {
+    /// One of each family with moderate parameters, in menu order.
+    pub fn gallery() -> Vec<Generator> {
+        vec![
+            Generator::Petersen { n: 500, k: 11 },
+            Generator::Grid { width: 20, height: 20 },
+            Generator::Torus { width: 20, height: 20 },
+            Generator::Hypercube { dimension: 7 },
+            Generator::Complete { n: 30 },
+            Generator::CompleteBipartite { m: 10, n: 20 },
+            Generator::RandomTree { n: 300 },
+            Generator::ErdosRenyi { n: 300, p: 0.01 },
+            Generator::BarabasiAlbert { n: 400, m: 2 },
+            Generator::WattsStrogatz { n: 300, k: 4, beta: 0.1 },
+            Generator::RandomGeometric { n: 400, radius: 0.08 },
+            Generator::RandomRegular { n: 300, degree: 3 },
+        ]
+    }
+
+    /// Parses `family:param,param,...`, e.g. `grid:20,30` or `er:200,0.05`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (family, params) = spec.split_once(':').unwrap_or((spec, ""));
+        let params = params
+            .split(',')
+            .filter(|param| !param.is_empty())
+            .collect::<Vec<_>>();
+        let invalid = || format!("invalid graph \"{}\"\n{}", spec, GENERATOR_USAGE);
+        let count = |i: usize| -> Result<usize, String> {
+            params.get(i).and_then(|param| param.parse().ok()).ok_or_else(invalid)
+        };
+        let real = |i: usize| -> Result<f64, String> {
+            params.get(i).and_then(|param| param.parse().ok()).ok_or_else(invalid)
+        };
+
+        let generator = match family {
+            "petersen" => Generator::Petersen { n: count(0)?, k: count(1)? },
+            "grid" => Generator::Grid { width: count(0)?, height: count(1)? },
+            "torus" => Generator::Torus { width: count(0)?, height: count(1)? },
+            "hypercube" => Generator::Hypercube { dimension: count(0)? },
+            "complete" => Generator::Complete { n: count(0)? },
+            "bipartite" => Generator::CompleteBipartite { m: count(0)?, n: count(1)? },
+            "tree" => Generator::RandomTree { n: count(0)? },
+            "er" => Generator::ErdosRenyi { n: count(0)?, p: real(1)? },
+            "ba" => Generator::BarabasiAlbert { n: count(0)?, m: count(1)? },
+            "ws" => Generator::WattsStrogatz { n: count(0)?, k: count(1)?, beta: real(2)? },
+            "geometric" => Generator::RandomGeometric { n: count(0)?, radius: real(1)? },
+            "regular" => Generator::RandomRegular { n: count(0)?, degree: count(1)? },
+            _ => return Err(invalid()),
+        };
+        generator.validate().map_err(|message| format!("{}\n{}", message, GENERATOR_USAGE))?;
+
+        Ok(generator)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.validate_parameters()?;
+        if self.vertex_ct().is_none_or(|vertex_ct| vertex_ct > MAX_VERTEX_CT) {
+            return Err(format!("graphs are limited to {} vertices", MAX_VERTEX_CT));
+        }
+        if self.expected_edge_ct() > MAX_EDGE_CT as f64 {
+            return Err(format!("graphs are limited to {} edges", MAX_EDGE_CT));
+        }
+        Ok(())
+    }
+
+    fn validate_parameters(&self) -> Result<(), String> {
+        match *self {
+            Generator::Petersen { n, k } if n < 3 || k == 0 || 2 * k >= n => {
+                Err("petersen needs n >= 3 and 0 < k < n / 2".to_string())
+            }
+            Generator::ErdosRenyi { p, .. } if !(0.0..=1.0).contains(&p) => {
+                Err("er needs 0 <= p <= 1".to_string())
+            }
+            Generator::BarabasiAlbert { n, m } if m == 0 || m >= n => {
+                Err("ba needs 0 < m < n".to_string())
+            }
+            Generator::WattsStrogatz { n, k, beta } if k % 2 != 0 || k >= n || !(0.0..=1.0).contains(&beta) => {
+                Err("ws needs an even k < n and 0 <= beta <= 1".to_string())
+            }
+            Generator::RandomRegular { n, degree } if degree >= n || (n * degree) % 2 != 0 => {
+                Err("regular needs degree < n and n * degree even".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Vertices the graph will have, or `None` if there are too many to count.
+    fn vertex_ct(&self) -> Option<usize> {
+        match *self {
+            Generator::Petersen { n, .. } => n.checked_mul(2),
+            Generator::Grid { width, height } | Generator::Torus { width, height } => width.checked_mul(height),
+            Generator::Hypercube { dimension } => u32::try_from(dimension).ok().and_then(|d| 1usize.checked_shl(d)),
+            Generator::CompleteBipartite { m, n } => m.checked_add(n),
+            Generator::Complete { n }
+            | Generator::RandomTree { n }
+            | Generator::ErdosRenyi { n, .. }
+            | Generator::BarabasiAlbert { n, .. }
+            | Generator::WattsStrogatz { n, .. }
+            | Generator::RandomGeometric { n, .. }
+            | Generator::RandomRegular { n, .. } => Some(n),
+        }
+    }
+
+    /// Edges the graph will have, or about how many for the random families. A float, so
+    /// huge parameters can't overflow it.
+    fn expected_edge_ct(&self) -> f64 {
+        let pairs = |n: usize| n as f64 * (n as f64 - 1.0) / 2.0;
+        match *self {
+            Generator::Petersen { n, .. } => 3.0 * n as f64,
+            Generator::Grid { width, height } | Generator::Torus { width, height } => {
+                2.0 * width as f64 * height as f64
+            }
+            Generator::Hypercube { dimension } => dimension as f64 * 2f64.powf(dimension as f64 - 1.0),
+            Generator::Complete { n } => pairs(n),
+            Generator::CompleteBipartite { m, n } => m as f64 * n as f64,
+            Generator::RandomTree { n } => n as f64,
+            Generator::ErdosRenyi { n, p } => p * pairs(n),
+            Generator::BarabasiAlbert { n, m } => m as f64 * n as f64,
+            Generator::WattsStrogatz { n, k, .. } => n as f64 * k as f64 / 2.0,
+            Generator::RandomGeometric { n, radius } => (std::f64::consts::PI * radius * radius).min(1.0) * pairs(n),
+            Generator::RandomRegular { n, degree } => n as f64 * degree as f64 / 2.0,
+        }
+    }
+
+    /// Human readable name with parameters, for menus and logs.
+    pub fn describe(&self) -> String {
+        match *self {
+            Generator::Petersen { n, k } => format!("Generalised Petersen GP({}, {})", n, k),
+            Generator::Grid { width, height } => format!("Grid {}x{}", width, height),
+            Generator::Torus { width, height } => format!("Torus {}x{}", width, height),
+            Generator::Hypercube { dimension } => format!("Hypercube Q{}", dimension),
+            Generator::Complete { n } => format!("Complete K{}", n),
+            Generator::CompleteBipartite { m, n } => format!("Complete bipartite K{},{}", m, n),
+            Generator::RandomTree { n } => format!("Random tree, {} vertices", n),
+            Generator::ErdosRenyi { n, p } => format!("Erdos-Renyi G({}, {})", n, p),
+            Generator::BarabasiAlbert { n, m } => format!("Barabasi-Albert n={} m={}", n, m),
+            Generator::WattsStrogatz { n, k, beta } => {
+                format!("Watts-Strogatz n={} k={} beta={}", n, k, beta)
+            }
+            Generator::RandomGeometric { n, radius } => {
+                format!("Random geometric n={} r={}", n, radius)
+            }
+            Generator::RandomRegular { n, degree } => format!("Random {}-regular, {} vertices", degree, n),
+        }
+    }
+
+    /// The same family with its size scaled by `factor`, keeping the other parameters valid.
+    pub fn scaled(&self, factor: f64) -> Self {
+        let scale = |value: usize, min: usize| ((value as f64 * factor).round() as usize).max(min);
+        let scaled = match *self {
+            Generator::Petersen { n, k } => Generator::Petersen { n: scale(n, 2 * k + 1), k },
+            Generator::Grid { width, height } => Generator::Grid { width: scale(width, 1), height: scale(height, 1) },
+            Generator::Torus { width, height } => Generator::Torus { width: scale(width, 3), height: scale(height, 3) },
+            Generator::Hypercube { dimension } => Generator::Hypercube {
+                dimension: if factor > 1.0 { dimension + 1 } else { dimension.saturating_sub(1) },
+            },
+            Generator::Complete { n } => Generator::Complete { n: scale(n, 1) },
+            Generator::CompleteBipartite { m, n } => Generator::CompleteBipartite { m: scale(m, 1), n: scale(n, 1) },
+            Generator::RandomTree { n } => Generator::RandomTree { n: scale(n, 1) },
+            Generator::ErdosRenyi { n, p } => Generator::ErdosRenyi { n: scale(n, 1), p },
+            Generator::BarabasiAlbert { n, m } => Generator::BarabasiAlbert { n: scale(n, m + 1), m },
+            Generator::WattsStrogatz { n, k, beta } => Generator::WattsStrogatz { n: scale(n, k + 1), k, beta },
+            Generator::RandomGeometric { n, radius } => Generator::RandomGeometric { n: scale(n, 1), radius },
+            Generator::RandomRegular { n, degree } => {
+                let mut n = scale(n, degree + 1);
+                if (n * degree) % 2 != 0 {
+                    n += 1;
+                }
+                Generator::RandomRegular { n, degree }
+            }
+        };
+
+        if scaled.validate().is_ok() { scaled } else { self.clone() }
+    }
+
+    /// Replaces the contents of `graph` with a graph from this family.
+    pub fn build<T: Graph>(&self, graph: &mut T, rng: &mut StdRng) {
+        let (vertex_ct, edges) = self.edges(rng);
+        graph.resize(0);
+        graph.resize(vertex_ct);
+        for (u, v) in edges {
+            graph.add_edge(u, v);
+        }
+    }
+
+    fn edges(&self, rng: &mut StdRng) -> (usize, Vec<(usize, usize)>) {
+        match *self {
+            Generator::Petersen { n, k } => (
+                2 * n,
+                (0..n)
+                    .flat_map(|i| [(i, (i + 1) % n), (i, n + i), (n + i, n + (i + k) % n)])
+                    .collect(),
+            ),
+            Generator::Grid { width, height } => (width * height, lattice(width, height, false)),
+            Generator::Torus { width, height } => (width * height, lattice(width, height, true)),
+            Generator::Hypercube { dimension } => {
+                let n = 1 << dimension;
+                (
+                    n,
+                    (0..n)
+                        .flat_map(|u| (0..dimension).map(move |bit| (u, u ^ (1 << bit))))
+                        .filter(|(u, v)| u < v)
+                        .collect(),
+                )
+            }
+            Generator::Complete { n } => (n, (0..n).flat_map(|u| (u + 1..n).map(move |v| (u, v))).collect()),
+            Generator::CompleteBipartite { m, n } => {
+                (m + n, (0..m).flat_map(|u| (m..m + n).map(move |v| (u, v))).collect())
+            }
+            Generator::RandomTree { n } => (n, random_tree(n, rng)),
+            Generator::ErdosRenyi { n, p } => (
+                n,
+                (0..n)
+                    .flat_map(|u| (u + 1..n).map(move |v| (u, v)))
+                    .filter(|_| rng.random_bool(p))
+                    .collect(),
+            ),
+            Generator::BarabasiAlbert { n, m } => (n, barabasi_albert(n, m, rng)),
+            Generator::WattsStrogatz { n, k, beta } => (n, watts_strogatz(n, k, beta, rng)),
+            Generator::RandomGeometric { n, radius } => {
+                let points = (0..n)
+                    .map(|_| (rng.random::<f64>(), rng.random::<f64>()))
+                    .collect::<Vec<_>>();
+                let edges = (0..n)
+                    .flat_map(|u| (u + 1..n).map(move |v| (u, v)))
+                    .filter(|&(u, v)| {
+                        let (dx, dy) = (points[u].0 - points[v].0, points[u].1 - points[v].1);
+                        dx * dx + dy * dy < radius * radius
+                    })
+                    .collect();
+                (n, edges)
+            }
+            Generator::RandomRegular { n, degree } => (n, random_regular(n, degree, rng)),
+        }
+    }
+}
+
+/// The in-window list of graph generators, starting from the gallery.
+pub struct GeneratorMenu {
+    entries: Vec<Generator>,
+    selected: usize,
+}
+
+/// What a key pressed in the generator menu asks for.
+#[derive(Debug, PartialEq)]
+pub enum MenuInput {
+    Stay,
+    Close,
+    /// Close the menu and replace the graph with this one.
+    Generate(Generator),
+}
+
+impl Default for GeneratorMenu {
+    fn default() -> Self {
+        Self {
+            entries: Generator::gallery(),
+            selected: 0,
+        }
+    }
+}
+
+impl GeneratorMenu {
+    /// Arrow keys pick and resize a generator, Enter picks it and Tab or Escape close the
+    /// menu.
+    pub fn input(&mut self, key: KeyCode) -> MenuInput {
+        let entry_ct = self.entries.len();
+        match key {
+            KeyCode::ArrowUp => self.selected = (self.selected + entry_ct - 1) % entry_ct,
+            KeyCode::ArrowDown => self.selected = (self.selected + 1) % entry_ct,
+            KeyCode::ArrowLeft => self.entries[self.selected] = self.entries[self.selected].scaled(0.5),
+            KeyCode::ArrowRight => self.entries[self.selected] = self.entries[self.selected].scaled(2.0),
+            KeyCode::Enter => return MenuInput::Generate(self.entries[self.selected].clone()),
+            KeyCode::Tab | KeyCode::Escape => return MenuInput::Close,
+            _ => {}
+        }
+        MenuInput::Stay
+    }
+
+    /// The menu as text, with the selected generator marked.
+    pub fn describe(&self) -> String {
+        let mut text = String::from("Generate graph (arrows, Enter, Tab)\n");
+        for (i, generator) in self.entries.iter().enumerate() {
+            let marker = if i == self.selected { '>' } else { ' ' };
+            text.push_str(&format!("\n{} {}", marker, generator.describe()));
+        }
+        text
+    }
+}
+
+fn lattice(width: usize, height: usize, wrap: bool) -> Vec<(usize, usize)> {
+    let index = |x: usize, y: usize| y * width + x;
+    let mut edges = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            if x + 1 < width {
+                edges.push((index(x, y), index(x + 1, y)));
+            } else if wrap && width > 2 {
+                edges.push((index(x, y), index(0, y)));
+            }
+            if y + 1 < height {
+                edges.push((index(x, y), index(x, y + 1)));
+            } else if wrap && height > 2 {
+                edges.push((index(x, y), index(x, 0)));
+            }
+        }
+    }
+    edges
+}
+
+/// Decodes a random Prüfer sequence.
+fn random_tree(n: usize, rng: &mut StdRng) -> Vec<(usize, usize)> {
+    if n < 2 {
+        return Vec::new();
+    }
+    let sequence = (0..n - 2).map(|_| rng.random_range(0..n)).collect::<Vec<_>>();
+    let mut degree = vec![1; n];
+    for &v in &sequence {
+        degree[v] += 1;
+    }
+    let mut leaves = (0..n)
+        .filter(|&v| degree[v] == 1)
+        .map(Reverse)
+        .collect::<BinaryHeap<_>>();
+
+    let mut edges = Vec::with_capacity(n - 1);
+    for &v in &sequence {
+        let Reverse(leaf) = leaves.pop().unwrap();
+        edges.push((leaf, v));
+        degree[v] -= 1;
+        if degree[v] == 1 {
+            leaves.push(Reverse(v));
+        }
+    }
+    let Reverse(u) = leaves.pop().unwrap();
+    let Reverse(v) = leaves.pop().unwrap();
+    edges.push((u, v));
+
+    edges
+}
+
+fn barabasi_albert(n: usize, m: usize, rng: &mut StdRng) -> Vec<(usize, usize)> {
+    // Every endpoint of every edge, so a uniform pick is proportional to degree.
+    let mut endpoints = Vec::new();
+    let mut edges = Vec::new();
+    for u in 0..=m {
+        for v in u + 1..=m {
+            edges.push((u, v));
+            endpoints.extend([u, v]);
+        }
+    }
+
+    let mut targets = HashSet::new();
+    for u in m + 1..n {
+        targets.clear();
+        while targets.len() < m {
+            targets.insert(endpoints[rng.random_range(0..endpoints.len())]);
+        }
+        let mut sorted = targets.iter().copied().collect::<Vec<_>>();
+        // Hash set order isn't seeded, so sort to keep the result reproducible.
+        sorted.sort();
+        for v in sorted {
+            edges.push((u, v));
+            endpoints.extend([u, v]);
+        }
+    }
+
+    edges
+}
+
+fn watts_strogatz(n: usize, k: usize, beta: f64, rng: &mut StdRng) -> Vec<(usize, usize)> {
+    let key = |u: usize, v: usize| (u.min(v), u.max(v));
+    let mut lattice = Vec::new();
+    for u in 0..n {
+        for offset in 1..=k / 2 {
+            lattice.push(key(u, (u + offset) % n));
+        }
+    }
+    let mut present = lattice.iter().copied().collect::<HashSet<_>>();
+
+    let mut edges = Vec::with_capacity(lattice.len());
+    for (u, v) in lattice {
+        if rng.random_bool(beta) {
+            let w = rng.random_range(0..n);
+            if w != u && !present.contains(&key(u, w)) {
+                present.remove(&(u, v));
+                present.insert(key(u, w));
+                edges.push(key(u, w));
+                continue;
+            }
+        }
+        edges.push((u, v));
+    }
+
+    edges
+}
+
+/// Pairs up stubs at random, only ever joining two that make a new edge between different
+/// vertices (Steger and Wormald), and starts over on the rare dead end. The last attempt
+/// keeps whatever it paired if none finish.
+fn random_regular(n: usize, degree: usize, rng: &mut StdRng) -> Vec<(usize, usize)> {
+    let key = |u: usize, v: usize| (u.min(v), u.max(v));
+    let mut edges = Vec::new();
+    let mut present = HashSet::new();
+
+    for _ in 0..RANDOM_REGULAR_ATTEMPTS {
+        let mut stubs = (0..n)
+            .flat_map(|u| std::iter::repeat_n(u, degree))
+            .collect::<Vec<_>>();
+        edges.clear();
+        present.clear();
+        while stubs.len() >= 2 {
+            let suitable = |(i, j): &(usize, usize)| {
+                stubs[*i] != stubs[*j] && !present.contains(&key(stubs[*i], stubs[*j]))
+            };
+            let stub_ct = stubs.len();
+            // Random picks almost always find a pair. Only near a dead end is it worth
+            // looking through them all.
+            let pair = (0..STUB_PICK_ATTEMPTS)
+                .map(|_| (rng.random_range(0..stub_ct), rng.random_range(0..stub_ct)))
+                .find(&suitable)
+                .or_else(|| (0..stub_ct).flat_map(|i| (i + 1..stub_ct).map(move |j| (i, j))).find(&suitable));
+            let Some((i, j)) = pair else {
+                break;
+            };
+            let edge = key(stubs[i], stubs[j]);
+            present.insert(edge);
+            edges.push(edge);
+            // The higher index goes first, so removing it doesn't move the other stub.
+            stubs.swap_remove(i.max(j));
+            stubs.swap_remove(i.min(j));
+        }
+        if stubs.is_empty() {
+            break;
+        }
+    }
+
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn degrees(vertex_ct: usize, edges: &[(usize, usize)]) -> Vec<usize> {
+        let mut degrees = vec![0; vertex_ct];
+        for (u, v) in edges {
+            degrees[*u] += 1;
+            degrees[*v] += 1;
+        }
+        degrees
+    }
+
+    fn generate(spec: &str, seed: u64) -> (usize, Vec<(usize, usize)>) {
+        Generator::parse(spec).unwrap().edges(&mut StdRng::seed_from_u64(seed))
+    }
+
+    #[test]
+    fn parses_families_and_parameters() {
+        assert_eq!(Generator::parse("grid:20,30"), Ok(Generator::Grid { width: 20, height: 30 }));
+        assert_eq!(Generator::parse("er:200,0.05"), Ok(Generator::ErdosRenyi { n: 200, p: 0.05 }));
+        assert_eq!(Generator::parse("hypercube:4"), Ok(Generator::Hypercube { dimension: 4 }));
+    }
+
+    #[test]
+    fn rejects_malformed_and_invalid_specs() {
+        for spec in [
+            "",
+            "moebius:10",
+            "grid:20",
+            "grid:20,x",
+            "er:200",
+            "complete:-3",
+            "petersen:10,5",
+            "petersen:2,0",
+            "er:100,1.5",
+            "ba:10,10",
+            "ws:100,3,0.1",
+            "regular:5,3",
+        ] {
+            assert!(Generator::parse(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn rejects_graphs_that_are_too_large() {
+        for spec in [
+            "complete:100000",
+            "grid:1000,1000",
+            "er:100000,0.5",
+            "hypercube:20",
+            "hypercube:70",
+            "torus:99999999999,99999999999",
+        ] {
+            let error = Generator::parse(spec).unwrap_err();
+            assert!(error.contains("limited to"), "{}: {}", spec, error);
+        }
+        assert!(Generator::parse("hypercube:14").is_ok());
+    }
+
+    #[test]
+    fn families_have_their_vertex_and_edge_counts() {
+        for n in [1, 2, 7] {
+            let (vertex_ct, edges) = generate(&format!("complete:{}", n), 0);
+            assert_eq!((vertex_ct, edges.len()), (n, n * (n - 1) / 2));
+        }
+        for d in [1, 3, 6] {
+            let (vertex_ct, edges) = generate(&format!("hypercube:{}", d), 0);
+            assert_eq!((vertex_ct, edges.len()), (1 << d, d << (d - 1)));
+        }
+        for (n, k) in [(5, 2), (10, 3), (12, 5)] {
+            let (vertex_ct, edges) = generate(&format!("petersen:{},{}", n, k), 0);
+            assert_eq!(vertex_ct, 2 * n);
+            assert!(degrees(vertex_ct, &edges).iter().all(|degree| *degree == 3), "GP({}, {})", n, k);
+        }
+        assert_eq!(generate("grid:4,3", 0).1.len(), 3 * 3 + 4 * 2);
+        assert_eq!(generate("torus:4,3", 0).1.len(), 2 * 4 * 3);
+        assert_eq!(generate("bipartite:3,5", 0).1.len(), 15);
+        assert_eq!(generate("tree:50", 0).1.len(), 49);
+        let (vertex_ct, edges) = generate("ba:100,3", 0);
+        assert_eq!((vertex_ct, edges.len()), (100, 6 + 3 * 96));
+    }
+
+    #[test]
+    fn menu_picks_and_resizes_generators() {
+        let mut menu = GeneratorMenu::default();
+        assert_eq!(menu.input(KeyCode::ArrowUp), MenuInput::Stay);
+        let last = Generator::gallery().pop().unwrap();
+        assert!(menu.describe().contains(&format!("> {}", last.describe())));
+
+        menu.input(KeyCode::ArrowDown);
+        menu.input(KeyCode::ArrowDown);
+        menu.input(KeyCode::ArrowRight);
+        assert_eq!(menu.input(KeyCode::Enter), MenuInput::Generate(Generator::Grid { width: 40, height: 40 }));
+        assert_eq!(menu.input(KeyCode::Escape), MenuInput::Close);
+    }
+
+    #[test]
+    fn random_regular_graphs_are_simple_and_regular() {
+        for (n, degree) in [(10, 3), (30, 4), (100, 5)] {
+            let (vertex_ct, edges) = generate(&format!("regular:{},{}", n, degree), 1);
+            assert!(degrees(vertex_ct, &edges).iter().all(|d| *d == degree), "regular:{},{}", n, degree);
+            assert!(edges.iter().all(|(u, v)| u < v));
+            assert_eq!(edges.iter().collect::<HashSet<_>>().len(), edges.len());
+        }
+    }
+
+    #[test]
+    fn seeds_reproduce_random_families() {
+        for spec in ["tree:100", "er:100,0.1", "ba:200,3", "ws:100,4,0.3", "geometric:100,0.2", "regular:100,3"] {
+            assert_eq!(generate(spec, 5), generate(spec, 5), "{}", spec);
+            assert_ne!(generate(spec, 5), generate(spec, 6), "{}", spec);
+        }
+    }
+}
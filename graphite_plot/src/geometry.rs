@@ -0,0 +1,85 @@
+use cgmath::{InnerSpace, Vector2};
+
+/// Convex hull of `points` in counter-clockwise order, by Andrew's monotone chain.
+pub fn convex_hull(points: &[Vector2<f32>]) -> Vec<Vector2<f32>> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    let cross = |o: Vector2<f32>, a: Vector2<f32>, b: Vector2<f32>| {
+        (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+    };
+    let mut hull: Vec<Vector2<f32>> = Vec::with_capacity(2 * sorted.len());
+    for pass in 0..2 {
+        let start = hull.len();
+        let iter: Box<dyn Iterator<Item = &Vector2<f32>>> = if pass == 0 {
+            Box::new(sorted.iter())
+        } else {
+            Box::new(sorted.iter().rev())
+        };
+        for &p in iter {
+            while hull.len() >= start + 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0 {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        // The last point of each chain starts the other one.
+        hull.pop();
+    }
+
+    hull
+}
+
+/// Grows a counter-clockwise convex polygon by `radius`, rounding each corner with arcs of
+/// `segments_per_quarter` segments per quarter turn. A single point becomes a circle and
+/// two points a capsule.
+pub fn inflate(hull: &[Vector2<f32>], radius: f32, segments_per_quarter: usize) -> Vec<Vector2<f32>> {
+    let segments_per_quarter = segments_per_quarter.max(1);
+    let quarter = std::f32::consts::FRAC_PI_2;
+    let mut boundary = Vec::new();
+
+    match hull.len() {
+        0 => {}
+        1 => {
+            let segment_ct = 4 * segments_per_quarter;
+            for i in 0..segment_ct {
+                let angle = i as f32 / segment_ct as f32 * 4.0 * quarter;
+                boundary.push(hull[0] + Vector2::new(angle.cos(), angle.sin()) * radius);
+            }
+        }
+        n => {
+            let outward = |from: Vector2<f32>, to: Vector2<f32>| {
+                let direction = (to - from).normalize();
+                Vector2::new(direction.y, -direction.x)
+            };
+            for i in 0..n {
+                let previous = hull[(i + n - 1) % n];
+                let current = hull[i];
+                let next = hull[(i + 1) % n];
+                let start_angle = {
+                    let normal = outward(previous, current);
+                    normal.y.atan2(normal.x)
+                };
+                let mut end_angle = {
+                    let normal = outward(current, next);
+                    normal.y.atan2(normal.x)
+                };
+                while end_angle < start_angle {
+                    end_angle += 4.0 * quarter;
+                }
+                let segment_ct = (((end_angle - start_angle) / quarter) * segments_per_quarter as f32)
+                    .ceil()
+                    .max(1.0) as usize;
+                for s in 0..=segment_ct {
+                    let angle = start_angle + (end_angle - start_angle) * s as f32 / segment_ct as f32;
+                    boundary.push(current + Vector2::new(angle.cos(), angle.sin()) * radius);
+                }
+            }
+        }
+    }
+
+    boundary
+}
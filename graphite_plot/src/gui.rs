@@ -0,0 +1,103 @@
+use winit::{event::WindowEvent, window::Window};
+
+/// egui state for one window. The window hands it events before the plot sees them, the plot
+/// describes its panels once per frame, and the result is painted last in the plot's own
+/// render pass.
+pub struct Gui {
+    state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    /// Output of the latest `run`, waiting to be painted.
+    frame: Option<GuiFrame>,
+}
+
+struct GuiFrame {
+    primitives: Vec<egui::ClippedPrimitive>,
+    textures_delta: egui::TexturesDelta,
+    screen: egui_wgpu::ScreenDescriptor,
+}
+
+impl Gui {
+    /// `sample_count` must match the render pass the panels are painted in.
+    pub fn new(window: &Window, device: &wgpu::Device, format: wgpu::TextureFormat, sample_count: u32) -> Self {
+        let context = egui::Context::default();
+        let state = egui_winit::State::new(
+            context,
+            egui::ViewportId::ROOT,
+            window,
+            Some(window.scale_factor() as f32),
+            None,
+            Some(device.limits().max_texture_dimension_2d as usize),
+        );
+        let renderer = egui_wgpu::Renderer::new(device, format, None, sample_count, false);
+
+        Self {
+            state,
+            renderer,
+            frame: None,
+        }
+    }
+
+    /// Passes `event` to egui. Returns true if egui used it, in which case the plot
+    /// shouldn't see it.
+    pub fn on_window_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        self.state.on_window_event(window, event).consumed
+    }
+
+    /// Lays out one frame of panels with `ui` and keeps the result for the next paint.
+    pub fn run(&mut self, window: &Window, size_in_pixels: [u32; 2], ui: impl FnMut(&egui::Context)) {
+        let input = self.state.take_egui_input(window);
+        let context = self.state.egui_ctx().clone();
+        let output = context.run(input, ui);
+        self.state.handle_platform_output(window, output.platform_output);
+
+        let primitives = context.tessellate(output.shapes, output.pixels_per_point);
+        // egui lays out in window pixels, which a surface clamped to the device's limits
+        // has fewer of.
+        let surface_scale = size_in_pixels[0] as f32 / window.inner_size().width.max(1) as f32;
+        // A frame that never got painted still has texture changes the next one relies on.
+        let mut textures_delta = self
+            .frame
+            .take()
+            .map(|frame| frame.textures_delta)
+            .unwrap_or_default();
+        textures_delta.append(output.textures_delta);
+        self.frame = Some(GuiFrame {
+            primitives,
+            textures_delta,
+            screen: egui_wgpu::ScreenDescriptor {
+                size_in_pixels,
+                pixels_per_point: output.pixels_per_point * surface_scale,
+            },
+        });
+    }
+
+    /// Uploads the textures and buffers the pending frame needs. Must come before the render
+    /// pass it is painted in.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder) {
+        let Some(frame) = self.frame.as_ref() else {
+            return;
+        };
+        for (id, image_delta) in &frame.textures_delta.set {
+            self.renderer.update_texture(device, queue, *id, image_delta);
+        }
+        // The plot registers no paint callbacks, so there are no extra command buffers to
+        // submit.
+        self.renderer
+            .update_buffers(device, queue, encoder, &frame.primitives, &frame.screen);
+    }
+
+    pub fn paint(&self, render_pass: &mut wgpu::RenderPass<'static>) {
+        if let Some(frame) = self.frame.as_ref() {
+            self.renderer.render(render_pass, &frame.primitives, &frame.screen);
+        }
+    }
+
+    /// Releases the textures egui is done with, once the frame using them is submitted.
+    pub fn finish(&mut self) {
+        if let Some(frame) = self.frame.take() {
+            for id in &frame.textures_delta.free {
+                self.renderer.free_texture(id);
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use instant::Duration;
+
+use crate::force_layout::LayoutSnapshot;
+
+/// Weight of the newest frame in the smoothed frame time.
+const FRAME_TIME_SMOOTHING: f32 = 0.1;
+
+/// Frame and simulation statistics shown in a corner of the plot, with an optional sheet of
+/// key bindings below them.
+pub struct Hud {
+    pub visible: bool,
+    pub show_help: bool,
+    /// Exponential moving average of the time between frames, in seconds.
+    frame_time: f32,
+}
+
+impl Default for Hud {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            show_help: false,
+            frame_time: 0.0,
+        }
+    }
+}
+
+impl Hud {
+    pub fn record_frame(&mut self, delta_t: Duration) {
+        let seconds = delta_t.as_secs_f32();
+        self.frame_time = if self.frame_time == 0.0 {
+            seconds
+        } else {
+            self.frame_time + (seconds - self.frame_time) * FRAME_TIME_SMOOTHING
+        };
+    }
+
+    pub fn fps(&self) -> f32 {
+        if self.frame_time > 0.0 { 1.0 / self.frame_time } else { 0.0 }
+    }
+
+    /// The statistics, one per line.
+    pub fn describe(&self, layout: &LayoutSnapshot, vertex_ct: usize, edge_ct: usize) -> String {
+        let state = if layout.paused {
+            "paused"
+        } else if layout.settled {
+            "settled"
+        } else {
+            "running"
+        };
+
+        format!(
+            "{:.0} FPS\nTick {:.2} ms\n{} vertices, {} edges\nKinetic energy {:.3}\nForce layout, {}: {}",
+            self.fps(),
+            layout.tick_time.as_secs_f32() * 1000.0,
+            vertex_ct,
+            edge_ct,
+            layout.kinetic_energy,
+            layout.integrator.name(),
+            state
+        )
+    }
+}
@@ -0,0 +1,70 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs::File, io::{self, BufReader}, path::Path};
+
+/// Icons drawn over vertices, cut from one image in a grid of equal cells numbered row by
+/// row from the top left.
+#[derive(Clone, Debug)]
+pub struct IconAtlas {
+    pub width: u32,
+    pub height: u32,
+    /// 8-bit sRGB RGBA rows from the top.
+    pub pixels: Vec<u8>,
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl IconAtlas {
+    /// A single transparent cell, for elements that draw no icons.
+    pub fn empty() -> Self {
+        Self {
+            width: 1,
+            height: 1,
+            pixels: vec![0; 4],
+            columns: 1,
+            rows: 1,
+        }
+    }
+
+    /// Where cell `index` is in the image, as the texture coordinates of its top left and
+    /// bottom right corners.
+    pub fn cell(&self, index: u32) -> Option<[f32; 4]> {
+        if index >= self.columns * self.rows {
+            return None;
+        }
+        let (column, row) = ((index % self.columns) as f32, (index / self.columns) as f32);
+        let (width, height) = (1.0 / self.columns as f32, 1.0 / self.rows as f32);
+        Some([column * width, row * height, (column + 1.0) * width, (row + 1.0) * height])
+    }
+}
+
+/// Reads a PNG of `columns` by `rows` icons.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_icon_atlas(path: impl AsRef<Path>, columns: u32, rows: u32) -> io::Result<IconAtlas> {
+    let invalid = |error: png::DecodingError| io::Error::new(io::ErrorKind::InvalidData, error);
+
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(invalid)?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(invalid)?;
+    let buffer = &buffer[..info.buffer_size()];
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buffer.to_vec(),
+        png::ColorType::Rgb => buffer.chunks(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buffer.chunks(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => buffer.chunks(1).flat_map(|p| [p[0], p[0], p[0], 255]).collect(),
+        // Palettes are expanded by the transformations above.
+        png::ColorType::Indexed => {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unexpanded palette image"));
+        }
+    };
+
+    Ok(IconAtlas {
+        width: info.width,
+        height: info.height,
+        pixels,
+        columns: columns.max(1),
+        rows: rows.max(1),
+    })
+}
@@ -3,15 +3,61 @@ mod draw;
 mod camera;
 mod plot_window;
 mod force_plot;
+mod force_layout;
+mod simulation;
 // mod draw;
 
 // mod graph_plot;
 mod texture;
 mod plot_graphic;
 mod mouse;
+mod initial_placement;
+mod colormap;
+mod community;
+mod geometry;
+mod paths;
+mod edge_weights;
+mod animation;
+mod metrics;
+mod overlay;
+mod options;
+mod generators;
+mod attributes;
+mod hud;
+mod actions;
+mod bindings;
+#[cfg(feature = "egui")]
+mod gui;
+mod picking;
+mod capture;
+#[cfg(not(target_arch = "wasm32"))]
+mod capturer;
+mod edge_list;
+mod theme;
+mod icons;
+mod bundling;
+mod analysis;
+#[cfg(test)]
+mod test_graphs;
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+pub use initial_placement::{InitialPlacement, read_positions, write_positions};
+pub use options::PlotOptions;
+pub use actions::Action;
+pub use bindings::{Bindings, read_bindings};
+pub use generators::Generator;
+pub use edge_list::{EdgeList, parse_edge_list};
+pub use capture::{CaptureOptions, RecordFormat};
+pub use draw::{PointStyle, RenderOptions, Shape, SizeUnits};
+pub use icons::IconAtlas;
+#[cfg(not(target_arch = "wasm32"))]
+pub use icons::read_icon_atlas;
+pub use theme::{Theme, read_theme};
 
-use gscientific::graph::petersen_graph;
+use gscientific::graph::{Graph, petersen_graph};
 use force_plot::ForcePlot;
+use rand::{SeedableRng, random, rngs::StdRng};
 use winit::
     event_loop::{ControlFlow, EventLoop}
 ;
@@ -34,55 +80,31 @@ pub fn init() {
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 pub fn run() {
+    run_with_options(PlotOptions::default());
+}
+
+pub fn run_with_options(mut options: PlotOptions) {
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::Poll);
-    let graph = petersen_graph(500, 11);
-    // graph.add_vertex();
-    // graph.random_regularish(100, 2);
-    // graph.resize(10);
-    // graph.add_edge(0, 1);
-    // graph.add_edge(1, 2);
-    // graph.add_edge(2, 3);
-    // graph.add_edge(3, 4);
-    // graph.add_edge(4, 0);
-    // graph.add_edge(0, 5);
-    // graph.add_edge(1, 6);
-    // graph.add_edge(2, 7);
-    // graph.add_edge(3, 8);
-    // graph.add_edge(4, 9);
-    // graph.add_edge(5, 7);
-    // graph.add_edge(6, 8);
-    // graph.add_edge(7, 9);
-    // graph.add_edge(8, 5);
-    // graph.add_edge(9, 6);
-    // let mut rng = rng();
-    // for _i in 0..100 {
-    //     let mut g = AdjacencyMatrix::new();
-    //     let old_vertices_ct = graph.vertex_ct();
-    //     let new_vertices_ct = (rng.next_u32() % 25 + 5) as usize;
-    //     g.random(new_vertices_ct , 0.7);
-    //     graph.merge(&g);
-        
-    //     for j in 0..(match (rng.next_u32() as usize).checked_rem(old_vertices_ct / 100) { Some(val) => val, None => 0}) {
-    //         graph.add_edge(rng.next_u32() as usize % old_vertices_ct, rng.next_u32() as usize % new_vertices_ct + old_vertices_ct);
-    //     }
-    // }
-
-    // let u = graph.add_vertex();
-    // let v = graph.add_vertex();
-    // graph.add_edge(u, v);
+    let graph = build_graph(&mut options);
+    let mut graph_plot = ForcePlot::with_options(graph, &options);
+    graph_plot.advance(options.steps);
 
-    let graph_plot = ForcePlot::new(graph);
 
-
-    let mut app = plot_window::PlotWindow::new(graph_plot);
+    let mut app = plot_window::PlotWindow::new(graph_plot).with_msaa(options.render.msaa_samples);
 
 
     event_loop.run_app(&mut app).unwrap();
 }
 
-
-#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-pub fn add(a: u32, b: u32) -> u32 {
-    a + b
+/// The graph `options` ask for, or the default one.
+fn build_graph(options: &mut PlotOptions) -> impl Graph + use<> {
+    // One seed drives both the generator and the layout, so `--seed` reproduces the whole plot.
+    let seed = *options.seed.get_or_insert_with(random);
+    let mut graph = petersen_graph(500, 11);
+    if let Some(generator) = &options.generator {
+        log::info!("Generating {}", generator.describe());
+        generator.build(&mut graph, &mut StdRng::seed_from_u64(seed));
+    }
+    graph
 }
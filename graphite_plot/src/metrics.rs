@@ -0,0 +1,297 @@
+use std::collections::{BinaryHeap, VecDeque};
+use std::cmp::Reverse;
+
+use gscientific::{
+    graph::Graph,
+    linalg::{BasicMatrix, Matrix},
+};
+
+const PAGERANK_DAMPING: f32 = 0.85;
+const PAGERANK_ITERATIONS: usize = 100;
+const PAGERANK_TOLERANCE: f32 = 1e-6;
+
+/// Per-vertex quantities that vertices can be colored and sized by.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VertexMetric {
+    Degree,
+    Betweenness,
+    Closeness,
+    PageRank,
+    Eccentricity,
+    CoreNumber,
+    Component,
+}
+
+impl VertexMetric {
+    /// The metric after `metric` when cycling through them, where `None` means no metric.
+    pub fn cycle(metric: Option<VertexMetric>) -> Option<VertexMetric> {
+        match metric {
+            None => Some(VertexMetric::Degree),
+            Some(VertexMetric::Degree) => Some(VertexMetric::Betweenness),
+            Some(VertexMetric::Betweenness) => Some(VertexMetric::Closeness),
+            Some(VertexMetric::Closeness) => Some(VertexMetric::PageRank),
+            Some(VertexMetric::PageRank) => Some(VertexMetric::Eccentricity),
+            Some(VertexMetric::Eccentricity) => Some(VertexMetric::CoreNumber),
+            Some(VertexMetric::CoreNumber) => Some(VertexMetric::Component),
+            Some(VertexMetric::Component) => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            VertexMetric::Degree => "Degree",
+            VertexMetric::Betweenness => "Betweenness",
+            VertexMetric::Closeness => "Closeness",
+            VertexMetric::PageRank => "PageRank",
+            VertexMetric::Eccentricity => "Eccentricity",
+            VertexMetric::CoreNumber => "Core number",
+            VertexMetric::Component => "Component",
+        }
+    }
+
+    /// Whether values are labels rather than magnitudes, and so get a categorical palette.
+    pub fn is_categorical(self) -> bool {
+        self == VertexMetric::Component
+    }
+
+    /// The metric for every vertex. `graph_distances` must be the unweighted all pairs
+    /// distances of `graph`, with -1 for unreachable pairs.
+    pub fn compute<T: Graph>(self, graph: &T, graph_distances: &BasicMatrix<i32>) -> Vec<f32> {
+        let adjacency = adjacency(graph);
+
+        match self {
+            VertexMetric::Degree => adjacency.iter().map(|neighbors| neighbors.len() as f32).collect(),
+            VertexMetric::Betweenness => betweenness(&adjacency),
+            VertexMetric::Closeness => closeness(graph_distances, adjacency.len()),
+            VertexMetric::PageRank => pagerank(&adjacency),
+            VertexMetric::Eccentricity => eccentricity(graph_distances, adjacency.len()),
+            VertexMetric::CoreNumber => core_numbers(&adjacency),
+            VertexMetric::Component => components(&adjacency),
+        }
+    }
+}
+
+fn adjacency<T: Graph>(graph: &T) -> Vec<Vec<usize>> {
+    (0..graph.vertex_ct())
+        .map(|u| graph.neighbors(u).map(|(v, _)| v).collect())
+        .collect()
+}
+
+/// Brandes' algorithm for unweighted graphs.
+fn betweenness(adjacency: &[Vec<usize>]) -> Vec<f32> {
+    let n = adjacency.len();
+    let mut centrality = vec![0.0; n];
+    let mut order = Vec::with_capacity(n);
+    let mut queue = VecDeque::new();
+    let mut path_ct = vec![0.0f32; n];
+    let mut distance = vec![-1i32; n];
+    let mut dependency = vec![0.0f32; n];
+
+    for s in 0..n {
+        order.clear();
+        path_ct.fill(0.0);
+        distance.fill(-1);
+        dependency.fill(0.0);
+        path_ct[s] = 1.0;
+        distance[s] = 0;
+        queue.push_back(s);
+
+        while let Some(u) = queue.pop_front() {
+            order.push(u);
+            for &v in &adjacency[u] {
+                if distance[v] < 0 {
+                    distance[v] = distance[u] + 1;
+                    queue.push_back(v);
+                }
+                if distance[v] == distance[u] + 1 {
+                    path_ct[v] += path_ct[u];
+                }
+            }
+        }
+
+        for &v in order.iter().rev() {
+            for &u in &adjacency[v] {
+                if distance[u] == distance[v] - 1 {
+                    dependency[u] += path_ct[u] / path_ct[v] * (1.0 + dependency[v]);
+                }
+            }
+            if v != s {
+                centrality[v] += dependency[v];
+            }
+        }
+    }
+
+    // Every path was counted from both of its ends.
+    centrality.iter().map(|c| c / 2.0).collect()
+}
+
+/// Harmonic closeness, which stays meaningful on disconnected graphs.
+fn closeness(graph_distances: &BasicMatrix<i32>, n: usize) -> Vec<f32> {
+    (0..n)
+        .map(|u| {
+            let total: f32 = (0..n)
+                .map(|v| *graph_distances.get(u, v))
+                .filter(|&d| d > 0)
+                .map(|d| 1.0 / d as f32)
+                .sum();
+            total / (n.max(2) - 1) as f32
+        })
+        .collect()
+}
+
+fn pagerank(adjacency: &[Vec<usize>]) -> Vec<f32> {
+    let n = adjacency.len();
+    if n == 0 {
+        return Vec::new();
+    }
+    let mut rank = vec![1.0 / n as f32; n];
+    let mut next = vec![0.0; n];
+
+    for _ in 0..PAGERANK_ITERATIONS {
+        // Rank held by vertices with no edges is spread evenly over the graph.
+        let dangling: f32 = (0..n).filter(|&u| adjacency[u].is_empty()).map(|u| rank[u]).sum();
+        next.fill((1.0 - PAGERANK_DAMPING + PAGERANK_DAMPING * dangling) / n as f32);
+        for u in 0..n {
+            let share = PAGERANK_DAMPING * rank[u] / adjacency[u].len().max(1) as f32;
+            for &v in &adjacency[u] {
+                next[v] += share;
+            }
+        }
+
+        let change: f32 = rank.iter().zip(&next).map(|(a, b)| (a - b).abs()).sum();
+        std::mem::swap(&mut rank, &mut next);
+        if change < PAGERANK_TOLERANCE {
+            break;
+        }
+    }
+
+    rank
+}
+
+/// Greatest distance to any reachable vertex.
+fn eccentricity(graph_distances: &BasicMatrix<i32>, n: usize) -> Vec<f32> {
+    (0..n)
+        .map(|u| (0..n).map(|v| *graph_distances.get(u, v)).max().unwrap_or(0).max(0) as f32)
+        .collect()
+}
+
+/// k-core numbers by repeatedly peeling off the vertex of least remaining degree.
+fn core_numbers(adjacency: &[Vec<usize>]) -> Vec<f32> {
+    let n = adjacency.len();
+    let mut degree: Vec<usize> = adjacency.iter().map(|neighbors| neighbors.len()).collect();
+    let mut removed = vec![false; n];
+    let mut core = vec![0.0; n];
+    let mut heap: BinaryHeap<Reverse<(usize, usize)>> =
+        (0..n).map(|u| Reverse((degree[u], u))).collect();
+    let mut k = 0;
+
+    while let Some(Reverse((d, u))) = heap.pop() {
+        if removed[u] || d != degree[u] {
+            continue;
+        }
+        removed[u] = true;
+        k = k.max(d);
+        core[u] = k as f32;
+        for &v in &adjacency[u] {
+            if !removed[v] {
+                degree[v] -= 1;
+                heap.push(Reverse((degree[v], v)));
+            }
+        }
+    }
+
+    core
+}
+
+/// Component labels, numbered by the smallest vertex in each component.
+fn components(adjacency: &[Vec<usize>]) -> Vec<f32> {
+    let n = adjacency.len();
+    let mut label = vec![usize::MAX; n];
+    let mut component_ct = 0;
+    let mut stack = Vec::new();
+
+    for s in 0..n {
+        if label[s] != usize::MAX {
+            continue;
+        }
+        label[s] = component_ct;
+        stack.push(s);
+        while let Some(u) = stack.pop() {
+            for &v in &adjacency[u] {
+                if label[v] == usize::MAX {
+                    label[v] = component_ct;
+                    stack.push(v);
+                }
+            }
+        }
+        component_ct += 1;
+    }
+
+    label.into_iter().map(|l| l as f32).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_graphs;
+
+    fn metric<T: Graph>(metric: VertexMetric, graph: &T) -> Vec<f32> {
+        metric.compute(graph, &graph.unweighted_all_pairs_distance())
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len(), "{:?} != {:?}", actual, expected);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn path_metrics() {
+        let path = test_graphs::path(5);
+        assert_close(&metric(VertexMetric::Degree, &path), &[1.0, 2.0, 2.0, 2.0, 1.0]);
+        // Vertex i lies between the i vertices before it and the 4 - i after it.
+        assert_close(&metric(VertexMetric::Betweenness, &path), &[0.0, 3.0, 4.0, 3.0, 0.0]);
+        let end = (1.0 + 1.0 / 2.0 + 1.0 / 3.0 + 1.0 / 4.0) / 4.0;
+        let next = (1.0 + 1.0 + 1.0 / 2.0 + 1.0 / 3.0) / 4.0;
+        let middle = (1.0 + 1.0 + 1.0 / 2.0 + 1.0 / 2.0) / 4.0;
+        assert_close(&metric(VertexMetric::Closeness, &path), &[end, next, middle, next, end]);
+        assert_close(&metric(VertexMetric::Eccentricity, &path), &[4.0, 3.0, 2.0, 3.0, 4.0]);
+        assert_close(&metric(VertexMetric::CoreNumber, &path), &[1.0; 5]);
+        assert_close(&metric(VertexMetric::Component, &path), &[0.0; 5]);
+    }
+
+    #[test]
+    fn star_metrics() {
+        let star = test_graphs::star(4);
+        // Every pair of leaves has its only shortest path through the center.
+        assert_close(&metric(VertexMetric::Betweenness, &star), &[6.0, 0.0, 0.0, 0.0, 0.0]);
+        // Solving center = 0.15 / 5 + 0.85 * 4 * leaf and leaf = 0.15 / 5 + 0.85 * center / 4.
+        let center = 0.132 / 0.2775;
+        let leaf = 0.03 + 0.2125 * center;
+        assert_close(&metric(VertexMetric::PageRank, &star), &[center, leaf, leaf, leaf, leaf]);
+        assert_close(&metric(VertexMetric::Eccentricity, &star), &[1.0, 2.0, 2.0, 2.0, 2.0]);
+    }
+
+    #[test]
+    fn barbell_metrics() {
+        let barbell = test_graphs::barbell(4);
+        // Only the bridge's ends are on paths between the cliques, 4 * 4 - 1 of them each.
+        assert_close(&metric(VertexMetric::Betweenness, &barbell), &[0.0, 0.0, 0.0, 12.0, 12.0, 0.0, 0.0, 0.0]);
+        assert_close(&metric(VertexMetric::CoreNumber, &barbell), &[3.0; 8]);
+        let pagerank = metric(VertexMetric::PageRank, &barbell);
+        assert!((pagerank.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+        assert!(pagerank[3] > pagerank[0]);
+    }
+
+    #[test]
+    fn disconnected_metrics() {
+        // A triangle with a pendant vertex, and a separate edge.
+        let graph = test_graphs::graph(6, &[(0, 1), (1, 2), (2, 0), (2, 3), (4, 5)]);
+        assert_close(&metric(VertexMetric::Component, &graph), &[0.0, 0.0, 0.0, 0.0, 1.0, 1.0]);
+        assert_close(&metric(VertexMetric::CoreNumber, &graph), &[2.0, 2.0, 2.0, 1.0, 1.0, 1.0]);
+        assert_close(&metric(VertexMetric::Eccentricity, &graph), &[2.0, 2.0, 1.0, 2.0, 1.0, 1.0]);
+        // Unreachable vertices add nothing to harmonic closeness.
+        assert_close(&metric(VertexMetric::Closeness, &graph)[4..], &[0.2, 0.2]);
+    }
+}
@@ -1,17 +1,56 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
+use cgmath::{InnerSpace, Vector2, Zero};
 use winit::{
     dpi::PhysicalPosition,
-    event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+    event::{ElementState, MouseButton, MouseScrollDelta, Touch, TouchPhase, WindowEvent},
+    keyboard::ModifiersState,
 };
 
+/// Pixels of smooth scrolling that count as one wheel line.
+const PIXELS_PER_LINE: f64 = 250.0;
+
+/// A pan, pinch and twist with two fingers or a trackpad, summed up since it was last
+/// consumed.
+#[derive(Clone, Copy, Debug)]
+pub struct Gesture {
+    /// Movement of the point between the fingers, in pixels.
+    pub pan: Vector2<f32>,
+    /// How many times farther apart the fingers are than before.
+    pub zoom: f32,
+    /// Counterclockwise twist on screen, in radians.
+    pub rotation: f32,
+    /// The point between the fingers, where it is now.
+    pub center: PhysicalPosition<f32>,
+}
+
+impl Default for Gesture {
+    fn default() -> Self {
+        Self {
+            pan: Vector2::zero(),
+            zoom: 1.0,
+            rotation: 0.0,
+            center: PhysicalPosition { x: 0., y: 0. },
+        }
+    }
+}
+
 pub struct MouseTracker {
     mouse_down: HashSet<MouseButton>,
     position: PhysicalPosition<f32>,
     scroll_delta: f32,
     cursor_in_window: bool,
+    /// Modifier keys held right now, for bindings that need them.
+    modifiers: ModifiersState,
+    /// Fingers on the screen, by touch id.
+    touches: HashMap<u64, PhysicalPosition<f32>>,
+    /// Whether the fingers on the screen are making a gesture. They keep doing so until all
+    /// of them are lifted, so lifting one of two doesn't start a drag.
+    touch_gesture: bool,
+    gesture: Gesture,
 }
 
+#[derive(Clone, Copy, Debug)]
 pub enum MouseEvent {
     ButtonPressed(MouseButton),
     ButtonReleased(MouseButton),
@@ -20,6 +59,7 @@ pub enum MouseEvent {
     CursorEntered,
     CursorExited,
     WheelScrolled,
+    Gestured,
 }
 
 impl MouseTracker {
@@ -29,6 +69,10 @@ impl MouseTracker {
             position: PhysicalPosition { x: 0., y: 0. },
             scroll_delta: 0.,
             cursor_in_window: true,
+            modifiers: ModifiersState::empty(),
+            touches: HashMap::new(),
+            touch_gesture: false,
+            gesture: Gesture::default(),
         }
     }
 
@@ -46,11 +90,11 @@ impl MouseTracker {
             },
             WindowEvent::MouseWheel { delta, .. } => { 
                 match delta {
-                    MouseScrollDelta::LineDelta(_v_lines, h_lines) => {
-                        self.scroll_delta += h_lines;
+                    MouseScrollDelta::LineDelta(_h_lines, v_lines) => {
+                        self.scroll_delta += v_lines;
                     },
                     MouseScrollDelta::PixelDelta(pixel_offset) => {
-                        self.scroll_delta += (pixel_offset.y / 250.0) as f32;
+                        self.scroll_delta += (pixel_offset.y / PIXELS_PER_LINE) as f32;
                     }
                 };
                 Some(MouseEvent::WheelScrolled)
@@ -72,18 +116,113 @@ impl MouseTracker {
                 else if self.mouse_down.contains(&MouseButton::Right) {
                     Some(MouseEvent::CursorDragged(MouseButton::Right))
                 }
+                else if self.mouse_down.contains(&MouseButton::Middle) {
+                    Some(MouseEvent::CursorDragged(MouseButton::Middle))
+                }
                 else {
                     Some(MouseEvent::CursorMoved)
                 }
             }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                None
+            }
+            WindowEvent::Touch(touch) => self.translate_touch(touch),
+            WindowEvent::PinchGesture { delta, .. } => {
+                self.gesture.zoom *= 1.0 + *delta as f32;
+                self.gesture.center = self.position;
+                Some(MouseEvent::Gestured)
+            }
+            WindowEvent::PanGesture { delta, .. } => {
+                self.gesture.pan += Vector2::new(delta.x, delta.y);
+                self.gesture.center = self.position;
+                Some(MouseEvent::Gestured)
+            }
+            WindowEvent::RotationGesture { delta, .. } => {
+                self.gesture.rotation += delta.to_radians();
+                self.gesture.center = self.position;
+                Some(MouseEvent::Gestured)
+            }
             _ => None,
         }
     }
 
+    /// A single finger stands in for the left mouse button. A second finger releases it and
+    /// turns the touches into a gesture.
+    fn translate_touch(&mut self, touch: &Touch) -> Option<MouseEvent> {
+        let position = touch.location.cast();
+        match touch.phase {
+            TouchPhase::Started => {
+                self.touches.insert(touch.id, position);
+                if self.touches.len() == 1 {
+                    self.position = position;
+                    self.mouse_down.insert(MouseButton::Left);
+                    Some(MouseEvent::ButtonPressed(MouseButton::Left))
+                } else if !self.touch_gesture {
+                    self.touch_gesture = true;
+                    self.mouse_down.remove(&MouseButton::Left);
+                    Some(MouseEvent::ButtonReleased(MouseButton::Left))
+                } else {
+                    None
+                }
+            }
+            TouchPhase::Moved => {
+                if !self.touch_gesture {
+                    self.touches.insert(touch.id, position);
+                    self.position = position;
+                    return Some(MouseEvent::CursorDragged(MouseButton::Left));
+                }
+
+                let before = self.finger_pair();
+                self.touches.insert(touch.id, position);
+                let (before_center, before_span) = before?;
+                let (center, span) = self.finger_pair()?;
+                self.gesture.pan += Vector2::new(center.x - before_center.x, center.y - before_center.y);
+                if before_span.magnitude2() > 0.0 {
+                    self.gesture.zoom *= span.magnitude() / before_span.magnitude();
+                }
+                // Pixel y points down, so a counterclockwise twist lowers the angle.
+                self.gesture.rotation -= wrap_angle(span.y.atan2(span.x) - before_span.y.atan2(before_span.x));
+                self.gesture.center = center;
+                Some(MouseEvent::Gestured)
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.touches.remove(&touch.id);
+                if !self.touches.is_empty() {
+                    return None;
+                }
+                if std::mem::take(&mut self.touch_gesture) {
+                    None
+                } else {
+                    self.mouse_down.remove(&MouseButton::Left);
+                    Some(MouseEvent::ButtonReleased(MouseButton::Left))
+                }
+            }
+        }
+    }
+
+    /// Midpoint of the two fingers with the lowest ids and the vector from the first to the
+    /// second.
+    fn finger_pair(&self) -> Option<(PhysicalPosition<f32>, Vector2<f32>)> {
+        let mut ids = self.touches.keys().copied().collect::<Vec<_>>();
+        ids.sort();
+        let a = self.touches[ids.first()?];
+        let b = self.touches[ids.get(1)?];
+
+        Some((
+            PhysicalPosition { x: (a.x + b.x) / 2.0, y: (a.y + b.y) / 2.0 },
+            Vector2::new(b.x - a.x, b.y - a.y),
+        ))
+    }
+
     pub fn get_position(&self) -> PhysicalPosition<f32> {
         self.position
     }
 
+    pub fn get_modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
     pub fn is_button_down(&self, button: MouseButton) -> bool {
         self.mouse_down.contains(&button)
     }
@@ -94,7 +233,142 @@ impl MouseTracker {
         tmp
     }
 
+    pub fn consume_gesture(&mut self) -> Gesture {
+        std::mem::take(&mut self.gesture)
+    }
+
     pub fn is_cursor_in_window(&self) -> bool {
         self.cursor_in_window
     }
-}
\ No newline at end of file
+}
+
+/// Brings an angle difference into `[-pi, pi]`.
+fn wrap_angle(angle: f32) -> f32 {
+    use std::f32::consts::{PI, TAU};
+    if angle > PI {
+        angle - TAU
+    } else if angle < -PI {
+        angle + TAU
+    } else {
+        angle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use winit::event::DeviceId;
+
+    use super::*;
+
+    fn wheel(delta: MouseScrollDelta) -> WindowEvent {
+        WindowEvent::MouseWheel { device_id: DeviceId::dummy(), delta, phase: TouchPhase::Moved }
+    }
+
+    fn touch(mouse: &mut MouseTracker, id: u64, phase: TouchPhase, x: f64, y: f64) -> Option<MouseEvent> {
+        mouse.translate_event(&WindowEvent::Touch(Touch {
+            device_id: DeviceId::dummy(),
+            phase,
+            location: PhysicalPosition { x, y },
+            force: None,
+            id,
+        }))
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn only_vertical_lines_scroll() {
+        let mut mouse = MouseTracker::new();
+        mouse.translate_event(&wheel(MouseScrollDelta::LineDelta(3.0, 0.0)));
+        assert_eq!(mouse.consume_scroll_delta(), 0.0);
+
+        mouse.translate_event(&wheel(MouseScrollDelta::LineDelta(1.0, 2.0)));
+        mouse.translate_event(&wheel(MouseScrollDelta::LineDelta(0.0, -0.5)));
+        assert_eq!(mouse.consume_scroll_delta(), 1.5);
+        assert_eq!(mouse.consume_scroll_delta(), 0.0);
+
+        let pixels = PhysicalPosition { x: 100.0, y: PIXELS_PER_LINE * 2.0 };
+        mouse.translate_event(&wheel(MouseScrollDelta::PixelDelta(pixels)));
+        assert_eq!(mouse.consume_scroll_delta(), 2.0);
+    }
+
+    #[test]
+    fn one_finger_drags_like_the_left_button() {
+        let mut mouse = MouseTracker::new();
+        assert!(matches!(touch(&mut mouse, 1, TouchPhase::Started, 10.0, 20.0), Some(MouseEvent::ButtonPressed(MouseButton::Left))));
+        assert!(mouse.is_button_down(MouseButton::Left));
+
+        assert!(matches!(touch(&mut mouse, 1, TouchPhase::Moved, 15.0, 25.0), Some(MouseEvent::CursorDragged(MouseButton::Left))));
+        assert_eq!(mouse.get_position(), PhysicalPosition { x: 15.0, y: 25.0 });
+
+        assert!(matches!(touch(&mut mouse, 1, TouchPhase::Ended, 15.0, 25.0), Some(MouseEvent::ButtonReleased(MouseButton::Left))));
+        assert!(!mouse.is_button_down(MouseButton::Left));
+    }
+
+    #[test]
+    fn two_fingers_pan_pinch_and_twist() {
+        let mut mouse = MouseTracker::new();
+        touch(&mut mouse, 1, TouchPhase::Started, 0.0, 0.0);
+        assert!(matches!(touch(&mut mouse, 2, TouchPhase::Started, 10.0, 0.0), Some(MouseEvent::ButtonReleased(MouseButton::Left))));
+        assert!(!mouse.is_button_down(MouseButton::Left));
+
+        // The second finger swings down to twice as far. Pixel y points down, so that is a
+        // clockwise twist on screen.
+        assert!(matches!(touch(&mut mouse, 2, TouchPhase::Moved, 0.0, 20.0), Some(MouseEvent::Gestured)));
+        let gesture = mouse.consume_gesture();
+        assert_close(gesture.zoom, 2.0);
+        assert_close(gesture.rotation, -PI / 2.0);
+        assert_close(gesture.pan.x, -5.0);
+        assert_close(gesture.pan.y, 10.0);
+        assert_eq!(gesture.center, PhysicalPosition { x: 0.0, y: 10.0 });
+
+        let gesture = mouse.consume_gesture();
+        assert_eq!((gesture.zoom, gesture.rotation), (1.0, 0.0));
+
+        // Lifting one finger doesn't turn the other back into a drag.
+        assert!(touch(&mut mouse, 1, TouchPhase::Ended, 0.0, 0.0).is_none());
+        assert!(!matches!(touch(&mut mouse, 2, TouchPhase::Moved, 5.0, 5.0), Some(MouseEvent::CursorDragged(_))));
+        assert!(touch(&mut mouse, 2, TouchPhase::Ended, 5.0, 5.0).is_none());
+
+        // Once all are lifted, one finger drags again.
+        assert!(matches!(touch(&mut mouse, 3, TouchPhase::Started, 0.0, 0.0), Some(MouseEvent::ButtonPressed(MouseButton::Left))));
+    }
+
+    #[test]
+    fn twisting_across_the_left_keeps_the_angle_small() {
+        let mut mouse = MouseTracker::new();
+        touch(&mut mouse, 1, TouchPhase::Started, 0.0, 0.0);
+        touch(&mut mouse, 2, TouchPhase::Started, -10.0, 1.0);
+        // The finger angle jumps from just under pi to just over -pi.
+        touch(&mut mouse, 2, TouchPhase::Moved, -10.0, -1.0);
+
+        let expected = -2.0 * (1.0f32).atan2(10.0);
+        assert_close(mouse.consume_gesture().rotation, expected);
+    }
+
+    #[test]
+    fn the_two_lowest_fingers_make_the_pair() {
+        let mut mouse = MouseTracker::new();
+        assert!(mouse.finger_pair().is_none());
+        touch(&mut mouse, 5, TouchPhase::Started, 10.0, 0.0);
+        assert!(mouse.finger_pair().is_none());
+        touch(&mut mouse, 9, TouchPhase::Started, 100.0, 100.0);
+        touch(&mut mouse, 2, TouchPhase::Started, 0.0, 0.0);
+
+        let (center, span) = mouse.finger_pair().unwrap();
+        assert_eq!(center, PhysicalPosition { x: 5.0, y: 0.0 });
+        assert_eq!(span, Vector2::new(10.0, 0.0));
+    }
+
+    #[test]
+    fn angles_wrap_into_half_turns() {
+        assert_close(wrap_angle(1.0), 1.0);
+        assert_close(wrap_angle(-1.0), -1.0);
+        assert_close(wrap_angle(1.5 * PI), -0.5 * PI);
+        assert_close(wrap_angle(-1.5 * PI), 0.5 * PI);
+    }
+}
@@ -0,0 +1,557 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::Vector2;
+use embedded_graphics::{
+    Pixel,
+    mono_font::{MonoTextStyle, ascii::FONT_6X10},
+    pixelcolor::BinaryColor,
+    prelude::{DrawTarget, OriginDimensions, Point, Size},
+    text::{Baseline, Text},
+    Drawable,
+};
+
+use crate::{
+    draw::Color,
+    plot_graphic::PlotGraphicState,
+    plot_window::PlotWindowState,
+};
+
+const GLYPH_WIDTH: u32 = 6;
+const GLYPH_HEIGHT: u32 = 10;
+const ATLAS_COLUMNS: u32 = 16;
+const ATLAS_ROWS: u32 = 6;
+const FIRST_GLYPH: u32 = 32;
+/// The DEL cell of the atlas is filled in completely and used for solid rectangles.
+const SOLID_GLYPH: u32 = 127;
+const LINE_SPACING: f32 = 2.0;
+const INITIAL_VERTEX_CAPACITY: usize = 1 << 12;
+
+/// Which corner of the window an overlay item is positioned relative to. The item's own
+/// matching corner is placed `position` pixels inwards from it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Anchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+pub enum OverlayItem {
+    Text {
+        text: String,
+        anchor: Anchor,
+        position: Vector2<f32>,
+        color: Color,
+        scale: f32,
+    },
+    Rect {
+        anchor: Anchor,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+        color: Color,
+    },
+    /// A horizontal gradient through `colors`, left to right.
+    Gradient {
+        anchor: Anchor,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+        colors: Vec<Color>,
+    },
+}
+
+pub trait GetOverlay {
+    fn get_overlay(&self) -> impl Iterator<Item = OverlayItem>;
+}
+
+/// Size in pixels of `text` when drawn at `scale`.
+pub fn text_size(text: &str, scale: f32) -> Vector2<f32> {
+    let line_ct = text.lines().count().max(1);
+    let column_ct = text.lines().map(|line| line.chars().count()).max().unwrap_or(0);
+
+    Vector2::new(
+        column_ct as f32 * GLYPH_WIDTH as f32 * scale,
+        line_ct as f32 * (GLYPH_HEIGHT as f32 + LINE_SPACING) * scale - LINE_SPACING * scale,
+    )
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct OverlayVertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+    color: [f32; 4],
+}
+
+impl OverlayVertex {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<OverlayVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: 2 * std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct OverlayUniform {
+    screen_size: [f32; 2],
+    _pad: [f32; 2],
+}
+
+struct GlyphAtlas {
+    pixels: Vec<u8>,
+}
+
+impl GlyphAtlas {
+    const WIDTH: u32 = ATLAS_COLUMNS * GLYPH_WIDTH;
+    const HEIGHT: u32 = ATLAS_ROWS * GLYPH_HEIGHT;
+
+    fn new() -> Self {
+        let mut atlas = Self {
+            pixels: vec![0; (Self::WIDTH * Self::HEIGHT) as usize],
+        };
+        let style = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
+
+        for code in FIRST_GLYPH..SOLID_GLYPH {
+            let origin = Self::cell_origin(code);
+            let glyph = char::from_u32(code).unwrap().to_string();
+            let _ = Text::with_baseline(
+                &glyph,
+                Point::new(origin.0 as i32, origin.1 as i32),
+                style,
+                Baseline::Top,
+            )
+            .draw(&mut atlas);
+        }
+
+        let origin = Self::cell_origin(SOLID_GLYPH);
+        for y in origin.1..origin.1 + GLYPH_HEIGHT {
+            for x in origin.0..origin.0 + GLYPH_WIDTH {
+                atlas.pixels[(y * Self::WIDTH + x) as usize] = 255;
+            }
+        }
+
+        atlas
+    }
+
+    fn cell_origin(code: u32) -> (u32, u32) {
+        let index = code - FIRST_GLYPH;
+        (
+            (index % ATLAS_COLUMNS) * GLYPH_WIDTH,
+            (index / ATLAS_COLUMNS) * GLYPH_HEIGHT,
+        )
+    }
+
+    /// Texture coordinates of the top-left and bottom-right corners of a glyph cell.
+    fn cell_tex_coords(c: char) -> ([f32; 2], [f32; 2]) {
+        let code = match c as u32 {
+            code @ FIRST_GLYPH..SOLID_GLYPH => code,
+            _ => '?' as u32,
+        };
+        let origin = Self::cell_origin(code);
+        (
+            [
+                origin.0 as f32 / Self::WIDTH as f32,
+                origin.1 as f32 / Self::HEIGHT as f32,
+            ],
+            [
+                (origin.0 + GLYPH_WIDTH) as f32 / Self::WIDTH as f32,
+                (origin.1 + GLYPH_HEIGHT) as f32 / Self::HEIGHT as f32,
+            ],
+        )
+    }
+
+    fn solid_tex_coords() -> [f32; 2] {
+        let origin = Self::cell_origin(SOLID_GLYPH);
+        [
+            (origin.0 as f32 + 0.5 * GLYPH_WIDTH as f32) / Self::WIDTH as f32,
+            (origin.1 as f32 + 0.5 * GLYPH_HEIGHT as f32) / Self::HEIGHT as f32,
+        ]
+    }
+}
+
+impl OriginDimensions for GlyphAtlas {
+    fn size(&self) -> Size {
+        Size::new(Self::WIDTH, Self::HEIGHT)
+    }
+}
+
+impl DrawTarget for GlyphAtlas {
+    type Color = BinaryColor;
+    type Error = std::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if point.x < 0 || point.y < 0 || point.x as u32 >= Self::WIDTH || point.y as u32 >= Self::HEIGHT {
+                continue;
+            }
+            if color.is_on() {
+                self.pixels[(point.y as u32 * Self::WIDTH + point.x as u32) as usize] = 255;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Screen-space text, rectangles and gradients drawn on top of the plot. Each plot's
+/// `PlotGraphicElement` implementation hands it to `prepare` and `draw`.
+pub struct OverlayElement {
+    render_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+
+    vertices: Vec<OverlayVertex>,
+    indices: Vec<u32>,
+}
+
+impl OverlayElement {
+    pub fn prepare<T: GetOverlay>(&mut self, window_state: &PlotWindowState, data: &T) {
+        let screen_size = Vector2::new(
+            window_state.config.width as f32,
+            window_state.config.height as f32,
+        );
+        self.vertices.clear();
+        self.indices.clear();
+
+        for item in data.get_overlay() {
+            match item {
+                OverlayItem::Text { text, anchor, position, color, scale } => {
+                    let origin = resolve_anchor(anchor, position, text_size(&text, scale), screen_size);
+                    self.push_text(&text, origin, color, scale);
+                }
+                OverlayItem::Rect { anchor, position, size, color } => {
+                    let origin = resolve_anchor(anchor, position, size, screen_size);
+                    self.push_quad(origin, size, GlyphAtlas::solid_tex_coords(), [color; 2]);
+                }
+                OverlayItem::Gradient { anchor, position, size, colors } => {
+                    let origin = resolve_anchor(anchor, position, size, screen_size);
+                    let segment_ct = colors.len().saturating_sub(1).max(1);
+                    let segment_width = size.x / segment_ct as f32;
+                    for (i, pair) in colors.windows(2).enumerate() {
+                        self.push_quad(
+                            origin + Vector2::new(i as f32 * segment_width, 0.0),
+                            Vector2::new(segment_width, size.y),
+                            GlyphAtlas::solid_tex_coords(),
+                            [pair[0], pair[1]],
+                        );
+                    }
+                }
+            }
+        }
+
+        let uniform = OverlayUniform {
+            screen_size: screen_size.into(),
+            _pad: [0.0; 2],
+        };
+        window_state
+            .queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+        let vertex_bytes = (self.vertices.len() * std::mem::size_of::<OverlayVertex>()) as u64;
+        if vertex_bytes > self.vertex_buffer.size() {
+            self.vertex_buffer = create_buffer(window_state, "Overlay Vertex Buffer", vertex_bytes.next_power_of_two(), wgpu::BufferUsages::VERTEX);
+        }
+        let index_bytes = (self.indices.len() * std::mem::size_of::<u32>()) as u64;
+        if index_bytes > self.index_buffer.size() {
+            self.index_buffer = create_buffer(window_state, "Overlay Index Buffer", index_bytes.next_power_of_two(), wgpu::BufferUsages::INDEX);
+        }
+
+        window_state.queue.write_buffer(
+            &self.vertex_buffer,
+            0,
+            bytemuck::cast_slice(&self.vertices),
+        );
+        window_state
+            .queue
+            .write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(&self.indices));
+    }
+
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        if self.indices.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..(self.indices.len() as u32), 0, 0..1);
+    }
+}
+
+fn resolve_anchor(
+    anchor: Anchor,
+    position: Vector2<f32>,
+    size: Vector2<f32>,
+    screen_size: Vector2<f32>,
+) -> Vector2<f32> {
+    match anchor {
+        Anchor::TopLeft => position,
+        Anchor::TopRight => Vector2::new(screen_size.x - position.x - size.x, position.y),
+        Anchor::BottomLeft => Vector2::new(position.x, screen_size.y - position.y - size.y),
+        Anchor::BottomRight => screen_size - position - size,
+    }
+}
+
+fn create_buffer(
+    window_state: &PlotWindowState,
+    label: &str,
+    size: u64,
+    usage: wgpu::BufferUsages,
+) -> wgpu::Buffer {
+    window_state.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size,
+        usage: usage | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+impl OverlayElement {
+    /// `depth_format` is that of the render pass's depth attachment, if it has one. The
+    /// overlay ignores depth either way and draws over everything.
+    pub fn new<T: GetOverlay>(
+        _graphic_state: &PlotGraphicState<T>,
+        window_state: &PlotWindowState,
+        depth_format: Option<wgpu::TextureFormat>,
+    ) -> Self {
+        let device = &window_state.device;
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Overlay Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../assets/overlay_shader.wgsl").into()),
+        });
+
+        let atlas = GlyphAtlas::new();
+        let atlas_size = wgpu::Extent3d {
+            width: GlyphAtlas::WIDTH,
+            height: GlyphAtlas::HEIGHT,
+            depth_or_array_layers: 1,
+        };
+        let atlas_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Glyph Atlas"),
+            size: atlas_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        window_state.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &atlas_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &atlas.pixels,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(GlyphAtlas::WIDTH),
+                rows_per_image: Some(GlyphAtlas::HEIGHT),
+            },
+            atlas_size,
+        );
+        let atlas_view = atlas_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let atlas_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let uniform_buffer = create_buffer(
+            window_state,
+            "Overlay Uniform Buffer",
+            std::mem::size_of::<OverlayUniform>() as u64,
+            wgpu::BufferUsages::UNIFORM,
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("overlay_bind_group_layout"),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&atlas_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&atlas_sampler),
+                },
+            ],
+            label: Some("overlay_bind_group"),
+        });
+
+        let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overlay Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Overlay Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[OverlayVertex::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: window_state.config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
+                format,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: window_state.sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        let vertex_buffer = create_buffer(
+            window_state,
+            "Overlay Vertex Buffer",
+            (INITIAL_VERTEX_CAPACITY * std::mem::size_of::<OverlayVertex>()) as u64,
+            wgpu::BufferUsages::VERTEX,
+        );
+        let index_buffer = create_buffer(
+            window_state,
+            "Overlay Index Buffer",
+            (INITIAL_VERTEX_CAPACITY / 4 * 6 * std::mem::size_of::<u32>()) as u64,
+            wgpu::BufferUsages::INDEX,
+        );
+
+        Self {
+            render_pipeline,
+            vertex_buffer,
+            index_buffer,
+            uniform_buffer,
+            bind_group,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    fn push_text(&mut self, text: &str, origin: Vector2<f32>, color: Color, scale: f32) {
+        let glyph_size = Vector2::new(GLYPH_WIDTH as f32, GLYPH_HEIGHT as f32) * scale;
+        let line_height = (GLYPH_HEIGHT as f32 + LINE_SPACING) * scale;
+
+        for (line_index, line) in text.lines().enumerate() {
+            for (column, c) in line.chars().enumerate() {
+                if c == ' ' {
+                    continue;
+                }
+                let (min, max) = GlyphAtlas::cell_tex_coords(c);
+                let corner = origin + Vector2::new(column as f32 * glyph_size.x, line_index as f32 * line_height);
+                self.push_textured_quad(corner, glyph_size, min, max, color);
+            }
+        }
+    }
+
+    /// A quad with a single texel of the atlas stretched over it, colored left to right.
+    fn push_quad(&mut self, corner: Vector2<f32>, size: Vector2<f32>, tex_coords: [f32; 2], colors: [Color; 2]) {
+        let index = self.vertices.len() as u32;
+        let left: [f32; 4] = colors[0].into();
+        let right: [f32; 4] = colors[1].into();
+
+        self.vertices.push(OverlayVertex { position: corner.into(), tex_coords, color: left });
+        self.vertices.push(OverlayVertex { position: [corner.x, corner.y + size.y], tex_coords, color: left });
+        self.vertices.push(OverlayVertex { position: [corner.x + size.x, corner.y], tex_coords, color: right });
+        self.vertices.push(OverlayVertex { position: (corner + size).into(), tex_coords, color: right });
+        self.indices.extend_from_slice(&[index, index + 1, index + 2, index + 2, index + 1, index + 3]);
+    }
+
+    fn push_textured_quad(&mut self, corner: Vector2<f32>, size: Vector2<f32>, min: [f32; 2], max: [f32; 2], color: Color) {
+        let index = self.vertices.len() as u32;
+        let color: [f32; 4] = color.into();
+
+        self.vertices.push(OverlayVertex { position: corner.into(), tex_coords: min, color });
+        self.vertices.push(OverlayVertex { position: [corner.x, corner.y + size.y], tex_coords: [min[0], max[1]], color });
+        self.vertices.push(OverlayVertex { position: [corner.x + size.x, corner.y], tex_coords: [max[0], min[1]], color });
+        self.vertices.push(OverlayVertex { position: (corner + size).into(), tex_coords: max, color });
+        self.indices.extend_from_slice(&[index, index + 1, index + 2, index + 2, index + 1, index + 3]);
+    }
+}
@@ -0,0 +1,359 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashSet, VecDeque},
+};
+
+use gscientific::graph::Graph;
+
+use crate::edge_weights::EdgeWeights;
+
+/// Distances closer than this are treated as ties, so float sums along different routes
+/// still count as the same shortest distance.
+const DISTANCE_TOLERANCE: f64 = 1e-9;
+
+/// Every shortest path from one source, kept as the predecessors of each vertex.
+pub struct ShortestPaths {
+    pub source: usize,
+    /// Distance from the source, or infinity if unreachable.
+    pub distance: Vec<f64>,
+    pub predecessors: Vec<Vec<usize>>,
+    /// Number of distinct shortest paths from the source to each vertex.
+    pub path_ct: Vec<f64>,
+}
+
+/// Priority queue entry that a `BinaryHeap` pops closest first.
+#[derive(PartialEq)]
+pub struct QueueEntry {
+    pub distance: f64,
+    pub vertex: usize,
+}
+
+impl Eq for QueueEntry {}
+
+impl PartialOrd for QueueEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueueEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .distance
+            .total_cmp(&self.distance)
+            .then_with(|| other.vertex.cmp(&self.vertex))
+    }
+}
+
+impl ShortestPaths {
+    /// Breadth first search, counting every edge as length 1.
+    pub fn unweighted<T: Graph>(graph: &T, source: usize) -> Self {
+        let n = graph.vertex_ct();
+        let mut paths = Self::empty(n, source);
+        let mut queue = VecDeque::from([source]);
+
+        while let Some(u) = queue.pop_front() {
+            for (v, _) in graph.neighbors(u) {
+                if paths.distance[v].is_infinite() {
+                    paths.distance[v] = paths.distance[u] + 1.0;
+                    queue.push_back(v);
+                }
+                if paths.distance[v] == paths.distance[u] + 1.0 {
+                    paths.predecessors[v].push(u);
+                    paths.path_ct[v] += paths.path_ct[u];
+                }
+            }
+        }
+
+        paths
+    }
+
+    /// Dijkstra's algorithm over the edge weights. Negative weights are treated as 0.
+    pub fn weighted<T: Graph>(graph: &T, weights: &EdgeWeights, source: usize) -> Self {
+        let n = graph.vertex_ct();
+        let mut paths = Self::empty(n, source);
+        let mut done = vec![false; n];
+        let mut queue = BinaryHeap::from([QueueEntry {
+            distance: 0.0,
+            vertex: source,
+        }]);
+
+        while let Some(QueueEntry { distance, vertex: u }) = queue.pop() {
+            if done[u] || distance > paths.distance[u] {
+                continue;
+            }
+            done[u] = true;
+
+            for (v, weight) in weights.neighbors(graph, u) {
+                if done[v] {
+                    continue;
+                }
+                let candidate = distance + weight.max(0.0);
+                if candidate < paths.distance[v] - DISTANCE_TOLERANCE {
+                    paths.distance[v] = candidate;
+                    paths.predecessors[v].clear();
+                    paths.predecessors[v].push(u);
+                    paths.path_ct[v] = paths.path_ct[u];
+                    queue.push(QueueEntry {
+                        distance: candidate,
+                        vertex: v,
+                    });
+                } else if (candidate - paths.distance[v]).abs() <= DISTANCE_TOLERANCE {
+                    paths.predecessors[v].push(u);
+                    paths.path_ct[v] += paths.path_ct[u];
+                }
+            }
+        }
+
+        paths
+    }
+
+    fn empty(n: usize, source: usize) -> Self {
+        let mut distance = vec![f64::INFINITY; n];
+        let mut path_ct = vec![0.0; n];
+        distance[source] = 0.0;
+        path_ct[source] = 1.0;
+
+        Self {
+            source,
+            distance,
+            predecessors: vec![Vec::new(); n],
+            path_ct,
+        }
+    }
+
+    /// One shortest path from the source to `target`, starting at the source.
+    pub fn path_to(&self, target: usize) -> Option<Vec<usize>> {
+        if self.distance[target].is_infinite() {
+            return None;
+        }
+        let mut path = vec![target];
+        let mut current = target;
+        while current != self.source {
+            current = self.predecessors[current][0];
+            path.push(current);
+        }
+        path.reverse();
+
+        Some(path)
+    }
+
+    /// Every edge on any shortest path from the source to `target`, as `(predecessor, vertex)`.
+    pub fn all_path_edges_to(&self, target: usize) -> HashSet<(usize, usize)> {
+        let mut edges = HashSet::new();
+        if self.distance[target].is_infinite() {
+            return edges;
+        }
+        let mut visited = HashSet::from([target]);
+        let mut stack = vec![target];
+
+        while let Some(v) = stack.pop() {
+            for &u in &self.predecessors[v] {
+                edges.insert((u, v));
+                if visited.insert(u) {
+                    stack.push(u);
+                }
+            }
+        }
+
+        edges
+    }
+}
+
+/// A source and target picked by the user, and the shortest paths between them.
+#[derive(Default)]
+pub struct PathSelection {
+    pub source: Option<usize>,
+    pub target: Option<usize>,
+    /// Use edge weights (Dijkstra) instead of hop counts (BFS).
+    pub weighted: bool,
+    /// Highlight every shortest path rather than a single one.
+    pub all_paths: bool,
+
+    /// Highlighted edges, stored with the smaller endpoint first.
+    edges: HashSet<(usize, usize)>,
+    vertices: HashSet<usize>,
+    length: Option<f64>,
+    path_ct: f64,
+}
+
+impl PathSelection {
+    pub fn is_active(&self) -> bool {
+        self.source.is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.source = None;
+        self.target = None;
+        self.update_highlight(None);
+    }
+
+    /// Recomputes the highlighted paths. Endpoints that no longer exist are dropped.
+    pub fn update<T: Graph>(&mut self, graph: &T, weights: &EdgeWeights) {
+        let n = graph.vertex_ct();
+        self.source = self.source.filter(|source| *source < n);
+        self.target = self.target.filter(|target| *target < n);
+
+        let paths = self.source.map(|source| {
+            if self.weighted {
+                ShortestPaths::weighted(graph, weights, source)
+            } else {
+                ShortestPaths::unweighted(graph, source)
+            }
+        });
+        self.update_highlight(paths);
+    }
+
+    fn update_highlight(&mut self, paths: Option<ShortestPaths>) {
+        self.edges.clear();
+        self.vertices.clear();
+        self.length = None;
+        self.path_ct = 0.0;
+
+        let Some(paths) = paths else {
+            return;
+        };
+        self.vertices.insert(paths.source);
+        let Some(target) = self.target else {
+            return;
+        };
+        if paths.distance[target].is_infinite() {
+            return;
+        }
+
+        let edges = if self.all_paths {
+            paths.all_path_edges_to(target).into_iter().collect::<Vec<_>>()
+        } else {
+            let path = paths.path_to(target).unwrap_or_default();
+            path.windows(2).map(|pair| (pair[0], pair[1])).collect()
+        };
+        for (u, v) in edges {
+            self.edges.insert((u.min(v), u.max(v)));
+            self.vertices.insert(u);
+            self.vertices.insert(v);
+        }
+        self.vertices.insert(target);
+        self.length = Some(paths.distance[target]);
+        self.path_ct = paths.path_ct[target];
+    }
+
+    pub fn contains_edge(&self, u: usize, v: usize) -> bool {
+        self.edges.contains(&(u.min(v), u.max(v)))
+    }
+
+    pub fn contains_vertex(&self, u: usize) -> bool {
+        self.vertices.contains(&u)
+    }
+
+    /// One line describing the selection, for the overlay.
+    pub fn describe(&self) -> Option<String> {
+        let source = self.source?;
+        let mode = if self.weighted { "weighted" } else { "hops" };
+        let Some(target) = self.target else {
+            return Some(format!("Path from {} ({}), T to pick a target", source, mode));
+        };
+
+        Some(match self.length {
+            Some(length) if self.all_paths => format!(
+                "Path {} -> {} ({}): length {}, {} shortest paths",
+                source, target, mode, length, self.path_ct
+            ),
+            Some(length) => format!("Path {} -> {} ({}): length {}", source, target, mode, length),
+            None => format!("No path from {} to {}", source, target),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_graphs;
+
+    /// 0 - 1 - 2 - 3 - 0, with a chord 0 - 2.
+    fn square_with_chord() -> (impl Graph, EdgeWeights) {
+        let graph = test_graphs::graph(4, &[(0, 1), (1, 2), (2, 3), (3, 0), (0, 2)]);
+        let mut weights = EdgeWeights::default();
+        for (u, v, weight) in [(0, 1, 1.0), (1, 2, 1.0), (2, 3, 3.0), (3, 0, 1.0), (0, 2, 2.5)] {
+            weights.set(u, v, weight);
+        }
+        (graph, weights)
+    }
+
+    #[test]
+    fn grid_hop_distances() {
+        let grid = test_graphs::grid(3, 3);
+        let paths = ShortestPaths::unweighted(&grid, 0);
+        assert_eq!(paths.distance, [0.0, 1.0, 2.0, 1.0, 2.0, 3.0, 2.0, 3.0, 4.0]);
+        // Choosing which 2 of the 4 steps go right.
+        assert_eq!(paths.path_ct[8], 6.0);
+        assert_eq!(paths.path_ct[4], 2.0);
+
+        let path = paths.path_to(8).unwrap();
+        assert_eq!(path.len(), 5);
+        assert_eq!((path[0], path[4]), (0, 8));
+        for pair in path.windows(2) {
+            assert!(grid.adjacent(pair[0], pair[1]));
+        }
+
+        // Every edge of the grid lies on a shortest path across it.
+        let edges = paths.all_path_edges_to(8);
+        assert_eq!(edges.len(), 12);
+        assert!(edges.iter().all(|(u, v)| paths.distance[*v] == paths.distance[*u] + 1.0));
+    }
+
+    #[test]
+    fn unreachable_target() {
+        let graph = test_graphs::graph(3, &[(0, 1)]);
+        let paths = ShortestPaths::unweighted(&graph, 0);
+        assert!(paths.distance[2].is_infinite());
+        assert_eq!(paths.path_ct[2], 0.0);
+        assert_eq!(paths.path_to(2), None);
+        assert!(paths.all_path_edges_to(2).is_empty());
+    }
+
+    #[test]
+    fn weighted_distances() {
+        let (graph, mut weights) = square_with_chord();
+        let paths = ShortestPaths::weighted(&graph, &weights, 0);
+        assert_eq!(paths.distance, [0.0, 1.0, 2.0, 1.0]);
+        assert_eq!(paths.path_to(2), Some(vec![0, 1, 2]));
+        assert_eq!(paths.path_ct[2], 1.0);
+
+        // Tying the way round through 3 gives a second shortest path.
+        weights.set(2, 3, 1.0);
+        let paths = ShortestPaths::weighted(&graph, &weights, 0);
+        assert_eq!(paths.path_ct[2], 2.0);
+        assert_eq!(paths.all_path_edges_to(2), HashSet::from([(0, 1), (1, 2), (0, 3), (3, 2)]));
+
+        // Negative weights count as 0.
+        weights.set(0, 2, -1.0);
+        let paths = ShortestPaths::weighted(&graph, &weights, 0);
+        assert_eq!(paths.distance[2], 0.0);
+        assert_eq!(paths.path_to(2), Some(vec![0, 2]));
+    }
+
+    #[test]
+    fn selection_highlights_paths() {
+        let (graph, weights) = square_with_chord();
+        let mut selection = PathSelection {
+            source: Some(0),
+            target: Some(2),
+            ..Default::default()
+        };
+        selection.update(&graph, &weights);
+        assert!(selection.contains_edge(2, 0));
+        assert!(!selection.contains_vertex(1));
+
+        selection.weighted = true;
+        selection.update(&graph, &weights);
+        assert!(selection.contains_edge(1, 0) && selection.contains_edge(2, 1));
+        assert!(!selection.contains_edge(0, 2));
+        assert_eq!(selection.describe().unwrap(), "Path 0 -> 2 (weighted): length 2");
+
+        // Endpoints past the end of a shrunk graph are dropped.
+        let graph = test_graphs::path(2);
+        selection.update(&graph, &weights);
+        assert_eq!((selection.source, selection.target), (Some(0), None));
+        assert!(selection.contains_vertex(0) && !selection.contains_edge(0, 1));
+    }
+}
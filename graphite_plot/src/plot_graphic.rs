@@ -3,12 +3,12 @@ use cgmath::Point2;
 use wgpu::{util::DeviceExt, PipelineLayout, RenderPass};
 use winit::{dpi::PhysicalSize, event::WindowEvent};
 
-use crate::{camera::{Camera, CameraUniform}, plot_window::PlotWindowState};
+use crate::{camera::{Camera, CameraUniform}, capture::{self, CopiedFrame}, draw::Color, plot_window::PlotWindowState};
 
 pub trait PlotGraphic {
     fn init(&mut self, state: &PlotWindowState);
     fn tick(&mut self, delta_t: instant::Duration);
-    fn input(&mut self, event: &WindowEvent, window_size: PhysicalSize<u32>) -> bool;
+    fn input(&mut self, event: &WindowEvent, window_state: &PlotWindowState) -> bool;
     // fn draw(&mut self, state: &mut PlotWindowState);
     fn update(&mut self, window_state: &mut PlotWindowState, rewrite_data: bool);
     fn render(&mut self, window_state: &mut PlotWindowState) -> Result<(), wgpu::SurfaceError>;
@@ -17,6 +17,11 @@ pub trait PlotGraphic {
 pub trait PlotGraphicElement<T> {
     fn update(&mut self, window_state: &PlotWindowState, data: &T);
     fn render(&self, graphic_state: &PlotGraphicState<T>, render_pass: &mut RenderPass);
+
+    /// Overlay elements are interface drawn over the plot, and left out of captured frames.
+    fn is_overlay(&self) -> bool {
+        false
+    }
 }
 
 pub struct PlotGraphicState<T> {
@@ -24,7 +29,11 @@ pub struct PlotGraphicState<T> {
     pub camera: Camera,
     pub camera_uniform: CameraUniform,
     pub camera_buffer: wgpu::Buffer,
+    /// For elements that need more bind groups than the camera's, and so their own layout.
+    pub camera_bind_group_layout: wgpu::BindGroupLayout,
     pub camera_bind_group: wgpu::BindGroup,
+    /// What every frame is cleared to before the elements are drawn.
+    pub background: Color,
 
     elements: Vec<Box<dyn PlotGraphicElement<T>>>,
 }
@@ -33,11 +42,12 @@ impl<T> PlotGraphicState<T> {
     pub fn new(window_state: &PlotWindowState) -> Self {
         let camera = Camera {
             center: Point2::new(0.0, 0.0),
-            size: PhysicalSize { width: 1.0 * window_state.config.width as f32 / window_state.config.height as f32, height: 1.0 }
+            size: PhysicalSize { width: 1.0 * window_state.config.width as f32 / window_state.config.height as f32, height: 1.0 },
+            rotation: 0.0,
         };
 
         let mut camera_uniform = CameraUniform::new(camera.size);
-        camera_uniform.update(&camera);
+        camera_uniform.update(&camera, PhysicalSize::new(window_state.config.width, window_state.config.height));
 
         let camera_buffer = window_state.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Camera Buffer"),
@@ -84,12 +94,16 @@ impl<T> PlotGraphicState<T> {
             camera,
             camera_uniform,
             camera_buffer,
+            camera_bind_group_layout,
             camera_bind_group,
+            background: Color::rgb(1.0, 1.0, 1.0),
             elements,
         }
     }
 
-    pub fn render(&self, window_state: &PlotWindowState) -> Result<(), wgpu::SurfaceError>  {
+    /// Draws a frame and, with `capture` set, draws the plot again without its overlays into
+    /// a texture and copies that for `Capturer::read_back`.
+    pub fn render(&self, window_state: &mut PlotWindowState, capture: bool) -> Result<Option<CopiedFrame>, wgpu::SurfaceError>  {
         let output = window_state.surface.get_current_texture()?;
         let view = output
             .texture
@@ -101,38 +115,68 @@ impl<T> PlotGraphicState<T> {
                 label: Some("Render Encoder"),
             });
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 1.0,
-                            g: 1.0,
-                            b: 1.0,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
+        #[cfg(feature = "egui")]
+        window_state.gui.prepare(&window_state.device, &window_state.queue, &mut encoder);
 
+        {
+            // egui paints into the same pass and needs it to outlive its borrows.
+            let mut render_pass = self.begin_pass(window_state, &mut encoder, &view).forget_lifetime();
             for element in &self.elements {
                 element.render(self, &mut render_pass);
             }
 
+            #[cfg(feature = "egui")]
+            window_state.gui.paint(&mut render_pass);
+
             // println!("Rendered!");
         }
 
+        let copied_frame = capture.then(|| {
+            let texture = capture::capture_texture(&window_state.device, &window_state.config);
+            let capture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            {
+                let mut render_pass = self.begin_pass(window_state, &mut encoder, &capture_view);
+                for element in self.elements.iter().filter(|element| !element.is_overlay()) {
+                    element.render(self, &mut render_pass);
+                }
+            }
+            capture::copy_frame(&window_state.device, &mut encoder, &texture)
+        });
+
         window_state.queue.submit(std::iter::once(encoder.finish()));
         output.present();
+        #[cfg(feature = "egui")]
+        window_state.gui.finish();
 
-        Ok(())
+        Ok(copied_frame)
+    }
+
+    /// Starts a pass that clears `view` to the background, multisampled if the window is.
+    fn begin_pass<'e>(&self, window_state: &PlotWindowState, encoder: &'e mut wgpu::CommandEncoder, view: &wgpu::TextureView) -> RenderPass<'e> {
+        let (target, resolve_target) = window_state.color_targets(view);
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: self.background.r as f64,
+                        g: self.background.g as f64,
+                        b: self.background.b as f64,
+                        a: 1.0,
+                    }),
+                    // Only the resolved frame is needed once the pass is done.
+                    store: match resolve_target {
+                        Some(_) => wgpu::StoreOp::Discard,
+                        None => wgpu::StoreOp::Store,
+                    },
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        })
     }
 
     pub fn add_element(&mut self, element: impl PlotGraphicElement<T> + 'static) {
@@ -147,7 +191,7 @@ impl<T> PlotGraphicState<T> {
 
     pub fn update(&mut self, window_state: &PlotWindowState) {
         self.camera.size.width = self.camera.size.height * window_state.config.width as f32 / window_state.config.height as f32;
-        self.camera_uniform.update(&self.camera);
+        self.camera_uniform.update(&self.camera, PhysicalSize::new(window_state.config.width, window_state.config.height));
         window_state.queue.write_buffer(
             &self.camera_buffer,
             0,
@@ -0,0 +1,199 @@
+use instant::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, Sender, TryRecvError},
+    },
+    thread::{self, JoinHandle},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+const WORKER_SLEEP: Duration = Duration::from_millis(1);
+
+pub trait Simulation: Send + 'static {
+    type Snapshot: Default + Send + 'static;
+
+    fn tick(&mut self, delta_t: Duration);
+    fn write_snapshot(&self, snapshot: &mut Self::Snapshot);
+    /// Whether `tick` would do nothing until the simulation receives another command.
+    fn is_idle(&self) -> bool;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+type Command<S> = Box<dyn FnOnce(&mut S) + Send>;
+
+#[cfg(not(target_arch = "wasm32"))]
+struct SharedSnapshot<T> {
+    snapshot: T,
+    fresh: bool,
+}
+
+/// Owns a simulation and hands out snapshots of it for rendering. The simulation runs in
+/// place until `start` is called; after that it runs on its own thread on native targets and
+/// is only reachable through commands. On wasm it always runs in place.
+pub struct SimulationRunner<S: Simulation> {
+    local: Option<S>,
+    dirty: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    worker: Option<Worker<S>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct Worker<S: Simulation> {
+    commands: Option<Sender<Command<S>>>,
+    shared: Arc<Mutex<SharedSnapshot<S::Snapshot>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<S: Simulation> SimulationRunner<S> {
+    pub fn new(simulation: S) -> Self {
+        Self {
+            local: Some(simulation),
+            dirty: true,
+            #[cfg(not(target_arch = "wasm32"))]
+            worker: None,
+        }
+    }
+
+    /// Moves the simulation onto a background thread. Does nothing on wasm.
+    pub fn start(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(simulation) = self.local.take() {
+            let (commands, receiver) = mpsc::channel();
+            let shared = Arc::new(Mutex::new(SharedSnapshot {
+                snapshot: S::Snapshot::default(),
+                fresh: false,
+            }));
+            let worker_shared = shared.clone();
+            let thread = thread::Builder::new()
+                .name("simulation".to_string())
+                .spawn(move || run_worker(simulation, receiver, worker_shared))
+                .expect("Couldn't spawn simulation thread");
+
+            self.worker = Some(Worker {
+                commands: Some(commands),
+                shared,
+                thread: Some(thread),
+            });
+        }
+    }
+
+    /// Applies `command` to the simulation, either immediately or on the simulation thread.
+    /// Commands are applied in the order they are sent.
+    pub fn send(&mut self, command: impl FnOnce(&mut S) + Send + 'static) {
+        if let Some(simulation) = self.local.as_mut() {
+            command(simulation);
+            self.dirty = true;
+            return;
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(commands) = self.worker.as_ref().and_then(|worker| worker.commands.as_ref())
+            && commands.send(Box::new(command)).is_err()
+        {
+            log::error!("Simulation thread has stopped");
+        }
+    }
+
+    /// Ticks an in-place simulation. A threaded simulation keeps its own time.
+    pub fn tick(&mut self, delta_t: Duration) {
+        if let Some(simulation) = self.local.as_mut() {
+            simulation.tick(delta_t);
+            self.dirty = true;
+        }
+    }
+
+    /// Replaces `snapshot` with the newest published state if it changed since the last call.
+    pub fn latest(&mut self, snapshot: &mut S::Snapshot) -> bool {
+        if let Some(simulation) = self.local.as_ref() {
+            if self.dirty {
+                simulation.write_snapshot(snapshot);
+                self.dirty = false;
+                return true;
+            }
+            return false;
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(worker) = self.worker.as_ref() {
+            let mut shared = worker.shared.lock().unwrap();
+            if shared.fresh {
+                std::mem::swap(&mut shared.snapshot, snapshot);
+                shared.fresh = false;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// The simulation, if it hasn't been moved to a background thread.
+    pub fn local_mut(&mut self) -> Option<&mut S> {
+        self.dirty = true;
+        self.local.as_mut()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<S: Simulation> Drop for Worker<S> {
+    fn drop(&mut self) {
+        // Closing the channel is what tells the worker to stop.
+        self.commands.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn run_worker<S: Simulation>(
+    mut simulation: S,
+    receiver: Receiver<Command<S>>,
+    shared: Arc<Mutex<SharedSnapshot<S::Snapshot>>>,
+) {
+    let mut back_buffer = S::Snapshot::default();
+    let mut last_tick = instant::Instant::now();
+    publish(&simulation, &mut back_buffer, &shared);
+
+    loop {
+        if simulation.is_idle() {
+            match receiver.recv() {
+                Ok(command) => command(&mut simulation),
+                Err(_) => return,
+            }
+            last_tick = instant::Instant::now();
+        }
+
+        loop {
+            match receiver.try_recv() {
+                Ok(command) => command(&mut simulation),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        }
+
+        let now = instant::Instant::now();
+        simulation.tick(now.duration_since(last_tick));
+        last_tick = now;
+
+        publish(&simulation, &mut back_buffer, &shared);
+
+        thread::sleep(WORKER_SLEEP);
+    }
+}
+
+/// Writes the simulation state into the back buffer and swaps it with the shared one, so
+/// the lock is only held for the swap.
+#[cfg(not(target_arch = "wasm32"))]
+fn publish<S: Simulation>(
+    simulation: &S,
+    back_buffer: &mut S::Snapshot,
+    shared: &Mutex<SharedSnapshot<S::Snapshot>>,
+) {
+    simulation.write_snapshot(back_buffer);
+    let mut shared = shared.lock().unwrap();
+    std::mem::swap(&mut shared.snapshot, back_buffer);
+    shared.fresh = true;
+}
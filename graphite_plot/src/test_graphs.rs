@@ -0,0 +1,54 @@
+//! Small graphs with known properties, for tests.
+
+use gscientific::graph::Graph;
+
+use crate::analysis::graph_from_edges;
+
+/// A graph on `vertex_ct` vertices with exactly `edges`.
+pub fn graph(vertex_ct: usize, edges: &[(usize, usize)]) -> impl Graph + use<> {
+    graph_from_edges(vertex_ct, edges)
+}
+
+/// 0 - 1 - ... - (vertex_ct - 1).
+pub fn path(vertex_ct: usize) -> impl Graph + use<> {
+    let edges = (1..vertex_ct).map(|v| (v - 1, v)).collect::<Vec<_>>();
+    graph(vertex_ct, &edges)
+}
+
+/// Vertex 0 joined to `leaf_ct` leaves.
+pub fn star(leaf_ct: usize) -> impl Graph + use<> {
+    let edges = (1..=leaf_ct).map(|v| (0, v)).collect::<Vec<_>>();
+    graph(leaf_ct + 1, &edges)
+}
+
+/// Two cliques of `clique_size` vertices, 0.. and `clique_size`.., joined by an edge between
+/// their last and first vertices.
+pub fn barbell(clique_size: usize) -> impl Graph + use<> {
+    let mut edges = Vec::new();
+    for offset in [0, clique_size] {
+        for u in 0..clique_size {
+            for v in u + 1..clique_size {
+                edges.push((offset + u, offset + v));
+            }
+        }
+    }
+    edges.push((clique_size - 1, clique_size));
+    graph(2 * clique_size, &edges)
+}
+
+/// A `width` by `height` grid, numbered row by row.
+pub fn grid(width: usize, height: usize) -> impl Graph + use<> {
+    let mut edges = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let v = y * width + x;
+            if x + 1 < width {
+                edges.push((v, v + 1));
+            }
+            if y + 1 < height {
+                edges.push((v, v + width));
+            }
+        }
+    }
+    graph(width * height, &edges)
+}
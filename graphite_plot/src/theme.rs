@@ -0,0 +1,231 @@
+use std::{fs, io, path::Path};
+
+use crate::draw::Color;
+
+/// Colors the plot is drawn in. The 3D plot highlights no hovered vertices, so it leaves
+/// `hover` unused.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub background: Color,
+    /// Fill of vertices that aren't given a color of their own.
+    pub vertex_fill: Color,
+    pub vertex_outline: Color,
+    /// How much of a vertex's radius its outline takes up, from 0 to 1.
+    pub outline_thickness: f32,
+    /// Edge color, including how transparent edges are.
+    pub edge: Color,
+    pub hover: Color,
+    /// The selected vertex or edge, or in 3D the vertex being dragged.
+    pub selection: Color,
+    /// Legend, status and panel text.
+    pub text: Color,
+    /// What panels put between their text and the graph.
+    pub panel: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::light()
+    }
+}
+
+impl Theme {
+    pub fn light() -> Self {
+        Self {
+            name: "light".to_string(),
+            background: Color::rgb(1.0, 1.0, 1.0),
+            vertex_fill: Color::rgb(0.1, 0.7, 0.1),
+            vertex_outline: Color::rgb(0.0, 0.0, 0.0),
+            outline_thickness: 0.2,
+            edge: Color::rgba(0.0, 0.0, 0.0, 0.5),
+            hover: Color::rgb(0.3, 0.5, 0.9),
+            selection: Color::rgb(0.1, 0.3, 0.8),
+            text: Color::rgb(0.0, 0.0, 0.0),
+            panel: Color::rgba(1.0, 1.0, 1.0, 0.85),
+        }
+    }
+
+    pub fn dark() -> Self {
+        Self {
+            name: "dark".to_string(),
+            background: Color::hex(0x1E1E1E),
+            vertex_fill: Color::hex(0x59A14F),
+            vertex_outline: Color::hex(0xD0D0D0),
+            outline_thickness: 0.15,
+            edge: Color { a: 0.35, ..Color::rgb(1.0, 1.0, 1.0) },
+            hover: Color::hex(0x8CB4FF),
+            selection: Color::hex(0xF28E2B),
+            text: Color::hex(0xE8E8E8),
+            panel: Color { a: 0.85, ..Color::hex(0x2A2A2A) },
+        }
+    }
+
+    /// High contrast and mostly gray, for paper and projectors.
+    pub fn print() -> Self {
+        Self {
+            name: "print".to_string(),
+            background: Color::rgb(1.0, 1.0, 1.0),
+            vertex_fill: Color::hex(0xBBBBBB),
+            vertex_outline: Color::rgb(0.0, 0.0, 0.0),
+            outline_thickness: 0.3,
+            edge: Color::rgb(0.0, 0.0, 0.0),
+            hover: Color::hex(0x4E79A7),
+            selection: Color::hex(0xE15759),
+            text: Color::rgb(0.0, 0.0, 0.0),
+            panel: Color::rgba(1.0, 1.0, 1.0, 0.95),
+        }
+    }
+
+    /// The built-in themes, in the order switching themes goes through them.
+    pub fn builtins() -> Vec<Theme> {
+        vec![Self::light(), Self::dark(), Self::print()]
+    }
+
+    pub fn builtin(name: &str) -> Option<Theme> {
+        Self::builtins().into_iter().find(|theme| theme.name == name)
+    }
+}
+
+/// Reads a theme, one setting per line:
+///
+/// ```text
+/// base = dark
+/// background = #101018
+/// edge = #FFFFFF40
+/// outline_thickness = 0.1
+/// ```
+///
+/// Colors are sRGB hex codes with an optional alpha byte. Settings left out are taken from
+/// the `base` theme, light unless given. The theme is named after the file unless it sets
+/// `name`. Blank lines and lines starting with `#` are skipped.
+pub fn read_theme(path: impl AsRef<Path>) -> io::Result<Theme> {
+    let path = path.as_ref();
+    let contents = fs::read_to_string(path)?;
+    let invalid = |line: usize, message: String| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line + 1, message))
+    };
+
+    let mut settings = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid(i, "expected <setting> = <value>".to_string()))?;
+        settings.push((i, key.trim(), value.trim()));
+    }
+
+    // The base goes first wherever it's written, so the other settings override it.
+    let mut theme = match settings.iter().find(|(_, key, _)| *key == "base") {
+        Some((i, _, base)) => {
+            Theme::builtin(base).ok_or_else(|| invalid(*i, format!("unknown theme \"{}\"", base)))?
+        }
+        None => Theme::light(),
+    };
+    theme.name = path.file_stem().map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+
+    for (i, key, value) in settings {
+        let color = || parse_color(value).ok_or_else(|| invalid(i, format!("invalid color \"{}\"", value)));
+        match key {
+            "base" => {}
+            "name" => theme.name = value.to_string(),
+            "background" => theme.background = color()?,
+            "vertex_fill" => theme.vertex_fill = color()?,
+            "vertex_outline" => theme.vertex_outline = color()?,
+            "outline_thickness" => {
+                theme.outline_thickness = value
+                    .parse()
+                    .ok()
+                    .filter(|thickness: &f32| (0.0..=1.0).contains(thickness))
+                    .ok_or_else(|| invalid(i, format!("invalid thickness \"{}\"", value)))?;
+            }
+            "edge" => theme.edge = color()?,
+            "hover" => theme.hover = color()?,
+            "selection" => theme.selection = color()?,
+            "text" => theme.text = color()?,
+            "panel" => theme.panel = color()?,
+            _ => return Err(invalid(i, format!("unknown setting \"{}\"", key))),
+        }
+    }
+
+    Ok(theme)
+}
+
+/// Parses `#RRGGBB` or `#RRGGBBAA`.
+fn parse_color(text: &str) -> Option<Color> {
+    let digits = text.strip_prefix('#')?;
+    if !matches!(digits.len(), 6 | 8) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let code = u32::from_str_radix(digits, 16).ok()?;
+    Some(match digits.len() {
+        6 => Color::hex(code),
+        _ => Color { a: (code & 0xff) as f32 / 255.0, ..Color::hex(code >> 8) },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads `contents` as a theme file named `name`.
+    fn read(name: &str, contents: &str) -> io::Result<Theme> {
+        let path = std::env::temp_dir().join(format!("{}_{}.theme", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        let theme = read_theme(&path);
+        fs::remove_file(&path).unwrap();
+        theme
+    }
+
+    fn error(name: &str, contents: &str) -> String {
+        read(name, contents).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn the_base_goes_first_wherever_it_is_written() {
+        let theme = read("base_last", "background = #101018\n\n# dark otherwise\nbase = dark\n").unwrap();
+        assert_eq!(theme.background, Color::hex(0x101018));
+        assert_eq!(theme.vertex_fill, Theme::dark().vertex_fill);
+        assert_eq!(theme.name, format!("base_last_{}", std::process::id()));
+
+        let theme = read("no_base", "name = mine\nedge = #000000\n").unwrap();
+        assert_eq!(theme, Theme { name: "mine".to_string(), edge: Color::hex(0x000000), ..Theme::light() });
+    }
+
+    #[test]
+    fn colors_take_an_optional_alpha_byte() {
+        let theme = read("alpha", "edge = #FFFFFF40\npanel = #2a2a2aff\n").unwrap();
+        assert_eq!(theme.edge, Color { a: 64.0 / 255.0, ..Color::hex(0xFFFFFF) });
+        assert_eq!(theme.panel, Color::hex(0x2A2A2A));
+
+        assert_eq!(parse_color("#FFFFF"), None);
+        assert_eq!(parse_color("#FFFFFFF"), None);
+        assert_eq!(parse_color("FFFFFF"), None);
+        assert_eq!(parse_color("#+FFFFF"), None);
+        assert_eq!(parse_color("#+FFFFFFF"), None);
+        assert_eq!(parse_color("#12345G"), None);
+        assert_eq!(error("bad_color", "text = #12 34 56\n"), "line 1: invalid color \"#12 34 56\"");
+    }
+
+    #[test]
+    fn outlines_take_up_none_to_all_of_the_radius() {
+        assert_eq!(read("thin", "outline_thickness = 0\n").unwrap().outline_thickness, 0.0);
+        assert_eq!(read("thick", "outline_thickness = 1\n").unwrap().outline_thickness, 1.0);
+        for thickness in ["-0.1", "1.5", "NaN", "wide"] {
+            assert_eq!(
+                error("bad_thickness", &format!("outline_thickness = {}\n", thickness)),
+                format!("line 1: invalid thickness \"{}\"", thickness)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_settings_and_bases() {
+        assert_eq!(error("unknown_key", "base = dark\nglow = #FFFFFF\n"), "line 2: unknown setting \"glow\"");
+        assert_eq!(error("unknown_base", "edge = #000000\nbase = neon\n"), "line 2: unknown theme \"neon\"");
+        assert_eq!(error("no_value", "background #FFFFFF\n"), "line 1: expected <setting> = <value>");
+    }
+}
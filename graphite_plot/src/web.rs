@@ -0,0 +1,312 @@
+use std::{cell::RefCell, rc::Rc};
+
+use cgmath::Vector2;
+use js_sys::{Array, Function, JSON, Object, Reflect};
+use wasm_bindgen::prelude::*;
+use winit::{
+    event::WindowEvent,
+    event_loop::{ControlFlow, EventLoop},
+    platform::web::EventLoopExtWebSys,
+};
+
+use crate::{
+    PlotOptions,
+    draw::Color,
+    edge_list::{EdgeList, parse_edge_list},
+    force_plot::ForcePlot,
+    picking::Pick,
+    plot_window::{PlotGraphic, PlotWindow, PlotWindowState},
+    theme::Theme,
+};
+
+/// What the JavaScript API needs from a plot, without naming its graph type.
+pub trait EmbeddedPlot: PlotGraphic {
+    /// Replaces the graph and restarts the layout.
+    fn load_edge_list(&mut self, edge_list: &EdgeList);
+    fn add_vertex(&mut self) -> usize;
+    fn remove_vertex(&mut self, v: usize) -> Result<(), String>;
+    fn add_edge(&mut self, u: usize, v: usize, weight: Option<f64>) -> Result<(), String>;
+    fn remove_edge(&mut self, u: usize, v: usize) -> Result<(), String>;
+    fn set_vertex_color(&mut self, v: usize, color: Color) -> Result<(), String>;
+    fn set_theme(&mut self, theme: Theme);
+    fn vertex_ct(&self) -> usize;
+    fn positions(&mut self) -> &[Vector2<f32>];
+    fn hovered_pick(&self) -> Option<Pick>;
+    fn selected_pick(&self) -> Option<Pick>;
+}
+
+#[derive(Default)]
+struct Callbacks {
+    hover: RefCell<Option<Function>>,
+    select: RefCell<Option<Function>>,
+}
+
+impl Callbacks {
+    /// Calls `callback` after cloning it out, so it may replace itself.
+    fn call(callback: &RefCell<Option<Function>>, pick: Option<Pick>) {
+        let callback = callback.borrow().clone();
+        if let Some(callback) = callback {
+            if let Err(error) = callback.call1(&JsValue::NULL, &pick_to_js(pick)) {
+                log::warn!("Callback failed: {:?}", error);
+            }
+        }
+    }
+}
+
+/// Drives the plot the window owns while JavaScript holds on to it too. The plot is only
+/// borrowed for the length of each call, so callbacks may call back into the API.
+struct SharedPlot {
+    plot: Rc<RefCell<dyn EmbeddedPlot>>,
+    callbacks: Rc<Callbacks>,
+}
+
+impl SharedPlot {
+    /// Runs `f` on the plot, then calls the callbacks for whatever it hovered or selected.
+    fn notifying<R>(&mut self, f: impl FnOnce(&mut dyn EmbeddedPlot) -> R) -> R {
+        let (result, hovered, selected) = {
+            let mut plot = self.plot.borrow_mut();
+            let hovered = plot.hovered_pick();
+            let selected = plot.selected_pick();
+            let result = f(&mut *plot);
+            (
+                result,
+                Some(plot.hovered_pick()).filter(|pick| *pick != hovered),
+                Some(plot.selected_pick()).filter(|pick| *pick != selected),
+            )
+        };
+
+        if let Some(pick) = hovered {
+            Callbacks::call(&self.callbacks.hover, pick);
+        }
+        if let Some(pick) = selected {
+            Callbacks::call(&self.callbacks.select, pick);
+        }
+        result
+    }
+}
+
+impl PlotGraphic for SharedPlot {
+    fn init(&mut self, state: &PlotWindowState) {
+        self.plot.borrow_mut().init(state);
+    }
+
+    fn tick(&mut self, delta_t: instant::Duration) {
+        self.plot.borrow_mut().tick(delta_t);
+    }
+
+    fn input(&mut self, event: &WindowEvent, window_state: &PlotWindowState) -> bool {
+        self.notifying(|plot| plot.input(event, window_state))
+    }
+
+    #[cfg(feature = "egui")]
+    fn gui(&mut self, context: &egui::Context) {
+        self.plot.borrow_mut().gui(context);
+    }
+
+    // Hover picks are read back here, a frame after the cursor moves.
+    fn update(&mut self, window_state: &mut PlotWindowState, rewrite_data: bool) {
+        self.notifying(|plot| plot.update(window_state, rewrite_data));
+    }
+
+    fn render(&mut self, window_state: &mut PlotWindowState) -> Result<(), wgpu::SurfaceError> {
+        self.plot.borrow_mut().render(window_state)
+    }
+}
+
+/// A plot embedded in a web page.
+///
+/// Picks passed to callbacks are `{ kind: "vertex", vertex }`, `{ kind: "edge", source, target }`
+/// or `null`.
+#[wasm_bindgen]
+pub struct Plot {
+    plot: Rc<RefCell<dyn EmbeddedPlot>>,
+    callbacks: Rc<Callbacks>,
+}
+
+#[wasm_bindgen]
+impl Plot {
+    /// Plots into the canvas with id `element_id`, or into a new canvas appended to the
+    /// element with that id. A page can only hold one plot.
+    #[wasm_bindgen(constructor)]
+    pub fn new(element_id: &str) -> Result<Plot, JsError> {
+        let event_loop = EventLoop::new().map_err(|error| JsError::new(&error.to_string()))?;
+        event_loop.set_control_flow(ControlFlow::Poll);
+
+        let mut options = PlotOptions::default();
+        let graph = crate::build_graph(&mut options);
+        let plot = Rc::new(RefCell::new(ForcePlot::with_options(graph, &options)));
+        let callbacks = Rc::new(Callbacks::default());
+        let shared = SharedPlot {
+            plot: plot.clone(),
+            callbacks: callbacks.clone(),
+        };
+        event_loop.spawn_app(
+            PlotWindow::new(shared)
+                .with_msaa(options.render.msaa_samples)
+                .in_element(element_id),
+        );
+
+        Ok(Plot { plot, callbacks })
+    }
+
+    /// Replaces the graph with `{ "vertex_ct": n, "edges": [[u, v], [u, v, weight], ...] }`,
+    /// given as a JSON string or an object. Without `vertex_ct`, the largest vertex named
+    /// sets the count.
+    #[wasm_bindgen(js_name = loadJson)]
+    pub fn load_json(&self, json: JsValue) -> Result<(), JsError> {
+        let value = match json.as_string() {
+            Some(text) => JSON::parse(&text).map_err(|_| JsError::new("invalid JSON"))?,
+            None => json,
+        };
+        let edge_list = edge_list_from_js(&value).map_err(|error| JsError::new(&error))?;
+        self.plot.borrow_mut().load_edge_list(&edge_list);
+        Ok(())
+    }
+
+    /// Replaces the graph with one edge per line, as `u v` or `u v weight`.
+    #[wasm_bindgen(js_name = loadEdgeList)]
+    pub fn load_edge_list(&self, text: &str) -> Result<(), JsError> {
+        let edge_list = parse_edge_list(text).map_err(|error| JsError::new(&error))?;
+        self.plot.borrow_mut().load_edge_list(&edge_list);
+        Ok(())
+    }
+
+    /// Returns the index of the new vertex.
+    #[wasm_bindgen(js_name = addVertex)]
+    pub fn add_vertex(&self) -> usize {
+        self.plot.borrow_mut().add_vertex()
+    }
+
+    /// Vertices after `vertex` move down one index.
+    #[wasm_bindgen(js_name = removeVertex)]
+    pub fn remove_vertex(&self, vertex: usize) -> Result<(), JsError> {
+        self.plot.borrow_mut().remove_vertex(vertex).map_err(|error| JsError::new(&error))
+    }
+
+    /// Sets the weight if the edge is already there.
+    #[wasm_bindgen(js_name = addEdge)]
+    pub fn add_edge(&self, source: usize, target: usize, weight: Option<f64>) -> Result<(), JsError> {
+        self.plot
+            .borrow_mut()
+            .add_edge(source, target, weight)
+            .map_err(|error| JsError::new(&error))
+    }
+
+    #[wasm_bindgen(js_name = removeEdge)]
+    pub fn remove_edge(&self, source: usize, target: usize) -> Result<(), JsError> {
+        self.plot
+            .borrow_mut()
+            .remove_edge(source, target)
+            .map_err(|error| JsError::new(&error))
+    }
+
+    /// Takes a CSS hex color, `#rrggbb`.
+    #[wasm_bindgen(js_name = setVertexColor)]
+    pub fn set_vertex_color(&self, vertex: usize, color: &str) -> Result<(), JsError> {
+        let color = parse_hex_color(color).ok_or_else(|| JsError::new(&format!("invalid color \"{}\", expected #rrggbb", color)))?;
+        self.plot
+            .borrow_mut()
+            .set_vertex_color(vertex, color)
+            .map_err(|error| JsError::new(&error))
+    }
+
+    /// Switches to a built-in theme: `light`, `dark` or `print`.
+    #[wasm_bindgen(js_name = setTheme)]
+    pub fn set_theme(&self, name: &str) -> Result<(), JsError> {
+        let theme = Theme::builtin(name).ok_or_else(|| JsError::new(&format!("unknown theme \"{}\"", name)))?;
+        self.plot.borrow_mut().set_theme(theme);
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = vertexCount)]
+    pub fn vertex_count(&self) -> usize {
+        self.plot.borrow().vertex_ct()
+    }
+
+    /// Vertex positions as `[x0, y0, x1, y1, ...]`.
+    pub fn positions(&self) -> Vec<f32> {
+        self.plot
+            .borrow_mut()
+            .positions()
+            .iter()
+            .flat_map(|position| [position.x, position.y])
+            .collect()
+    }
+
+    /// Calls `callback` with the pick under the cursor whenever it changes.
+    #[wasm_bindgen(js_name = onHover)]
+    pub fn on_hover(&self, callback: Option<Function>) {
+        *self.callbacks.hover.borrow_mut() = callback;
+    }
+
+    /// Calls `callback` with the clicked vertex or edge whenever the selection changes.
+    #[wasm_bindgen(js_name = onSelect)]
+    pub fn on_select(&self, callback: Option<Function>) {
+        *self.callbacks.select.borrow_mut() = callback;
+    }
+}
+
+fn edge_list_from_js(value: &JsValue) -> Result<EdgeList, String> {
+    let get = |object: &JsValue, key: &str| Reflect::get(object, &JsValue::from_str(key)).unwrap_or(JsValue::UNDEFINED);
+    let vertex = |value: JsValue| match value.as_f64() {
+        Some(v) if v >= 0.0 && v.fract() == 0.0 => Ok(v as usize),
+        _ => Err(format!("invalid vertex {:?}", value)),
+    };
+
+    let vertex_ct = match get(value, "vertex_ct") {
+        ct if ct.is_undefined() => None,
+        ct => Some(vertex(ct)?),
+    };
+    let edges = get(value, "edges");
+    if !Array::is_array(&edges) {
+        return Err("expected an \"edges\" array".to_string());
+    }
+
+    let mut list = Vec::new();
+    for edge in Array::from(&edges).iter() {
+        let invalid = || format!("expected [u, v] or [u, v, weight], got {:?}", edge);
+        if !Array::is_array(&edge) {
+            return Err(invalid());
+        }
+        let fields = Array::from(&edge);
+        let weight = match fields.length() {
+            2 => None,
+            3 => Some(fields.get(2).as_f64().ok_or_else(invalid)?),
+            _ => return Err(invalid()),
+        };
+        list.push((vertex(fields.get(0))?, vertex(fields.get(1))?, weight));
+    }
+
+    EdgeList::new(vertex_ct, list)
+}
+
+fn pick_to_js(pick: Option<Pick>) -> JsValue {
+    let Some(pick) = pick else {
+        return JsValue::NULL;
+    };
+
+    let object = Object::new();
+    let set = |key: &str, value: JsValue| {
+        Reflect::set(&object, &JsValue::from_str(key), &value).expect("Couldn't set a property of a plain object");
+    };
+    match pick {
+        Pick::Vertex(v) => {
+            set("kind", JsValue::from_str("vertex"));
+            set("vertex", JsValue::from(v as f64));
+        }
+        Pick::Edge(u, v) => {
+            set("kind", JsValue::from_str("edge"));
+            set("source", JsValue::from(u as f64));
+            set("target", JsValue::from(v as f64));
+        }
+    }
+    object.into()
+}
+
+fn parse_hex_color(text: &str) -> Option<Color> {
+    let digits = text.strip_prefix('#')?;
+    if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(digits, 16).ok().map(Color::hex)
+}
@@ -3,23 +3,46 @@ struct CameraUniform {
     view_proj: mat4x4<f32>,
     scale_proj: mat4x4<f32>,
     aspect: f32,
+    // Size of the surface in pixels.
+    viewport: vec2<f32>,
+    // What distant lines and points fade towards.
+    fog_color: vec3<f32>,
+    fog_strength: f32,
+    // Depths in front of the camera over which fog thickens.
+    fog_range: vec2<f32>,
 };
 
 @group(0) @binding(0) // 1.
 var<uniform> camera: CameraUniform;
 
+// How far something `depth` in front of the camera has faded into the fog.
+fn fog_amount(depth: f32) -> f32 {
+    let t = clamp((depth - camera.fog_range.x) / (camera.fog_range.y - camera.fog_range.x), 0.0, 1.0);
+    return t * camera.fog_strength;
+}
+
 struct VertexInput {
     @location(0) current: vec3<f32>,
     @location(1) next: vec3<f32>,
     @location(2) offset_distance: f32,
-    @location(3) color: vec3<f32>,
+    @location(3) color: vec4<f32>,
+    // Nonzero when offset_distance is in pixels rather than clip space units.
+    @location(4) pixel_sized: u32,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
-    @location(0) color: vec3<f32>,
+    @location(0) color: vec4<f32>,
 };
 
+// Zero rather than NaN for a line seen end on.
+fn direction(v: vec2<f32>) -> vec2<f32> {
+    if dot(v, v) < 1e-12 {
+        return vec2<f32>(0.0, 0.0);
+    }
+    return normalize(v);
+}
+
 @vertex
 fn vs_main(
     model: VertexInput,
@@ -32,12 +55,19 @@ fn vs_main(
     let next_projected = camera.view_proj * vec4<f32>(model.next, 1.0);
     var next_screen: vec2<f32> = vec2<f32>(next_projected.x, next_projected.y) / next_projected.w;
 
-    let dir = normalize(next_screen - current_screen);
-    var normal: vec2<f32> = vec2<f32>(-dir.y, dir.x);
-
-    let offset = vec4<f32>(normal * model.offset_distance, 0.0, 0.0);
-    out.color = model.color;
-    out.clip_position = (camera.view_proj * vec4<f32>(model.current, 1.0)) + offset;
+    var offset: vec4<f32>;
+    if model.pixel_sized != 0u {
+        // Offset across the line as it appears on screen, a set number of pixels at any depth.
+        let dir = direction((next_screen - current_screen) * camera.viewport);
+        let normal = vec2<f32>(-dir.y, dir.x) * model.offset_distance / camera.viewport * 2.0;
+        offset = vec4<f32>(normal * current_projected.w, 0.0, 0.0);
+    } else {
+        let dir = direction(next_screen - current_screen);
+        let normal = vec2<f32>(-dir.y, dir.x);
+        offset = vec4<f32>(normal * model.offset_distance, 0.0, 0.0);
+    }
+    out.color = vec4<f32>(mix(model.color.rgb, camera.fog_color, fog_amount(current_projected.w)), model.color.a);
+    out.clip_position = current_projected + offset;
     return out;
 }
 
@@ -45,5 +75,5 @@ fn vs_main(
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(in.color, 0.5);
+    return in.color;
 }
\ No newline at end of file
@@ -0,0 +1,108 @@
+// Renders vertex and edge IDs instead of colors. 0 means nothing was hit.
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    scale_proj: mat4x4<f32>,
+    aspect: f32,
+    // Size of the surface in pixels.
+    viewport: vec2<f32>,
+    // What distant lines and points fade towards.
+    fog_color: vec3<f32>,
+    fog_strength: f32,
+    // Depths in front of the camera over which fog thickens.
+    fog_range: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// Only a few pixels around the cursor are rendered, into a texture just that big.
+struct PickWindow {
+    scale: vec2<f32>,
+    offset: vec2<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> pick_window: PickWindow;
+
+// Moves a clip space position on the surface into the pick window.
+fn to_pick_window(clip_position: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(clip_position.xy * pick_window.scale + pick_window.offset * clip_position.w, clip_position.zw);
+}
+
+struct PointInput {
+    @location(0) corner: vec2<f32>,
+    @location(1) position: vec3<f32>,
+    @location(2) size: f32,
+    @location(3) id: u32,
+    // Nonzero when size is in pixels rather than world units.
+    @location(4) pixel_sized: u32,
+};
+
+struct PointOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) corner: vec2<f32>,
+    @location(1) @interpolate(flat) id: u32,
+};
+
+@vertex
+fn vs_point(in: PointInput) -> PointOutput {
+    var out: PointOutput;
+    let center = camera.view_proj * vec4<f32>(in.position, 1.0);
+    if in.pixel_sized != 0u {
+        out.clip_position = center + vec4<f32>(in.corner * in.size / camera.viewport * 2.0 * center.w, 0.0, 0.0);
+    } else {
+        out.clip_position = center + camera.scale_proj * vec4<f32>(in.corner * in.size, 0.0, 0.0);
+    }
+    out.corner = in.corner;
+    out.clip_position = to_pick_window(out.clip_position);
+    out.id = in.id;
+    return out;
+}
+
+@fragment
+fn fs_point(in: PointOutput) -> @location(0) u32 {
+    // The quad spans -1..1 and the drawn disk has radius 1.
+    if dot(in.corner, in.corner) > 1.0 {
+        discard;
+    }
+    return in.id;
+}
+
+struct LineInput {
+    @location(0) current: vec3<f32>,
+    @location(1) next: vec3<f32>,
+    @location(2) offset_distance: f32,
+    @location(3) id: u32,
+    // Nonzero when offset_distance is in pixels rather than clip space units.
+    @location(4) pixel_sized: u32,
+};
+
+struct LineOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) @interpolate(flat) id: u32,
+};
+
+@vertex
+fn vs_line(in: LineInput) -> LineOutput {
+    var out: LineOutput;
+    let current_projected = camera.view_proj * vec4<f32>(in.current, 1.0);
+    let next_projected = camera.view_proj * vec4<f32>(in.next, 1.0);
+    let screen_delta = next_projected.xy / next_projected.w - current_projected.xy / current_projected.w;
+    if in.pixel_sized != 0u {
+        let dir = normalize(screen_delta * camera.viewport);
+        let normal = vec2<f32>(-dir.y, dir.x) * in.offset_distance / camera.viewport * 2.0;
+        out.clip_position = current_projected + vec4<f32>(normal * current_projected.w, 0.0, 0.0);
+    } else {
+        let dir = normalize(screen_delta);
+        let normal = vec2<f32>(-dir.y, dir.x);
+        out.clip_position = current_projected + vec4<f32>(normal * in.offset_distance, 0.0, 0.0);
+    }
+    out.clip_position = to_pick_window(out.clip_position);
+    out.id = in.id;
+    return out;
+}
+
+@fragment
+fn fs_line(in: LineOutput) -> @location(0) u32 {
+    return in.id;
+}
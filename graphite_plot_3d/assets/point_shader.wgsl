@@ -2,15 +2,33 @@ struct CameraUniform {
     view_proj: mat4x4<f32>,
     scale_proj: mat4x4<f32>,
     aspect: f32,
+    // Size of the surface in pixels.
+    viewport: vec2<f32>,
+    // What distant lines and points fade towards.
+    fog_color: vec3<f32>,
+    fog_strength: f32,
+    // Depths in front of the camera over which fog thickens.
+    fog_range: vec2<f32>,
 };
 
 @group(0) @binding(0) // 1.
 var<uniform> camera: CameraUniform;
 
+// How far something `depth` in front of the camera has faded into the fog.
+fn fog_amount(depth: f32) -> f32 {
+    let t = clamp((depth - camera.fog_range.x) / (camera.fog_range.y - camera.fog_range.x), 0.0, 1.0);
+    return t * camera.fog_strength;
+}
+
 struct InstanceInput {
     @location(2) position: vec3<f32>,
     @location(3) color: vec3<f32>,
     @location(4) size: f32,
+    // Nonzero when size is in pixels rather than world units.
+    @location(5) pixel_sized: u32,
+    @location(6) outline_color: vec3<f32>,
+    // Fraction of the radius the outline takes up.
+    @location(7) outline_thickness: f32,
 }
 
 struct VertexInput {
@@ -22,6 +40,14 @@ struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
     @location(1) color: vec3<f32>,
+    // How far the sphere's front bulges towards the camera, in clip space, so each
+    // fragment can be given the depth of the surface it shows.
+    @location(2) bulge: vec4<f32>,
+    @location(3) fog: f32,
+    // The clip position again, since the builtin reaches the fragment shader in pixels.
+    @location(4) quad: vec4<f32>,
+    @location(5) outline_color: vec3<f32>,
+    @location(6) outline_thickness: f32,
 }
 
 @vertex
@@ -31,20 +57,63 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.tex_coords = model.tex_coords;
-    out.clip_position = camera.view_proj * vec4<f32>(instance.position, 1.0) + camera.scale_proj * vec4<f32>(model.position * instance.size, 0.0, 0.0);
+    let center = camera.view_proj * vec4<f32>(instance.position, 1.0);
+    var radius = instance.size;
+    if instance.pixel_sized != 0u {
+        let corner = model.position * instance.size / camera.viewport * 2.0;
+        out.clip_position = center + vec4<f32>(corner * center.w, 0.0, 0.0);
+        // The radius in view space that covers that many pixels at this depth.
+        radius = instance.size / camera.viewport.y * 2.0 * center.w / camera.scale_proj[1][1];
+    } else {
+        out.clip_position = center + camera.scale_proj * vec4<f32>(model.position * instance.size, 0.0, 0.0);
+    }
+    out.quad = out.clip_position;
+    out.bulge = camera.scale_proj * vec4<f32>(0.0, 0.0, radius, 0.0);
     out.color = instance.color;
+    out.outline_color = instance.outline_color;
+    out.outline_thickness = instance.outline_thickness;
+    out.fog = fog_amount(center.w);
     return out;
 }
 
 // Fragment shader
 
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+}
+
+// Lit from the upper left, in view space.
+const LIGHT: vec3<f32> = vec3<f32>(-0.4, 0.6, 0.7);
+
 @fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    if (in.tex_coords.x - 0.5) * (in.tex_coords.x - 0.5) + (in.tex_coords.y - 0.5) * (in.tex_coords.y - 0.5) <= 0.25 {
-        return vec4<f32>(in.color * (0.5 + in.tex_coords.y * 0.5), 1.0);
-    }
-    else {
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    // Where on the unit disk the fragment is, which is where on the sphere it is seen from
+    // the front.
+    let disk = in.tex_coords * 2.0 - vec2<f32>(1.0, 1.0);
+    let r = length(disk);
+    // Fades the rim out over about a pixel, which alpha to coverage turns into partly
+    // covered samples when multisampling.
+    let alpha = 1.0 - smoothstep(1.0 - fwidth(r), 1.0, r);
+    if alpha <= 0.0 {
         discard;
     }
+    let normal = vec3<f32>(disk, sqrt(max(1.0 - r * r, 0.0)));
+
+    let light = normalize(LIGHT);
+    let diffuse = max(dot(normal, light), 0.0);
+    let halfway = normalize(light + vec3<f32>(0.0, 0.0, 1.0));
+    let specular = pow(max(dot(normal, halfway), 0.0), 32.0) * 0.3;
+    let lit = in.color * (0.35 + 0.65 * diffuse) + vec3<f32>(specular);
+    // The outline rings the silhouette, unlit. It comes from the theme; per-vertex shapes,
+    // outlines and icons are only drawn by the 2D point shader.
+    let inner = 1.0 - in.outline_thickness;
+    let fill = 1.0 - smoothstep(inner - fwidth(r), inner, r);
+    let shaded = mix(in.outline_color, lit, fill);
+
+    var out: FragmentOutput;
+    out.color = vec4<f32>(mix(shaded, camera.fog_color, in.fog), alpha);
+    let surface = in.quad + in.bulge * normal.z;
+    out.depth = surface.z / surface.w;
+    return out;
 }
- 
\ No newline at end of file
@@ -0,0 +1,85 @@
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+use crate::bindings::{Modifiers, Stroke};
+
+/// Everything a key or mouse button can be bound to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    /// Drag the vertex under the cursor, or pan when there is none.
+    Grab,
+    OpenGeneratorMenu,
+    CycleIntegrator,
+    /// Camera movement, for as long as the key is held.
+    MoveForward,
+    MoveBackward,
+    OrbitLeft,
+    OrbitRight,
+    /// Save the current frame as a PNG.
+    #[cfg(not(target_arch = "wasm32"))]
+    Screenshot,
+    /// Start or stop recording the layout.
+    #[cfg(not(target_arch = "wasm32"))]
+    ToggleRecording,
+    /// Switch to the next built-in or loaded theme.
+    CycleTheme,
+    /// Bow every edge out, not only parallel ones.
+    ToggleCurvedEdges,
+}
+
+/// Name used in bindings files and a short description of each action.
+pub const ACTIONS: &[(Action, &str, &str)] = &[
+    (Action::Grab, "grab", "drag vertex or pan"),
+    (Action::OpenGeneratorMenu, "open_generator_menu", "graph generators"),
+    (Action::CycleIntegrator, "cycle_integrator", "next integrator"),
+    (Action::MoveForward, "move_forward", "move camera closer"),
+    (Action::MoveBackward, "move_backward", "move camera away"),
+    (Action::OrbitLeft, "orbit_left", "orbit camera left"),
+    (Action::OrbitRight, "orbit_right", "orbit camera right"),
+    #[cfg(not(target_arch = "wasm32"))]
+    (Action::Screenshot, "screenshot", "save screenshot"),
+    #[cfg(not(target_arch = "wasm32"))]
+    (Action::ToggleRecording, "toggle_recording", "record"),
+    (Action::CycleTheme, "cycle_theme", "theme"),
+    (Action::ToggleCurvedEdges, "toggle_curved_edges", "curved edges"),
+];
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        ACTIONS.iter().find(|(action, _, _)| *action == self).unwrap().1
+    }
+
+    pub fn description(self) -> &'static str {
+        ACTIONS.iter().find(|(action, _, _)| *action == self).unwrap().2
+    }
+
+    pub fn parse(name: &str) -> Option<Action> {
+        ACTIONS.iter().find(|(_, action_name, _)| *action_name == name).map(|(action, _, _)| *action)
+    }
+}
+
+/// What every action is bound to before a bindings file overrides it.
+pub fn default_bindings() -> Vec<(Vec<Stroke>, Action)> {
+    let none = Modifiers::default();
+    let key = |key| vec![Stroke::key(key, none)];
+    let mouse = |button| vec![Stroke::mouse(button, none)];
+
+    vec![
+        (mouse(MouseButton::Left), Action::Grab),
+        (key(KeyCode::Tab), Action::OpenGeneratorMenu),
+        (key(KeyCode::KeyI), Action::CycleIntegrator),
+        (key(KeyCode::KeyW), Action::MoveForward),
+        (key(KeyCode::ArrowUp), Action::MoveForward),
+        (key(KeyCode::KeyS), Action::MoveBackward),
+        (key(KeyCode::ArrowDown), Action::MoveBackward),
+        (key(KeyCode::KeyA), Action::OrbitLeft),
+        (key(KeyCode::ArrowLeft), Action::OrbitLeft),
+        (key(KeyCode::KeyD), Action::OrbitRight),
+        (key(KeyCode::ArrowRight), Action::OrbitRight),
+        #[cfg(not(target_arch = "wasm32"))]
+        (key(KeyCode::F12), Action::Screenshot),
+        #[cfg(not(target_arch = "wasm32"))]
+        (vec![Stroke::key(KeyCode::F12, Modifiers { shift: true, ..none })], Action::ToggleRecording),
+        (key(KeyCode::KeyL), Action::CycleTheme),
+        (key(KeyCode::KeyE), Action::ToggleCurvedEdges),
+    ]
+}
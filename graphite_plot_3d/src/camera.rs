@@ -1,6 +1,8 @@
-use winit::{event::{ElementState, KeyEvent, WindowEvent}, keyboard::{KeyCode, PhysicalKey}};
+use winit::{dpi::PhysicalSize, event::{ElementState, KeyEvent, WindowEvent}, keyboard::PhysicalKey};
 use cgmath::{EuclideanSpace, InnerSpace, SquareMatrix, Vector2, Vector3, Vector4};
 
+use crate::{actions::Action, bindings::{Bindings, Input}};
+
 
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
@@ -59,6 +61,12 @@ impl Camera {
     pub fn zoom(&mut self, wheel_delta: f32) {
         self.eye = cgmath::Point3::from_vec((-0.1 * wheel_delta).exp() * (self.eye - self.target) + self.target.to_vec());
     }
+
+    /// Turns the plot counterclockwise on screen, around the line of sight.
+    pub fn roll(&mut self, angle: f32) {
+        let forward = (self.target - self.eye).normalize();
+        self.up = cgmath::Matrix3::from_axis_angle(forward, cgmath::Rad(angle)) * self.up;
+    }
 }
 
 // We need this for Rust to store our data correctly for the shaders
@@ -71,7 +79,16 @@ pub struct CameraUniform {
     view_proj: [[f32; 4]; 4],
     scale_proj: [[f32; 4]; 4],
     aspect: f32,
-    _pad: [u8; 12],
+    _pad: [u8; 4],
+    /// Size of the surface in pixels, for lines and points sized in pixels.
+    viewport: [f32; 2],
+    /// What distant lines and points fade towards, usually the background.
+    fog_color: [f32; 3],
+    /// How far the farthest lines and points fade, from 0 (not at all) to 1.
+    fog_strength: f32,
+    /// Depths in front of the camera over which fog thickens.
+    fog_range: [f32; 2],
+    _pad2: [u8; 8],
 }
 
 impl CameraUniform {
@@ -81,14 +98,31 @@ impl CameraUniform {
             view_proj: cgmath::Matrix4::identity().into(),
             scale_proj: cgmath::Matrix4::identity().into(),
             aspect: 0.5,
-            _pad: [0u8; 12],
+            _pad: [0u8; 4],
+            viewport: [1.0, 1.0],
+            fog_color: [1.0, 1.0, 1.0],
+            fog_strength: 0.0,
+            fog_range: [0.0, 1.0],
+            _pad2: [0u8; 8],
         }
     }
 
-    pub fn update(&mut self, camera: &Camera) {
+    pub fn set_fog(&mut self, strength: f32) {
+        self.fog_strength = strength;
+    }
+
+    pub fn set_fog_color(&mut self, color: [f32; 3]) {
+        self.fog_color = color;
+    }
+
+    pub fn update(&mut self, camera: &Camera, viewport: PhysicalSize<u32>) {
 
         self.view_proj = camera.build_view_projection_matrix().into();
         self.scale_proj = camera.build_scale_projection_matrix().into();
+        self.viewport = [viewport.width.max(1) as f32, viewport.height.max(1) as f32];
+        // Fog follows the camera in and out, so the plot keeps the same depth cues at any zoom.
+        let distance = (camera.target - camera.eye).magnitude();
+        self.fog_range = [0.5 * distance, 2.0 * distance];
     }
 }
 
@@ -111,7 +145,8 @@ impl CameraController {
         }
     }
 
-    pub fn process_events(&mut self, event: &WindowEvent) -> bool {
+    /// Starts or stops the movement `bindings` give the key of `event`.
+    pub fn process_events(&mut self, event: &WindowEvent, bindings: &Bindings) -> bool {
         match event {
             WindowEvent::KeyboardInput {
                 event:
@@ -123,19 +158,20 @@ impl CameraController {
                 ..
             } => {
                 let is_pressed = *state == ElementState::Pressed;
-                match keycode {KeyCode::KeyW | KeyCode::ArrowUp => {
+                match bindings.held(Input::Key(*keycode)) {
+                    Some(Action::MoveForward) => {
                         self.is_forward_pressed = is_pressed;
                         true
                     }
-                    KeyCode::KeyA | KeyCode::ArrowLeft => {
+                    Some(Action::OrbitLeft) => {
                         self.is_left_pressed = is_pressed;
                         true
                     }
-                    KeyCode::KeyS | KeyCode::ArrowDown => {
+                    Some(Action::MoveBackward) => {
                         self.is_backward_pressed = is_pressed;
                         true
                     }
-                    KeyCode::KeyD | KeyCode::ArrowRight => {
+                    Some(Action::OrbitRight) => {
                         self.is_right_pressed = is_pressed;
                         true
                     }
@@ -1,14 +1,17 @@
 use bytemuck::{Pod, Zeroable};
-use cgmath::Vector3;
+use cgmath::{InnerSpace, Vector3};
+
+use crate::camera::Camera;
 use wgpu::util::DeviceExt;
 
 use crate::{
+    overlay::{GetOverlay, OverlayElement},
     plot_graphic::{PlotGraphicElement, PlotGraphicState},
     plot_window::PlotWindowState,
     texture,
 };
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Color {
     pub r: f32,
     pub g: f32,
@@ -26,6 +29,78 @@ impl Color {
     pub fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
         Self { r, g, b, a }
     }
+
+    /// Color from an sRGB hex code such as `0x440154`, converted to the linear values the
+    /// surface expects.
+    pub fn hex(code: u32) -> Self {
+        let channel = |shift: u32| srgb_to_linear(((code >> shift) & 0xff) as f32 / 255.0);
+
+        Self::rgb(channel(16), channel(8), channel(0))
+    }
+}
+
+/// What line widths and point sizes are measured in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SizeUnits {
+    /// Units of the plot, so lines and points grow and shrink as it zooms.
+    #[default]
+    World,
+    /// Surface pixels, so lines and points look the same at any zoom.
+    Pixels,
+}
+
+impl SizeUnits {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "world" => Some(Self::World),
+            "pixels" | "px" => Some(Self::Pixels),
+            _ => None,
+        }
+    }
+
+    /// The flag the shaders take, nonzero for pixels.
+    pub fn shader_flag(self) -> u32 {
+        match self {
+            Self::World => 0,
+            Self::Pixels => 1,
+        }
+    }
+}
+
+/// How the plot is drawn, as opposed to what it shows.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderOptions {
+    /// Samples per pixel. Falls back to 1 where the device can't multisample that much.
+    pub msaa_samples: u32,
+    pub line_units: SizeUnits,
+    /// Vertex sizes stay in world units by default, since they're also how far apart the
+    /// layout keeps vertices.
+    pub point_units: SizeUnits,
+    /// Bow every edge out, not only parallel ones, which always fan out.
+    pub curved_edges: bool,
+    /// How far the farthest lines and points fade into the background, from 0 to 1. Fading
+    /// with depth helps tell near from far.
+    pub fog: f32,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            msaa_samples: 4,
+            line_units: SizeUnits::Pixels,
+            point_units: SizeUnits::World,
+            curved_edges: false,
+            fog: 0.5,
+        }
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
 }
 
 impl Into<[f32; 3]> for Color {
@@ -34,13 +109,77 @@ impl Into<[f32; 3]> for Color {
     }
 }
 
+impl Into<[f32; 4]> for Color {
+    fn into(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+/// Segments a Bézier curve is drawn with.
+const CURVE_SEGMENT_CT: usize = 16;
+
+/// Path of a line, from its first point to its last.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Curve {
+    Straight(Vector3<f32>, Vector3<f32>),
+    /// Quadratic Bézier curve, pulled towards the middle point.
+    Quadratic(Vector3<f32>, Vector3<f32>, Vector3<f32>),
+    /// Cubic Bézier curve, leaving towards the second point and arriving from the third.
+    Cubic(Vector3<f32>, Vector3<f32>, Vector3<f32>, Vector3<f32>),
+}
+
+impl Curve {
+    pub fn point(&self, t: f32) -> Vector3<f32> {
+        let s = 1.0 - t;
+        match *self {
+            Curve::Straight(a, b) => a * s + b * t,
+            Curve::Quadratic(a, b, c) => a * (s * s) + b * (2.0 * s * t) + c * (t * t),
+            Curve::Cubic(a, b, c, d) => {
+                a * (s * s * s) + b * (3.0 * s * s * t) + c * (3.0 * s * t * t) + d * (t * t * t)
+            }
+        }
+    }
+
+    /// Points along the curve from start to end, close enough together to draw it with
+    /// straight segments.
+    pub fn points(&self) -> impl Iterator<Item = Vector3<f32>> + use<> {
+        let curve = *self;
+        let segment_ct = match curve {
+            Curve::Straight(..) => 1,
+            _ => CURVE_SEGMENT_CT,
+        };
+        (0..=segment_ct).map(move |i| curve.point(i as f32 / segment_ct as f32))
+    }
+
+    /// The curve with its ends moved `start` and `end` towards the points after and before
+    /// them, which trims about that much off each end. An end closer than that to the next
+    /// point stays where it is.
+    pub fn trimmed(&self, start: f32, end: f32) -> Curve {
+        let toward = |from: Vector3<f32>, to: Vector3<f32>, distance: f32| {
+            let length = (to - from).magnitude();
+            if length > distance { from + (to - from) * (distance / length) } else { from }
+        };
+        match *self {
+            Curve::Straight(a, b) => Curve::Straight(toward(a, b, start), toward(b, a, end)),
+            Curve::Quadratic(a, b, c) => Curve::Quadratic(toward(a, b, start), b, toward(c, b, end)),
+            Curve::Cubic(a, b, c, d) => Curve::Cubic(toward(a, b, start), b, c, toward(d, c, end)),
+        }
+    }
+
+    /// The straight segments `points` joins.
+    pub fn segments(&self) -> impl Iterator<Item = (Vector3<f32>, Vector3<f32>)> + use<> {
+        self.points().zip(self.points().skip(1))
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 struct LineVertex {
     position: [f32; 3],
     next: [f32; 3],
     offset_distance: f32,
-    color: [f32; 3],
+    color: [f32; 4],
+    pixel_sized: u32,
 }
 
 impl LineVertex {
@@ -48,13 +187,15 @@ impl LineVertex {
         v: cgmath::Vector3<f32>,
         next: cgmath::Vector3<f32>,
         offset_distance: f32,
-        color: [f32; 3],
+        color: [f32; 4],
+        units: SizeUnits,
     ) -> LineVertex {
         Self {
             position: v.into(),
             next: next.into(),
             offset_distance: offset_distance,
             color: color,
+            pixel_sized: units.shader_flag(),
         }
     }
 
@@ -82,7 +223,15 @@ impl LineVertex {
                     offset: (2 * std::mem::size_of::<[f32; 3]>() + std::mem::size_of::<f32>())
                         as wgpu::BufferAddress,
                     shader_location: 3,
-                    format: wgpu::VertexFormat::Float32x3,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: (2 * std::mem::size_of::<[f32; 3]>()
+                        + std::mem::size_of::<f32>()
+                        + std::mem::size_of::<[f32; 4]>())
+                        as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Uint32,
                 },
             ],
         }
@@ -144,6 +293,9 @@ struct PointInstance {
     position: [f32; 3],
     color: [f32; 3],
     size: f32,
+    pixel_sized: u32,
+    outline_color: [f32; 3],
+    outline_thickness: f32,
 }
 
 impl PointInstance {
@@ -171,6 +323,21 @@ impl PointInstance {
                     shader_location: 4,
                     format: wgpu::VertexFormat::Float32,
                 },
+                wgpu::VertexAttribute {
+                    offset: 2 * mem::size_of::<[f32; 3]>() as wgpu::BufferAddress + mem::size_of::<f32>() as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Uint32,
+                },
+                wgpu::VertexAttribute {
+                    offset: 2 * mem::size_of::<[f32; 3]>() as wgpu::BufferAddress + 2 * mem::size_of::<f32>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: 3 * mem::size_of::<[f32; 3]>() as wgpu::BufferAddress + 2 * mem::size_of::<f32>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32,
+                },
             ],
         }
     }
@@ -181,27 +348,47 @@ pub struct PointElement {
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     instance_buffer: wgpu::Buffer,
+    units: SizeUnits,
 
     instances: Vec<PointInstance>,
 }
 
+/// Points as their center, color and radius, in the units of the element drawing them.
 pub trait GetPoints {
     fn get_points(&self) -> impl Iterator<Item = (Vector3<f32>, Color, f32)>;
+
+    /// Color of every point's outline, and how much of its radius the outline takes up.
+    fn point_outline(&self) -> (Color, f32) {
+        (Color::rgb(0.0, 0.0, 0.0), 0.0)
+    }
 }
 
 impl<T: GetPoints> PlotGraphicElement<T> for PointElement {
-    fn update(&mut self, window_state: &PlotWindowState, graphic: &T) {
+    fn update(&mut self, window_state: &PlotWindowState, _camera: &Camera, graphic: &T) {
         self.instances.clear();
 
+        let (outline_color, outline_thickness) = graphic.point_outline();
         for (position, color, size) in graphic.get_points() {
             self.instances.push(PointInstance {
                 position: position.into(),
                 color: color.into(),
                 size: size,
+                pixel_sized: self.units.shader_flag(),
+                outline_color: outline_color.into(),
+                outline_thickness,
             });
         }
 
         // println!("{:?}", self.instances);
+        let instance_bytes = std::mem::size_of_val(self.instances.as_slice()) as u64;
+        if instance_bytes > self.instance_buffer.size() {
+            self.instance_buffer = window_state.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Point Instance Buffer"),
+                size: instance_bytes.next_power_of_two(),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        }
         window_state.queue.write_buffer(
             &self.instance_buffer,
             0,
@@ -279,9 +466,10 @@ impl PointElement {
                         bias: wgpu::DepthBiasState::default(),
                     }), // 1.
                     multisample: wgpu::MultisampleState {
-                        count: 1,                         // 2.
+                        count: window_state.sample_count, // 2.
                         mask: !0,                         // 3.
-                        alpha_to_coverage_enabled: false, // 4.
+                        // Smooths the disk's rim, which isn't a triangle edge.
+                        alpha_to_coverage_enabled: window_state.sample_count > 1, // 4.
                     },
                     multiview: None, // 5.
                     cache: None,     // 6.
@@ -319,39 +507,60 @@ impl PointElement {
             instance_buffer,
             vertex_buffer,
             index_buffer,
+            units: SizeUnits::World,
             instances,
         }
     }
+
+    /// Measures point sizes in `units` instead of world units.
+    pub fn with_units(mut self, units: SizeUnits) -> Self {
+        self.units = units;
+        self
+    }
 }
 
+/// Lines as their path, color and width, in the units of the element drawing them.
 pub trait GetLines {
-    fn get_lines(&self) -> impl Iterator<Item = (Vector3<f32>, Vector3<f32>, Color)>;
+    fn get_lines(&self) -> impl Iterator<Item = (Curve, Color, f32)>;
 }
 
 pub struct LineElement {
     render_pipeline: wgpu::RenderPipeline,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
+    units: SizeUnits,
 
     vertices: Vec<LineVertex>,
-    indices: Vec<u16>,
+    indices: Vec<u32>,
 }
 
 impl<T: GetLines> PlotGraphicElement<T> for LineElement {
-    fn update(&mut self, window_state: &PlotWindowState, lines: &T) {
+    fn update(&mut self, window_state: &PlotWindowState, camera: &Camera, lines: &T) {
         let mut index = 0;
         self.vertices.clear();
         self.indices.clear();
 
-        for (x0, x1, color) in lines.get_lines() {
+        // Lines are blended back to front, since they don't hide what's behind them. Curves
+        // are sorted a segment at a time, so they can pass in front of and behind others.
+        let view_proj = camera.build_view_projection_matrix();
+        let depth = |x0: Vector3<f32>, x1: Vector3<f32>| (view_proj * ((x0 + x1) / 2.0).extend(1.0)).w;
+        let mut sorted: Vec<_> = lines
+            .get_lines()
+            .flat_map(|(curve, color, width)| curve.segments().map(move |(x0, x1)| (x0, x1, color, width)))
+            .filter(|(x0, x1, ..)| x0 != x1)
+            .collect();
+        sorted.sort_by(|(a0, a1, ..), (b0, b1, ..)| depth(*b0, *b1).total_cmp(&depth(*a0, *a1)));
+
+        for (x0, x1, color, width) in sorted {
+            let offset = width / 2.0;
             self.vertices
-                .push(LineVertex::new(x0, x1, 0.02, color.into()));
+                .push(LineVertex::new(x0, x1, offset, color.into(), self.units));
             self.vertices
-                .push(LineVertex::new(x0, x1, -0.02, color.into()));
+                .push(LineVertex::new(x0, x1, -offset, color.into(), self.units));
             self.vertices
-                .push(LineVertex::new(x1, x0, -0.02, color.into()));
+                .push(LineVertex::new(x1, x0, -offset, color.into(), self.units));
             self.vertices
-                .push(LineVertex::new(x1, x0, 0.02, color.into()));
+                .push(LineVertex::new(x1, x0, offset, color.into(), self.units));
 
             self.indices.push(index + 0);
             self.indices.push(index + 1);
@@ -362,6 +571,25 @@ impl<T: GetLines> PlotGraphicElement<T> for LineElement {
             index += 4;
         }
 
+        let vertex_bytes = std::mem::size_of_val(self.vertices.as_slice()) as u64;
+        if vertex_bytes > self.vertex_buffer.size() {
+            self.vertex_buffer = window_state.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Edge Vertex Buffer"),
+                size: vertex_bytes.next_power_of_two(),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        }
+        let index_bytes = std::mem::size_of_val(self.indices.as_slice()) as u64;
+        if index_bytes > self.index_buffer.size() {
+            self.index_buffer = window_state.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Edge Index Buffer"),
+                size: index_bytes.next_power_of_two(),
+                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        }
+
         window_state.queue.write_buffer(
             &self.vertex_buffer,
             0,
@@ -372,11 +600,18 @@ impl<T: GetLines> PlotGraphicElement<T> for LineElement {
             .write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(&self.indices));
     }
 
+    fn is_transparent(&self) -> bool {
+        true
+    }
+
     fn render(&self, graphic_state: &PlotGraphicState<T>, render_pass: &mut wgpu::RenderPass) {
+        if self.indices.is_empty() {
+            return;
+        }
         render_pass.set_pipeline(&self.render_pipeline); // 2.
         render_pass.set_bind_group(0, &graphic_state.camera_bind_group, &[]);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
         render_pass.draw_indexed(0..(self.indices.len() as u32), 0, 0..1);
     }
 }
@@ -412,7 +647,7 @@ impl LineElement {
                         targets: &[Some(wgpu::ColorTargetState {
                             // 4.
                             format: window_state.config.format,
-                            blend: Some(wgpu::BlendState::REPLACE),
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                             write_mask: wgpu::ColorWrites::ALL,
                         })],
                         compilation_options: wgpu::PipelineCompilationOptions::default(),
@@ -431,13 +666,15 @@ impl LineElement {
                     },
                     depth_stencil: Some(wgpu::DepthStencilState {
                         format: texture::Texture::DEPTH_FORMAT,
-                        depth_write_enabled: true,
+                        // Lines are tested against the vertices in front of them but don't
+                        // hide each other, so they blend however they overlap.
+                        depth_write_enabled: false,
                         depth_compare: wgpu::CompareFunction::Less,
                         stencil: wgpu::StencilState::default(),
                         bias: wgpu::DepthBiasState::default(),
                     }), // 1.
                     multisample: wgpu::MultisampleState {
-                        count: 1,                         // 2.
+                        count: window_state.sample_count, // 2.
                         mask: !0,                         // 3.
                         alpha_to_coverage_enabled: false, // 4.
                     },
@@ -466,8 +703,34 @@ impl LineElement {
             render_pipeline,
             vertex_buffer,
             index_buffer,
+            units: SizeUnits::World,
             indices,
             vertices,
         }
     }
+
+    /// Measures line widths in `units` instead of world units.
+    pub fn with_units(mut self, units: SizeUnits) -> Self {
+        self.units = units;
+        self
+    }
+}
+
+impl<T: GetOverlay> PlotGraphicElement<T> for OverlayElement {
+    fn update(&mut self, window_state: &PlotWindowState, _camera: &Camera, data: &T) {
+        self.prepare(window_state, data);
+    }
+
+    fn render(&self, _graphic_state: &PlotGraphicState<T>, render_pass: &mut wgpu::RenderPass) {
+        self.draw(render_pass);
+    }
+
+    /// Drawn last, over everything else.
+    fn is_transparent(&self) -> bool {
+        true
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
 }
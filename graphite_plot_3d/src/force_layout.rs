@@ -0,0 +1,516 @@
+use std::sync::Arc;
+
+use instant::Duration;
+
+use cgmath::{InnerSpace, Vector3, Zero};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::simulation::Simulation;
+
+const VERTEX_REPULSION: f32 = 3.0;
+const EDGE_SPRING_STIFFNESS: f32 = 6.0;
+const EDGE_EQ_LENGTH: f32 = 1.0;
+const RESISTANCE: f32 = 0.5;
+const CENTERING: f32 = 0.05;
+const SPEED_LIMIT: f32 = 200.0;
+pub const SIMULATION_TIMESTEP: f32 = 1.0 / 120.0;
+const MAX_STEPS_PER_TICK: u32 = 8;
+const SETTLED_KINETIC_ENERGY: f32 = 1e-4;
+const SETTLED_STEP_CT: u32 = 60;
+/// Repulsion is gathered in up to this many blocks of particles, each into its own force
+/// buffer. The count only depends on the particle count, not the thread count, so the
+/// buffers are summed in the same order however the blocks are scheduled.
+const MAX_FORCE_BLOCK_CT: usize = 64;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Integrator {
+    Euler,
+    VelocityVerlet,
+    RungeKutta4,
+}
+
+impl Integrator {
+    pub fn next(self) -> Self {
+        match self {
+            Integrator::Euler => Integrator::VelocityVerlet,
+            Integrator::VelocityVerlet => Integrator::RungeKutta4,
+            Integrator::RungeKutta4 => Integrator::Euler,
+        }
+    }
+}
+
+/// The physical side of a force plot. It only knows particles and adjacency lists so it can
+/// run on its own thread while the plot keeps the graph itself.
+pub struct ForceLayout {
+    pub particles: Vec<PhysicsParticle>,
+    pub adjacency: Arc<Vec<Vec<usize>>>,
+    /// A particle held by the user, which the integrator leaves alone.
+    pub pinned: Option<usize>,
+    pub integrator: Integrator,
+    /// While set, time only passes in `advance_frame`, this much per call. Recordings use it
+    /// so every frame shows the same simulated time, however long frames take to draw.
+    pub fixed_frame: Option<Duration>,
+    /// Number of `advance_frame` calls so far.
+    frame_ct: u64,
+    time_accumulator: f32,
+    settled: bool,
+    settled_step_ct: u32,
+}
+
+#[derive(Default)]
+pub struct LayoutSnapshot {
+    pub positions: Vec<Vector3<f32>>,
+    pub settled: bool,
+    pub frame_ct: u64,
+}
+
+impl ForceLayout {
+    pub fn new(particles: Vec<PhysicsParticle>, adjacency: Arc<Vec<Vec<usize>>>) -> Self {
+        Self {
+            particles,
+            adjacency,
+            pinned: None,
+            integrator: Integrator::VelocityVerlet,
+            fixed_frame: None,
+            frame_ct: 0,
+            time_accumulator: 0.0,
+            settled: false,
+            settled_step_ct: 0,
+        }
+    }
+
+    /// Advances the simulation by however many fixed timesteps fit into `delta_t`, so the
+    /// layout doesn't depend on the frame rate. Does nothing while frames are fixed.
+    pub fn tick(&mut self, delta_t: Duration) {
+        if self.fixed_frame.is_some() {
+            return;
+        }
+        self.advance(delta_t);
+    }
+
+    /// Advances the simulation by one `fixed_frame`.
+    pub fn advance_frame(&mut self) {
+        if let Some(frame) = self.fixed_frame {
+            self.advance(frame);
+        }
+        self.frame_ct += 1;
+    }
+
+    fn advance(&mut self, delta_t: Duration) {
+        if self.settled {
+            self.time_accumulator = 0.0;
+            return;
+        }
+
+        self.time_accumulator = (self.time_accumulator + delta_t.as_secs_f32())
+            .min(MAX_STEPS_PER_TICK as f32 * SIMULATION_TIMESTEP);
+
+        while self.time_accumulator >= SIMULATION_TIMESTEP {
+            self.step(SIMULATION_TIMESTEP);
+            self.time_accumulator -= SIMULATION_TIMESTEP;
+
+            if self.settled {
+                self.time_accumulator = 0.0;
+                break;
+            }
+        }
+    }
+
+    pub fn step(&mut self, delta_t: f32) {
+        match self.integrator {
+            Integrator::Euler => {
+                self.accumulate_forces();
+                for u in 0..self.particles.len() {
+                    if !self.is_pinned(u) {
+                        self.particles[u].step(delta_t);
+                    }
+                }
+            }
+            Integrator::VelocityVerlet => {
+                self.accumulate_forces();
+                for u in 0..self.particles.len() {
+                    if !self.is_pinned(u) {
+                        self.particles[u].verlet_position_step(delta_t);
+                    }
+                }
+                self.accumulate_forces();
+                for u in 0..self.particles.len() {
+                    if !self.is_pinned(u) {
+                        self.particles[u].verlet_velocity_step(delta_t);
+                    }
+                }
+            }
+            Integrator::RungeKutta4 => self.runge_kutta_step(delta_t),
+        }
+
+        self.update_settled();
+    }
+
+    fn runge_kutta_step(&mut self, delta_t: f32) {
+        let start = self
+            .particles
+            .iter()
+            .map(|particle| (particle.position, particle.velocity))
+            .collect::<Vec<_>>();
+        let mut k_position = vec![[Vector3::zero(); 4]; self.particles.len()];
+        let mut k_velocity = vec![[Vector3::zero(); 4]; self.particles.len()];
+        let stage_t = [0.0, 0.5 * delta_t, 0.5 * delta_t, delta_t];
+
+        for stage in 0..4 {
+            if stage > 0 {
+                for u in 0..self.particles.len() {
+                    if self.is_pinned(u) {
+                        continue;
+                    }
+                    self.particles[u].position = start[u].0 + k_position[u][stage - 1] * stage_t[stage];
+                    self.particles[u].velocity = start[u].1 + k_velocity[u][stage - 1] * stage_t[stage];
+                }
+            }
+
+            self.accumulate_forces();
+            for u in 0..self.particles.len() {
+                k_position[u][stage] = self.particles[u].velocity;
+                k_velocity[u][stage] = self.particles[u].get_acceleration();
+            }
+        }
+
+        for u in 0..self.particles.len() {
+            if self.is_pinned(u) {
+                continue;
+            }
+            let k_x = k_position[u];
+            let k_v = k_velocity[u];
+            self.particles[u].position =
+                start[u].0 + (k_x[0] + k_x[1] * 2.0 + k_x[2] * 2.0 + k_x[3]) * (delta_t / 6.0);
+            self.particles[u].velocity =
+                start[u].1 + (k_v[0] + k_v[1] * 2.0 + k_v[2] * 2.0 + k_v[3]) * (delta_t / 6.0);
+            self.particles[u].limit_speed();
+        }
+    }
+
+    /// The sequential and `parallel` builds split the repulsion into the same blocks and sum
+    /// them in the same order, so they produce identical layouts.
+    fn accumulate_forces(&mut self) {
+        let forces = repulsion_forces(&self.particles);
+
+        for (u, force) in forces.into_iter().enumerate() {
+            let force = force + particle_force(&self.particles, &self.adjacency[u], u);
+            self.particles[u].clear_force();
+            self.particles[u].add_force(force);
+        }
+    }
+
+    fn is_pinned(&self, u: usize) -> bool {
+        self.pinned == Some(u)
+    }
+
+    pub fn kinetic_energy(&self) -> f32 {
+        self.particles
+            .iter()
+            .map(|particle| particle.kinetic_energy())
+            .sum()
+    }
+
+    fn update_settled(&mut self) {
+        let particle_ct = self.particles.len().max(1) as f32;
+        if self.kinetic_energy() / particle_ct < SETTLED_KINETIC_ENERGY {
+            self.settled_step_ct += 1;
+        } else {
+            self.settled_step_ct = 0;
+        }
+
+        if self.settled_step_ct >= SETTLED_STEP_CT {
+            self.settled = true;
+        }
+    }
+
+    /// Resumes a settled simulation. Called whenever a particle is moved by the user.
+    pub fn wake(&mut self) {
+        self.settled = false;
+        self.settled_step_ct = 0;
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.settled
+    }
+
+    pub fn set_position(&mut self, u: usize, position: Vector3<f32>) {
+        if let Some(particle) = self.particles.get_mut(u) {
+            particle.position = position;
+            self.wake();
+        }
+    }
+}
+
+impl Simulation for ForceLayout {
+    type Snapshot = LayoutSnapshot;
+
+    fn tick(&mut self, delta_t: Duration) {
+        ForceLayout::tick(self, delta_t);
+    }
+
+    fn write_snapshot(&self, snapshot: &mut LayoutSnapshot) {
+        snapshot.positions.clear();
+        snapshot
+            .positions
+            .extend(self.particles.iter().map(|particle| particle.position));
+        snapshot.settled = self.settled;
+        snapshot.frame_ct = self.frame_ct;
+    }
+
+    fn is_idle(&self) -> bool {
+        self.settled || self.fixed_frame.is_some()
+    }
+}
+
+/// Force on particle `u` other than repulsion: damping, centering and springs to its
+/// `neighbors`.
+fn particle_force(particles: &[PhysicsParticle], neighbors: &[usize], u: usize) -> Vector3<f32> {
+    let mut force = -particles[u].get_velocity() * RESISTANCE
+        - particles[u].get_position() * CENTERING;
+
+    for &v in neighbors {
+        if v == u {
+            continue;
+        }
+        let r = (particles[u].get_position() - particles[v].get_position()).magnitude();
+        let direction = (particles[u].get_position() - particles[v].get_position()) / r;
+
+        force += (EDGE_EQ_LENGTH - r) * EDGE_SPRING_STIFFNESS * direction;
+    }
+
+    force
+}
+
+/// Repulsion between every pair of particles, each pair computed once and applied to both
+/// ends.
+fn repulsion_forces(particles: &[PhysicsParticle]) -> Vec<Vector3<f32>> {
+    let block_ct = MAX_FORCE_BLOCK_CT.min(particles.len());
+    let block_forces = |block| repulsion_block_forces(particles, block, block_ct);
+
+    #[cfg(feature = "parallel")]
+    let blocks = (0..block_ct).into_par_iter().map(block_forces).collect::<Vec<_>>();
+    #[cfg(not(feature = "parallel"))]
+    let blocks = (0..block_ct).map(block_forces);
+
+    sum_forces(particles.len(), blocks)
+}
+
+/// Repulsion between every particle `u` in `block`, which holds every `block_ct`th particle,
+/// and the particles after it. Interleaving the blocks gives each a similar share of the
+/// pairs.
+fn repulsion_block_forces(particles: &[PhysicsParticle], block: usize, block_ct: usize) -> Vec<Vector3<f32>> {
+    let mut forces = vec![Vector3::zero(); particles.len()];
+    for u in (block..particles.len()).step_by(block_ct) {
+        for v in u + 1..particles.len() {
+            let r = (particles[u].get_position() - particles[v].get_position()).magnitude();
+            let direction = (particles[u].get_position() - particles[v].get_position()) / r;
+
+            let force = 1. / (r * r) * VERTEX_REPULSION * direction;
+            forces[u] += force;
+            forces[v] -= force;
+        }
+    }
+    forces
+}
+
+/// Sums per-block force buffers in block order.
+fn sum_forces(particle_ct: usize, blocks: impl IntoIterator<Item = Vec<Vector3<f32>>>) -> Vec<Vector3<f32>> {
+    blocks.into_iter().fold(vec![Vector3::zero(); particle_ct], |mut sum, block| {
+        for (sum, force) in sum.iter_mut().zip(block) {
+            *sum += force;
+        }
+        sum
+    })
+}
+
+pub struct PhysicsParticle {
+    position: Vector3<f32>,
+    velocity: Vector3<f32>,
+    force: Vector3<f32>,
+    previous_acceleration: Vector3<f32>,
+    mass: f32,
+}
+
+
+impl PhysicsParticle {
+    pub fn new(position: Vector3<f32>, mass: f32) -> PhysicsParticle {
+
+        Self {
+            position: position,
+            velocity: Vector3::zero(),
+            force: Vector3::zero(),
+            previous_acceleration: Vector3::zero(),
+            mass: mass,
+        }
+    }
+
+    pub fn add_force(&mut self, force: Vector3<f32>) {
+        self.force += force;
+    }
+
+    pub fn clear_force(&mut self) {
+        self.force = Vector3::zero();
+    }
+
+    pub fn clear_velocity(&mut self) {
+        self.velocity = Vector3::zero();
+    }
+
+    /// Semi-implicit Euler step.
+    pub fn step(&mut self, delta_t: f32) {
+        self.velocity += self.get_acceleration() * delta_t;
+        self.limit_speed();
+        self.position += self.velocity * delta_t;
+    }
+
+    /// First half of a velocity-Verlet step. Forces must be re-accumulated at the new
+    /// position before calling `verlet_velocity_step`.
+    pub fn verlet_position_step(&mut self, delta_t: f32) {
+        self.previous_acceleration = self.get_acceleration();
+        self.position += self.velocity * delta_t + 0.5 * self.previous_acceleration * delta_t * delta_t;
+    }
+
+    pub fn verlet_velocity_step(&mut self, delta_t: f32) {
+        self.velocity += 0.5 * (self.previous_acceleration + self.get_acceleration()) * delta_t;
+        self.limit_speed();
+    }
+
+    pub fn limit_speed(&mut self) {
+        let vel_mag = self.velocity.magnitude();
+
+        if vel_mag > SPEED_LIMIT {
+            self.velocity = self.velocity / vel_mag * SPEED_LIMIT;
+        }
+    }
+
+    pub fn get_position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    pub fn get_velocity(&self) -> Vector3<f32> {
+        self.velocity
+    }
+
+    pub fn get_acceleration(&self) -> Vector3<f32> {
+        self.force / self.mass
+    }
+
+    pub fn kinetic_energy(&self) -> f32 {
+        0.5 * self.mass * self.velocity.magnitude2()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    /// Two particles joined by an edge, off balance.
+    fn spring(integrator: Integrator) -> ForceLayout {
+        let particles = vec![
+            PhysicsParticle::new(Vector3::new(-1.0, 0.0, 0.0), 1.0),
+            PhysicsParticle::new(Vector3::new(1.5, 0.3, -0.2), 1.0),
+        ];
+        let mut layout = ForceLayout::new(particles, Arc::new(vec![vec![1], vec![0]]));
+        layout.integrator = integrator;
+        layout
+    }
+
+    fn positions(layout: &ForceLayout) -> Vec<Vector3<f32>> {
+        layout.particles.iter().map(|particle| particle.get_position()).collect()
+    }
+
+    #[test]
+    fn spring_converges() {
+        for integrator in [Integrator::VelocityVerlet, Integrator::RungeKutta4] {
+            let mut layout = spring(integrator);
+            // Centering is much weaker than damping, so the pair drifts to the origin slowly.
+            for _ in 0..24000 {
+                layout.step(SIMULATION_TIMESTEP);
+            }
+
+            assert!(layout.is_settled(), "{:?} didn't settle", integrator);
+            layout.accumulate_forces();
+            for particle in &layout.particles {
+                assert!(particle.get_velocity().magnitude() < 1e-3, "{:?}", integrator);
+                assert!(particle.get_acceleration().magnitude() < 1e-3, "{:?}", integrator);
+            }
+        }
+    }
+
+    #[test]
+    fn ticks_whole_timesteps() {
+        let mut ticked = spring(Integrator::VelocityVerlet);
+        let mut stepped = spring(Integrator::VelocityVerlet);
+
+        // Two and a half steps' worth runs two, and the half left over carries into the next
+        // tick.
+        ticked.tick(Duration::from_secs_f32(2.5 * SIMULATION_TIMESTEP));
+        stepped.step(SIMULATION_TIMESTEP);
+        stepped.step(SIMULATION_TIMESTEP);
+        assert_eq!(positions(&ticked), positions(&stepped));
+
+        ticked.tick(Duration::from_secs_f32(0.6 * SIMULATION_TIMESTEP));
+        stepped.step(SIMULATION_TIMESTEP);
+        assert_eq!(positions(&ticked), positions(&stepped));
+    }
+
+    #[test]
+    fn long_ticks_are_capped() {
+        let mut ticked = spring(Integrator::VelocityVerlet);
+        let mut stepped = spring(Integrator::VelocityVerlet);
+
+        ticked.tick(Duration::from_secs(1));
+        for _ in 0..MAX_STEPS_PER_TICK {
+            stepped.step(SIMULATION_TIMESTEP);
+        }
+        assert_eq!(positions(&ticked), positions(&stepped));
+    }
+
+    #[test]
+    fn repulsion_matches_sequential_sum() {
+        // More particles than blocks.
+        let mut rng = StdRng::seed_from_u64(1);
+        let particles = (0..150)
+            .map(|_| PhysicsParticle::new(Vector3::new(rng.random(), rng.random(), rng.random()) * 10.0, 1.0))
+            .collect::<Vec<_>>();
+
+        // Whichever build this is, it sums exactly like the sequential one.
+        let forces = repulsion_forces(&particles);
+        let sequential = sum_forces(
+            particles.len(),
+            (0..MAX_FORCE_BLOCK_CT).map(|block| repulsion_block_forces(&particles, block, MAX_FORCE_BLOCK_CT)),
+        );
+        assert_eq!(forces, sequential);
+
+        // And it agrees with every particle gathering its own forces.
+        for u in 0..particles.len() {
+            let mut expected = Vector3::zero();
+            for v in (0..particles.len()).filter(|v| *v != u) {
+                let offset = particles[u].get_position() - particles[v].get_position();
+                expected += VERTEX_REPULSION / offset.magnitude2() * offset.normalize();
+            }
+            assert!((forces[u] - expected).magnitude() < 1e-3 * expected.magnitude().max(1.0), "particle {}", u);
+        }
+    }
+
+    #[test]
+    fn settles_after_quiet_steps() {
+        let mut layout = ForceLayout::new(
+            vec![PhysicsParticle::new(Vector3::zero(), 1.0)],
+            Arc::new(vec![vec![]]),
+        );
+        for _ in 1..SETTLED_STEP_CT {
+            layout.step(SIMULATION_TIMESTEP);
+        }
+        assert!(!layout.is_settled());
+        layout.step(SIMULATION_TIMESTEP);
+        assert!(layout.is_settled());
+        assert!(layout.is_idle());
+
+        layout.wake();
+        assert!(!layout.is_settled());
+    }
+}
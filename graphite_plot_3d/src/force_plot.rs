@@ -1,70 +1,189 @@
-use instant::Duration;
-use winit::{dpi::{PhysicalPosition, PhysicalSize}, event::MouseButton};
+use std::{collections::HashMap, sync::Arc};
+
+use winit::{dpi::{PhysicalPosition, PhysicalSize}, event::{ElementState, KeyEvent}, keyboard::{KeyCode, PhysicalKey}};
 
 use crate::{
-    draw::{Color, GetLines, GetPoints, LineElement, PointElement }, mouse::{MouseEvent, MouseTracker}, plot_graphic::PlotGraphicState, plot_window::{PlotWindowState, PlotGraphic}
+    actions::Action, bindings::{Bindings, Stroke}, draw::{Color, Curve, GetLines, GetPoints, LineElement, PointElement, SizeUnits}, force_layout::{ForceLayout, LayoutSnapshot, PhysicsParticle, SIMULATION_TIMESTEP}, generators::{Generator, GeneratorMenu, MenuInput}, initial_placement::InitialPlacement, mouse::{MouseEvent, MouseTracker}, options::PlotOptions, overlay::{Anchor, GetOverlay, OverlayElement, OverlayItem, text_size}, picking::{GetPickables, Pick, Picker}, plot_graphic::PlotGraphicState, plot_window::{PlotWindowState, PlotGraphic}, simulation::SimulationRunner, texture, theme::Theme
 };
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{capture::CopiedFrame, capturer::Capturer};
 
 use gscientific::graph::Graph;
-use cgmath::{num_traits::Pow, InnerSpace, Transform, Vector2, Vector3, Vector4, Zero};
-use rand::random;
-
-const VERTEX_REPULSION: f32 = 3.0;
-const EDGE_SPRING_STIFFNESS: f32 = 6.0;
-const EDGE_EQ_LENGTH: f32 = 1.0;
-const RESISTANCE: f32 = 0.5;
-const CENTERING: f32 = 0.05;
-const SPEED_LIMIT: f32 = 200.0;
+use cgmath::{num_traits::Pow, InnerSpace, Matrix4, SquareMatrix, Transform, Vector2, Vector3, Vector4, Zero};
+use rand::{SeedableRng, random, rngs::StdRng};
+
+/// Radius of a vertex until it is given a size.
+const DEFAULT_VERTEX_SIZE: f32 = 0.1;
+/// Radius of a default-sized vertex drawn in pixels. Other sizes keep their proportion to it.
+const VERTEX_SIZE_PIXELS: f32 = 6.0;
+/// Edge width in clip space units.
+const EDGE_WIDTH: f32 = 0.04;
+/// Width of an edge drawn in pixels.
+const EDGE_WIDTH_PIXELS: f32 = 1.5;
+/// How far apart parallel edges bow out, as a fraction of their length.
+const PARALLEL_EDGE_SPREAD: f32 = 0.2;
+/// How far every edge bows out with curved edges on, as a fraction of its length.
+const CURVED_EDGE_BEND: f32 = 0.15;
+/// How far a self-loop reaches from its vertex, in default vertex radii. Each further loop
+/// on the same vertex reaches half again as far.
+const LOOP_SIZE: f32 = 4.0;
+/// Angle in radians between where a self-loop leaves its vertex and the way it points.
+const LOOP_SPREAD: f32 = 0.5;
+/// Edges are easier to hit than they are to see.
+const EDGE_PICK_WIDTH: f32 = 0.08;
+const MENU_TEXT_SCALE: f32 = 2.0;
+/// Distance in pixels of the generator menu from the window's corner.
+const MENU_MARGIN: f32 = 12.0;
 
 struct ForcePlotModel<T: Graph> {
     graph: T,
-    particles: Vec<PhysicsParticle>,
+    /// The latest positions published by the simulation.
+    layout: LayoutSnapshot,
     colors: Vec<Color>,
     sizes: Vec<f32>,
     interacted_particle: Option<usize>,
+    /// Colors of everything not colored by the data.
+    theme: Theme,
+    line_units: SizeUnits,
+    point_units: SizeUnits,
+    /// The camera's view projection as of the last update, for sizing vertices drawn in pixels.
+    view_proj: Matrix4<f32>,
+    /// World units per surface pixel, per unit of depth in front of the camera.
+    pixel_scale: f32,
+    /// Which way the camera looks. Edges bow out across it, so the bends stay in view.
+    view_direction: Vector3<f32>,
+    /// Bow every edge out, not only parallel ones.
+    curved_edges: bool,
+    generator_menu: Option<GeneratorMenu>,
 }
 
 impl<T: Graph> ForcePlotModel<T> {
-    fn tick(&mut self, delta_t: Duration) {
-        for u in 0..self.particles.len() {
-            self.particles[u].clear_force();
-            let tmp_force = -self.particles[u].get_velocity() * RESISTANCE
-                - self.particles[u].get_position() * CENTERING;
-            self.particles[u].add_force(tmp_force);
-
-            for v in 0..u {
-                let r = (self.particles[u].get_position() - self.particles[v].get_position())
-                    .magnitude();
-                let direction =
-                    (self.particles[u].get_position() - self.particles[v].get_position()) / r;
-
-                self.particles[u].add_force(1. / (r * r) * VERTEX_REPULSION * direction);
-                self.particles[v].add_force(-1. / (r * r) * VERTEX_REPULSION * direction);
-
-                if self.graph.adjacent(u, v) {
-                    self.particles[u]
-                        .add_force((EDGE_EQ_LENGTH - r) * EDGE_SPRING_STIFFNESS * direction);
-                    self.particles[v]
-                        .add_force(-(EDGE_EQ_LENGTH - r) * EDGE_SPRING_STIFFNESS * direction);
-                }
-            }
+    /// An edge width in clip space units, in the units edges are drawn in.
+    fn line_width(&self, width: f32) -> f32 {
+        match self.line_units {
+            SizeUnits::World => width,
+            SizeUnits::Pixels => width / EDGE_WIDTH * EDGE_WIDTH_PIXELS,
+        }
+    }
+
+    /// A vertex size in world units, in the units vertices are drawn in.
+    fn point_size(&self, size: f32) -> f32 {
+        match self.point_units {
+            SizeUnits::World => size,
+            SizeUnits::Pixels => size / DEFAULT_VERTEX_SIZE * VERTEX_SIZE_PIXELS,
         }
+    }
 
-        for u in 0..self.particles.len() {
-            if self.interacted_particle.is_some() && u == self.interacted_particle.unwrap() {
-                continue;
+    /// How far from its center a vertex of `size` at `position` is drawn, in world units.
+    /// Vertices drawn in pixels cover more of the world the farther away they are.
+    fn world_radius(&self, size: f32, position: Vector3<f32>) -> f32 {
+        match self.point_units {
+            SizeUnits::World => size,
+            SizeUnits::Pixels => {
+                let depth = (self.view_proj * position.extend(1.0)).w;
+                self.point_size(size) * self.pixel_scale * depth
             }
-            self.particles[u].step(delta_t.as_secs_f32());
         }
     }
+
+    /// Every edge with both endpoints laid out, and the curve between their centers it's
+    /// drawn along. Parallel edges fan out and self-loops on the same vertex nest.
+    fn edge_curves(&self) -> impl Iterator<Item = (usize, usize, Curve)> {
+        let positions = &self.layout.positions;
+        let mut parallel_cts = HashMap::new();
+        for (u, v, _) in self.graph.edges() {
+            *parallel_cts.entry((u.min(v), u.max(v))).or_insert(0) += 1;
+        }
+
+        let mut drawn_cts = HashMap::new();
+        self.graph
+            .edges()
+            .filter(|(u, v, _)| *u < positions.len() && *v < positions.len())
+            .map(move |(u, v, _)| {
+                let pair = (u.min(v), u.max(v));
+                let drawn_ct = drawn_cts.entry(pair).or_insert(0);
+                let curve = if u == v {
+                    self.loop_curve(u, *drawn_ct)
+                } else {
+                    self.edge_curve(u, v, *drawn_ct, parallel_cts[&pair])
+                };
+                *drawn_ct += 1;
+                (u, v, curve)
+            })
+    }
+
+    /// The `index`th of `parallel_ct` edges between `u` and `v`.
+    fn edge_curve(&self, u: usize, v: usize, index: usize, parallel_ct: usize) -> Curve {
+        let (a, b) = (self.layout.positions[u], self.layout.positions[v]);
+        let mut bend = (index as f32 - (parallel_ct - 1) as f32 / 2.0) * PARALLEL_EDGE_SPREAD;
+        if self.curved_edges {
+            bend += CURVED_EDGE_BEND;
+        }
+
+        // Bends are measured from the lower endpoint, so parallel edges fan out the same
+        // whichever way round the graph lists them.
+        let along = if u < v { b - a } else { a - b };
+        let across = along.cross(self.view_direction);
+        if bend == 0.0 || across.magnitude2() == 0.0 {
+            return Curve::Straight(a, b);
+        }
+        let across = across.normalize() * along.magnitude();
+        // A quadratic curve gets halfway to its middle point.
+        Curve::Quadratic(a, (a + b) / 2.0 + across * (2.0 * bend), b)
+    }
+
+    /// The `index`th self-loop on `u`, pointing away from its neighbors, where there's most
+    /// room for it, and facing the camera.
+    fn loop_curve(&self, u: usize, index: usize) -> Curve {
+        let positions = &self.layout.positions;
+        let center = positions[u];
+        let toward_neighbors = self
+            .graph
+            .neighbors(u)
+            .filter(|(v, _)| *v != u && *v < positions.len())
+            .map(|(v, _)| positions[v] - center)
+            .fold(Vector3::zero(), |sum, offset| sum + offset);
+        // Loops lie flat to the screen, so none hides behind its own vertex.
+        let flatten = |v: Vector3<f32>| v - self.view_direction * v.dot(self.view_direction);
+        let mut outward = -flatten(toward_neighbors);
+        if outward.magnitude2() == 0.0 {
+            outward = flatten(Vector3::unit_y());
+        }
+        if outward.magnitude2() == 0.0 {
+            outward = flatten(Vector3::unit_x());
+        }
+        let outward = outward.normalize();
+        let side = outward.cross(self.view_direction);
+
+        let size = self.world_radius(DEFAULT_VERTEX_SIZE, center) * LOOP_SIZE * (1.0 + 0.5 * index as f32);
+        let (sin, cos) = LOOP_SPREAD.sin_cos();
+        let leaving = outward * cos + side * sin;
+        let returning = outward * cos - side * sin;
+        Curve::Cubic(center, center + leaving * size, center + returning * size, center)
+    }
 }
 
 pub struct ForcePlot<T: Graph> {
     model: ForcePlotModel<T>,
-    
+    simulation: SimulationRunner<ForceLayout>,
+    rng: StdRng,
+
     mouse_tracker: MouseTracker,
+    bindings: Bindings,
     drag_previous_position: Vector2<f32>,
     state: Option<PlotGraphicState<ForcePlotModel<T>>>,
+    picker: Option<Picker>,
+    /// Built-in themes and any loaded ones, in the order switching themes goes through them.
+    themes: Vec<Theme>,
+    fog: f32,
+    #[cfg(not(target_arch = "wasm32"))]
+    capturer: Capturer,
+    /// Save the next frame drawn.
+    #[cfg(not(target_arch = "wasm32"))]
+    screenshot_requested: bool,
+    /// Layout frame the recording is waiting for before it captures again.
+    #[cfg(not(target_arch = "wasm32"))]
+    next_recorded_frame: u64,
 }
 
 fn pixel_to_clip(pixel: PhysicalPosition<f32>, window_size: PhysicalSize<u32>) -> Vector2<f32> {
@@ -74,48 +193,75 @@ fn pixel_to_clip(pixel: PhysicalPosition<f32>, window_size: PhysicalSize<u32>) -
 impl<T: Graph> PlotGraphic for ForcePlot<T> {
     fn init(&mut self, state: &PlotWindowState) {
         self.state = Some(PlotGraphicState::new(state));
-        let point_element = PointElement::new(self.state.as_ref().unwrap(), state);
-        let line_element = LineElement::new(self.state.as_ref().unwrap(), state);
+        self.state.as_mut().unwrap().set_fog(self.fog);
+        self.state.as_mut().unwrap().set_background(self.model.theme.background);
+        let point_element = PointElement::new(self.state.as_ref().unwrap(), state).with_units(self.model.point_units);
+        let line_element = LineElement::new(self.state.as_ref().unwrap(), state).with_units(self.model.line_units);
+        let overlay_element = OverlayElement::new(self.state.as_ref().unwrap(), state, Some(texture::Texture::DEPTH_FORMAT));
         self.state.as_mut().unwrap().add_element(point_element);
         self.state.as_mut().unwrap().add_element(line_element);
+        self.state.as_mut().unwrap().add_element(overlay_element);
+        self.picker = Some(
+            Picker::new(self.state.as_ref().unwrap(), state).with_units(self.model.point_units, self.model.line_units),
+        );
+        self.simulation.start();
     }
 
     fn tick(&mut self, delta_t: instant::Duration) {
-        self.model.tick(delta_t);
+        self.simulation.tick(delta_t);
     }
 
-    fn input(&mut self, event: &winit::event::WindowEvent, window_size: PhysicalSize<u32>) -> bool {
-        if self.state.as_mut().unwrap().camera_controller.process_events(event) {
+    fn input(&mut self, event: &winit::event::WindowEvent, window_state: &PlotWindowState) -> bool {
+        let window_size = window_state.size;
+        // The menu takes the arrow keys from the camera while it is open.
+        if self.model.generator_menu.is_some()
+            && let winit::event::WindowEvent::KeyboardInput { event: KeyEvent {
+                state: ElementState::Pressed,
+                physical_key: PhysicalKey::Code(key),
+                ..
+            }, .. } = event
+        {
+            self.generator_menu_input(*key);
+            return true;
+        }
+        if self.state.as_mut().unwrap().camera_controller.process_events(event, &self.bindings) {
             return true;
         }
 
-        match self.mouse_tracker.translate_event(event) {
+        let mouse_event = self.mouse_tracker.translate_event(event);
+        let modifiers = self.mouse_tracker.get_modifiers();
+        let stroke = match event {
+            winit::event::WindowEvent::KeyboardInput { event: KeyEvent {
+                state: ElementState::Pressed,
+                physical_key: PhysicalKey::Code(key),
+                ..
+            }, .. } => Some(Stroke::key(*key, modifiers)),
+            _ => match mouse_event {
+                // Touches arrive as mouse buttons too.
+                Some(MouseEvent::ButtonPressed(button)) => Some(Stroke::mouse(button, modifiers)),
+                _ => None,
+            },
+        };
+        if let Some(action) = stroke.and_then(|stroke| self.bindings.press(stroke)) {
+            self.perform(action, window_state);
+        }
+
+        match mouse_event {
             Some(mouse_event) => {
                 match mouse_event {
-                    MouseEvent::ButtonPressed(MouseButton::Left) => {
-                        let mouse_pixel_pos = self.mouse_tracker.get_position();
-                        let mouse_position = pixel_to_clip(mouse_pixel_pos, window_size);
-
-                        match self.get_particle_index_by_clip_position(mouse_position) {
-                            Some(index) => {
-                                self.model.interacted_particle = Some(index);
-                            },
-                            None => {
-                                self.drag_previous_position = mouse_position;
-                            }
-                        }
-                    },
                     MouseEvent::ButtonReleased(_) => {
                         self.model.interacted_particle = None;
+                        self.simulation.send(|layout| layout.pinned = None);
                     },
                     MouseEvent::CursorDragged(_) => {
                         match self.model.interacted_particle {
                             Some(index) => {
                                 let mouse_clip = pixel_to_clip(self.mouse_tracker.get_position(), window_size);
-                                let particle_clip = self.state.as_ref().unwrap().camera.world_to_clip(self.model.particles[index].position);
+                                let particle_clip = self.state.as_ref().unwrap().camera.world_to_clip(self.model.layout.positions[index]);
                                 let new_clip = Vector3::new(mouse_clip.x, mouse_clip.y, particle_clip.z);
                                 let new_world = self.state.as_ref().unwrap().camera.clip_to_world(new_clip);
-                                self.model.particles[index].position = new_world;
+                                self.simulation
+                                    .send(move |layout| layout.set_position(index, new_world));
                             },
                             None => {
                                 let mouse_position = pixel_to_clip(self.mouse_tracker.get_position(), window_size);
@@ -129,6 +275,17 @@ impl<T: Graph> PlotGraphic for ForcePlot<T> {
                     MouseEvent::WheelScrolled => {
                         self.state.as_mut().unwrap().camera.zoom(self.mouse_tracker.consume_scroll_delta());
                     },
+                    MouseEvent::Gestured => {
+                        let gesture = self.mouse_tracker.consume_gesture();
+                        let camera = &mut self.state.as_mut().unwrap().camera;
+                        camera.pan(Vector2::new(
+                            2.0 * gesture.pan.x / window_size.width as f32,
+                            -2.0 * gesture.pan.y / window_size.height as f32,
+                        ));
+                        // `zoom` scales the distance to the target by exp(-0.1 * delta).
+                        camera.zoom(10.0 * gesture.zoom.ln());
+                        camera.roll(gesture.rotation);
+                    },
                     _ => {
 
                     }
@@ -145,7 +302,16 @@ impl<T: Graph> PlotGraphic for ForcePlot<T> {
     fn render(&mut self, window_state: &mut PlotWindowState) -> Result<(), wgpu::SurfaceError> {
         match &self.state {
             Some(state) => {
-                state.render(window_state)?;
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    let record = self.capturer.is_recording()
+                        && self.capturer.can_record()
+                        && self.model.layout.frame_ct >= self.next_recorded_frame;
+                    let frame = state.render(window_state, self.screenshot_requested || record)?;
+                    self.frame_drawn(window_state, frame, record);
+                }
+                #[cfg(target_arch = "wasm32")]
+                state.render(window_state, false)?;
             },
             None => {
                 println!("Graphic state not initialized yet!");
@@ -156,100 +322,283 @@ impl<T: Graph> PlotGraphic for ForcePlot<T> {
     }
     
     fn update(&mut self, window_state: &mut PlotWindowState, rewrite_data: bool) {
+        self.simulation.latest(&mut self.model.layout);
         let state = self.state.as_mut();
 
         state.map(|state| {
+            let camera = &state.camera;
+            self.model.view_proj = camera.build_view_projection_matrix();
+            self.model.view_direction = (camera.target - camera.eye).normalize();
+            self.model.pixel_scale = 2.0 * (camera.fovy.to_radians() / 2.0).tan() / window_state.config.height.max(1) as f32;
             if rewrite_data {
                 state.update_data(window_state, &self.model);
+                if let Some(picker) = self.picker.as_mut() {
+                    picker.update(window_state, &self.model);
+                }
             }
             state.update(window_state);
         });
+        self.resolve_picks(window_state);
     }
 }
 
-// fn logistic(x: f32) -> f32 {
-//     1.0 / (1.0 + (-x).exp())
-// }
+impl<T: Graph> ForcePlot<T> {
+    /// Carries out what a key or mouse binding asked for. Camera movement is left to the
+    /// camera controller.
+    fn perform(&mut self, action: Action, window_state: &PlotWindowState) {
+        match action {
+            Action::Grab => {
+                let mouse_pixel_pos = self.mouse_tracker.get_position();
+                let mouse_position = pixel_to_clip(mouse_pixel_pos, window_state.size);
+
+                match self.pick_vertex(window_state) {
+                    Some(index) => {
+                        self.model.interacted_particle = Some(index);
+                        self.simulation.send(move |layout| layout.pinned = Some(index));
+                    },
+                    None => {
+                        self.drag_previous_position = mouse_position;
+                    }
+                }
+            },
+            Action::OpenGeneratorMenu => {
+                self.model.generator_menu = Some(GeneratorMenu::default());
+            },
+            Action::CycleIntegrator => {
+                self.simulation.send(|layout| {
+                    layout.integrator = layout.integrator.next();
+                    log::info!("Integrator: {:?}", layout.integrator);
+                    layout.wake();
+                });
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::Screenshot => {
+                self.screenshot_requested = true;
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::ToggleRecording => {
+                self.toggle_recording();
+            },
+            Action::CycleTheme => {
+                self.cycle_theme();
+            },
+            Action::ToggleCurvedEdges => {
+                self.model.curved_edges = !self.model.curved_edges;
+            },
+            _ => {}
+        }
+    }
 
-pub struct PhysicsParticle {
-    position: Vector3<f32>,
-    velocity: Vector3<f32>,
-    force: Vector3<f32>,
-    mass: f32,
-}
+    pub fn with_options(graph: T, options: &PlotOptions) -> Self {
+        let seed = options.seed.unwrap_or_else(random);
+        log::info!("Layout seed: {}", seed);
+        let mut rng = StdRng::seed_from_u64(seed);
 
+        let particles = options
+            .initial_placement
+            .place(&graph, (graph.vertex_ct() as f32).pow(1. / 3.), &mut rng)
+            .into_iter()
+            .map(|position| PhysicsParticle::new(position, 1.0))
+            .collect::<Vec<PhysicsParticle>>();
+        let colors = (0..graph.vertex_ct()).map(|_i| options.theme.vertex_fill).collect();
+        let mut sizes= Vec::new();
+        sizes.resize(graph.vertex_ct(), DEFAULT_VERTEX_SIZE);
+        let state = None;
+        let interacted_particle = None;
+        let drag_previous_position = Vector2::zero();
 
-impl PhysicsParticle {
-    pub fn new(position: Vector3<f32>, mass: f32) -> PhysicsParticle {
+        let adjacency = (0..graph.vertex_ct())
+            .map(|u| graph.neighbors(u).map(|(v, _)| v).collect())
+            .collect::<Vec<Vec<usize>>>();
+        let simulation = SimulationRunner::new(ForceLayout::new(particles, Arc::new(adjacency)));
+        let mut themes = Theme::builtins();
+        if !themes.contains(&options.theme) {
+            themes.push(options.theme.clone());
+        }
+
+        let model = ForcePlotModel {
+            graph,
+            layout: LayoutSnapshot::default(),
+            colors,
+            sizes,
+            interacted_particle,
+            theme: options.theme.clone(),
+            line_units: options.render.line_units,
+            point_units: options.render.point_units,
+            view_proj: Matrix4::identity(),
+            pixel_scale: 0.0,
+            view_direction: -Vector3::unit_z(),
+            curved_edges: options.render.curved_edges,
+            generator_menu: None,
+        };
 
         Self {
-            position: position,
-            velocity: Vector3::zero(),
-            force: Vector3::zero(),
-            mass: mass,
+            model,
+            simulation,
+            rng,
+            state,
+            mouse_tracker: MouseTracker::new(),
+            bindings: options.bindings.clone(),
+            drag_previous_position,
+            picker: None,
+            themes,
+            fog: options.render.fog,
+            #[cfg(not(target_arch = "wasm32"))]
+            capturer: Capturer::new(options.capture.clone()),
+            #[cfg(not(target_arch = "wasm32"))]
+            screenshot_requested: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            next_recorded_frame: 0,
         }
     }
 
-    pub fn add_force(&mut self, force: Vector3<f32>) {
-        self.force += force;
+    /// Runs `step_ct` fixed simulation steps without rendering. Together with a fixed seed
+    /// this reproduces a layout exactly.
+    /// Only possible before the plot is shown, while the simulation hasn't moved to its own
+    /// thread.
+    pub fn advance(&mut self, step_ct: usize) {
+        if let Some(layout) = self.simulation.local_mut() {
+            for _ in 0..step_ct {
+                if layout.is_settled() {
+                    break;
+                }
+                layout.step(SIMULATION_TIMESTEP);
+            }
+        }
     }
 
-    pub fn clear_force(&mut self) {
-        self.force = Vector3::zero();
+    /// Switches colors. Vertices with the old theme's fill take the new one.
+    fn set_theme(&mut self, theme: Theme) {
+        let old_fill = self.model.theme.vertex_fill;
+        for color in self.model.colors.iter_mut().filter(|color| **color == old_fill) {
+            *color = theme.vertex_fill;
+        }
+        if let Some(state) = self.state.as_mut() {
+            state.set_background(theme.background);
+        }
+        self.model.theme = theme;
     }
 
-    pub fn clear_velocity(&mut self) {
-        self.velocity = Vector3::zero();
+    fn cycle_theme(&mut self) {
+        let current = self.themes.iter().position(|theme| *theme == self.model.theme);
+        let next = current.map_or(0, |i| (i + 1) % self.themes.len());
+        self.set_theme(self.themes[next].clone());
     }
 
-    pub fn step(&mut self, delta_t: f32) {
-        self.velocity += self.force / self.mass * delta_t;
-        let vel_mag = self.velocity.magnitude();
-
-        if vel_mag > SPEED_LIMIT {
-            self.velocity = self.velocity / vel_mag * SPEED_LIMIT;
+    /// Passes a key to the generator menu, and replaces the graph with the generator it picks.
+    fn generator_menu_input(&mut self, key: KeyCode) {
+        let Some(menu) = self.model.generator_menu.as_mut() else {
+            return;
+        };
+        match menu.input(key) {
+            MenuInput::Stay => {}
+            MenuInput::Close => self.model.generator_menu = None,
+            MenuInput::Generate(generator) => {
+                self.model.generator_menu = None;
+                self.replace_graph(&generator);
+            }
         }
-        
-        self.position += self.velocity * delta_t;
     }
 
-    pub fn get_position(&self) -> Vector3<f32> {
-        self.position
+    /// Swaps in a freshly generated graph and restarts the layout from a random ball.
+    fn replace_graph(&mut self, generator: &Generator) {
+        log::info!("Generating {}", generator.describe());
+        generator.build(&mut self.model.graph, &mut self.rng);
+
+        let graph = &self.model.graph;
+        self.model.colors = vec![self.model.theme.vertex_fill; graph.vertex_ct()];
+        self.model.sizes = vec![DEFAULT_VERTEX_SIZE; graph.vertex_ct()];
+        self.model.interacted_particle = None;
+
+        let particles = InitialPlacement::Sphere
+            .place(graph, (graph.vertex_ct() as f32).pow(1. / 3.), &mut self.rng)
+            .into_iter()
+            .map(|position| PhysicsParticle::new(position, 1.0))
+            .collect::<Vec<PhysicsParticle>>();
+        let adjacency = (0..graph.vertex_ct())
+            .map(|u| graph.neighbors(u).map(|(v, _)| v).collect())
+            .collect::<Vec<Vec<usize>>>();
+        self.simulation.send(move |layout| {
+            layout.particles = particles;
+            layout.adjacency = Arc::new(adjacency);
+            layout.pinned = None;
+            layout.wake();
+        });
     }
 
-    pub fn get_velocity(&self) -> Vector3<f32> {
-        self.velocity
+    /// Hands the frame a screenshot or the recording asked for to the capturer, and passes
+    /// on frames read back since. A recorded frame lets the layout move on to the next one.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn frame_drawn(&mut self, window_state: &PlotWindowState, frame: Option<CopiedFrame>, record: bool) {
+        let screenshot = std::mem::take(&mut self.screenshot_requested);
+        if let Some(frame) = frame {
+            self.capturer.read_back(frame, screenshot, record);
+        }
+        if !self.capturer.poll(&window_state.device) {
+            self.toggle_recording();
+            return;
+        }
+
+        if record {
+            self.next_recorded_frame += 1;
+            self.simulation.send(|layout| layout.advance_frame());
+        }
     }
-}
 
+    /// Starts recording with the layout on a fixed frame clock, or stops the recording and
+    /// hands the layout back to the wall clock.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn toggle_recording(&mut self) {
+        if self.capturer.is_recording() {
+            self.capturer.stop_recording();
+            self.simulation.send(|layout| layout.fixed_frame = None);
+            return;
+        }
 
-impl<T: Graph> ForcePlot<T> {
-    pub fn new(graph: T) -> Self {
-        let particles = (0..graph.vertex_ct())
-            .map(|_i| {
-                PhysicsParticle::new(Vector3::new(random(), random(), random()).normalize() * (graph.vertex_ct() as f32).pow(1. / 3.), 1.0)
-            })
-            .collect::<Vec<PhysicsParticle>>();
-        let colors = (0..graph.vertex_ct()).map(|_i| {Color::rgb(0.1, 0.7, 0.1)}).collect();
-        let mut sizes= Vec::new();
-        sizes.resize(graph.vertex_ct(), 0.1);
-        let state = None;
-        let interacted_particle = None;
-        let drag_previous_position = Vector2::zero();
+        match self.capturer.start_recording() {
+            Ok(()) => {
+                let frame_interval = self.capturer.options().frame_interval();
+                self.simulation.latest(&mut self.model.layout);
+                self.next_recorded_frame = self.model.layout.frame_ct + 1;
+                self.simulation.send(move |layout| {
+                    layout.fixed_frame = Some(frame_interval);
+                    layout.advance_frame();
+                });
+            }
+            Err(error) => log::error!("Couldn't start recording: {}", error),
+        }
+    }
 
-        let mut model = ForcePlotModel {
-            graph,
-            colors,
-            sizes,
-            interacted_particle,
-            particles,
+    /// The vertex or edge under the cursor. Uses the picking pass's answer if it was read
+    /// back for this cursor position, and searches the vertices on the CPU otherwise; only
+    /// picked vertices are acted on in 3D.
+    fn pick(&self, window_state: &PlotWindowState) -> Option<Pick> {
+        let pixel = self.mouse_tracker.get_position();
+        self.state.as_ref()?;
+        if let Some(pick) = self.picker.as_ref().and_then(|picker| picker.latest(pixel)) {
+            // Read back a frame ago, so the vertex may have been removed since.
+            return pick.filter(|pick| !matches!(pick, Pick::Vertex(v) if *v >= self.model.layout.positions.len()));
+        }
+
+        self.get_particle_index_by_clip_position(pixel_to_clip(pixel, window_state.size))
+            .map(Pick::Vertex)
+    }
+
+    /// Keeps a pick under the cursor read back, at most a frame or two old, for clicks to use.
+    fn resolve_picks(&mut self, window_state: &PlotWindowState) {
+        let (Some(state), Some(picker)) = (self.state.as_ref(), self.picker.as_mut()) else {
+            return;
         };
+        picker.poll(window_state);
+        if self.mouse_tracker.is_cursor_in_window() {
+            picker.request(state, window_state, self.mouse_tracker.get_position());
+        }
+    }
 
-        Self {
-            model,
-            state,
-            mouse_tracker: MouseTracker::new(),
-            drag_previous_position,
+    fn pick_vertex(&self, window_state: &PlotWindowState) -> Option<usize> {
+        match self.pick(window_state) {
+            Some(Pick::Vertex(v)) => Some(v),
+            _ => None,
         }
     }
 
@@ -259,8 +608,8 @@ impl<T: Graph> ForcePlot<T> {
         let mut min_depth = f32::MAX;
         let mut index = None;
 
-        for i in 0..self.model.particles.len() {
-            let particle_world = self.model.particles[i].position;
+        for (i, size) in self.model.sizes.iter().enumerate().take(self.model.layout.positions.len()) {
+            let particle_world = self.model.layout.positions[i];
             let mut particle_clip = projection * Vector4::new(particle_world.x, particle_world.y, particle_world.z, 1.0);
             particle_clip /= particle_clip.w;
             let cursor_clip = Vector4::new(cursor_position.x, cursor_position.y, particle_clip.z, 1.0);
@@ -270,7 +619,7 @@ impl<T: Graph> ForcePlot<T> {
 
             let distance = (particle_world - cursor_world).magnitude();
 
-            if distance < 0.1 && particle_clip.z < min_depth {
+            if distance < self.model.world_radius(*size, particle_world) && particle_clip.z < min_depth {
                 min_depth = particle_clip.z;
                 index = Some(i);
             }
@@ -280,20 +629,205 @@ impl<T: Graph> ForcePlot<T> {
     }
 }
 
+// The layout snapshot can lag a frame behind the graph, so only vertices the simulation
+// has already published are drawn.
 impl<T: Graph> GetPoints for ForcePlotModel<T> {
     fn get_points(&self) -> impl Iterator<Item = (Vector3<f32>, Color, f32)> {
-        (0..self.graph.vertex_ct()).map(|i| (self.particles[i].position, self.colors[i], self.sizes[i]))
+        self.layout.positions.iter().zip(&self.colors).zip(&self.sizes).enumerate().map(|(u, ((position, color), size))| {
+            let color = if Some(u) == self.interacted_particle { self.theme.selection } else { *color };
+            (*position, color, self.point_size(*size))
+        })
+    }
+
+    fn point_outline(&self) -> (Color, f32) {
+        (self.theme.vertex_outline, self.theme.outline_thickness)
     }
     // fn get_points(&self) -> impl Iterator<Item = Vector3<f32>> {
     //     self.particles.iter().map(|particle| particle.position)
     // }
 }
 
+impl<T: Graph> GetPickables for ForcePlotModel<T> {
+    fn get_pick_points(&self) -> impl Iterator<Item = (Vector3<f32>, f32)> {
+        self.layout.positions.iter().zip(&self.sizes).map(|(position, size)| (*position, self.point_size(*size)))
+    }
+
+    fn get_pick_lines(&self) -> impl Iterator<Item = (Vector3<f32>, Vector3<f32>, f32, (usize, usize))> {
+        let positions = &self.layout.positions;
+        let width = self.line_width(EDGE_PICK_WIDTH);
+        // Trimmed to the vertex disks, so an edge never shows in front of its own endpoints.
+        self.edge_curves().flat_map(move |(u, v, curve)| {
+            let curve = curve.trimmed(
+                self.world_radius(self.sizes[u], positions[u]),
+                self.world_radius(self.sizes[v], positions[v]),
+            );
+            curve.segments().map(move |(x0, x1)| (x0, x1, width, (u, v)))
+        })
+    }
+}
+
 impl<T: Graph> GetLines for ForcePlotModel<T> {
-    fn get_lines(&self) -> impl Iterator<Item = (Vector3<f32>, Vector3<f32>, Color)> {
-        self.graph.edges().map(|(u,v, _)| 
-            (self.particles[u].position + (self.particles[v].position - self.particles[u].position).normalize() * 0.1,
-             self.particles[v].position - (self.particles[v].position - self.particles[u].position).normalize() * 0.1,
-            Color::rgba(0., 0., 0., 0.5)))
+    fn get_lines(&self) -> impl Iterator<Item = (Curve, Color, f32)> {
+        let positions = &self.layout.positions;
+        let width = self.line_width(EDGE_WIDTH);
+        // Edges stop at the rim of a default-sized vertex.
+        self.edge_curves().map(move |(u, v, curve)| {
+            let curve = curve.trimmed(
+                self.world_radius(DEFAULT_VERTEX_SIZE, positions[u]),
+                self.world_radius(DEFAULT_VERTEX_SIZE, positions[v]),
+            );
+            (curve, self.theme.edge, width)
+        })
+    }
+}
+
+/// The generator menu while it is open, on a translucent backdrop in the top right corner.
+impl<T: Graph> GetOverlay for ForcePlotModel<T> {
+    fn get_overlay(&self) -> impl Iterator<Item = OverlayItem> {
+        let mut items = Vec::new();
+        if let Some(menu) = self.generator_menu.as_ref() {
+            let text = menu.describe();
+            let position = Vector2::new(MENU_MARGIN, MENU_MARGIN);
+            let padding = position / 2.0;
+            items.push(OverlayItem::Rect {
+                anchor: Anchor::TopRight,
+                position: position - padding,
+                size: text_size(&text, MENU_TEXT_SCALE) + padding * 2.0,
+                color: self.theme.panel,
+            });
+            items.push(OverlayItem::Text {
+                text,
+                anchor: Anchor::TopRight,
+                position,
+                color: self.theme.text,
+                scale: MENU_TEXT_SCALE,
+            });
+        }
+        items.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::initial_placement::read_positions;
+    use gscientific::graph::petersen_graph;
+
+    const STEP_CT: usize = 200;
+
+    /// Positions after `STEP_CT` steps from `placement`, starting from `seed`.
+    fn layout(placement: &InitialPlacement, seed: u64) -> Vec<Vector3<f32>> {
+        let options = PlotOptions {
+            seed: Some(seed),
+            initial_placement: placement.clone(),
+            ..PlotOptions::default()
+        };
+        let mut plot = ForcePlot::with_options(petersen_graph(10, 3), &options);
+        plot.advance(STEP_CT);
+        plot.simulation.latest(&mut plot.model.layout);
+        plot.model.layout.positions.clone()
+    }
+
+    fn assert_reproducible(placement: &InitialPlacement) {
+        let first = layout(placement, 7);
+        // Bit-equal, not just close.
+        let bits = |positions: &[Vector3<f32>]| {
+            positions.iter().map(|p| (p.x.to_bits(), p.y.to_bits(), p.z.to_bits())).collect::<Vec<_>>()
+        };
+        assert_eq!(bits(&first), bits(&layout(placement, 7)), "{:?}", placement);
+        assert_ne!(bits(&first), bits(&layout(placement, 8)), "{:?}", placement);
+    }
+
+    #[test]
+    fn sphere_layouts_are_reproducible() {
+        assert_reproducible(&InitialPlacement::Sphere);
+    }
+
+    #[test]
+    fn spectral_layouts_are_reproducible() {
+        assert_reproducible(&InitialPlacement::Spectral);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn file_layouts_are_reproducible() {
+        let path = std::env::temp_dir().join(format!("graphite_plot_3d_positions_{}.txt", std::process::id()));
+        // Fewer positions than vertices, so the rest are placed at random.
+        let contents = (0..12).map(|i| format!("{} {} {}\n", i, i * i, -i)).collect::<String>();
+        std::fs::write(&path, contents).unwrap();
+        let placement = InitialPlacement::Positions(read_positions(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        assert_reproducible(&placement);
+    }
+
+    /// A plot with exactly `edges`, its vertices at `positions` and sized in world units.
+    fn placed(edges: &[(usize, usize)], positions: Vec<Vector3<f32>>) -> ForcePlot<impl Graph> {
+        let mut graph = petersen_graph(5, 2);
+        graph.resize(0);
+        graph.resize(positions.len());
+        for (u, v) in edges {
+            graph.add_edge(*u, *v);
+        }
+        let mut plot = ForcePlot::with_options(graph, &PlotOptions::default());
+        plot.model.layout.positions = positions;
+        plot.model.point_units = SizeUnits::World;
+        plot
+    }
+
+    /// The middle of a loop's control points, relative to its vertex.
+    fn loop_direction(curve: Curve) -> Vector3<f32> {
+        let Curve::Cubic(start, leaving, returning, end) = curve else {
+            panic!("loops are cubic");
+        };
+        assert_eq!(start, end);
+        for point in [leaving, returning] {
+            assert!(point.x.is_finite() && point.y.is_finite() && point.z.is_finite(), "{:?}", point);
+            assert_ne!(point, start);
+        }
+        (leaving + returning) / 2.0 - start
+    }
+
+    #[test]
+    fn loops_point_away_from_the_neighbors_and_face_the_camera() {
+        let positions = vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(3.0, 0.0, 0.0),
+            // Straight behind its neighbor, as seen from the camera.
+            Vector3::new(0.0, 0.0, -3.0),
+            Vector3::new(5.0, 5.0, 0.0),
+        ];
+        let plot = placed(&[(0, 1), (0, 2)], positions);
+
+        let away = loop_direction(plot.model.loop_curve(0, 0));
+        assert!(away.x < 0.0 && away.y.abs() < 1e-6, "{:?}", away);
+        assert!(away.dot(plot.model.view_direction).abs() < 1e-6, "{:?}", away);
+
+        let behind = loop_direction(plot.model.loop_curve(2, 0));
+        assert!(behind.y > 0.0 && behind.dot(plot.model.view_direction).abs() < 1e-6, "{:?}", behind);
+        let isolated = loop_direction(plot.model.loop_curve(3, 0));
+        assert!(isolated.y > 0.0, "{:?}", isolated);
+    }
+
+    #[test]
+    fn parallel_edges_fan_out_evenly() {
+        let (a, b) = (Vector3::new(0.0, 0.0, 0.0), Vector3::new(4.0, 2.0, -1.0));
+        let plot = placed(&[(0, 1)], vec![a, b]);
+        let across = (b - a).cross(plot.model.view_direction).normalize();
+        // How far across the edge each curve's control point is, in edge lengths.
+        let bend = |curve: Curve| match curve {
+            Curve::Straight(..) => 0.0,
+            Curve::Quadratic(_, control, _) => (control - (a + b) / 2.0).dot(across) / (b - a).magnitude(),
+            curve => panic!("unexpected {:?}", curve),
+        };
+
+        for parallel_ct in 1..=5 {
+            let bends = (0..parallel_ct).map(|i| bend(plot.model.edge_curve(0, 1, i, parallel_ct))).collect::<Vec<_>>();
+            assert!(bends.windows(2).all(|pair| pair[1] - pair[0] > 0.1), "{:?}", bends);
+            for (bend, mirrored) in bends.iter().zip(bends.iter().rev()) {
+                assert!((bend + mirrored).abs() < 1e-5, "{:?}", bends);
+            }
+            let reversed = (0..parallel_ct).map(|i| bend(plot.model.edge_curve(1, 0, i, parallel_ct))).collect::<Vec<_>>();
+            assert_eq!(bends, reversed);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::{fs, io, path::Path};
+
+use cgmath::{InnerSpace, Vector3, Zero};
+use gscientific::graph::Graph;
+use rand::{Rng, rngs::StdRng};
+
+const SPECTRAL_ITERATIONS: usize = 300;
+const SPECTRAL_JITTER: f32 = 1e-3;
+
+#[derive(Clone, Debug, Default)]
+pub enum InitialPlacement {
+    /// Uniformly distributed over a ball whose volume grows with the vertex count.
+    #[default]
+    Sphere,
+    /// The three smallest non-trivial eigenvectors of the graph Laplacian.
+    Spectral,
+    /// Explicit positions, e.g. loaded with `read_positions`. Vertices without a position
+    /// fall back to `Sphere`.
+    Positions(Vec<Vector3<f32>>),
+}
+
+impl InitialPlacement {
+    pub fn place<T: Graph>(&self, graph: &T, radius: f32, rng: &mut StdRng) -> Vec<Vector3<f32>> {
+        match self {
+            InitialPlacement::Sphere => sphere(graph.vertex_ct(), radius, rng),
+            InitialPlacement::Spectral => spectral(graph, radius, rng),
+            InitialPlacement::Positions(positions) => {
+                let mut placed = positions.clone();
+                placed.truncate(graph.vertex_ct());
+                placed.extend(sphere(graph.vertex_ct() - placed.len(), radius, rng));
+                placed
+            }
+        }
+    }
+}
+
+fn sphere(vertex_ct: usize, radius: f32, rng: &mut StdRng) -> Vec<Vector3<f32>> {
+    (0..vertex_ct)
+        .map(|_| loop {
+            let candidate = Vector3::new(
+                2.0 * rng.random::<f32>() - 1.0,
+                2.0 * rng.random::<f32>() - 1.0,
+                2.0 * rng.random::<f32>() - 1.0,
+            );
+            if candidate.magnitude2() <= 1.0 {
+                break candidate * radius;
+            }
+        })
+        .collect()
+}
+
+/// Orthogonal iteration on `2 * max_degree * I - L`, deflating the constant vector, so the
+/// dominant vectors found are the Fiedler vector and its two successors.
+fn spectral<T: Graph>(graph: &T, radius: f32, rng: &mut StdRng) -> Vec<Vector3<f32>> {
+    let n = graph.vertex_ct();
+    if n < 4 {
+        return sphere(n, radius, rng);
+    }
+
+    let degrees = (0..n)
+        .map(|u| graph.neighbors(u).count() as f32)
+        .collect::<Vec<f32>>();
+    let shift = 2.0 * degrees.iter().cloned().fold(1.0, f32::max);
+    let constant = 1.0 / (n as f32).sqrt();
+
+    let mut basis: Vec<Vec<f32>> = Vec::new();
+    for _ in 0..3 {
+        let mut x = (0..n).map(|_| rng.random::<f32>() - 0.5).collect::<Vec<f32>>();
+
+        for _ in 0..SPECTRAL_ITERATIONS {
+            let mut y = (0..n)
+                .map(|u| {
+                    let neighbor_sum = graph.neighbors(u).map(|(v, _)| x[v]).sum::<f32>();
+                    (shift - degrees[u]) * x[u] + neighbor_sum
+                })
+                .collect::<Vec<f32>>();
+
+            let mean = y.iter().sum::<f32>() * constant;
+            y.iter_mut().for_each(|y_u| *y_u -= mean * constant);
+            for b in &basis {
+                let projection = y.iter().zip(b).map(|(y_u, b_u)| y_u * b_u).sum::<f32>();
+                y.iter_mut().zip(b).for_each(|(y_u, b_u)| *y_u -= projection * b_u);
+            }
+
+            let norm = y.iter().map(|y_u| y_u * y_u).sum::<f32>().sqrt();
+            if norm == 0.0 {
+                break;
+            }
+            x = y.into_iter().map(|y_u| y_u / norm).collect();
+        }
+
+        basis.push(x);
+    }
+
+    let mut positions = (0..n)
+        .map(|u| Vector3::new(basis[0][u], basis[1][u], basis[2][u]))
+        .collect::<Vec<Vector3<f32>>>();
+    let max_norm = positions
+        .iter()
+        .map(|p| p.magnitude())
+        .fold(0.0, f32::max);
+    let scale = if max_norm > 0.0 { radius / max_norm } else { 0.0 };
+
+    // Symmetric graphs put several vertices on the same point, which the force model can't
+    // separate, so nudge everything slightly.
+    for position in &mut positions {
+        *position = *position * scale
+            + Vector3::new(rng.random::<f32>() - 0.5, rng.random::<f32>() - 0.5, rng.random::<f32>() - 0.5)
+                * SPECTRAL_JITTER
+                * radius;
+        if !position.x.is_finite() || !position.y.is_finite() || !position.z.is_finite() {
+            *position = Vector3::zero();
+        }
+    }
+
+    positions
+}
+
+/// Reads one position per line as whitespace or comma separated coordinates.
+pub fn read_positions(path: impl AsRef<Path>) -> io::Result<Vec<Vector3<f32>>> {
+    let contents = fs::read_to_string(path)?;
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let coordinates = line
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|token| !token.is_empty())
+                .map(|token| token.parse::<f32>())
+                .collect::<Result<Vec<f32>, _>>()
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            match coordinates.as_slice() {
+                [x, y, z, ..] => Ok(Vector3::new(*x, *y, *z)),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("expected three coordinates, got \"{}\"", line),
+                )),
+            }
+        })
+        .collect()
+}
@@ -1,17 +1,62 @@
+//! A 3D force-directed graph plot. It shares the layout, generators, key bindings, themes,
+//! capture and the text overlay with the 2D plot, but only shows the generator menu on the
+//! overlay, so some 2D features are left out:
+//!
+//! - Hover tooltips and the pinned info panel. Picking is only used to grab vertices.
+//! - The HUD and the key binding help sheet. Bindings are set with a `--bindings` file.
+//! - Per-vertex shapes, outlines and icon atlases. Every vertex is a shaded sphere with the
+//!   theme's outline.
+//! - Edge bundling.
+
 // mod spectral_plot;
 mod draw;
 mod camera;
 mod plot_window;
 mod force_plot;
+mod force_layout;
+#[path = "../../graphite_plot/src/simulation.rs"]
+mod simulation;
 // mod draw;
 
 // mod graph_plot;
 mod texture;
 mod plot_graphic;
+#[path = "../../graphite_plot/src/mouse.rs"]
 mod mouse;
+mod initial_placement;
+mod options;
+#[path = "../../graphite_plot/src/generators.rs"]
+mod generators;
+mod picking;
+mod actions;
+// There is no help sheet in 3D.
+#[allow(dead_code)]
+#[path = "../../graphite_plot/src/bindings.rs"]
+mod bindings;
+#[path = "../../graphite_plot/src/capture.rs"]
+mod capture;
+#[cfg(not(target_arch = "wasm32"))]
+#[path = "../../graphite_plot/src/capturer.rs"]
+mod capturer;
+// Only the generator menu is drawn on the overlay, which leaves some of it unused.
+#[allow(dead_code)]
+#[path = "../../graphite_plot/src/overlay.rs"]
+mod overlay;
+#[path = "../../graphite_plot/src/theme.rs"]
+mod theme;
+
+pub use initial_placement::{InitialPlacement, read_positions};
+pub use options::PlotOptions;
+pub use actions::Action;
+pub use bindings::{Bindings, read_bindings};
+pub use generators::Generator;
+pub use capture::{CaptureOptions, RecordFormat};
+pub use draw::{RenderOptions, SizeUnits};
+pub use theme::{Theme, read_theme};
 
-use gscientific::graph::petersen_graph;
+use gscientific::graph::{Graph, petersen_graph};
 use force_plot::ForcePlot;
+use rand::{SeedableRng, random, rngs::StdRng};
 use winit::
     event_loop::{ControlFlow, EventLoop}
 ;
@@ -34,55 +79,37 @@ pub fn init() {
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 pub fn run() {
-    let event_loop = EventLoop::new().unwrap();
-    event_loop.set_control_flow(ControlFlow::Poll);
-    let graph = petersen_graph(2500, 101);
-    // graph.add_vertex();
-    // graph.random_regularish(100, 2);
-    // graph.resize(10);
-    // graph.add_edge(0, 1);
-    // graph.add_edge(1, 2);
-    // graph.add_edge(2, 3);
-    // graph.add_edge(3, 4);
-    // graph.add_edge(4, 0);
-    // graph.add_edge(0, 5);
-    // graph.add_edge(1, 6);
-    // graph.add_edge(2, 7);
-    // graph.add_edge(3, 8);
-    // graph.add_edge(4, 9);
-    // graph.add_edge(5, 7);
-    // graph.add_edge(6, 8);
-    // graph.add_edge(7, 9);
-    // graph.add_edge(8, 5);
-    // graph.add_edge(9, 6);
-    // let mut rng = rng();
-    // for _i in 0..100 {
-    //     let mut g = AdjacencyMatrix::new();
-    //     let old_vertices_ct = graph.vertex_ct();
-    //     let new_vertices_ct = (rng.next_u32() % 25 + 5) as usize;
-    //     g.random(new_vertices_ct , 0.7);
-    //     graph.merge(&g);
-        
-    //     for j in 0..(match (rng.next_u32() as usize).checked_rem(old_vertices_ct / 100) { Some(val) => val, None => 0}) {
-    //         graph.add_edge(rng.next_u32() as usize % old_vertices_ct, rng.next_u32() as usize % new_vertices_ct + old_vertices_ct);
-    //     }
-    // }
-
-    // let u = graph.add_vertex();
-    // let v = graph.add_vertex();
-    // graph.add_edge(u, v);
+    run_with_options(PlotOptions::default());
+}
 
-    let graph_plot = ForcePlot::new(graph);
+pub fn run_with_options(options: PlotOptions) {
+    run_window(plot_window(options));
+}
 
+/// Plots into a new canvas appended to the element with id `element_id`.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn run_in_element(element_id: &str) {
+    run_window(plot_window(PlotOptions::default()).in_element(element_id));
+}
 
-    let mut app = plot_window::PlotWindow::new(graph_plot);
+fn plot_window(mut options: PlotOptions) -> plot_window::PlotWindow<ForcePlot<impl Graph>> {
+    // One seed drives both the generator and the layout, so `--seed` reproduces the whole plot.
+    let seed = *options.seed.get_or_insert_with(random);
+    let mut graph = petersen_graph(2500, 101);
+    if let Some(generator) = &options.generator {
+        log::info!("Generating {}", generator.describe());
+        generator.build(&mut graph, &mut StdRng::seed_from_u64(seed));
+    }
 
+    let mut graph_plot = ForcePlot::with_options(graph, &options);
+    graph_plot.advance(options.steps);
 
-    event_loop.run_app(&mut app).unwrap();
+    plot_window::PlotWindow::new(graph_plot).with_msaa(options.render.msaa_samples)
 }
 
-
-#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-pub fn add(a: u32, b: u32) -> u32 {
-    a + b
+fn run_window(mut app: plot_window::PlotWindow<ForcePlot<impl Graph>>) {
+    let event_loop = EventLoop::new().unwrap();
+    event_loop.set_control_flow(ControlFlow::Poll);
+    event_loop.run_app(&mut app).unwrap();
 }
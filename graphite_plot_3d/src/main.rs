@@ -1,9 +1,15 @@
-
-use graphite_plot_3d::run;
+use graphite_plot_3d::{run_with_options, PlotOptions};
 
 
 fn main() {
-
-    run();
+    let options = match PlotOptions::from_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+
+    run_with_options(options);
     
 }
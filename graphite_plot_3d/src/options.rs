@@ -0,0 +1,131 @@
+use crate::{
+    bindings::{Bindings, read_bindings},
+    capture::{CaptureOptions, RecordFormat},
+    draw::{RenderOptions, SizeUnits},
+    generators::{GENERATOR_USAGE, Generator},
+    initial_placement::{InitialPlacement, read_positions},
+    theme::{Theme, read_theme},
+};
+
+const USAGE: &str = "usage: graphite_plot_3d [--seed <u64>] [--init sphere|spectral|<positions file>] [--steps <n>] [--graph <family>:<params>] [--bindings <file>] [--capture-dir <dir>] [--record gif|png] [--record-fps <n>] [--msaa 1|2|4|8|16] [--line-units pixels|world] [--point-units pixels|world] [--curved-edges] [--fog <0..1>] [--theme light|dark|print|<theme file>]";
+
+#[derive(Clone, Debug, Default)]
+pub struct PlotOptions {
+    /// Seed for every random choice made by the plot. A random seed is chosen (and logged)
+    /// when this is `None`.
+    pub seed: Option<u64>,
+    pub initial_placement: InitialPlacement,
+    /// Fixed simulation steps run before the plot is shown, so a seeded figure opens on the
+    /// same layout every time.
+    pub steps: usize,
+    /// Graph to plot instead of the default generalised Petersen graph.
+    pub generator: Option<Generator>,
+    /// Key and mouse bindings, the defaults unless overridden with a bindings file.
+    pub bindings: Bindings,
+    pub capture: CaptureOptions,
+    pub render: RenderOptions,
+    /// Colors to start with. Loaded themes join the built-in ones when switching at runtime.
+    pub theme: Theme,
+}
+
+impl PlotOptions {
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = PlotOptions::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    let value = args.next().ok_or(USAGE)?;
+                    options.seed = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid seed \"{}\"\n{}", value, USAGE))?,
+                    );
+                }
+                "--init" => {
+                    let value = args.next().ok_or(USAGE)?;
+                    options.initial_placement = match value.as_str() {
+                        "sphere" => InitialPlacement::Sphere,
+                        "spectral" => InitialPlacement::Spectral,
+                        path => InitialPlacement::Positions(
+                            read_positions(path)
+                                .map_err(|err| format!("couldn't read \"{}\": {}", path, err))?,
+                        ),
+                    };
+                }
+                "--steps" => {
+                    let value = args.next().ok_or(USAGE)?;
+                    options.steps = value
+                        .parse()
+                        .map_err(|_| format!("invalid step count \"{}\"\n{}", value, USAGE))?;
+                }
+                "--graph" => {
+                    let value = args.next().ok_or(USAGE)?;
+                    options.generator = Some(Generator::parse(&value)?);
+                }
+                "--bindings" => {
+                    let path = args.next().ok_or(USAGE)?;
+                    options.bindings = read_bindings(&path)
+                        .map_err(|err| format!("couldn't read \"{}\": {}", path, err))?;
+                }
+                "--capture-dir" => {
+                    options.capture.directory = args.next().ok_or(USAGE)?.into();
+                }
+                "--record" => {
+                    let value = args.next().ok_or(USAGE)?;
+                    options.capture.record_format = RecordFormat::parse(&value)
+                        .ok_or_else(|| format!("invalid recording format \"{}\"\n{}", value, USAGE))?;
+                }
+                "--record-fps" => {
+                    let value = args.next().ok_or(USAGE)?;
+                    options.capture.record_fps = value
+                        .parse()
+                        .ok()
+                        .filter(|fps| *fps > 0)
+                        .ok_or_else(|| format!("invalid frame rate \"{}\"\n{}", value, USAGE))?;
+                }
+                "--msaa" => {
+                    let value = args.next().ok_or(USAGE)?;
+                    options.render.msaa_samples = value
+                        .parse()
+                        .ok()
+                        .filter(|samples: &u32| samples.is_power_of_two() && *samples <= 16)
+                        .ok_or_else(|| format!("invalid sample count \"{}\"\n{}", value, USAGE))?;
+                }
+                "--line-units" | "--point-units" => {
+                    let value = args.next().ok_or(USAGE)?;
+                    let units = SizeUnits::parse(&value)
+                        .ok_or_else(|| format!("invalid units \"{}\"\n{}", value, USAGE))?;
+                    if arg == "--line-units" {
+                        options.render.line_units = units;
+                    } else {
+                        options.render.point_units = units;
+                    }
+                }
+                "--curved-edges" => {
+                    options.render.curved_edges = true;
+                }
+                "--fog" => {
+                    let value = args.next().ok_or(USAGE)?;
+                    options.render.fog = value
+                        .parse()
+                        .ok()
+                        .filter(|fog: &f32| (0.0..=1.0).contains(fog))
+                        .ok_or_else(|| format!("invalid fog \"{}\"\n{}", value, USAGE))?;
+                }
+                "--theme" => {
+                    let value = args.next().ok_or(USAGE)?;
+                    options.theme = match Theme::builtin(&value) {
+                        Some(theme) => theme,
+                        None => read_theme(&value)
+                            .map_err(|err| format!("couldn't read \"{}\": {}", value, err))?,
+                    };
+                }
+                "--help" | "-h" => return Err(format!("{}\n{}", USAGE, GENERATOR_USAGE)),
+                _ => return Err(format!("unknown argument \"{}\"\n{}", arg, USAGE)),
+            }
+        }
+
+        Ok(options)
+    }
+}
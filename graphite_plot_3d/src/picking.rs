@@ -0,0 +1,540 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU8, Ordering},
+};
+
+use bytemuck::{Pod, Zeroable};
+use cgmath::Vector3;
+use wgpu::util::DeviceExt;
+use winit::dpi::PhysicalPosition;
+
+use crate::{draw::SizeUnits, plot_graphic::PlotGraphicState, plot_window::PlotWindowState, texture};
+
+const ID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+/// Set on the IDs of edges, which are otherwise numbered like vertices.
+const EDGE_ID_BIT: u32 = 1 << 31;
+/// Pixels around the cursor a pick reaches, so thin edges don't need a steady hand.
+const PICK_RADIUS: u32 = 3;
+/// Width and height of the window of pixels rendered for each pick.
+const PICK_SIZE: u32 = 2 * PICK_RADIUS + 1;
+/// Buffer rows for texture copies must be padded to this many bytes.
+const READBACK_ROW_SIZE: u32 = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+const READBACK_SIZE: u64 = READBACK_ROW_SIZE as u64 * PICK_SIZE as u64;
+
+const READBACK_IDLE: u8 = 0;
+const READBACK_PENDING: u8 = 1;
+const READBACK_MAPPED: u8 = 2;
+const READBACK_FAILED: u8 = 3;
+
+/// What lies under a pixel.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Pick {
+    Vertex(usize),
+    Edge(usize, usize),
+}
+
+/// Geometry drawn into the picking pass. Vertex IDs are their positions in
+/// `get_pick_points`; vertices with size 0 can't be picked. Sizes and widths are in the
+/// units the picker was given, as they are for the elements drawing the same geometry.
+pub trait GetPickables {
+    fn get_pick_points(&self) -> impl Iterator<Item = (Vector3<f32>, f32)>;
+    /// Line segments as their endpoints, width in clip space and the edge they belong to.
+    fn get_pick_lines(&self) -> impl Iterator<Item = (Vector3<f32>, Vector3<f32>, f32, (usize, usize))>;
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct PickPointInstance {
+    position: [f32; 3],
+    size: f32,
+    id: u32,
+    pixel_sized: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct PickLineVertex {
+    position: [f32; 3],
+    next: [f32; 3],
+    offset_distance: f32,
+    id: u32,
+    pixel_sized: u32,
+}
+
+/// Maps clip space of the whole surface to clip space of the pick window.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct PickWindowUniform {
+    scale: [f32; 2],
+    offset: [f32; 2],
+}
+
+impl PickWindowUniform {
+    /// The window of `PICK_SIZE` pixels whose top left corner is `corner`, on a surface of
+    /// `width` by `height` pixels.
+    fn new(corner: (i64, i64), width: u32, height: u32) -> Self {
+        let size = PICK_SIZE as f32;
+        let (width, height) = (width as f32, height as f32);
+        let (x, y) = (corner.0 as f32, corner.1 as f32);
+        Self {
+            scale: [width / size, height / size],
+            offset: [(width - 2.0 * x) / size - 1.0, 1.0 - (height - 2.0 * y) / size],
+        }
+    }
+}
+
+const PICK_QUAD: &[[f32; 2]] = &[[-1.0, -1.0], [-1.0, 1.0], [1.0, -1.0], [1.0, 1.0]];
+const PICK_QUAD_INDEX: &[u16] = &[0, 2, 1, 1, 2, 3];
+
+/// Renders vertex and edge IDs into an integer texture and reads back what lies nearest the
+/// cursor. Only a few pixels around the cursor are rendered, into a texture just that big,
+/// and the readback is mapped asynchronously: `request` starts a pick and `poll` hands it
+/// over on a later frame, so picking never waits on the GPU. The pass has its own depth
+/// buffer, so the nearest vertex or edge wins.
+pub struct Picker {
+    point_pipeline: wgpu::RenderPipeline,
+    line_pipeline: wgpu::RenderPipeline,
+    quad_buffer: wgpu::Buffer,
+    quad_index_buffer: wgpu::Buffer,
+    point_buffer: wgpu::Buffer,
+    line_vertex_buffer: wgpu::Buffer,
+    line_index_buffer: wgpu::Buffer,
+    id_texture: wgpu::Texture,
+    id_view: wgpu::TextureView,
+    depth_texture: texture::Texture,
+    window_buffer: wgpu::Buffer,
+    window_bind_group: wgpu::BindGroup,
+    readback_buffer: wgpu::Buffer,
+    readback_state: Arc<AtomicU8>,
+    /// Cursor position the readback in flight was requested at.
+    requested: Option<PhysicalPosition<f32>>,
+    /// A pick that finished without a readback, waiting for `poll`.
+    ready: Option<Option<Pick>>,
+    /// The last pick read back and the cursor position it was requested at.
+    latest: Option<(PhysicalPosition<f32>, Option<Pick>)>,
+    point_units: SizeUnits,
+    line_units: SizeUnits,
+
+    points: Vec<PickPointInstance>,
+    line_vertices: Vec<PickLineVertex>,
+    line_indices: Vec<u32>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl Picker {
+    pub fn new<T>(graphic_state: &PlotGraphicState<T>, window_state: &PlotWindowState) -> Self {
+        let device = &window_state.device;
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Picking Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../assets/picking_shader.wgsl").into()),
+        });
+        let window_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("pick_window_bind_group_layout"),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Picking Pipeline Layout"),
+            bind_group_layouts: &[&graphic_state.camera_bind_group_layout, &window_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = |label: &str, vertex_entry: &str, fragment_entry: &str, buffers: &[wgpu::VertexBufferLayout]| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some(vertex_entry),
+                    buffers,
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some(fragment_entry),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: ID_FORMAT,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: texture::Texture::DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    // Vertices are drawn after edges and win ties with them.
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+                cache: None,
+            })
+        };
+
+        let point_pipeline = pipeline(
+            "Point Picking Pipeline",
+            "vs_point",
+            "fs_point",
+            &[
+                wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+                },
+                wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<PickPointInstance>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![1 => Float32x3, 2 => Float32, 3 => Uint32, 4 => Uint32],
+                },
+            ],
+        );
+        let line_pipeline = pipeline(
+            "Line Picking Pipeline",
+            "vs_line",
+            "fs_line",
+            &[wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<PickLineVertex>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32, 3 => Uint32, 4 => Uint32],
+            }],
+        );
+
+        let quad_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Picking Quad Buffer"),
+            contents: bytemuck::cast_slice(PICK_QUAD),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let quad_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Picking Quad Index Buffer"),
+            contents: bytemuck::cast_slice(PICK_QUAD_INDEX),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let (id_texture, id_view) = create_id_texture(window_state);
+        let depth_texture = texture::Texture::create_depth_texture(
+            device,
+            &wgpu::SurfaceConfiguration {
+                width: PICK_SIZE,
+                height: PICK_SIZE,
+                ..window_state.config.clone()
+            },
+            1,
+            "Picking Depth Texture",
+        );
+        let window_buffer = create_buffer(
+            window_state,
+            "Pick Window Buffer",
+            std::mem::size_of::<PickWindowUniform>() as u64,
+            wgpu::BufferUsages::UNIFORM,
+        );
+        let window_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &window_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: window_buffer.as_entire_binding(),
+            }],
+            label: Some("pick_window_bind_group"),
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Picking Readback Buffer"),
+            size: READBACK_SIZE,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            point_pipeline,
+            line_pipeline,
+            quad_buffer,
+            quad_index_buffer,
+            point_buffer: create_buffer(window_state, "Picking Point Buffer", 1 << 16, wgpu::BufferUsages::VERTEX),
+            line_vertex_buffer: create_buffer(window_state, "Picking Line Buffer", 1 << 16, wgpu::BufferUsages::VERTEX),
+            line_index_buffer: create_buffer(window_state, "Picking Line Index Buffer", 1 << 16, wgpu::BufferUsages::INDEX),
+            id_texture,
+            id_view,
+            depth_texture,
+            window_buffer,
+            window_bind_group,
+            readback_buffer,
+            readback_state: Arc::new(AtomicU8::new(READBACK_IDLE)),
+            requested: None,
+            ready: None,
+            latest: None,
+            point_units: SizeUnits::World,
+            line_units: SizeUnits::World,
+            points: Vec::new(),
+            line_vertices: Vec::new(),
+            line_indices: Vec::new(),
+            edges: Vec::new(),
+        }
+    }
+
+    /// Measures point sizes and line widths the way the elements drawing them do.
+    pub fn with_units(mut self, point_units: SizeUnits, line_units: SizeUnits) -> Self {
+        self.point_units = point_units;
+        self.line_units = line_units;
+        self
+    }
+
+    /// Starts picking what is drawn nearest `pixel`, within a few pixels of it. The answer
+    /// comes from `poll` on a later frame. Does nothing while an earlier pick is still being
+    /// read back.
+    pub fn request<T>(
+        &mut self,
+        graphic_state: &PlotGraphicState<T>,
+        window_state: &PlotWindowState,
+        pixel: PhysicalPosition<f32>,
+    ) {
+        if self.readback_state.load(Ordering::Acquire) != READBACK_IDLE {
+            return;
+        }
+        let surface_pixel = window_state.to_surface(pixel);
+        let (width, height) = (window_state.config.width, window_state.config.height);
+        if surface_pixel.x < 0.0 || surface_pixel.y < 0.0 || surface_pixel.x >= width as f32 || surface_pixel.y >= height as f32 {
+            self.ready = Some(None);
+            self.latest = Some((pixel, None));
+            return;
+        }
+
+        let corner = (
+            surface_pixel.x as i64 - PICK_RADIUS as i64,
+            surface_pixel.y as i64 - PICK_RADIUS as i64,
+        );
+        window_state.queue.write_buffer(
+            &self.window_buffer,
+            0,
+            bytemuck::cast_slice(&[PickWindowUniform::new(corner, width, height)]),
+        );
+
+        let mut encoder = window_state
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Picking Encoder"),
+            });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Picking Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.id_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_bind_group(0, &graphic_state.camera_bind_group, &[]);
+            render_pass.set_bind_group(1, &self.window_bind_group, &[]);
+
+            if !self.line_indices.is_empty() {
+                render_pass.set_pipeline(&self.line_pipeline);
+                render_pass.set_vertex_buffer(0, self.line_vertex_buffer.slice(..));
+                render_pass.set_index_buffer(self.line_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(0..self.line_indices.len() as u32, 0, 0..1);
+            }
+            if !self.points.is_empty() {
+                render_pass.set_pipeline(&self.point_pipeline);
+                render_pass.set_vertex_buffer(0, self.quad_buffer.slice(..));
+                render_pass.set_vertex_buffer(1, self.point_buffer.slice(..));
+                render_pass.set_index_buffer(self.quad_index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                render_pass.draw_indexed(0..PICK_QUAD_INDEX.len() as u32, 0, 0..self.points.len() as u32);
+            }
+        }
+        encoder.copy_texture_to_buffer(
+            self.id_texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &self.readback_buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(READBACK_ROW_SIZE),
+                    rows_per_image: Some(PICK_SIZE),
+                },
+            },
+            self.id_texture.size(),
+        );
+        window_state.queue.submit(std::iter::once(encoder.finish()));
+
+        self.readback_state.store(READBACK_PENDING, Ordering::Release);
+        let readback_state = self.readback_state.clone();
+        self.readback_buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            let state = if result.is_ok() { READBACK_MAPPED } else { READBACK_FAILED };
+            readback_state.store(state, Ordering::Release);
+        });
+        self.requested = Some(pixel);
+    }
+
+    /// The requested pick, once it has been read back. Checks without blocking, so it can be
+    /// called every frame.
+    pub fn poll(&mut self, window_state: &PlotWindowState) -> Option<Option<Pick>> {
+        if let Some(pick) = self.ready.take() {
+            return Some(pick);
+        }
+        if self.readback_state.load(Ordering::Acquire) == READBACK_PENDING {
+            window_state.device.poll(wgpu::Maintain::Poll);
+        }
+        match self.readback_state.load(Ordering::Acquire) {
+            READBACK_MAPPED => {
+                let ids = {
+                    let mapped = self.readback_buffer.slice(..).get_mapped_range();
+                    bytemuck::cast_slice::<u8, u32>(&mapped).to_vec()
+                };
+                self.readback_buffer.unmap();
+                self.readback_state.store(READBACK_IDLE, Ordering::Release);
+
+                let pick = self.nearest(&ids);
+                self.latest = self.requested.take().map(|pixel| (pixel, pick));
+                Some(pick)
+            }
+            READBACK_FAILED => {
+                self.readback_state.store(READBACK_IDLE, Ordering::Release);
+                self.requested = None;
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// The last pick read back, if it was requested with the cursor at `pixel`.
+    pub fn latest(&self, pixel: PhysicalPosition<f32>) -> Option<Option<Pick>> {
+        self.latest.filter(|(at, _)| *at == pixel).map(|(_, pick)| pick)
+    }
+
+    /// The pick nearest the middle of the window of IDs read back.
+    fn nearest(&self, ids: &[u32]) -> Option<Pick> {
+        let row_length = (READBACK_ROW_SIZE / 4) as usize;
+        let center = PICK_RADIUS as i32;
+        (0..PICK_SIZE as i32)
+            .flat_map(|y| (0..PICK_SIZE as i32).map(move |x| (x, y)))
+            .filter_map(|(x, y)| {
+                let id = ids[y as usize * row_length + x as usize];
+                (id != 0).then(|| ((x - center).pow(2) + (y - center).pow(2), id))
+            })
+            .min_by_key(|(distance, _)| *distance)
+            .and_then(|(_, id)| self.decode(id))
+    }
+
+    fn decode(&self, id: u32) -> Option<Pick> {
+        match id {
+            0 => None,
+            id if id & EDGE_ID_BIT != 0 => self
+                .edges
+                .get((id & !EDGE_ID_BIT) as usize - 1)
+                .map(|(u, v)| Pick::Edge(*u, *v)),
+            id => Some(Pick::Vertex(id as usize - 1)),
+        }
+    }
+
+    /// Rewrites the pickable geometry, which should match what was last drawn.
+    pub fn update<T: GetPickables>(&mut self, window_state: &PlotWindowState, data: &T) {
+        self.points.clear();
+        self.line_vertices.clear();
+        self.line_indices.clear();
+        self.edges.clear();
+
+        for (i, (position, size)) in data.get_pick_points().enumerate() {
+            self.points.push(PickPointInstance {
+                position: position.into(),
+                size,
+                id: i as u32 + 1,
+                pixel_sized: self.point_units.shader_flag(),
+            });
+        }
+        // Segments with no length have no direction to be widened across.
+        for (x0, x1, width, edge) in data.get_pick_lines().filter(|(x0, x1, _, _)| x0 != x1) {
+            self.edges.push(edge);
+            let id = self.edges.len() as u32 | EDGE_ID_BIT;
+            let index = self.line_vertices.len() as u32;
+            let offset = width / 2.0;
+            for (position, next, offset_distance) in [(x0, x1, offset), (x0, x1, -offset), (x1, x0, -offset), (x1, x0, offset)] {
+                self.line_vertices.push(PickLineVertex {
+                    position: position.into(),
+                    next: next.into(),
+                    offset_distance,
+                    id,
+                    pixel_sized: self.line_units.shader_flag(),
+                });
+            }
+            self.line_indices
+                .extend_from_slice(&[index, index + 1, index + 3, index, index + 3, index + 2]);
+        }
+
+        write_growing(window_state, &mut self.point_buffer, "Picking Point Buffer", wgpu::BufferUsages::VERTEX, bytemuck::cast_slice(&self.points));
+        write_growing(window_state, &mut self.line_vertex_buffer, "Picking Line Buffer", wgpu::BufferUsages::VERTEX, bytemuck::cast_slice(&self.line_vertices));
+        write_growing(window_state, &mut self.line_index_buffer, "Picking Line Index Buffer", wgpu::BufferUsages::INDEX, bytemuck::cast_slice(&self.line_indices));
+    }
+}
+
+fn create_id_texture(window_state: &PlotWindowState) -> (wgpu::Texture, wgpu::TextureView) {
+    let texture = window_state.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Picking ID Texture"),
+        size: wgpu::Extent3d {
+            width: PICK_SIZE,
+            height: PICK_SIZE,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: ID_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    (texture, view)
+}
+
+fn create_buffer(window_state: &PlotWindowState, label: &str, size: u64, usage: wgpu::BufferUsages) -> wgpu::Buffer {
+    window_state.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size,
+        usage: usage | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn write_growing(
+    window_state: &PlotWindowState,
+    buffer: &mut wgpu::Buffer,
+    label: &str,
+    usage: wgpu::BufferUsages,
+    contents: &[u8],
+) {
+    if contents.len() as u64 > buffer.size() {
+        *buffer = create_buffer(window_state, label, (contents.len() as u64).next_power_of_two(), usage);
+    }
+    window_state.queue.write_buffer(buffer, 0, contents);
+}
+
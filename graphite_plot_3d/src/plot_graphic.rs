@@ -4,11 +4,22 @@ use std::collections::HashMap;
 use wgpu::{util::DeviceExt, PipelineLayout, RenderPass};
 use winit::{dpi::PhysicalSize, event::WindowEvent};
 
-use crate::{camera::{Camera, CameraController, CameraUniform}, plot_window::PlotWindowState, texture};
+use crate::{camera::{Camera, CameraController, CameraUniform}, capture::{self, CopiedFrame}, draw::Color, plot_window::PlotWindowState, texture};
 
 pub trait PlotGraphicElement<T> {
-    fn update(&mut self, window_state: &PlotWindowState, data: &T);
+    fn update(&mut self, window_state: &PlotWindowState, camera: &Camera, data: &T);
     fn render(&self, graphic_state: &PlotGraphicState<T>, render_pass: &mut RenderPass);
+
+    /// Transparent elements are drawn after the opaque ones, so whatever they are in front
+    /// of is already there to blend with.
+    fn is_transparent(&self) -> bool {
+        false
+    }
+
+    /// Overlay elements are interface drawn over the plot, and left out of captured frames.
+    fn is_overlay(&self) -> bool {
+        false
+    }
 }
 
 pub struct PlotGraphicState<T> {
@@ -16,8 +27,11 @@ pub struct PlotGraphicState<T> {
     pub camera: Camera,
     pub camera_uniform: CameraUniform,
     pub camera_buffer: wgpu::Buffer,
+    pub camera_bind_group_layout: wgpu::BindGroupLayout,
     pub camera_bind_group: wgpu::BindGroup,
     pub camera_controller: CameraController,
+    /// What the plot is drawn over, and what fog fades towards.
+    background: Color,
 
     elements: Vec<Box<dyn PlotGraphicElement<T>>>,
 }
@@ -39,7 +53,7 @@ impl<T> PlotGraphicState<T> {
         };
 
         let mut camera_uniform = CameraUniform::new();
-        camera_uniform.update(&camera);
+        camera_uniform.update(&camera, PhysicalSize::new(window_state.config.width, window_state.config.height));
 
         let camera_buffer = window_state.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Camera Buffer"),
@@ -51,7 +65,7 @@ impl<T> PlotGraphicState<T> {
             window_state.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -87,13 +101,17 @@ impl<T> PlotGraphicState<T> {
             camera,
             camera_uniform,
             camera_buffer,
+            camera_bind_group_layout,
             camera_bind_group,
             camera_controller,
+            background: Color::rgb(1.0, 1.0, 1.0),
             elements,
         }
     }
 
-    pub fn render(&self, window_state: &PlotWindowState) -> Result<(), wgpu::SurfaceError>  {
+    /// Draws a frame and, with `capture` set, draws the plot again without its overlays into
+    /// a texture and copies that for `Capturer::read_back`.
+    pub fn render(&self, window_state: &PlotWindowState, capture: bool) -> Result<Option<CopiedFrame>, wgpu::SurfaceError>  {
         let output = window_state.surface.get_current_texture()?;
         let view = output
             .texture
@@ -105,45 +123,59 @@ impl<T> PlotGraphicState<T> {
                 label: Some("Render Encoder"),
             });
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 1.0,
-                            g: 1.0,
-                            b: 1.0,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &window_state.depth_texture.view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }),
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
-
-            for element in &self.elements {
-                element.render(self, &mut render_pass);
-            }
-
-            // println!("Rendered!");
-        }
+        self.draw(window_state, &mut encoder, &view, true);
+        let copied_frame = capture.then(|| {
+            let texture = capture::capture_texture(&window_state.device, &window_state.config);
+            self.draw(window_state, &mut encoder, &texture.create_view(&wgpu::TextureViewDescriptor::default()), false);
+            capture::copy_frame(&window_state.device, &mut encoder, &texture)
+        });
 
         window_state.queue.submit(std::iter::once(encoder.finish()));
         output.present();
 
-        Ok(())
+        Ok(copied_frame)
+    }
+
+    /// Draws the elements into `view`, multisampled if the window is. Overlays are left out
+    /// without `overlays`.
+    fn draw(&self, window_state: &PlotWindowState, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, overlays: bool) {
+        let (target, resolve_target) = window_state.color_targets(view);
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: self.background.r as f64,
+                        g: self.background.g as f64,
+                        b: self.background.b as f64,
+                        a: 1.0,
+                    }),
+                    // Only the resolved frame is needed once the pass is done.
+                    store: match resolve_target {
+                        Some(_) => wgpu::StoreOp::Discard,
+                        None => wgpu::StoreOp::Store,
+                    },
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &window_state.depth_texture.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        let opaque = self.elements.iter().filter(|element| !element.is_transparent());
+        let transparent = self.elements.iter().filter(|element| element.is_transparent());
+        for element in opaque.chain(transparent).filter(|element| overlays || !element.is_overlay()) {
+            element.render(self, &mut render_pass);
+        }
     }
 
     pub fn add_element(&mut self, element: impl PlotGraphicElement<T> + 'static) {
@@ -152,14 +184,24 @@ impl<T> PlotGraphicState<T> {
 
     pub fn update_data(&mut self, window_state: &PlotWindowState, data: &T) {
         for element in &mut self.elements {
-            element.update(window_state, data);
+            element.update(window_state, &self.camera, data);
         }
     }
 
+    /// Fades lines and points towards the background with depth, fully at `strength` 1.
+    pub fn set_fog(&mut self, strength: f32) {
+        self.camera_uniform.set_fog(strength);
+    }
+
+    pub fn set_background(&mut self, background: Color) {
+        self.background = background;
+        self.camera_uniform.set_fog_color(background.into());
+    }
+
     pub fn update(&mut self, window_state: &PlotWindowState) {
         self.camera_controller.update_camera(&mut self.camera);
         self.camera.aspect = window_state.config.width as f32 / window_state.config.height as f32;
-        self.camera_uniform.update(&self.camera);
+        self.camera_uniform.update(&self.camera, PhysicalSize::new(window_state.config.width, window_state.config.height));
         window_state.queue.write_buffer(
             &self.camera_buffer,
             0,
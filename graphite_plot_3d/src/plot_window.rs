@@ -2,8 +2,10 @@ use std::sync::Arc;
 
 use instant::Instant;
 use winit::{
-    application::ApplicationHandler, dpi::PhysicalSize, event::WindowEvent, event_loop::ActiveEventLoop, window::{Window, WindowId}
+    application::ApplicationHandler, dpi::{PhysicalPosition, PhysicalSize}, event::WindowEvent, event_loop::ActiveEventLoop, window::{Window, WindowId}
 };
+#[cfg(target_arch = "wasm32")]
+use winit::dpi::LogicalSize;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
@@ -15,7 +17,7 @@ const REDRAW_RATE: u128 = 1000 / 60;
 pub trait PlotGraphic {
     fn init(&mut self, state: &PlotWindowState);
     fn tick(&mut self, delta_t: instant::Duration);
-    fn input(&mut self, event: &WindowEvent, window_size: PhysicalSize<u32>) -> bool;
+    fn input(&mut self, event: &WindowEvent, window_state: &PlotWindowState) -> bool;
     // fn draw(&mut self, state: &mut PlotWindowState);
     fn update(&mut self, window_state: &mut PlotWindowState, rewrite_data: bool);
     fn render(&mut self, window_state: &mut PlotWindowState) -> Result<(), wgpu::SurfaceError>;
@@ -27,6 +29,13 @@ pub struct PlotWindow<T: PlotGraphic> {
     state: Option<PlotWindowState<'static>>,
     last_tick: Instant,
     plot_graphic: T,
+    /// Samples per pixel asked for. The device may only allow fewer.
+    sample_count: u32,
+    /// Id of the element to add a canvas to.
+    #[cfg(target_arch = "wasm32")]
+    element_id: String,
+    #[cfg(target_arch = "wasm32")]
+    container_observer: Option<ContainerObserver>,
 }
 
 impl<T: PlotGraphic> PlotWindow<T> {
@@ -36,8 +45,25 @@ impl<T: PlotGraphic> PlotWindow<T> {
             state: None,
             last_tick: Instant::now(),
             plot_graphic: plot_graphic,
+            sample_count: 1,
+            #[cfg(target_arch = "wasm32")]
+            element_id: "wasm-example".to_string(),
+            #[cfg(target_arch = "wasm32")]
+            container_observer: None,
         }
     }
+
+    /// Draws with `sample_count` samples per pixel, smoothing the edges of lines and points.
+    pub fn with_msaa(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn in_element(mut self, element_id: &str) -> Self {
+        self.element_id = element_id.to_string();
+        self
+    }
 }
 
 impl<T: PlotGraphic> ApplicationHandler for PlotWindow<T> {
@@ -47,28 +73,34 @@ impl<T: PlotGraphic> ApplicationHandler for PlotWindow<T> {
             .unwrap();
 
         #[cfg(target_arch = "wasm32")]
-        {
-            // Winit prevents sizing with CSS, so we have to set
-            // the size manually when on web.
-            use winit::dpi::PhysicalSize;
-            // let _ = window.request_inner_size(PhysicalSize::new(450, 400));
-
+        let container = {
             use winit::platform::web::WindowExtWebSys;
             web_sys::window()
                 .and_then(|win| win.document())
                 .and_then(|doc| {
-                    let dst = doc.get_element_by_id("wasm-example")?;
-                    let canvas = web_sys::Element::from(window.canvas()?);
+                    let dst = doc.get_element_by_id(&self.element_id)?;
+                    let canvas = window.canvas()?;
+                    // Touches should reach the plot instead of scrolling or zooming the page.
+                    canvas.style().set_property("touch-action", "none").ok()?;
+                    // An inline canvas leaves room below it for descenders, which would grow
+                    // the container every time the canvas fills it.
+                    canvas.style().set_property("display", "block").ok()?;
+                    let canvas = web_sys::Element::from(canvas);
                     dst.append_child(&canvas).ok()?;
-                    Some(())
+                    Some(dst)
                 })
-                .expect("Couldn't append canvas to document body.");
-        }
+                .expect("Couldn't append canvas to document body.")
+        };
 
         let window = Arc::new(window);
 
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.container_observer = Some(ContainerObserver::new(window.clone(), &container));
+        }
+
         self.window = Some(window.clone());
-        self.state = Some(pollster::block_on(PlotWindowState::new(window)));
+        self.state = Some(pollster::block_on(PlotWindowState::new(window, self.sample_count)));
         self.plot_graphic.init(self.state.as_ref().unwrap());
     }
 
@@ -80,7 +112,7 @@ impl<T: PlotGraphic> ApplicationHandler for PlotWindow<T> {
     ) {
         let state = self.state.as_mut().unwrap();
 
-        if self.plot_graphic.input(&event, state.size)
+        if self.plot_graphic.input(&event, state)
         {
             return;
         }
@@ -127,18 +159,59 @@ impl<T: PlotGraphic> ApplicationHandler for PlotWindow<T> {
     }
 }
 
+/// Keeps a window the size of the element its canvas was added to.
+#[cfg(target_arch = "wasm32")]
+struct ContainerObserver {
+    observer: web_sys::ResizeObserver,
+    _callback: Closure<dyn FnMut(js_sys::Array)>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl ContainerObserver {
+    fn new(window: Arc<Window>, container: &web_sys::Element) -> Self {
+        let callback = Closure::<dyn FnMut(js_sys::Array)>::new(move |entries: js_sys::Array| {
+            let Some(entry) = entries.iter().last() else {
+                return;
+            };
+            let rect = entry.unchecked_into::<web_sys::ResizeObserverEntry>().content_rect();
+            // Requested in CSS pixels. Winit scales them by `devicePixelRatio`, so the
+            // surface gets one texel per device pixel and stays sharp on HiDPI screens.
+            let _ = window.request_inner_size(LogicalSize::new(rect.width(), rect.height()));
+        });
+        let observer = web_sys::ResizeObserver::new(callback.as_ref().unchecked_ref())
+            .expect("Couldn't create a ResizeObserver.");
+        observer.observe(container);
+
+        Self {
+            observer,
+            _callback: callback,
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Drop for ContainerObserver {
+    fn drop(&mut self) {
+        self.observer.disconnect();
+    }
+}
+
 pub struct PlotWindowState<'a> {
     pub surface: wgpu::Surface<'a>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
-    pub size: winit::dpi::PhysicalSize<u32>,
+    pub size: PhysicalSize<u32>,
+    /// Samples per pixel every pipeline drawing to the surface must use.
+    pub sample_count: u32,
+    /// Drawn into and resolved to the surface when multisampling.
+    msaa_view: Option<wgpu::TextureView>,
     pub depth_texture: texture::Texture,
 }
 
 impl<'a> PlotWindowState<'a> {
     // Creating some of the wgpu types requires async code
-    pub async fn new(window: Arc<Window>) -> PlotWindowState<'a> {
+    pub async fn new(window: Arc<Window>, sample_count: u32) -> PlotWindowState<'a> {
         let size = window.inner_size();
 
         // The instance is a handle to our GPU
@@ -182,6 +255,7 @@ impl<'a> PlotWindowState<'a> {
             .await
             .unwrap();
 
+        let surface_size = clamp_surface_size(size, device.limits().max_texture_dimension_2d);
         let surface_caps = surface.get_capabilities(&adapter);
         // Shader code in this tutorial assumes an sRGB surface texture. Using a different
         // one will result in all the colors coming out darker. If you want to support non
@@ -195,16 +269,18 @@ impl<'a> PlotWindowState<'a> {
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
-            width: size.width,
-            height: size.height,
+            width: surface_size.width,
+            height: surface_size.height,
             present_mode: surface_caps.present_modes[0],
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
 
+        let sample_count = supported_sample_count(&adapter, config.format, sample_count);
+        let msaa_view = create_msaa_view(&device, &config, sample_count);
         let depth_texture =
-            texture::Texture::create_depth_texture(&device, &config, "depth_texture");
+            texture::Texture::create_depth_texture(&device, &config, sample_count, "depth_texture");
 
         Self {
             surface,
@@ -212,27 +288,94 @@ impl<'a> PlotWindowState<'a> {
             queue,
             config,
             size,
+            sample_count,
+            msaa_view,
             depth_texture,
         }
     }
 
-    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+    fn resize(&mut self, new_size: PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
-            let mut width = new_size.width;
-            let mut height = new_size.height;
-            // #[cfg(target_arch = "wasm32")]
-            // {
-            //     width = std::cmp::min(width, 1024);
-            //     height = std::cmp::min(height, 1024);
-            // }
-            let new_new_size = winit::dpi::PhysicalSize::<u32>::new(width, height);
+            let surface_size = clamp_surface_size(new_size, self.device.limits().max_texture_dimension_2d);
             self.size = new_size;
-            self.config.width = new_new_size.width;
-            self.config.height = new_new_size.height;
+            self.config.width = surface_size.width;
+            self.config.height = surface_size.height;
 
             self.surface.configure(&self.device, &self.config);
+            self.msaa_view = create_msaa_view(&self.device, &self.config, self.sample_count);
             self.depth_texture =
-                texture::Texture::create_depth_texture(&self.device, &self.config, "Depth Texture");
+                texture::Texture::create_depth_texture(&self.device, &self.config, self.sample_count, "Depth Texture");
         }
     }
+
+    /// The view to draw a frame into and the one to resolve it to, given the view of the
+    /// surface texture.
+    pub fn color_targets<'v>(&'v self, surface_view: &'v wgpu::TextureView) -> (&'v wgpu::TextureView, Option<&'v wgpu::TextureView>) {
+        match &self.msaa_view {
+            Some(msaa_view) => (msaa_view, Some(surface_view)),
+            None => (surface_view, None),
+        }
+    }
+
+    /// Converts a position in window pixels, as the cursor reports them, to surface pixels.
+    /// The two differ once the surface was scaled down to fit the device's limits.
+    pub fn to_surface(&self, pixel: PhysicalPosition<f32>) -> PhysicalPosition<f32> {
+        PhysicalPosition::new(
+            pixel.x * self.config.width as f32 / self.size.width.max(1) as f32,
+            pixel.y * self.config.height as f32 / self.size.height.max(1) as f32,
+        )
+    }
+}
+
+/// `requested` if the surface and depth formats allow that many samples, and 1 otherwise.
+fn supported_sample_count(adapter: &wgpu::Adapter, format: wgpu::TextureFormat, requested: u32) -> u32 {
+    if requested <= 1 {
+        return 1;
+    }
+    let supported = [format, texture::Texture::DEPTH_FORMAT]
+        .iter()
+        .all(|format| adapter.get_texture_format_features(*format).flags.sample_count_supported(requested));
+    if supported {
+        requested
+    } else {
+        log::warn!("{}x multisampling isn't supported here, drawing without it", requested);
+        1
+    }
+}
+
+/// The multisampled texture frames are drawn into before they're resolved to the surface.
+fn create_msaa_view(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, sample_count: u32) -> Option<wgpu::TextureView> {
+    if sample_count <= 1 {
+        return None;
+    }
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Multisampled Frame"),
+        size: wgpu::Extent3d {
+            width: config.width.max(1),
+            height: config.height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+}
+
+/// Scales `size` down, keeping its aspect ratio, until neither side is over
+/// `max_dimension`. WebGL caps textures at a few thousand pixels, which a large canvas on a
+/// HiDPI screen can pass.
+fn clamp_surface_size(size: PhysicalSize<u32>, max_dimension: u32) -> PhysicalSize<u32> {
+    let longest = size.width.max(size.height);
+    if longest <= max_dimension {
+        return size;
+    }
+    let scale = max_dimension as f64 / longest as f64;
+    PhysicalSize::new(
+        ((size.width as f64 * scale) as u32).clamp(1, max_dimension),
+        ((size.height as f64 * scale) as u32).clamp(1, max_dimension),
+    )
 }